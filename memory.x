@@ -1,6 +1,7 @@
 MEMORY
 {
   /* NOTE K = KiBi = 1024 bytes */
-  FLASH : ORIGIN = 0x00000000, LENGTH = 512K
+  /* The top 32K holds the measurement log (src/flash_log.rs) */
+  FLASH : ORIGIN = 0x00000000, LENGTH = 480K
   RAM : ORIGIN = 0x20000000, LENGTH = 128K
 }
\ No newline at end of file
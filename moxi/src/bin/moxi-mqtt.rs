@@ -0,0 +1,127 @@
+//! `moxi-mqtt`: publish units' readings to MQTT for Home Assistant.
+
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+use moxi::client::{BAUD_RATE, Client};
+use moxi::mqtt::{self, DISCOVERY_PREFIX, Publisher};
+use pico_args::Arguments;
+use rumqttc::{Connection, Event, Packet};
+
+const USAGE: &str = "\
+Usage: moxi-mqtt --port <PATH>... [--baud <RATE>] [--broker <HOST>] [--broker-port <PORT>]
+                 [--username <USER> --password <PASS>] [--discovery-prefix <PREFIX>]
+
+Polls each unit's serial port and publishes its readings to an MQTT broker
+(default localhost:1883), with Home Assistant discovery under <PREFIX>
+(default homeassistant).
+";
+
+/// Default broker port.
+const BROKER_PORT: u16 = 1883;
+
+/// Queued publishes before new ones are dropped.
+const QUEUE_CAP: usize = 64;
+
+/// Wait before reopening a failed port or reconnecting to the broker.
+const RETRY: Duration = Duration::from_secs(5);
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let mut args = Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("moxi-mqtt: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mut args: Arguments) -> Result<()> {
+    let ports: Vec<String> = args.values_from_str("--port")?;
+    let baud_rate = args.opt_value_from_str("--baud")?.unwrap_or(BAUD_RATE);
+    let broker: String = args
+        .opt_value_from_str("--broker")?
+        .unwrap_or_else(|| "localhost".to_owned());
+    let broker_port = args
+        .opt_value_from_str("--broker-port")?
+        .unwrap_or(BROKER_PORT);
+    let username: Option<String> = args.opt_value_from_str("--username")?;
+    let password: Option<String> = args.opt_value_from_str("--password")?;
+    let prefix: String = args
+        .opt_value_from_str("--discovery-prefix")?
+        .unwrap_or_else(|| DISCOVERY_PREFIX.to_owned());
+    let rest = args.finish();
+    if !rest.is_empty() {
+        return Err(format!("unexpected arguments {rest:?}").into());
+    }
+    if ports.is_empty() {
+        return Err("missing --port (see --help)".into());
+    }
+
+    let client_id = format!("moxi-mqtt-{}", std::process::id());
+    let mut options = mqtt::options(&client_id, &broker, broker_port);
+    if let Some(username) = username {
+        options.set_credentials(username, password.unwrap_or_default());
+    }
+    let (client, connection) = rumqttc::Client::new(options, QUEUE_CAP);
+    let publisher = Publisher::new(client, &prefix);
+
+    thread::scope(|s| {
+        for port in &ports {
+            let publisher = &publisher;
+            s.spawn(move || poll_forever(port, baud_rate, publisher));
+        }
+        drive(connection, &publisher);
+    });
+    Ok(())
+}
+
+/// Drive the broker connection, announcing units after each (re)connect.
+fn drive(mut connection: Connection, publisher: &Publisher) {
+    for event in connection.iter() {
+        match event {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                eprintln!("moxi-mqtt: connected to broker");
+                if let Err(e) = publisher.connected() {
+                    eprintln!("moxi-mqtt: announce failed: {e}");
+                }
+            }
+            Ok(_) => {}
+            // Iterating again reconnects
+            Err(e) => {
+                eprintln!("moxi-mqtt: broker: {e}");
+                thread::sleep(RETRY);
+            }
+        }
+    }
+}
+
+/// Bridge a unit, reopening its port after failures.
+fn poll_forever(port: &str, baud_rate: u32, publisher: &Publisher) {
+    loop {
+        match Client::open(port, baud_rate) {
+            Ok(mut client) => {
+                let (serial, e) = mqtt::bridge(&mut client, publisher, |e| {
+                    eprintln!("moxi-mqtt: {port}: publish failed: {e}");
+                });
+                eprintln!("moxi-mqtt: {port}: {e}");
+                if let Some(serial) = serial
+                    && let Err(e) = publisher.offline(serial)
+                {
+                    eprintln!("moxi-mqtt: {port}: publish failed: {e}");
+                }
+            }
+            Err(e) => eprintln!("moxi-mqtt: {port}: {e}"),
+        }
+        thread::sleep(RETRY);
+    }
+}
@@ -0,0 +1,86 @@
+//! `moxi-prometheus`: serve units' readings to Prometheus.
+
+use std::net::TcpListener;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+use moxi::client::{BAUD_RATE, Client};
+use moxi::prometheus::{self, Metrics};
+use pico_args::Arguments;
+
+const USAGE: &str = "\
+Usage: moxi-prometheus --port <PATH>... [--baud <RATE>] [--listen <ADDR>]
+
+Polls each unit's serial port and serves gauges at http://<ADDR>/metrics
+(default 0.0.0.0:9464), labelled with each unit's serial number.
+";
+
+/// Default listen address.
+const LISTEN: &str = "0.0.0.0:9464";
+
+/// Wait before reopening a failed port.
+const RETRY: Duration = Duration::from_secs(5);
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let mut args = Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("moxi-prometheus: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mut args: Arguments) -> Result<()> {
+    let ports: Vec<String> = args.values_from_str("--port")?;
+    let baud_rate = args.opt_value_from_str("--baud")?.unwrap_or(BAUD_RATE);
+    let listen: String = args
+        .opt_value_from_str("--listen")?
+        .unwrap_or_else(|| LISTEN.to_owned());
+    let rest = args.finish();
+    if !rest.is_empty() {
+        return Err(format!("unexpected arguments {rest:?}").into());
+    }
+    if ports.is_empty() {
+        return Err("missing --port (see --help)".into());
+    }
+
+    let listener = TcpListener::bind(&listen)?;
+    eprintln!("moxi-prometheus: serving http://{listen}/metrics");
+
+    let metrics = Metrics::new();
+    thread::scope(|s| {
+        for port in &ports {
+            let metrics = &metrics;
+            s.spawn(move || poll_forever(port, baud_rate, metrics));
+        }
+        prometheus::serve(&listener, &metrics)
+    })?;
+    Ok(())
+}
+
+/// Poll a unit, reopening its port after failures.
+fn poll_forever(port: &str, baud_rate: u32, metrics: &Metrics) {
+    loop {
+        match Client::open(port, baud_rate) {
+            Ok(mut client) => loop {
+                if let Err(e) = prometheus::poll(&mut client, port, metrics) {
+                    eprintln!("moxi-prometheus: {port}: {e}");
+                    break;
+                }
+            },
+            Err(e) => eprintln!("moxi-prometheus: {port}: {e}"),
+        }
+        metrics.down(port);
+        thread::sleep(RETRY);
+    }
+}
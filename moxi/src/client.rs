@@ -0,0 +1,249 @@
+//! Request/reply client over a serial port.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use moxi_protocol::{
+    self as protocol, Config, Decoder, FRAME_MAX, Frame, Measurement, Message, NackReason, Status,
+};
+use serialport::SerialPort;
+
+/// Default framed protocol baud rate.
+pub const BAUD_RATE: u32 = 115_200;
+
+/// Default reply timeout.
+pub const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Client failures.
+#[derive(Debug)]
+pub enum Error {
+    /// Port failure or timeout.
+    Io(io::Error),
+    /// Frame could not be encoded.
+    Encode(protocol::Error),
+    /// Unit refused the request.
+    Nack(NackReason),
+    /// Unit answered with an unexpected message.
+    Unexpected(Box<Message>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "serial port: {e}"),
+            Self::Encode(e) => write!(f, "encode failed: {e:?}"),
+            Self::Nack(NackReason::Malformed) => f.write_str("unit could not decode the request"),
+            Self::Nack(NackReason::Unsupported) => f.write_str("unit does not support the request"),
+            Self::Nack(NackReason::Invalid) => f.write_str("unit rejected the value as invalid"),
+            Self::Unexpected(message) => write!(f, "unexpected reply: {message:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Client for one unit.
+#[derive(Debug)]
+pub struct Client<P> {
+    port: P,
+    decoder: Decoder,
+    /// Decoded frames not yet consumed.
+    pending: VecDeque<Frame>,
+    seq: u16,
+}
+
+impl Client<Box<dyn SerialPort>> {
+    /// Open a unit's serial port.
+    ///
+    /// # Errors
+    /// Returns [`Error::Io`] if the port cannot be opened.
+    pub fn open(path: &str, baud_rate: u32) -> Result<Self, Error> {
+        let port = serialport::new(path, baud_rate)
+            .timeout(TIMEOUT)
+            .open()
+            .map_err(io::Error::from)?;
+        Ok(Self::new(port))
+    }
+}
+
+impl<P: Read + Write> Client<P> {
+    /// Wrap a connected port, whose read timeout bounds each reply.
+    pub const fn new(port: P) -> Self {
+        Self {
+            port,
+            decoder: Decoder::new(),
+            pending: VecDeque::new(),
+            seq: 0,
+        }
+    }
+
+    /// Unit identity and health.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] if the request fails.
+    pub fn status(&mut self) -> Result<Status, Error> {
+        match self.request(Message::GetStatus)? {
+            Message::Status(status) => Ok(status),
+            other => Err(Error::Unexpected(Box::new(other))),
+        }
+    }
+
+    /// Current configuration.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] if the request fails.
+    pub fn config(&mut self) -> Result<Config, Error> {
+        match self.request(Message::GetConfig)? {
+            Message::Config(config) => Ok(config),
+            other => Err(Error::Unexpected(Box::new(other))),
+        }
+    }
+
+    /// Replace the configuration.
+    ///
+    /// # Errors
+    /// Returns [`Error::Nack`] for out-of-range values.
+    pub fn set_config(&mut self, config: Config) -> Result<(), Error> {
+        self.command(Message::SetConfig(config))
+    }
+
+    /// Set the unit's wall clock (ms since the Unix epoch).
+    ///
+    /// # Errors
+    /// Returns an [`Error`] if the request fails.
+    pub fn set_time(&mut self, unix_ms: u64) -> Result<(), Error> {
+        self.command(Message::SetTime(unix_ms))
+    }
+
+    /// Force CO2 recalibration against a reference level (ppm).
+    ///
+    /// # Errors
+    /// Returns [`Error::Nack`] for an out-of-range reference.
+    pub fn calibrate(&mut self, ppm: u16) -> Result<(), Error> {
+        self.command(Message::Calibrate(ppm))
+    }
+
+    /// Download every logged record, oldest first.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] if any chunk request fails.
+    pub fn log(&mut self) -> Result<Vec<Measurement>, Error> {
+        let mut records = Vec::new();
+        let mut offset = 0;
+        loop {
+            let chunk = match self.request(Message::GetLog(offset))? {
+                Message::LogChunk(chunk) => chunk,
+                other => return Err(Error::Unexpected(Box::new(other))),
+            };
+            if chunk.records.is_empty() {
+                return Ok(records);
+            }
+            let count = u32::try_from(chunk.records.len()).unwrap_or(u32::MAX);
+            offset = chunk.offset.saturating_add(count);
+            records.extend(chunk.records);
+        }
+    }
+
+    /// Wait for the next streamed measurement.
+    ///
+    /// # Errors
+    /// Returns [`Error::Io`] if none arrives before the port times out.
+    pub fn measurement(&mut self) -> Result<Measurement, Error> {
+        loop {
+            if let Message::Measurement(m) = self.receive()?.message {
+                return Ok(m);
+            }
+        }
+    }
+
+    /// Wait for the next streamed measurement, treating a port timeout as
+    /// none yet: low power units only report every 30 s.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] for any failure other than a timeout.
+    pub fn poll_measurement(&mut self) -> Result<Option<Measurement>, Error> {
+        match self.measurement() {
+            Ok(m) => Ok(Some(m)),
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Send a request expecting an [`Message::Ack`].
+    fn command(&mut self, message: Message) -> Result<(), Error> {
+        match self.request(message)? {
+            Message::Ack(_) => Ok(()),
+            other => Err(Error::Unexpected(Box::new(other))),
+        }
+    }
+
+    /// Send a request and wait for its reply, skipping streamed measurements.
+    fn request(&mut self, message: Message) -> Result<Message, Error> {
+        // Zero is reserved for replies to undecodable frames
+        self.seq = self.seq.wrapping_add(1).max(1);
+        let seq = self.seq;
+
+        let mut encoded = [0; FRAME_MAX];
+        let len = protocol::encode(&Frame { seq, message }, &mut encoded).map_err(Error::Encode)?;
+        self.port
+            .write_all(encoded.get(..len).unwrap_or_default())?;
+        self.port.flush()?;
+
+        loop {
+            match self.receive()? {
+                Frame {
+                    message: Message::Measurement(_),
+                    ..
+                } => {}
+                Frame {
+                    message: Message::Nack(_, reason),
+                    seq: reply_seq,
+                } if reply_seq == seq || reply_seq == 0 => return Err(Error::Nack(reason)),
+                Frame {
+                    seq: reply_seq,
+                    message,
+                } if reply_seq == seq => return Ok(message),
+                // Late reply to an earlier request
+                Frame { .. } => {}
+            }
+        }
+    }
+
+    /// Next well-formed frame from the unit.
+    fn receive(&mut self) -> Result<Frame, Error> {
+        let mut chunk = [0; 64];
+        loop {
+            if let Some(frame) = self.pending.pop_front() {
+                return Ok(frame);
+            }
+
+            let len = match self.port.read(&mut chunk) {
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            for &byte in chunk.get(..len).unwrap_or_default() {
+                // Corrupt frames are dropped; the request times out instead
+                if let Some(Ok(frame)) = self.decoder.feed(byte) {
+                    self.pending.push_back(frame);
+                }
+            }
+        }
+    }
+}
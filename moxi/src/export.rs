@@ -0,0 +1,75 @@
+//! Log export formats.
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use moxi_protocol::Measurement;
+
+/// CSV column names.
+pub const CSV_HEADER: &str = "uptime_ms,unix_ms,co2_ppm,humidity,temp_c,hpa";
+
+/// Export file format.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown format '{s}' (expected csv or json)")),
+        }
+    }
+}
+
+/// Write records in the given format.
+///
+/// # Errors
+/// Returns an [`io::Error`] if writing fails.
+pub fn write(format: Format, records: &[Measurement], out: impl Write) -> io::Result<()> {
+    match format {
+        Format::Csv => write_csv(records, out),
+        Format::Json => write_json(records, out),
+    }
+}
+
+/// Write records as CSV with a header row; absent values are empty.
+///
+/// # Errors
+/// Returns an [`io::Error`] if writing fails.
+pub fn write_csv(records: &[Measurement], mut out: impl Write) -> io::Result<()> {
+    writeln!(out, "{CSV_HEADER}")?;
+    for m in records {
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            m.timestamp.uptime_ms,
+            optional(m.timestamp.unix_ms),
+            m.co2_ppm,
+            m.humidity,
+            m.temp_c,
+            optional(m.hpa),
+        )?;
+    }
+    Ok(())
+}
+
+/// Write records as a JSON array.
+///
+/// # Errors
+/// Returns an [`io::Error`] if writing fails.
+pub fn write_json(records: &[Measurement], mut out: impl Write) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut out, records)?;
+    writeln!(out)
+}
+
+/// CSV field for an optional value.
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
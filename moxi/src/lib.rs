@@ -0,0 +1,10 @@
+//! Host-side client for units speaking the framed serial protocol.
+
+pub mod client;
+pub mod export;
+pub mod mqtt;
+pub mod prometheus;
+pub mod sim;
+
+pub use client::{Client, Error};
+pub use moxi_protocol as protocol;
@@ -0,0 +1,184 @@
+//! `moxi`: manage units over their USB serial port.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use moxi::client::{BAUD_RATE, Client};
+use moxi::export::{self, Format};
+use moxi::protocol::{Measurement, PowerMode};
+use pico_args::Arguments;
+use serialport::SerialPort;
+
+const USAGE: &str = "\
+Usage: moxi --port <PATH> [--baud <RATE>] <COMMAND>
+
+Commands:
+  status                          Show unit identity and health
+  live                            Stream readings until interrupted
+  config [OPTIONS]                Show configuration, updating any given option
+      --co2-alarm <PPM>
+      --power-mode <high|low>
+      --temp-offset <C>
+      --unit-id <ID>
+  calibrate <PPM>                 Force CO2 recalibration against a reference level
+                                  (run the unit at that level for 3 minutes first)
+  sync-time                       Set the unit's clock from this computer
+  log [--format <csv|json>] [--output <FILE>]
+                                  Download the unit's flash log (CSV to stdout by default)
+";
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let mut args = Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("moxi: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mut args: Arguments) -> Result<()> {
+    let port: String = args
+        .opt_value_from_str("--port")?
+        .ok_or("missing --port (see --help)")?;
+    let baud_rate = args.opt_value_from_str("--baud")?.unwrap_or(BAUD_RATE);
+    let command = args.subcommand()?.ok_or("missing command (see --help)")?;
+    let mut client = Client::open(&port, baud_rate)?;
+
+    match command.as_str() {
+        "status" => {
+            finish(args)?;
+            let status = client.status()?;
+            println!("serial:        {:08x}", status.serial);
+            println!("uptime:        {} s", status.uptime_ms / 1000);
+            println!("clock set:     {}", status.clock_set);
+            println!("co2 sensor:    {}", health(status.co2_ok));
+            println!("pressure:      {}", health(status.pressure_ok));
+            println!("sensor errors: {}", status.sensor_errors);
+        }
+        "live" => {
+            finish(args)?;
+            live(&mut client)?;
+        }
+        "config" => config(&mut client, args)?,
+        "calibrate" => {
+            let ppm = args.free_from_str()?;
+            finish(args)?;
+            client.calibrate(ppm)?;
+            println!("recalibration to {ppm} ppm requested");
+        }
+        "sync-time" => {
+            finish(args)?;
+            let unix_ms = u64::try_from(SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis())?;
+            client.set_time(unix_ms)?;
+            println!("clock set");
+        }
+        "log" => {
+            let format: Format = args.opt_value_from_str("--format")?.unwrap_or_default();
+            let output: Option<String> = args.opt_value_from_str("--output")?;
+            finish(args)?;
+            let records = client.log()?;
+            match output {
+                Some(path) => {
+                    export::write(format, &records, BufWriter::new(File::create(&path)?))?;
+                    eprintln!("{} records written to {path}", records.len());
+                }
+                None => export::write(format, &records, io::stdout().lock())?,
+            }
+        }
+        other => return Err(format!("unknown command '{other}' (see --help)").into()),
+    }
+    Ok(())
+}
+
+/// Print streamed readings.
+fn live(client: &mut Client<Box<dyn SerialPort>>) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    loop {
+        if let Some(m) = client.poll_measurement()? {
+            writeln!(stdout, "{}", reading(&m))?;
+        }
+    }
+}
+
+/// Show the configuration after applying any given changes.
+fn config(client: &mut Client<Box<dyn SerialPort>>, mut args: Arguments) -> Result<()> {
+    let mut config = client.config()?;
+    let current = config;
+    if let Some(ppm) = args.opt_value_from_str("--co2-alarm")? {
+        config.co2_alarm_ppm = ppm;
+    }
+    if let Some(mode) = args.opt_value_from_fn("--power-mode", power_mode)? {
+        config.power_mode = mode;
+    }
+    if let Some(offset) = args.opt_value_from_str("--temp-offset")? {
+        config.temp_offset_c = offset;
+    }
+    if let Some(id) = args.opt_value_from_str("--unit-id")? {
+        config.unit_id = id;
+    }
+    finish(args)?;
+
+    if config != current {
+        client.set_config(config)?;
+    }
+    println!("co2 alarm:   {} ppm", config.co2_alarm_ppm);
+    println!(
+        "power mode:  {}",
+        match config.power_mode {
+            PowerMode::High => "high",
+            PowerMode::Low => "low",
+        }
+    );
+    println!("temp offset: {} C", config.temp_offset_c);
+    println!("unit id:     {}", config.unit_id);
+    Ok(())
+}
+
+/// One line for a reading.
+fn reading(m: &Measurement) -> String {
+    let time = m.timestamp.unix_ms.map_or_else(
+        || format!("+{}s", m.timestamp.uptime_ms / 1000),
+        |ms| format!("{ms}ms"),
+    );
+    let pressure = m
+        .hpa
+        .map(|hpa| format!(" {hpa:.1} hPa"))
+        .unwrap_or_default();
+    format!(
+        "{time} {:.0} ppm {:.2} C {:.1} %{pressure}",
+        m.co2_ppm, m.temp_c, m.humidity
+    )
+}
+
+fn power_mode(s: &str) -> std::result::Result<PowerMode, &'static str> {
+    match s {
+        "high" => Ok(PowerMode::High),
+        "low" => Ok(PowerMode::Low),
+        _ => Err("expected high or low"),
+    }
+}
+
+const fn health(ok: bool) -> &'static str {
+    if ok { "ok" } else { "no readings" }
+}
+
+/// Reject leftover arguments.
+fn finish(args: Arguments) -> Result<()> {
+    let rest = args.finish();
+    if rest.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("unexpected arguments {rest:?}").into())
+    }
+}
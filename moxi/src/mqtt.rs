@@ -0,0 +1,294 @@
+//! MQTT bridge with Home Assistant discovery.
+//!
+//! Each unit appears as a device keyed by its FICR serial number, with one
+//! sensor entity per reading. Readings are published as one JSON state
+//! message per measurement:
+//!
+//! | Topic                                                 | Retained | Payload            |
+//! |-------------------------------------------------------|----------|--------------------|
+//! | `<prefix>/sensor/moxi_<serial>/<entity>/config`       | yes      | discovery config   |
+//! | `moxi/<serial>/state`                                 | no       | readings (JSON)    |
+//! | `moxi/<serial>/availability`                          | yes      | `online`/`offline` |
+//! | `moxi/bridge/availability`                            | yes      | `online`/`offline` |
+
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use moxi_protocol::{Measurement, Status};
+use rumqttc::{Client as MqttClient, ClientError, LastWill, MqttOptions, QoS};
+use serde_json::{Value, json};
+
+use crate::client::{self, Client};
+
+/// Default Home Assistant discovery prefix.
+pub const DISCOVERY_PREFIX: &str = "homeassistant";
+
+/// Bridge availability topic, cleared by the broker if the bridge drops.
+pub const BRIDGE_AVAILABILITY: &str = "moxi/bridge/availability";
+
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+/// Home Assistant sensor entity.
+struct Entity {
+    /// State JSON key and entity id suffix.
+    key: &'static str,
+    name: &'static str,
+    device_class: Option<&'static str>,
+    unit: Option<&'static str>,
+    state_class: &'static str,
+    diagnostic: bool,
+}
+
+/// Entities published for each unit.
+const ENTITIES: [Entity; 5] = [
+    Entity {
+        key: "co2",
+        name: "CO2",
+        device_class: Some("carbon_dioxide"),
+        unit: Some("ppm"),
+        state_class: "measurement",
+        diagnostic: false,
+    },
+    Entity {
+        key: "temperature",
+        name: "Temperature",
+        device_class: Some("temperature"),
+        unit: Some("°C"),
+        state_class: "measurement",
+        diagnostic: false,
+    },
+    Entity {
+        key: "humidity",
+        name: "Humidity",
+        device_class: Some("humidity"),
+        unit: Some("%"),
+        state_class: "measurement",
+        diagnostic: false,
+    },
+    Entity {
+        key: "pressure",
+        name: "Pressure",
+        device_class: Some("atmospheric_pressure"),
+        unit: Some("hPa"),
+        state_class: "measurement",
+        diagnostic: false,
+    },
+    Entity {
+        key: "sensor_errors",
+        name: "Sensor errors",
+        device_class: None,
+        unit: None,
+        state_class: "total_increasing",
+        diagnostic: true,
+    },
+];
+
+/// Unit id used in topics and entity ids.
+#[must_use]
+pub fn unit_id(serial: u32) -> String {
+    format!("{serial:08x}")
+}
+
+/// Topic carrying a unit's readings.
+#[must_use]
+pub fn state_topic(serial: u32) -> String {
+    format!("moxi/{}/state", unit_id(serial))
+}
+
+/// Topic carrying a unit's availability.
+#[must_use]
+pub fn availability_topic(serial: u32) -> String {
+    format!("moxi/{}/availability", unit_id(serial))
+}
+
+/// Discovery config topics and payloads for a unit's entities.
+#[must_use]
+pub fn discovery(prefix: &str, serial: u32) -> Vec<(String, Value)> {
+    let id = unit_id(serial);
+    let device = json!({
+        "identifiers": [format!("moxi_{id}")],
+        "name": format!("Moxi {id}"),
+        "manufacturer": "rustymicrobit",
+        "model": "micro:bit v2 air quality monitor",
+        "serial_number": id,
+    });
+
+    ENTITIES
+        .iter()
+        .map(|entity| {
+            let unique_id = format!("moxi_{id}_{}", entity.key);
+            let mut config = json!({
+                "name": entity.name,
+                "unique_id": unique_id,
+                "object_id": unique_id,
+                "state_topic": state_topic(serial),
+                "value_template": format!("{{{{ value_json.{} }}}}", entity.key),
+                "state_class": entity.state_class,
+                "availability": [
+                    { "topic": BRIDGE_AVAILABILITY },
+                    { "topic": availability_topic(serial) },
+                ],
+                "availability_mode": "all",
+                "device": device,
+            });
+            if let Some(object) = config.as_object_mut() {
+                if let Some(device_class) = entity.device_class {
+                    object.insert("device_class".into(), device_class.into());
+                }
+                if let Some(unit) = entity.unit {
+                    object.insert("unit_of_measurement".into(), unit.into());
+                }
+                if entity.diagnostic {
+                    object.insert("entity_category".into(), "diagnostic".into());
+                }
+            }
+            (
+                format!("{prefix}/sensor/moxi_{id}/{}/config", entity.key),
+                config,
+            )
+        })
+        .collect()
+}
+
+/// State payload for a measurement; pressure is `null` when absent.
+#[must_use]
+pub fn state(status: &Status, m: &Measurement) -> Value {
+    json!({
+        "co2": m.co2_ppm,
+        "temperature": m.temp_c,
+        "humidity": m.humidity,
+        "pressure": m.hpa,
+        "sensor_errors": status.sensor_errors,
+    })
+}
+
+/// Broker connection options, with the bridge's last will.
+#[must_use]
+pub fn options(client_id: &str, host: &str, port: u16) -> MqttOptions {
+    let mut options = MqttOptions::new(client_id, host, port);
+    options
+        .set_keep_alive(Duration::from_secs(30))
+        .set_last_will(LastWill::new(
+            BRIDGE_AVAILABILITY,
+            OFFLINE,
+            QoS::AtLeastOnce,
+            true,
+        ));
+    options
+}
+
+/// Publishes units' discovery, availability, and readings.
+///
+/// Publishes are queued without blocking, so a lost broker never stalls
+/// polling; discovery is replayed by [`Publisher::connected`].
+pub struct Publisher {
+    client: MqttClient,
+    prefix: String,
+    /// Units announced so far.
+    units: Mutex<BTreeSet<u32>>,
+}
+
+impl Publisher {
+    /// Publish through `client` under a discovery prefix.
+    #[must_use]
+    pub fn new(client: MqttClient, prefix: &str) -> Self {
+        Self {
+            client,
+            prefix: prefix.to_owned(),
+            units: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Announce the bridge and every known unit after (re)connecting.
+    ///
+    /// # Errors
+    /// Returns a [`ClientError`] if the publish queue is full or closed.
+    pub fn connected(&self) -> Result<(), ClientError> {
+        self.send(BRIDGE_AVAILABILITY, ONLINE, true)?;
+        let units = self
+            .units
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        units
+            .into_iter()
+            .try_for_each(|serial| self.announce(serial))
+    }
+
+    /// Publish a unit's readings, announcing it first if new.
+    ///
+    /// # Errors
+    /// Returns a [`ClientError`] if the publish queue is full or closed.
+    pub fn publish(&self, status: &Status, m: &Measurement) -> Result<(), ClientError> {
+        let new = self
+            .units
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(status.serial);
+        if new {
+            self.announce(status.serial)?;
+        }
+        self.send(
+            &state_topic(status.serial),
+            &state(status, m).to_string(),
+            false,
+        )
+    }
+
+    /// Mark a unit unavailable; it is announced again when it next reports.
+    ///
+    /// # Errors
+    /// Returns a [`ClientError`] if the publish queue is full or closed.
+    pub fn offline(&self, serial: u32) -> Result<(), ClientError> {
+        self.units
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&serial);
+        self.send(&availability_topic(serial), OFFLINE, true)
+    }
+
+    /// Publish a unit's discovery configs and mark it available.
+    fn announce(&self, serial: u32) -> Result<(), ClientError> {
+        for (topic, config) in discovery(&self.prefix, serial) {
+            self.send(&topic, &config.to_string(), true)?;
+        }
+        self.send(&availability_topic(serial), ONLINE, true)
+    }
+
+    fn send(&self, topic: &str, payload: &str, retain: bool) -> Result<(), ClientError> {
+        self.client
+            .try_publish(topic, QoS::AtLeastOnce, retain, payload.as_bytes())
+    }
+}
+
+/// Poll a unit until it fails, publishing each measurement.
+///
+/// Publish failures are passed to `report` without interrupting polling.
+/// Returns the unit's serial number, if it answered, with the failure.
+pub fn bridge<P: Read + Write>(
+    client: &mut Client<P>,
+    publisher: &Publisher,
+    mut report: impl FnMut(ClientError),
+) -> (Option<u32>, client::Error) {
+    let mut serial = None;
+    loop {
+        let status = match client.status() {
+            Ok(status) => status,
+            Err(e) => return (serial, e),
+        };
+        serial = Some(status.serial);
+
+        match client.poll_measurement() {
+            Ok(Some(m)) => {
+                if let Err(e) = publisher.publish(&status, &m) {
+                    report(e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => return (serial, e),
+        }
+    }
+}
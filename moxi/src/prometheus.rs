@@ -0,0 +1,251 @@
+//! Prometheus exporter: unit readings served as text exposition over HTTP.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use moxi_protocol::{Measurement, Status};
+
+use crate::client::{Client, Error};
+
+/// Exposition format content type.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Time allowed for a scrape request to arrive.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Latest state of one unit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sample {
+    /// Whether the last poll succeeded.
+    pub up: bool,
+    pub status: Option<Status>,
+    pub measurement: Option<Measurement>,
+}
+
+/// Latest samples, keyed by serial port path.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    units: Mutex<BTreeMap<String, Sample>>,
+}
+
+impl Metrics {
+    /// Build an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the sample for the unit on `port`.
+    pub fn update(&self, port: &str, f: impl FnOnce(&mut Sample)) {
+        let mut units = self.units.lock().unwrap_or_else(PoisonError::into_inner);
+        f(units.entry(port.to_owned()).or_default());
+    }
+
+    /// Mark the unit on `port` unreachable.
+    pub fn down(&self, port: &str) {
+        self.update(port, |sample| sample.up = false);
+    }
+}
+
+/// Text exposition of every unit that has reported its serial number.
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let samples: Vec<_> = self
+            .units
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .filter_map(|s| s.status.map(|status| (status, *s)))
+            .collect();
+
+        for family in &FAMILIES {
+            writeln!(f, "# HELP {} {}", family.name, family.help)?;
+            writeln!(f, "# TYPE {} {}", family.name, family.kind)?;
+            for (status, sample) in &samples {
+                let serial = status.serial;
+                for (label, value) in (family.series)(status, sample) {
+                    match label {
+                        Some((key, label)) => writeln!(
+                            f,
+                            "{}{{serial=\"{serial:08x}\",{key}=\"{label}\"}} {value}",
+                            family.name
+                        )?,
+                        None => writeln!(f, "{}{{serial=\"{serial:08x}\"}} {value}", family.name)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Extra label and value of one series.
+type Series = (Option<(&'static str, &'static str)>, String);
+
+/// Metric family, with series per unit.
+struct Family {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    series: fn(&Status, &Sample) -> Vec<Series>,
+}
+
+/// Exported metric families.
+const FAMILIES: [Family; 9] = [
+    Family {
+        name: "moxi_up",
+        kind: "gauge",
+        help: "Whether the last poll succeeded.",
+        series: |_, s| vec![(None, flag(s.up))],
+    },
+    Family {
+        name: "moxi_co2_ppm",
+        kind: "gauge",
+        help: "CO2 concentration (ppm).",
+        series: |_, s| {
+            s.measurement
+                .map(|m| (None, m.co2_ppm.to_string()))
+                .into_iter()
+                .collect()
+        },
+    },
+    Family {
+        name: "moxi_temperature_celsius",
+        kind: "gauge",
+        help: "Temperature (C).",
+        series: |_, s| {
+            s.measurement
+                .map(|m| (None, m.temp_c.to_string()))
+                .into_iter()
+                .collect()
+        },
+    },
+    Family {
+        name: "moxi_humidity_percent",
+        kind: "gauge",
+        help: "Relative humidity (%).",
+        series: |_, s| {
+            s.measurement
+                .map(|m| (None, m.humidity.to_string()))
+                .into_iter()
+                .collect()
+        },
+    },
+    Family {
+        name: "moxi_pressure_hpa",
+        kind: "gauge",
+        help: "Barometric pressure (hPa).",
+        series: |_, s| {
+            s.measurement
+                .and_then(|m| m.hpa)
+                .map(|hpa| (None, hpa.to_string()))
+                .into_iter()
+                .collect()
+        },
+    },
+    Family {
+        name: "moxi_sensor_ok",
+        kind: "gauge",
+        help: "Whether a sensor has reported.",
+        series: |status, _| {
+            vec![
+                (Some(("sensor", "co2")), flag(status.co2_ok)),
+                (Some(("sensor", "pressure")), flag(status.pressure_ok)),
+            ]
+        },
+    },
+    Family {
+        name: "moxi_sensor_errors_total",
+        kind: "counter",
+        help: "Failed sensor transactions since power on.",
+        series: |status, _| vec![(None, status.sensor_errors.to_string())],
+    },
+    Family {
+        name: "moxi_clock_set",
+        kind: "gauge",
+        help: "Whether the unit's wall clock is set.",
+        series: |status, _| vec![(None, flag(status.clock_set))],
+    },
+    Family {
+        name: "moxi_uptime_seconds",
+        kind: "gauge",
+        help: "Time since power on.",
+        series: |status, _| {
+            vec![(
+                None,
+                Duration::from_millis(status.uptime_ms)
+                    .as_secs_f64()
+                    .to_string(),
+            )]
+        },
+    },
+];
+
+/// Sample value of a flag.
+fn flag(value: bool) -> String {
+    u8::from(value).to_string()
+}
+
+/// Poll a unit once: refresh its status and wait for a measurement.
+///
+/// # Errors
+/// Returns an [`Error`] if the unit does not answer.
+pub fn poll<P: Read + Write>(
+    client: &mut Client<P>,
+    port: &str,
+    metrics: &Metrics,
+) -> Result<(), Error> {
+    let status = client.status()?;
+    metrics.update(port, |sample| {
+        sample.up = true;
+        sample.status = Some(status);
+    });
+
+    if let Some(m) = client.poll_measurement()? {
+        metrics.update(port, |sample| sample.measurement = Some(m));
+    }
+    Ok(())
+}
+
+/// Answer scrapes on `listener` until it fails.
+///
+/// # Errors
+/// Returns an [`io::Error`] if accepting connections fails.
+pub fn serve(listener: &TcpListener, metrics: &Metrics) -> io::Result<()> {
+    for stream in listener.incoming() {
+        if let Err(e) = respond(&stream?, metrics) {
+            eprintln!("scrape failed: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Answer one HTTP request: `/metrics` or 404.
+fn respond(mut stream: &TcpStream, metrics: &Metrics) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Drain headers
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = match path {
+        "/metrics" => ("200 OK", metrics.to_string()),
+        _ => ("404 Not Found", String::from("not found\n")),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
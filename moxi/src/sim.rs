@@ -0,0 +1,169 @@
+//! Simulated unit, standing in for a device in tests and demos.
+
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+use moxi_protocol::{
+    self as protocol, Config, Decoder, FRAME_MAX, Frame, LOG_CHUNK_RECORDS, LogChunk, Measurement,
+    Message, NackReason, PowerMode, Status, Timestamp,
+};
+
+/// Simulated unit state.
+#[derive(Clone, Debug)]
+pub struct Unit {
+    pub status: Status,
+    pub config: Config,
+    /// Latest readings, streamed periodically.
+    pub measurement: Measurement,
+    /// Logged records, or `None` for a unit without a log.
+    pub log: Option<Vec<Measurement>>,
+    /// Recalibration references received (ppm).
+    pub calibrations: Vec<u16>,
+    /// Wall clock set by the host (ms since the Unix epoch).
+    pub unix_ms: Option<u64>,
+    seq: u16,
+}
+
+impl Unit {
+    /// Build a healthy unit with plausible readings and an empty log.
+    #[must_use]
+    pub const fn new(serial: u32) -> Self {
+        Self {
+            status: Status {
+                serial,
+                uptime_ms: 0,
+                clock_set: false,
+                co2_ok: true,
+                pressure_ok: true,
+                sensor_errors: 0,
+            },
+            config: Config {
+                co2_alarm_ppm: 1000,
+                power_mode: PowerMode::High,
+                temp_offset_c: 2.95,
+                unit_id: 1,
+            },
+            measurement: Measurement {
+                timestamp: Timestamp {
+                    uptime_ms: 0,
+                    unix_ms: None,
+                },
+                co2_ppm: 612.0,
+                humidity: 41.5,
+                temp_c: 21.25,
+                hpa: Some(1013.2),
+            },
+            log: Some(Vec::new()),
+            calibrations: Vec::new(),
+            unix_ms: None,
+            seq: 0,
+        }
+    }
+
+    /// Reply to one request.
+    pub fn handle(&mut self, request: Frame) -> Frame {
+        let Frame { seq, message } = request;
+        let message = match message {
+            Message::GetStatus => Message::Status(Status {
+                clock_set: self.unix_ms.is_some(),
+                ..self.status
+            }),
+            Message::GetConfig => Message::Config(self.config),
+            Message::SetConfig(config) => {
+                self.config = config;
+                Message::Ack(seq)
+            }
+            Message::SetTime(unix_ms) => {
+                self.unix_ms = Some(unix_ms);
+                Message::Ack(seq)
+            }
+            Message::GetLog(offset) => {
+                self.log
+                    .as_ref()
+                    .map_or(Message::Nack(seq, NackReason::Unsupported), |log| {
+                        Message::LogChunk(LogChunk {
+                            offset,
+                            records: usize::try_from(offset)
+                                .ok()
+                                .and_then(|start| log.get(start..))
+                                .unwrap_or_default()
+                                .iter()
+                                .take(LOG_CHUNK_RECORDS)
+                                .copied()
+                                .collect(),
+                        })
+                    })
+            }
+            Message::Calibrate(ppm) => {
+                self.calibrations.push(ppm);
+                Message::Ack(seq)
+            }
+            _ => Message::Nack(seq, NackReason::Unsupported),
+        };
+        Frame { seq, message }
+    }
+
+    /// Serve requests on `port` and stream a measurement every `interval`,
+    /// until the port closes or fails.
+    ///
+    /// The port's read timeout should be shorter than `interval`.
+    pub fn serve(&mut self, mut port: impl Read + Write, interval: Duration) {
+        let start = Instant::now();
+        let mut streamed = start;
+        let mut decoder = Decoder::new();
+        let mut chunk = [0; 64];
+        loop {
+            let len = match port.read(&mut chunk) {
+                Ok(0) => return,
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => 0,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => return,
+            };
+
+            let uptime_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+            self.status.uptime_ms = uptime_ms;
+            for &byte in chunk.get(..len).unwrap_or_default() {
+                let reply = match decoder.feed(byte) {
+                    Some(Ok(request)) => self.handle(request),
+                    Some(Err(_)) => Frame {
+                        seq: 0,
+                        message: Message::Nack(0, NackReason::Malformed),
+                    },
+                    None => continue,
+                };
+                if send(&mut port, &reply).is_err() {
+                    return;
+                }
+            }
+
+            if streamed.elapsed() >= interval {
+                streamed = Instant::now();
+                self.seq = self.seq.wrapping_add(1);
+                let measurement = Measurement {
+                    timestamp: Timestamp {
+                        uptime_ms,
+                        unix_ms: self.unix_ms,
+                    },
+                    ..self.measurement
+                };
+                let frame = Frame {
+                    seq: self.seq,
+                    message: Message::Measurement(measurement),
+                };
+                if send(&mut port, &frame).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Encode and write one frame.
+fn send(port: &mut impl Write, frame: &Frame) -> io::Result<()> {
+    let mut encoded = [0; FRAME_MAX];
+    let len = protocol::encode(frame, &mut encoded)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}")))?;
+    port.write_all(encoded.get(..len).unwrap_or_default())?;
+    port.flush()
+}
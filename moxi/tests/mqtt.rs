@@ -0,0 +1,239 @@
+//! Discovery payload tests, plus bridge tests against a throwaway mosquitto
+//! broker, ignored unless run with `cargo test -- --ignored` and `mosquitto`
+//! installed.
+
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+    use std::process::{Child, Command, Stdio};
+    use std::sync::Arc;
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use moxi::Client;
+    use moxi::mqtt::{self, BRIDGE_AVAILABILITY, DISCOVERY_PREFIX, Publisher};
+    use moxi::protocol::{Measurement, Status, Timestamp};
+    use moxi::sim::Unit;
+    use rumqttc::{Event, MqttOptions, Packet, QoS};
+    use serde_json::{Value, json};
+    use serialport::{SerialPort, TTYPort};
+
+    const SERIAL: u32 = 0x1234_abcd;
+    const CO2_CONFIG: &str = "homeassistant/sensor/moxi_1234abcd/co2/config";
+
+    /// Broker process, killed on drop.
+    struct Broker {
+        child: Child,
+        port: u16,
+    }
+
+    impl Broker {
+        /// Start mosquitto on a free local port.
+        fn start() -> Self {
+            let port = TcpListener::bind("127.0.0.1:0")
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port();
+            Self::start_on(port)
+        }
+
+        fn start_on(port: u16) -> Self {
+            let child = Command::new("mosquitto")
+                .args(["-p", &port.to_string()])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("mosquitto not installed");
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while TcpStream::connect(("127.0.0.1", port)).is_err() {
+                assert!(Instant::now() < deadline, "mosquitto did not start");
+                thread::sleep(Duration::from_millis(20));
+            }
+            Self { child, port }
+        }
+    }
+
+    impl Drop for Broker {
+        fn drop(&mut self) {
+            // Fails if it already exited, which mustn't panic while unwinding
+            if let Err(e) = self.child.kill() {
+                eprintln!("mosquitto kill failed: {e}");
+            }
+            if let Err(e) = self.child.wait() {
+                eprintln!("mosquitto wait failed: {e}");
+            }
+        }
+    }
+
+    fn options(id: &str, port: u16) -> MqttOptions {
+        let mut options = MqttOptions::new(id, "127.0.0.1", port);
+        options.set_keep_alive(Duration::from_secs(5));
+        options
+    }
+
+    /// Subscribe to everything, resubscribing after reconnects.
+    fn subscribe(port: u16) -> Receiver<(String, String)> {
+        let (client, mut connection) = rumqttc::Client::new(options("subscriber", port), 16);
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        client.subscribe("#", QoS::AtLeastOnce).unwrap();
+                    }
+                    Ok(Event::Incoming(Packet::Publish(p))) => {
+                        let payload = String::from_utf8_lossy(&p.payload).into_owned();
+                        if tx.send((p.topic, payload)).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => thread::sleep(Duration::from_millis(100)),
+                }
+            }
+        });
+        rx
+    }
+
+    /// Bridge a simulated unit to the broker, as `moxi-mqtt` does.
+    fn bridge(port: u16) {
+        let (client, mut connection) =
+            rumqttc::Client::new(mqtt::options("bridge", "127.0.0.1", port), 64);
+        let publisher = Arc::new(Publisher::new(client, DISCOVERY_PREFIX));
+
+        let driver = Arc::clone(&publisher);
+        thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => driver.connected().unwrap(),
+                    Ok(_) => {}
+                    Err(_) => thread::sleep(Duration::from_millis(100)),
+                }
+            }
+        });
+
+        let (mut host, mut device) = TTYPort::pair().unwrap();
+        host.set_timeout(Duration::from_secs(2)).unwrap();
+        device.set_timeout(Duration::from_millis(10)).unwrap();
+        let mut unit = Unit::new(SERIAL);
+        thread::spawn(move || unit.serve(device, Duration::from_millis(100)));
+        thread::spawn(move || mqtt::bridge(&mut Client::new(host), &publisher, drop));
+    }
+
+    /// Wait for a message on `topic`.
+    fn expect(rx: &Receiver<(String, String)>, topic: &str) -> String {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let (received, payload) = rx
+                .recv_timeout(remaining)
+                .unwrap_or_else(|_| panic!("no message on {topic}"));
+            if received == topic {
+                return payload;
+            }
+        }
+    }
+
+    #[test]
+    fn co2_discovery() {
+        let configs = mqtt::discovery("homeassistant", SERIAL);
+        assert_eq!(configs.len(), 5);
+
+        let (topic, config) = &configs[0];
+        assert_eq!(topic, CO2_CONFIG);
+        assert_eq!(config["unique_id"], "moxi_1234abcd_co2");
+        assert_eq!(config["device_class"], "carbon_dioxide");
+        assert_eq!(config["unit_of_measurement"], "ppm");
+        assert_eq!(config["state_class"], "measurement");
+        assert_eq!(config["state_topic"], "moxi/1234abcd/state");
+        assert_eq!(config["value_template"], "{{ value_json.co2 }}");
+        assert_eq!(config["device"]["identifiers"], json!(["moxi_1234abcd"]));
+        assert_eq!(config["availability"][0]["topic"], BRIDGE_AVAILABILITY);
+        assert_eq!(
+            config["availability"][1]["topic"],
+            "moxi/1234abcd/availability"
+        );
+    }
+
+    #[test]
+    fn diagnostic_discovery() {
+        let configs = mqtt::discovery("ha", SERIAL);
+        let (topic, config) = configs
+            .iter()
+            .find(|(topic, _)| topic.contains("sensor_errors"))
+            .unwrap();
+        assert_eq!(topic, "ha/sensor/moxi_1234abcd/sensor_errors/config");
+        assert_eq!(config["state_class"], "total_increasing");
+        assert_eq!(config["entity_category"], "diagnostic");
+        assert_eq!(config.get("device_class"), None);
+        assert_eq!(config.get("unit_of_measurement"), None);
+    }
+
+    #[test]
+    fn state_without_pressure() {
+        let status = Unit::new(SERIAL).status;
+        let m = Measurement {
+            timestamp: Timestamp {
+                uptime_ms: 0,
+                unix_ms: None,
+            },
+            co2_ppm: 900.0,
+            humidity: 50.5,
+            temp_c: 19.75,
+            hpa: None,
+        };
+        assert_eq!(
+            mqtt::state(
+                &Status {
+                    sensor_errors: 2,
+                    ..status
+                },
+                &m
+            ),
+            json!({
+                "co2": 900.0,
+                "temperature": 19.75,
+                "humidity": 50.5,
+                "pressure": Value::Null,
+                "sensor_errors": 2,
+            })
+        );
+    }
+
+    #[test]
+    #[ignore = "needs mosquitto"]
+    fn publishes_discovery_and_state() {
+        let broker = Broker::start();
+        let rx = subscribe(broker.port);
+        bridge(broker.port);
+
+        assert_eq!(expect(&rx, BRIDGE_AVAILABILITY), "online");
+        let config: Value = serde_json::from_str(&expect(&rx, CO2_CONFIG)).unwrap();
+        assert_eq!(config["device_class"], "carbon_dioxide");
+        assert_eq!(expect(&rx, "moxi/1234abcd/availability"), "online");
+        let state: Value = serde_json::from_str(&expect(&rx, "moxi/1234abcd/state")).unwrap();
+        assert_eq!(state["co2"], 612.0);
+    }
+
+    #[test]
+    #[ignore = "needs mosquitto"]
+    fn reannounces_after_broker_restart() {
+        let broker = Broker::start();
+        let port = broker.port;
+        let rx = subscribe(port);
+        bridge(port);
+        expect(&rx, CO2_CONFIG);
+
+        // Restarted broker has lost the retained discovery configs
+        drop(broker);
+        thread::sleep(Duration::from_millis(200));
+        while rx.try_recv().is_ok() {}
+        let _broker = Broker::start_on(port);
+        expect(&rx, CO2_CONFIG);
+        expect(&rx, "moxi/1234abcd/state");
+    }
+}
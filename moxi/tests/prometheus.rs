@@ -0,0 +1,95 @@
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use moxi::Client;
+    use moxi::prometheus::{self, Metrics};
+    use moxi::sim::Unit;
+    use serialport::{SerialPort, TTYPort};
+
+    /// Serve `unit` on a pseudo-terminal, returning a client for it.
+    fn connect(mut unit: Unit) -> Client<TTYPort> {
+        let (mut host, mut device) = TTYPort::pair().unwrap();
+        host.set_timeout(Duration::from_secs(2)).unwrap();
+        device.set_timeout(Duration::from_millis(10)).unwrap();
+        thread::spawn(move || unit.serve(device, Duration::from_millis(50)));
+        Client::new(host)
+    }
+
+    /// Serve `metrics` on an ephemeral localhost port.
+    fn listen(metrics: &Arc<Metrics>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let metrics = Arc::clone(metrics);
+        thread::spawn(move || prometheus::serve(&listener, &metrics));
+        addr
+    }
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn scrape_units() {
+        let metrics = Arc::new(Metrics::new());
+        let addr = listen(&metrics);
+
+        let mut first = Unit::new(0x1234_abcd);
+        first.status.sensor_errors = 3;
+        let mut second = Unit::new(0x0000_0042);
+        second.measurement.hpa = None;
+        second.status.pressure_ok = false;
+
+        prometheus::poll(&mut connect(first), "/dev/first", &metrics).unwrap();
+        prometheus::poll(&mut connect(second), "/dev/second", &metrics).unwrap();
+
+        let response = get(addr, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4"));
+        for line in [
+            "# TYPE moxi_co2_ppm gauge",
+            "moxi_up{serial=\"1234abcd\"} 1",
+            "moxi_co2_ppm{serial=\"1234abcd\"} 612",
+            "moxi_temperature_celsius{serial=\"1234abcd\"} 21.25",
+            "moxi_humidity_percent{serial=\"1234abcd\"} 41.5",
+            "moxi_pressure_hpa{serial=\"1234abcd\"} 1013.2",
+            "moxi_sensor_ok{serial=\"1234abcd\",sensor=\"pressure\"} 1",
+            "# TYPE moxi_sensor_errors_total counter",
+            "moxi_sensor_errors_total{serial=\"1234abcd\"} 3",
+            "moxi_co2_ppm{serial=\"00000042\"} 612",
+            "moxi_sensor_ok{serial=\"00000042\",sensor=\"pressure\"} 0",
+        ] {
+            assert!(response.contains(&format!("{line}\n")), "missing {line}");
+        }
+        assert!(!response.contains("moxi_pressure_hpa{serial=\"00000042\"}"));
+    }
+
+    #[test]
+    fn unreachable_unit_is_down() {
+        let metrics = Arc::new(Metrics::new());
+        let addr = listen(&metrics);
+
+        prometheus::poll(&mut connect(Unit::new(7)), "/dev/unit", &metrics).unwrap();
+        metrics.down("/dev/unit");
+
+        let response = get(addr, "/metrics");
+        assert!(response.contains("moxi_up{serial=\"00000007\"} 0\n"));
+        // Last readings are kept
+        assert!(response.contains("moxi_co2_ppm{serial=\"00000007\"} 612\n"));
+    }
+
+    #[test]
+    fn unknown_path() {
+        let addr = listen(&Arc::new(Metrics::new()));
+        assert!(get(addr, "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use std::thread::{self, JoinHandle};
+    use std::time::Duration;
+
+    use moxi::export::{self, CSV_HEADER, Format};
+    use moxi::protocol::{Config, Measurement, NackReason, PowerMode, Timestamp};
+    use moxi::sim::Unit;
+    use moxi::{Client, Error};
+    use serialport::{SerialPort, TTYPort};
+
+    const SERIAL: u32 = 0x1234_abcd;
+
+    /// Serve `unit` on one end of a pseudo-terminal, returning a client on the
+    /// other and the unit's final state once the client hangs up.
+    fn connect(mut unit: Unit, interval: Duration) -> (Client<TTYPort>, JoinHandle<Unit>) {
+        let (mut host, mut device) = TTYPort::pair().unwrap();
+        host.set_timeout(Duration::from_secs(2)).unwrap();
+        device.set_timeout(Duration::from_millis(10)).unwrap();
+        let handle = thread::spawn(move || {
+            unit.serve(device, interval);
+            unit
+        });
+        (Client::new(host), handle)
+    }
+
+    const fn record(uptime_ms: u64, hpa: Option<f32>) -> Measurement {
+        Measurement {
+            timestamp: Timestamp {
+                uptime_ms,
+                unix_ms: None,
+            },
+            co2_ppm: 800.0,
+            humidity: 45.5,
+            temp_c: 22.25,
+            hpa,
+        }
+    }
+
+    #[test]
+    fn status_and_time() {
+        let (mut client, unit) = connect(Unit::new(SERIAL), Duration::MAX);
+        let status = client.status().unwrap();
+        assert_eq!(status.serial, SERIAL);
+        assert!(!status.clock_set);
+
+        client.set_time(1_700_000_000_000).unwrap();
+        assert!(client.status().unwrap().clock_set);
+
+        drop(client);
+        assert_eq!(unit.join().unwrap().unix_ms, Some(1_700_000_000_000));
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let (mut client, unit) = connect(Unit::new(SERIAL), Duration::MAX);
+        let config = Config {
+            co2_alarm_ppm: 1400,
+            power_mode: PowerMode::Low,
+            temp_offset_c: 4.0,
+            unit_id: 7,
+        };
+        client.set_config(config).unwrap();
+        assert_eq!(client.config().unwrap(), config);
+
+        drop(client);
+        assert_eq!(unit.join().unwrap().config, config);
+    }
+
+    #[test]
+    fn calibrate() {
+        let (mut client, unit) = connect(Unit::new(SERIAL), Duration::MAX);
+        client.calibrate(420).unwrap();
+
+        drop(client);
+        assert_eq!(unit.join().unwrap().calibrations, [420]);
+    }
+
+    #[test]
+    fn log_download_spans_chunks() {
+        let records: Vec<_> = (0..5).map(|i| record(i * 5_000, Some(1000.5))).collect();
+        let mut unit = Unit::new(SERIAL);
+        unit.log = Some(records.clone());
+        let (mut client, _unit) = connect(unit, Duration::MAX);
+
+        assert_eq!(client.log().unwrap(), records);
+    }
+
+    #[test]
+    fn log_unsupported() {
+        let mut unit = Unit::new(SERIAL);
+        unit.log = None;
+        let (mut client, _unit) = connect(unit, Duration::MAX);
+
+        assert!(matches!(
+            client.log(),
+            Err(Error::Nack(NackReason::Unsupported))
+        ));
+    }
+
+    #[test]
+    #[expect(clippy::float_cmp, reason = "values are exact and representable")]
+    fn replies_skip_streamed_measurements() {
+        let (mut client, _unit) = connect(Unit::new(SERIAL), Duration::ZERO);
+        assert_eq!(client.measurement().unwrap().co2_ppm, 612.0);
+        for _ in 0..10 {
+            assert_eq!(client.status().unwrap().serial, SERIAL);
+        }
+    }
+
+    #[test]
+    fn poll_measurement_times_out_quietly() {
+        let (mut host, mut device) = TTYPort::pair().unwrap();
+        host.set_timeout(Duration::from_millis(100)).unwrap();
+        device.set_timeout(Duration::from_millis(10)).unwrap();
+        thread::spawn(move || Unit::new(SERIAL).serve(device, Duration::MAX));
+        let mut client = Client::new(host);
+        assert!(client.poll_measurement().unwrap().is_none());
+        assert_eq!(client.status().unwrap().serial, SERIAL);
+    }
+
+    #[test]
+    fn export_csv() {
+        let records = [record(0, Some(1000.5)), record(5_000, None)];
+        let mut out = Vec::new();
+        export::write(Format::Csv, &records, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{CSV_HEADER}\n0,,800,45.5,22.25,1000.5\n5000,,800,45.5,22.25,\n")
+        );
+    }
+
+    #[test]
+    fn export_json() {
+        let records = [record(0, None)];
+        let mut out = Vec::new();
+        export::write(Format::Json, &records, &mut out).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value[0]["timestamp"]["uptime_ms"], 0);
+        assert_eq!(value[0]["co2_ppm"], 800.0);
+        assert_eq!(value[0]["hpa"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,238 @@
+//! Binary serial protocol shared by the firmware and host tools.
+//!
+//! Each frame is a postcard-encoded [`Frame`] followed by its CRC-16
+//! (little-endian), COBS-encoded and terminated by a zero byte.
+#![no_std]
+
+use heapless::Vec;
+use serde::{Deserialize, Serialize};
+
+/// Largest encoded frame, including COBS overhead and delimiter.
+pub const FRAME_MAX: usize = 128;
+
+/// Log records per chunk.
+pub const LOG_CHUNK_RECORDS: usize = 2;
+
+/// Largest postcard payload (frame bytes before the CRC).
+const PAYLOAD_MAX: usize = FRAME_MAX - 1 - cobs::max_encoding_overhead(FRAME_MAX) - 2;
+
+/// Frame delimiter.
+pub const DELIMITER: u8 = 0x00;
+
+/// Codec failures.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Frame does not fit the buffer.
+    Overflow,
+    /// Malformed COBS framing.
+    Cobs,
+    /// CRC mismatch.
+    Crc,
+    /// Payload is not a valid frame.
+    Deserialize,
+}
+
+/// Sequenced message.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame {
+    /// Sender's sequence number, echoed in acks and replies.
+    pub seq: u16,
+    pub message: Message,
+}
+
+/// Protocol messages.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Message {
+    /// Device: latest readings.
+    Measurement(Measurement),
+    /// Host: request a [`Message::Status`].
+    GetStatus,
+    /// Device: unit status.
+    Status(Status),
+    /// Host: request a [`Message::Config`].
+    GetConfig,
+    /// Device: current configuration.
+    Config(Config),
+    /// Host: replace the configuration.
+    SetConfig(Config),
+    /// Host: set the wall clock (ms since the Unix epoch).
+    SetTime(u64),
+    /// Host: request log records starting at an offset.
+    GetLog(u32),
+    /// Device: log records starting at an offset, empty past the end.
+    LogChunk(LogChunk),
+    /// Device: request with the given sequence number succeeded.
+    Ack(u16),
+    /// Device: request with the given sequence number failed.
+    Nack(u16, NackReason),
+    /// Host: force CO2 recalibration against a reference level (ppm).
+    Calibrate(u16),
+}
+
+/// Why a request was refused.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NackReason {
+    /// Frame failed to decode.
+    Malformed,
+    /// Message is not a request.
+    Unsupported,
+    /// Value out of range.
+    Invalid,
+}
+
+/// Point in time of a reading.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    pub uptime_ms: u64,
+    pub unix_ms: Option<u64>,
+}
+
+/// Sensor readings.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Measurement {
+    pub timestamp: Timestamp,
+    pub co2_ppm: f32,
+    pub humidity: f32,
+    pub temp_c: f32,
+    pub hpa: Option<f32>,
+}
+
+/// Unit identity and health.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Status {
+    /// FICR serial number.
+    pub serial: u32,
+    pub uptime_ms: u64,
+    pub clock_set: bool,
+    pub co2_ok: bool,
+    pub pressure_ok: bool,
+    /// Failed sensor transactions since power on.
+    pub sensor_errors: u32,
+}
+
+/// Sensor polling power mode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerMode {
+    High,
+    Low,
+}
+
+/// Adjustable unit configuration.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    pub co2_alarm_ppm: u16,
+    pub power_mode: PowerMode,
+    pub temp_offset_c: f32,
+    pub unit_id: u8,
+}
+
+/// Block of log records.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LogChunk {
+    /// Index of the first record.
+    pub offset: u32,
+    pub records: Vec<Measurement, LOG_CHUNK_RECORDS>,
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial 0xFFFF).
+#[must_use]
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            }
+        })
+    })
+}
+
+/// Encode a frame into `out`, including the trailing delimiter.
+///
+/// # Errors
+/// Returns [`Error::Overflow`] if the frame does not fit.
+pub fn encode(frame: &Frame, out: &mut [u8]) -> Result<usize, Error> {
+    let mut payload = [0; PAYLOAD_MAX + 2];
+    let used = postcard::to_slice(frame, &mut payload)
+        .ok()
+        .ok_or(Error::Overflow)?
+        .len();
+    let crc = crc16(payload.get(..used).ok_or(Error::Overflow)?).to_le_bytes();
+    payload
+        .get_mut(used..used + 2)
+        .ok_or(Error::Overflow)?
+        .copy_from_slice(&crc);
+
+    let len = cobs::try_encode(payload.get(..used + 2).ok_or(Error::Overflow)?, out)
+        .ok()
+        .ok_or(Error::Overflow)?;
+    *out.get_mut(len).ok_or(Error::Overflow)? = DELIMITER;
+    Ok(len + 1)
+}
+
+/// Decode one COBS-encoded frame (without delimiter), in place.
+///
+/// # Errors
+/// Returns an [`Error`] for bad framing, CRC, or payload.
+pub fn decode(encoded: &mut [u8]) -> Result<Frame, Error> {
+    let len = cobs::decode_in_place(encoded).ok().ok_or(Error::Cobs)?;
+    let (payload, crc) = encoded
+        .get(..len)
+        .and_then(<[u8]>::split_last_chunk::<2>)
+        .ok_or(Error::Cobs)?;
+    if crc16(payload) != u16::from_le_bytes(*crc) {
+        return Err(Error::Crc);
+    }
+    postcard::from_bytes(payload).ok().ok_or(Error::Deserialize)
+}
+
+/// Accumulates received bytes into frames.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buf: Vec<u8, FRAME_MAX>,
+    overflowed: bool,
+}
+
+impl Decoder {
+    /// Build an empty decoder.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            overflowed: false,
+        }
+    }
+
+    /// Feed one byte, returning a result at each frame delimiter.
+    pub fn feed(&mut self, byte: u8) -> Option<Result<Frame, Error>> {
+        if byte != DELIMITER {
+            if self.buf.push(byte).is_err() {
+                self.overflowed = true;
+            }
+            return None;
+        }
+
+        let result = if self.overflowed {
+            Err(Error::Overflow)
+        } else if self.buf.is_empty() {
+            // Leading or repeated delimiters resynchronize
+            return None;
+        } else {
+            decode(&mut self.buf)
+        };
+        self.buf.clear();
+        self.overflowed = false;
+        Some(result)
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod tests {
+    use moxi_protocol::{
+        Config, DELIMITER, Decoder, Error, FRAME_MAX, Frame, LogChunk, Measurement, Message,
+        NackReason, PowerMode, Timestamp, crc16, decode, encode,
+    };
+
+    const MEASUREMENT: Measurement = Measurement {
+        timestamp: Timestamp {
+            uptime_ms: 12_345,
+            unix_ms: Some(1_700_000_000_000),
+        },
+        co2_ppm: 842.0,
+        humidity: 41.7,
+        temp_c: 22.5,
+        hpa: Some(1013.25),
+    };
+
+    fn encoded(frame: &Frame) -> Vec<u8> {
+        let mut out = [0; FRAME_MAX];
+        let len = encode(frame, &mut out).unwrap();
+        out[..len].to_vec()
+    }
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+    }
+
+    #[test]
+    fn ack_wire_format() {
+        // postcard: seq varint 7, variant 9 (Ack), seq varint 7; CRC LE; COBS
+        let frame = Frame {
+            seq: 7,
+            message: Message::Ack(7),
+        };
+        let crc = crc16(&[0x07, 0x09, 0x07]).to_le_bytes();
+        let mut expected = vec![0x06, 0x07, 0x09, 0x07];
+        expected.extend_from_slice(&crc);
+        expected.push(DELIMITER);
+        assert!(crc.iter().all(|&b| b != 0));
+        assert_eq!(encoded(&frame), expected);
+    }
+
+    #[test]
+    fn round_trip_every_message() {
+        let config = Config {
+            co2_alarm_ppm: 1200,
+            power_mode: PowerMode::Low,
+            temp_offset_c: 2.95,
+            unit_id: 3,
+        };
+        let messages = [
+            Message::Measurement(MEASUREMENT),
+            Message::GetStatus,
+            Message::GetConfig,
+            Message::Config(config),
+            Message::SetConfig(config),
+            Message::SetTime(1_700_000_000_000),
+            Message::GetLog(40),
+            Message::LogChunk(LogChunk {
+                offset: 40,
+                records: [MEASUREMENT, MEASUREMENT].into_iter().collect(),
+            }),
+            Message::Ack(u16::MAX),
+            Message::Nack(3, NackReason::Invalid),
+            Message::Calibrate(420),
+        ];
+
+        for (seq, message) in (0..).zip(messages) {
+            let frame = Frame { seq, message };
+            let mut wire = encoded(&frame);
+            assert_eq!(wire.pop(), Some(DELIMITER));
+            assert!(!wire.contains(&DELIMITER));
+            assert_eq!(decode(&mut wire), Ok(frame));
+        }
+    }
+
+    #[test]
+    fn corrupted_frames() {
+        let frame = Frame {
+            seq: 1,
+            message: Message::Measurement(MEASUREMENT),
+        };
+        let mut wire = encoded(&frame);
+        wire.pop();
+        wire[4] ^= 0x01;
+        assert_eq!(decode(&mut wire), Err(Error::Crc));
+
+        let mut truncated = vec![0x05, 0x01];
+        assert_eq!(decode(&mut truncated), Err(Error::Cobs));
+    }
+
+    #[test]
+    fn decoder_stream() {
+        let first = Frame {
+            seq: 1,
+            message: Message::GetConfig,
+        };
+        let second = Frame {
+            seq: 2,
+            message: Message::SetTime(5),
+        };
+
+        let mut stream = vec![DELIMITER, 0x42];
+        stream.push(DELIMITER);
+        stream.extend(encoded(&first));
+        stream.extend(encoded(&second));
+
+        let mut decoder = Decoder::new();
+        let results: Vec<_> = stream.into_iter().filter_map(|b| decoder.feed(b)).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], Err(Error::Cobs));
+        assert_eq!(results[1], Ok(first));
+        assert_eq!(results[2], Ok(second));
+    }
+
+    #[test]
+    fn decoder_overflow_resyncs() {
+        let mut decoder = Decoder::new();
+        for _ in 0..=FRAME_MAX {
+            assert_eq!(decoder.feed(0x01), None);
+        }
+        assert_eq!(decoder.feed(DELIMITER), Some(Err(Error::Overflow)));
+
+        let frame = Frame {
+            seq: 9,
+            message: Message::GetStatus,
+        };
+        let result = encoded(&frame).into_iter().find_map(|b| decoder.feed(b));
+        assert_eq!(result, Some(Ok(frame)));
+    }
+}
@@ -0,0 +1,212 @@
+//! Simulated sensor feeds.
+//!
+//! Feeds are given as specs on the command line:
+//!
+//! | Spec                         | Readings                                    |
+//! |------------------------------|---------------------------------------------|
+//! | `constant:<r>`               | fixed                                       |
+//! | `ramp:<r>:<r>:<secs>`        | linear over `<secs>`, then held at the end  |
+//! | `trace:<file>`               | recorded `moxi log` CSV, last record held   |
+//!
+//! where `<r>` is `<co2 ppm>,<humidity %>,<temperature °C>`, each in range
+//! or `-` where no sensor provides it (e.g. `-,-,21` without a CO2 sensor).
+
+use std::str::FromStr;
+use std::time::Duration;
+use std::{fmt, fs, io};
+
+use rustymicrobit_moxi::ui::Readings;
+use rustymicrobit_moxi::units::{Celsius, Fahrenheit, Ppm, RelativeHumidity};
+
+/// `moxi log` CSV header.
+pub const TRACE_HEADER: &str = "uptime_ms,unix_ms,co2_ppm,humidity,temp_c,hpa";
+
+/// Feed errors.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Malformed feed spec.
+    Spec(String),
+    /// Malformed trace, at a 1-based line.
+    Trace(usize),
+    /// Trace without records.
+    EmptyTrace,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Spec(spec) => write!(f, "invalid feed {spec:?} (see --help)"),
+            Self::Trace(line) => write!(f, "invalid trace record on line {line}"),
+            Self::EmptyTrace => write!(f, "trace has no records"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Simulated readings over time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Feed {
+    Constant(Readings),
+    Ramp {
+        from: Readings,
+        to: Readings,
+        period: Duration,
+    },
+    /// Records with their offsets from the first, in order.
+    Trace(Vec<(Duration, Readings)>),
+}
+
+impl Feed {
+    /// Readings `elapsed` into the simulation.
+    #[must_use]
+    pub fn at(&self, elapsed: Duration) -> Readings {
+        match self {
+            Self::Constant(readings) => *readings,
+            Self::Ramp { from, to, period } => {
+                let t = if period.is_zero() {
+                    1.0
+                } else {
+                    (elapsed.as_secs_f32() / period.as_secs_f32()).min(1.0)
+                };
+                // Between readings in range, so in range; past the end, or
+                // where either is unknown, otherwise
+                let lerp = |a: Option<f32>, b: Option<f32>| Some((b? - a?).mul_add(t, a?));
+                Readings {
+                    co2: lerp(from.co2.map(Ppm::into_inner), to.co2.map(Ppm::into_inner))
+                        .and_then(|co2| Ppm::try_new(co2).ok())
+                        .or(to.co2),
+                    humidity: lerp(
+                        from.humidity.map(RelativeHumidity::into_inner),
+                        to.humidity.map(RelativeHumidity::into_inner),
+                    )
+                    .and_then(|humidity| RelativeHumidity::try_new(humidity).ok())
+                    .or(to.humidity),
+                    temp_f: lerp(
+                        from.temp_f.map(Fahrenheit::into_inner),
+                        to.temp_f.map(Fahrenheit::into_inner),
+                    )
+                    .and_then(|temp_f| Fahrenheit::try_new(temp_f).ok())
+                    .or(to.temp_f),
+                }
+            }
+            Self::Trace(records) => {
+                let next = records.partition_point(|(offset, _)| *offset <= elapsed);
+                records
+                    .get(next.saturating_sub(1))
+                    .map_or_else(idle, |(_, readings)| *readings)
+            }
+        }
+    }
+
+    /// Parse a `moxi log` CSV trace.
+    ///
+    /// # Errors
+    /// Returns [`Error::Trace`] for a malformed record, or
+    /// [`Error::EmptyTrace`] if there are none.
+    pub fn trace(csv: &str) -> Result<Self, Error> {
+        let mut records = Vec::new();
+        let mut first = None;
+        for (i, line) in csv.lines().enumerate() {
+            if line.is_empty() || (i == 0 && line == TRACE_HEADER) {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').collect();
+            let [uptime_ms, _unix_ms, co2, humidity, temp_c, _hpa] = fields.as_slice() else {
+                return Err(Error::Trace(i + 1));
+            };
+            let parse = |field: &str| field.parse::<f32>().ok().ok_or(Error::Trace(i + 1));
+            let uptime_ms: u64 = uptime_ms.parse().ok().ok_or(Error::Trace(i + 1))?;
+            let first = *first.get_or_insert(uptime_ms);
+            records.push((
+                Duration::from_millis(uptime_ms.saturating_sub(first)),
+                Readings::new(parse(co2)?, parse(humidity)?, parse(temp_c)?)
+                    .ok()
+                    .ok_or(Error::Trace(i + 1))?,
+            ));
+        }
+        if records.is_empty() {
+            return Err(Error::EmptyTrace);
+        }
+        // Keep offsets ordered across unit restarts
+        let mut latest = Duration::ZERO;
+        for (offset, _) in &mut records {
+            latest = latest.max(*offset);
+            *offset = latest;
+        }
+        Ok(Self::Trace(records))
+    }
+}
+
+/// Default readings, as a typical indoor room.
+#[expect(clippy::expect_used, reason = "constant readings are in range")]
+fn idle() -> Readings {
+    Readings::new(612.0, 41.5, 21.25).expect("idle readings in range")
+}
+
+impl Default for Feed {
+    fn default() -> Self {
+        Self::Constant(idle())
+    }
+}
+
+impl FromStr for Feed {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self, Error> {
+        let invalid = || Error::Spec(spec.to_owned());
+        let (kind, args) = spec.split_once(':').ok_or_else(invalid)?;
+        match kind {
+            "constant" => Ok(Self::Constant(readings(args).ok_or_else(invalid)?)),
+            "ramp" => {
+                let mut parts = args.split(':');
+                let (Some(from), Some(to), Some(secs), None) =
+                    (parts.next(), parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(invalid());
+                };
+                Ok(Self::Ramp {
+                    from: readings(from).ok_or_else(invalid)?,
+                    to: readings(to).ok_or_else(invalid)?,
+                    period: secs
+                        .parse()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f32(secs).ok())
+                        .ok_or_else(invalid)?,
+                })
+            }
+            "trace" => Self::trace(&fs::read_to_string(args)?),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Parse `<co2>,<rh>,<temp>`, each a value or `-`.
+fn readings(args: &str) -> Option<Readings> {
+    let mut values = args.split(',').map(|value| match value {
+        "-" => Some(None),
+        value => value.parse::<f32>().ok().map(Some),
+    });
+    let (Some(Some(co2)), Some(Some(humidity)), Some(Some(temp_c)), None) =
+        (values.next(), values.next(), values.next(), values.next())
+    else {
+        return None;
+    };
+    let temp_f = match temp_c {
+        Some(temp_c) => Some(Celsius::try_new(temp_c).ok()?.to_fahrenheit().ok()?),
+        None => None,
+    };
+    Some(Readings {
+        co2: co2.map(Ppm::try_new).transpose().ok()?,
+        humidity: humidity.map(RelativeHumidity::try_new).transpose().ok()?,
+        temp_f,
+    })
+}
@@ -0,0 +1,26 @@
+//! Host simulator for the unit's display: runs the library's dashboard, page
+//! and button logic against simulated sensor feeds, rendered as ANSI art.
+
+pub mod feed;
+pub mod render;
+
+pub use rustymicrobit_moxi::{grayscale, page, ui, units};
+
+/// Library defmt logs are discarded on the host.
+#[defmt::global_logger]
+struct Logger;
+
+// SAFETY: stateless, so acquire and release need no bookkeeping.
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {}
+    unsafe fn flush() {}
+    unsafe fn release() {}
+    unsafe fn write(_bytes: &[u8]) {}
+}
+
+defmt::timestamp!("{=u64}", 0);
+
+#[unsafe(no_mangle)]
+extern "Rust" fn _defmt_panic() -> ! {
+    std::process::abort()
+}
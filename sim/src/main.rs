@@ -0,0 +1,255 @@
+//! `moxi-sim`: run the unit's display in the terminal.
+
+use std::collections::VecDeque;
+use std::io::{self, Stdout, Write};
+use std::ops::ControlFlow;
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use moxi_sim::feed::Feed;
+use moxi_sim::grayscale::Grayscale;
+use moxi_sim::page::Pages;
+use moxi_sim::render::{self, Brightness};
+use moxi_sim::ui::{ButtonState, DASHBOARD_FRAME, GREETING, Pollutants, Readings, Screen, Ui};
+use moxi_sim::units::{Fahrenheit, Ppm, RelativeHumidity};
+use pico_args::Arguments;
+use termion::cursor::{Goto, HideCursor};
+use termion::event::Key;
+use termion::input::{Keys, TermRead};
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::{AsyncReader, clear};
+
+const USAGE: &str = "\
+Usage: moxi-sim [--feed <SPEC>] [--worst <SPEC>] [--second <SPEC>] [--speed <FACTOR>]
+
+Runs the unit's display against simulated readings. <SPEC> is one of
+  constant:<R>                 fixed readings
+  ramp:<R>:<R>:<SECS>          linear from the first to the second over <SECS>
+  trace:<FILE>                 replay of a `moxi log` CSV export
+where <R> is <CO2 ppm>,<humidity %>,<temperature C>, e.g. constant:612,41.5,21.25,
+each - if no sensor provides it, e.g. constant:-,-,21.25 without a CO2 sensor.
+
+--feed drives the unit's sensors (default constant:612,41.5,21.25), --worst
+the mesh's worst readings on the Worst page, and --second a second CO2 sensor
+on the Sensor B page (both default none; pages without a feed are skipped).
+--speed runs the feeds faster than real time.
+
+Keys: a, b: buttons A and B; l: logo; space: A and B together; q: quit.
+";
+
+/// Pending button presses before more are dropped, as on the unit.
+const BUTTON_QUEUE: usize = 3;
+
+/// Key polling interval.
+const POLL: Duration = Duration::from_millis(10);
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let mut args = Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        print!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("moxi-sim: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(mut args: Arguments) -> Result<()> {
+    let feed: Feed = args.opt_value_from_str("--feed")?.unwrap_or_default();
+    let worst: Option<Feed> = args.opt_value_from_str("--worst")?;
+    let second: Option<Feed> = args.opt_value_from_str("--second")?;
+    let speed: f64 = args.opt_value_from_str("--speed")?.unwrap_or(1.0);
+    let rest = args.finish();
+    if !rest.is_empty() {
+        return Err(format!("unexpected arguments {rest:?}").into());
+    }
+    if !(speed.is_finite() && speed > 0.0) {
+        return Err("--speed must be positive".into());
+    }
+
+    let mut terminal = Terminal::open()?;
+    simulate(&mut terminal, &feed, worst.as_ref(), second.as_ref(), speed)?;
+    // Leave the prompt below the simulator
+    write!(terminal.out, "\r\n")?;
+    Ok(())
+}
+
+/// Run the display as the unit's display task does, until quit.
+fn simulate(
+    terminal: &mut Terminal,
+    feed: &Feed,
+    worst: Option<&Feed>,
+    second: Option<&Feed>,
+    speed: f64,
+) -> io::Result<()> {
+    let start = Instant::now();
+    // Pollutants aren't fed, so their pages are skipped
+    let fitted = feed.at(Duration::ZERO);
+    let mut ui = Ui::with_pages(Pages {
+        local: fitted != Readings::default(),
+        co2: fitted.co2.is_some(),
+        mesh: worst.is_some(),
+        second: second.is_some(),
+        particulates: false,
+        gases: false,
+    });
+    let greeting = Screen::Scroll {
+        text: GREETING.try_into().unwrap_or_default(),
+        duration: None,
+    };
+    if terminal.show(&greeting, "")? == ControlFlow::Break(Stop::Quit) {
+        return Ok(());
+    }
+
+    let pollutants = Pollutants::default();
+    loop {
+        let elapsed = start.elapsed().mul_f64(speed);
+        let local = feed.at(elapsed);
+        ui.record(
+            u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+            &local,
+        );
+        let readings = ui.readings(
+            local,
+            worst.map(|f| f.at(elapsed)),
+            second.map(|f| f.at(elapsed)),
+        );
+        let line = status(ui.page().title(), &readings);
+        let screen = match terminal.buttons.pop_front() {
+            Some(button) => ui.press(button, &readings, &pollutants),
+            None => ui.dashboard(&readings, &pollutants),
+        };
+        match terminal.show(&screen, &line)? {
+            ControlFlow::Break(Stop::Quit) => return Ok(()),
+            ControlFlow::Break(Stop::Pressed) => {}
+            ControlFlow::Continue(()) => {
+                if matches!(screen, Screen::Scroll { .. }) {
+                    ui.scrolled();
+                }
+            }
+        }
+    }
+}
+
+/// Page and readings shown under the matrix, `--` where unknown.
+fn status(title: &str, readings: &Readings) -> String {
+    let known = |value: Option<f32>, decimals: usize| {
+        value.map_or_else(|| "--".to_owned(), |value| format!("{value:.decimals$}"))
+    };
+    format!(
+        "{title}: {} ppm, {} %, {} °F",
+        known(readings.co2.map(Ppm::into_inner), 0),
+        known(readings.humidity.map(RelativeHumidity::into_inner), 1),
+        known(readings.temp_f.map(Fahrenheit::into_inner), 1)
+    )
+}
+
+/// Why a screen was cut short.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Stop {
+    /// A button was pressed, interrupting it.
+    Pressed,
+    Quit,
+}
+
+/// Raw mode terminal, restored on drop.
+struct Terminal {
+    out: HideCursor<RawTerminal<Stdout>>,
+    keys: Keys<AsyncReader>,
+    buttons: VecDeque<ButtonState>,
+}
+
+impl Terminal {
+    fn open() -> io::Result<Self> {
+        let mut out = HideCursor::from(io::stdout().into_raw_mode()?);
+        write!(out, "{}", clear::All)?;
+        Ok(Self {
+            out,
+            keys: termion::async_stdin().keys(),
+            buttons: VecDeque::with_capacity(BUTTON_QUEUE),
+        })
+    }
+
+    /// Show a screen as the unit would, until done, a button press or quit.
+    fn show(&mut self, screen: &Screen, status: &str) -> io::Result<ControlFlow<Stop>> {
+        match screen {
+            Screen::Dashboard(animation) => {
+                for frame in animation {
+                    self.draw(frame, Brightness::Min, status)?;
+                    let flow = self.wait(Duration::from_millis(DASHBOARD_FRAME.as_millis()))?;
+                    if flow.is_break() {
+                        return Ok(flow);
+                    }
+                }
+                Ok(ControlFlow::Continue(()))
+            }
+            Screen::Scroll { text, duration } => {
+                let duration = duration.map(|d| Duration::from_millis(d.as_millis()));
+                let frames = render::scroll_frames(text);
+                let step = render::scroll_duration(text, duration)
+                    / u32::try_from(frames.len()).unwrap_or(u32::MAX).max(1);
+                for frame in &frames {
+                    self.draw(&Grayscale::from(*frame), Brightness::Max, status)?;
+                    let flow = self.wait(step)?;
+                    if flow.is_break() {
+                        return Ok(flow);
+                    }
+                }
+                Ok(ControlFlow::Continue(()))
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &Grayscale, brightness: Brightness, status: &str) -> io::Result<()> {
+        write!(self.out, "{}", Goto(1, 1))?;
+        for line in render::ansi(frame, brightness) {
+            write!(self.out, " {line}{}\r\n", clear::UntilNewline)?;
+        }
+        write!(
+            self.out,
+            "\r\n {status}{}\r\n a/b: buttons  l: logo  space: a+b  q: quit{}",
+            clear::UntilNewline,
+            clear::UntilNewline
+        )?;
+        self.out.flush()
+    }
+
+    /// Wait, queueing button presses and stopping early on one.
+    fn wait(&mut self, duration: Duration) -> io::Result<ControlFlow<Stop>> {
+        let deadline = Instant::now() + duration;
+        loop {
+            // Yields until no more input is buffered
+            for key in self.keys.by_ref() {
+                let button = match key? {
+                    Key::Char('a') => ButtonState::A,
+                    Key::Char('b') => ButtonState::B,
+                    Key::Char('l') => ButtonState::C,
+                    Key::Char(' ') => ButtonState::AB,
+                    Key::Char('q') | Key::Ctrl('c') | Key::Esc => {
+                        return Ok(ControlFlow::Break(Stop::Quit));
+                    }
+                    _ => continue,
+                };
+                if self.buttons.len() < BUTTON_QUEUE {
+                    self.buttons.push_back(button);
+                }
+            }
+            if !self.buttons.is_empty() {
+                return Ok(ControlFlow::Break(Stop::Pressed));
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(ControlFlow::Continue(()));
+            }
+            thread::sleep(remaining.min(POLL));
+        }
+    }
+}
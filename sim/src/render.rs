@@ -0,0 +1,152 @@
+//! LED matrix rendering: scroll text frames and ANSI art.
+
+use std::time::Duration;
+
+use rustymicrobit_moxi::bitmap::Bitmap;
+use rustymicrobit_moxi::dashboard::{LED_COLS, LED_ROWS};
+use rustymicrobit_moxi::grayscale::{Grayscale, LEVEL_MAX};
+use termion::color::{Fg, Reset, Rgb};
+
+/// One matrix frame, top to bottom.
+pub type Frame = [Bitmap; LED_ROWS];
+
+/// LED brightness, as set by the display task.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Brightness {
+    /// Dashboard.
+    Min,
+    /// Scrolled text.
+    Max,
+}
+
+/// Lit LED colours by brightness, and the unlit colour.
+const LIT_MIN: Rgb = Rgb(120, 0, 0);
+const LIT_MAX: Rgb = Rgb(255, 40, 40);
+const UNLIT: Rgb = Rgb(40, 40, 40);
+
+/// Frames scrolling `text` in from the right until it has left the matrix.
+#[must_use]
+pub fn scroll_frames(text: &str) -> Vec<Frame> {
+    // Columns top row first, in the low LED_ROWS bits
+    let mut columns = vec![0_u8; LED_COLS];
+    for c in text.chars() {
+        let glyph = glyph(c);
+        columns.extend((0..LED_COLS).map(|col| {
+            glyph.iter().fold(0, |column, row| {
+                (column << 1) | (row >> (LED_COLS - 1 - col) & 1)
+            })
+        }));
+        columns.push(0);
+    }
+    columns.extend([0; LED_COLS]);
+
+    columns
+        .windows(LED_COLS)
+        .map(|window| {
+            let mut frame = [Bitmap::empty(LED_COLS); LED_ROWS];
+            for (col, column) in window.iter().enumerate() {
+                for (row, bitmap) in frame.iter_mut().enumerate() {
+                    if column >> (LED_ROWS - 1 - row) & 1 != 0 {
+                        bitmap.set(col);
+                    }
+                }
+            }
+            frame
+        })
+        .collect()
+}
+
+/// Time to scroll `text`: `duration` if given, else the BSP's default of
+/// half a second per character.
+#[must_use]
+pub fn scroll_duration(text: &str, duration: Option<Duration>) -> Duration {
+    duration.unwrap_or_else(|| {
+        Duration::from_millis(500)
+            .saturating_mul(u32::try_from(text.chars().count()).unwrap_or(u32::MAX))
+    })
+}
+
+/// Frame as ANSI art, one line per row, each LED two cells wide and shaded
+/// by its level.
+#[must_use]
+pub fn ansi(frame: &Grayscale, brightness: Brightness) -> Vec<String> {
+    let lit = match brightness {
+        Brightness::Min => LIT_MIN,
+        Brightness::Max => LIT_MAX,
+    };
+    (0..LED_ROWS)
+        .map(|row| {
+            let mut line = String::new();
+            for col in 0..LED_COLS {
+                line += &Fg(shade(lit, frame.level(row, col))).to_string();
+                line += "██ ";
+            }
+            line += &Fg(Reset).to_string();
+            line
+        })
+        .collect()
+}
+
+/// Colour of an LED at `level`, from unlit to `lit`.
+fn shade(lit: Rgb, level: u8) -> Rgb {
+    let level = i32::from(level.min(LEVEL_MAX));
+    let channel = |unlit: u8, lit: u8| {
+        let (unlit, lit) = (i32::from(unlit), i32::from(lit));
+        u8::try_from(unlit + (lit - unlit) * level / i32::from(LEVEL_MAX)).unwrap_or(u8::MAX)
+    };
+    Rgb(
+        channel(UNLIT.0, lit.0),
+        channel(UNLIT.1, lit.1),
+        channel(UNLIT.2, lit.2),
+    )
+}
+
+/// 5x5 glyph, top row first with the leftmost LED in bit 4. Lower case is
+/// shown as upper case, and unknown characters as `?`.
+#[must_use]
+pub const fn glyph(c: char) -> [u8; LED_ROWS] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000],
+        '!' => [0b00100, 0b00100, 0b00100, 0b00000, 0b00100],
+        '%' => [0b11001, 0b11010, 0b00100, 0b01011, 0b10011],
+        '-' => [0b00000, 0b00000, 0b01110, 0b00000, 0b00000],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00100],
+        '0' => [0b01110, 0b10011, 0b10101, 0b11001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b11110, 0b00001, 0b01110, 0b10000, 0b11111],
+        '3' => [0b11110, 0b00001, 0b00110, 0b00001, 0b11110],
+        '4' => [0b10010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b11110],
+        '6' => [0b01110, 0b10000, 0b11110, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00010, 0b00100, 0b01000, 0b10000],
+        '8' => [0b01110, 0b10001, 0b01110, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b01111, 0b00001, 0b01110],
+        'A' => [0b01110, 0b10001, 0b11111, 0b10001, 0b10001],
+        'B' => [0b11110, 0b10001, 0b11110, 0b10001, 0b11110],
+        'C' => [0b01111, 0b10000, 0b10000, 0b10000, 0b01111],
+        'D' => [0b11110, 0b10001, 0b10001, 0b10001, 0b11110],
+        'E' => [0b11111, 0b10000, 0b11110, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b11110, 0b10000, 0b10000],
+        'G' => [0b01111, 0b10000, 0b10011, 0b10001, 0b01111],
+        'H' => [0b10001, 0b10001, 0b11111, 0b10001, 0b10001],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b01110],
+        'J' => [0b00111, 0b00010, 0b00010, 0b10010, 0b01100],
+        'K' => [0b10010, 0b10100, 0b11000, 0b10100, 0b10010],
+        'L' => [0b10000, 0b10000, 0b10000, 0b10000, 0b11111],
+        'M' => [0b10001, 0b11011, 0b10101, 0b10001, 0b10001],
+        'N' => [0b10001, 0b11001, 0b10101, 0b10011, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b01110],
+        'P' => [0b11110, 0b10001, 0b11110, 0b10000, 0b10000],
+        'Q' => [0b01110, 0b10001, 0b10101, 0b10010, 0b01101],
+        'R' => [0b11110, 0b10001, 0b11110, 0b10010, 0b10001],
+        'S' => [0b01111, 0b10000, 0b01110, 0b00001, 0b11110],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100],
+        'U' => [0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'V' => [0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        'W' => [0b10001, 0b10001, 0b10101, 0b11011, 0b10001],
+        'X' => [0b10001, 0b01010, 0b00100, 0b01010, 0b10001],
+        'Y' => [0b10001, 0b01010, 0b00100, 0b00100, 0b00100],
+        'Z' => [0b11111, 0b00010, 0b00100, 0b01000, 0b11111],
+        _ => [0b01110, 0b10001, 0b00110, 0b00000, 0b00100],
+    }
+}
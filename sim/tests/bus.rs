@@ -0,0 +1,186 @@
+//! I2C bus supervision and recovery.
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::convert::Infallible;
+    use core::future;
+
+    use embassy_futures::block_on;
+    use embedded_hal::delay::DelayNs;
+    use embedded_hal::digital::{ErrorType as PinErrorType, InputPin, OutputPin};
+    use embedded_hal_async::i2c::{ErrorKind, ErrorType, I2c, Operation};
+    use rustymicrobit_moxi::bus::{self, BusError, FAULTS_BEFORE_RECOVERY, Supervised};
+    use rustymicrobit_moxi::emulator::Bus;
+
+    /// Bus failing every transaction with an error, or never completing.
+    struct FaultyBus(Option<ErrorKind>);
+
+    impl ErrorType for FaultyBus {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for FaultyBus {
+        async fn transaction(
+            &mut self,
+            _address: u8,
+            _operations: &mut [Operation<'_>],
+        ) -> Result<(), ErrorKind> {
+            match self.0 {
+                Some(kind) => Err(kind),
+                None => future::pending().await,
+            }
+        }
+    }
+
+    /// Target holding SDA low until clocked a number of times.
+    struct Target {
+        scl: Cell<bool>,
+        sda_out: Cell<bool>,
+        /// SCL falling edges before the target releases SDA.
+        holding: Cell<u8>,
+        /// SDA rising edges while SCL is high.
+        stops: Cell<u8>,
+    }
+
+    impl Target {
+        fn holding(pulses: u8) -> Self {
+            Self {
+                scl: Cell::new(true),
+                sda_out: Cell::new(true),
+                holding: Cell::new(pulses),
+                stops: Cell::new(0),
+            }
+        }
+
+        fn sda(&self) -> bool {
+            self.sda_out.get() && self.holding.get() == 0
+        }
+    }
+
+    struct Scl<'a>(&'a Target);
+    struct Sda<'a>(&'a Target);
+
+    impl PinErrorType for Scl<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for Scl<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            if self.0.scl.replace(false) {
+                self.0.holding.set(self.0.holding.get().saturating_sub(1));
+            }
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.scl.set(true);
+            Ok(())
+        }
+    }
+
+    impl PinErrorType for Sda<'_> {
+        type Error = Infallible;
+    }
+
+    impl InputPin for Sda<'_> {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(self.0.sda())
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.0.sda())
+        }
+    }
+
+    impl OutputPin for Sda<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.sda_out.set(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            let was_low = !self.0.sda();
+            self.0.sda_out.set(true);
+            if was_low && self.0.sda() && self.0.scl.get() {
+                self.0.stops.set(self.0.stops.get() + 1);
+            }
+            Ok(())
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    #[test]
+    fn nacks_are_not_bus_faults() {
+        let empty = Bus::<0>::new([]);
+        let mut i2c = Supervised::new(&empty);
+        for _ in 0..=FAULTS_BEFORE_RECOVERY {
+            assert!(block_on(i2c.write(0x62, &[0])).is_err());
+        }
+        assert_eq!(i2c.faults(), 0);
+    }
+
+    #[test]
+    fn repeated_faults_request_recovery() {
+        let mut i2c = Supervised::new(FaultyBus(Some(ErrorKind::Bus)));
+        for _ in 1..FAULTS_BEFORE_RECOVERY {
+            assert_eq!(
+                block_on(i2c.write(0x62, &[0])),
+                Err(BusError::I2c(ErrorKind::Bus))
+            );
+        }
+        assert_eq!(i2c.faults(), FAULTS_BEFORE_RECOVERY - 1);
+        assert!(block_on(i2c.write(0x62, &[0])).is_err());
+        assert_eq!(i2c.faults(), 0);
+        assert!(bus::recovery_pending());
+        block_on(bus::recovery_requested());
+        assert!(!bus::recovery_pending());
+
+        // Faults during the recovery don't queue another
+        for _ in 0..=FAULTS_BEFORE_RECOVERY {
+            assert!(block_on(i2c.write(0x62, &[0])).is_err());
+        }
+        assert_eq!(i2c.faults(), 0);
+        assert!(!bus::recovery_pending());
+        bus::recovered();
+        assert!(block_on(i2c.write(0x62, &[0])).is_err());
+        assert_eq!(i2c.faults(), 1);
+    }
+
+    #[test]
+    fn stalled_transaction_times_out() {
+        let mut i2c = Supervised::new(FaultyBus(None));
+        assert_eq!(block_on(i2c.write(0x62, &[0])), Err(BusError::Timeout));
+        assert_eq!(i2c.faults(), 1);
+    }
+
+    #[test]
+    fn release_sda_clocks_then_stops() {
+        let target = Target::holding(4);
+        let released = bus::release_sda(&mut Scl(&target), &mut Sda(&target), &mut NoDelay);
+        assert_eq!(released, Ok(true));
+        assert_eq!(target.stops.get(), 1);
+        assert!(target.scl.get());
+    }
+
+    #[test]
+    fn release_sda_gives_up() {
+        let target = Target::holding(u8::MAX);
+        let released = bus::release_sda(&mut Scl(&target), &mut Sda(&target), &mut NoDelay);
+        assert_eq!(released, Ok(false));
+        assert_eq!(target.holding.get(), u8::MAX - 9);
+    }
+
+    #[test]
+    fn release_sda_idle_bus() {
+        let target = Target::holding(0);
+        let released = bus::release_sda(&mut Scl(&target), &mut Sda(&target), &mut NoDelay);
+        assert_eq!(released, Ok(true));
+        assert_eq!(target.holding.get(), 0);
+    }
+}
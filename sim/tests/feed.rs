@@ -0,0 +1,87 @@
+//! Feed spec and trace parsing tests.
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use moxi_sim::feed::{Error, Feed, TRACE_HEADER};
+    use moxi_sim::ui::Readings;
+
+    fn from() -> Readings {
+        Readings::new(400.0, 30.0, 18.0).unwrap()
+    }
+
+    fn to() -> Readings {
+        Readings::new(2000.0, 70.0, 28.0).unwrap()
+    }
+
+    #[test]
+    fn constant() {
+        let feed: Feed = "constant:400,30,18".parse().unwrap();
+        assert_eq!(feed, Feed::Constant(from()));
+        assert_eq!(feed.at(Duration::from_secs(3600)), from());
+    }
+
+    #[test]
+    fn ramp_holds_at_end() {
+        let feed: Feed = "ramp:400,30,18:2000,70,28:60".parse().unwrap();
+        assert_eq!(feed.at(Duration::ZERO), from());
+        assert_eq!(
+            feed.at(Duration::from_secs(30)),
+            Readings::new(1200.0, 50.0, 23.0).unwrap()
+        );
+        assert_eq!(feed.at(Duration::from_secs(600)), to());
+    }
+
+    #[test]
+    fn unknown_readings() {
+        let feed: Feed = "ramp:-,-,18:-,70,28:60".parse().unwrap();
+        let readings = feed.at(Duration::from_secs(30));
+        assert_eq!(readings.co2, None);
+        assert_eq!(readings.humidity, to().humidity);
+        assert_eq!(
+            readings.temp_f,
+            Readings::new(0.0, 0.0, 23.0).unwrap().temp_f
+        );
+    }
+
+    #[test]
+    fn invalid_specs() {
+        for spec in [
+            "constant",
+            "constant:400,30",
+            "constant:400,30,18,1",
+            "constant:400,30,",
+            "constant:400,130,18",
+            "ramp:400,30,18:2000,70,28",
+            "ramp:400,30,18:2000,70,28:-1",
+            "sine:400,30,18",
+        ] {
+            assert!(
+                matches!(spec.parse::<Feed>(), Err(Error::Spec(_))),
+                "{spec}"
+            );
+        }
+    }
+
+    #[test]
+    fn trace_replays_records() {
+        let csv = format!(
+            "{TRACE_HEADER}\n\
+             10000,,400,30,18,\n\
+             20000,1760000000000,2000,70,28,1013.2\n"
+        );
+        let feed = Feed::trace(&csv).unwrap();
+        assert_eq!(feed.at(Duration::ZERO), from());
+        assert_eq!(feed.at(Duration::from_millis(9999)), from());
+        assert_eq!(feed.at(Duration::from_secs(10)), to());
+        assert_eq!(feed.at(Duration::from_secs(3600)), to());
+    }
+
+    #[test]
+    fn invalid_traces() {
+        assert!(matches!(Feed::trace(TRACE_HEADER), Err(Error::EmptyTrace)));
+        let csv = format!("{TRACE_HEADER}\n10000,,400,30,18,\n20000,,high,70,28,\n");
+        assert!(matches!(Feed::trace(&csv), Err(Error::Trace(3))));
+    }
+}
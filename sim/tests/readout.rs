@@ -0,0 +1,103 @@
+//! Scrolled readout formatting tests.
+
+#[cfg(test)]
+mod tests {
+    use heapless::String;
+    use rustymicrobit_moxi::readout::{self, DEGREES_F, Fixed, Readout};
+
+    fn text(value: Fixed) -> std::string::String {
+        value.to_string()
+    }
+
+    #[test]
+    fn whole_values() {
+        assert_eq!(text(Fixed::whole(612.0)), "612");
+        assert_eq!(text(Fixed::whole(72.5)), "73");
+        assert_eq!(text(Fixed::whole(0.0)), "0");
+    }
+
+    #[test]
+    fn decimals() {
+        assert_eq!(text(Fixed::new(72.5, 1)), "72.5");
+        assert_eq!(text(Fixed::new(1013.24, 1)), "1013.2");
+        assert_eq!(text(Fixed::new(3.0, 2)), "3.00");
+        assert_eq!(text(Fixed::new(0.05, 2)), "0.05");
+        assert_eq!(text(Fixed::new(9.996, 2)), "10.00");
+    }
+
+    #[test]
+    fn decimals_clamped() {
+        assert_eq!(Fixed::new(1.5, 9), Fixed::new(1.5, readout::DECIMALS_MAX));
+        assert_eq!(text(Fixed::new(1.5, 9)), "1.500");
+    }
+
+    #[test]
+    fn signed_values() {
+        assert_eq!(text(Fixed::whole(-4.0)), "-4");
+        assert_eq!(text(Fixed::new(-3.7, 1)), "-3.7");
+        assert_eq!(text(Fixed::new(-0.5, 1)), "-0.5");
+        // Rounds to zero, unsigned
+        assert_eq!(text(Fixed::new(-0.04, 1)), "0.0");
+    }
+
+    #[test]
+    fn unshowable_values() {
+        assert_eq!(text(Fixed::whole(f32::NAN)), "--");
+        assert_eq!(text(Fixed::whole(f32::INFINITY)), "--");
+        assert_eq!(text(Fixed::whole(f32::NEG_INFINITY)), "--");
+        assert_eq!(text(Fixed::new(3.0e9, 0)), "--");
+        assert_eq!(text(Fixed::new(-3.0e6, 3)), "--");
+        assert_eq!(text(Fixed::whole(-2_000_000_000.0)), "-2000000000");
+    }
+
+    #[test]
+    fn readouts() {
+        let temp = Fixed::new(-3.7, 1);
+        assert_eq!(
+            Readout {
+                label: None,
+                value: temp,
+                units: DEGREES_F,
+            }
+            .to_string(),
+            " -3.7 F"
+        );
+        assert_eq!(
+            Readout {
+                label: Some("B"),
+                value: 612,
+                units: "ppm",
+            }
+            .to_string(),
+            " B 612 ppm"
+        );
+        assert_eq!(
+            Readout {
+                label: Some("B"),
+                value: 7,
+                units: "",
+            }
+            .to_string(),
+            " B 7"
+        );
+    }
+
+    #[test]
+    fn overflow_truncated() {
+        let text: String<8> = readout::truncated(Readout {
+            label: Some("B"),
+            value: Fixed::new(-1013.25, 2),
+            units: "hPa",
+        });
+        assert_eq!(text.as_str(), " B -1013");
+        // Never splits a multi-byte character
+        let text: String<7> = readout::truncated(Readout {
+            label: None,
+            value: Fixed::new(72.5, 1),
+            units: "°F",
+        });
+        assert_eq!(text.as_str(), " 72.5 ");
+        let text: String<20> = readout::truncated(Fixed::new(f32::NAN, 3));
+        assert_eq!(text.as_str(), "--");
+    }
+}
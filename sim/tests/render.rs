@@ -0,0 +1,86 @@
+//! Scroll text and ANSI rendering tests.
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use moxi_sim::render::{self, Brightness};
+    use rustymicrobit_moxi::bitmap::Bitmap;
+    use rustymicrobit_moxi::dashboard::{LED_COLS, LED_ROWS};
+    use rustymicrobit_moxi::grayscale::{Grayscale, LEVEL_MAX};
+
+    fn lit(frame: &render::Frame) -> Vec<String> {
+        frame
+            .iter()
+            .map(|row| {
+                (0..LED_COLS)
+                    .map(|col| if row.is_set(col) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scrolls_in_from_the_right() {
+        let frames = render::scroll_frames("1");
+        // Blank, glyph sliding in and out, blank
+        assert_eq!(frames.len(), 12);
+        assert_eq!(frames[0], [Bitmap::empty(LED_COLS); LED_ROWS]);
+        assert_eq!(
+            lit(&frames[2]),
+            [".....", "....#", ".....", ".....", "....#"]
+        );
+        assert_eq!(
+            lit(&frames[5]),
+            ["..#..", ".##..", "..#..", "..#..", ".###."]
+        );
+        assert_eq!(frames[11], [Bitmap::empty(LED_COLS); LED_ROWS]);
+    }
+
+    #[test]
+    fn lower_case_as_upper_case() {
+        assert_eq!(render::glyph('o'), render::glyph('O'));
+        assert_eq!(render::glyph('~'), render::glyph('?'));
+    }
+
+    #[test]
+    fn scroll_durations() {
+        assert_eq!(
+            render::scroll_duration(" 612 ppm", Some(Duration::from_millis(4500))),
+            Duration::from_millis(4500)
+        );
+        assert_eq!(
+            render::scroll_duration("Home", None),
+            Duration::from_secs(2)
+        );
+        // By character, not byte
+        assert_eq!(render::scroll_duration("°F", None), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn ansi_rows() {
+        let frame = Grayscale::from(render::scroll_frames("1")[5]);
+        let lines = render::ansi(&frame, Brightness::Max);
+        assert_eq!(lines.len(), LED_ROWS);
+        assert_eq!(lines[4].matches("██").count(), LED_COLS);
+        assert_ne!(
+            render::ansi(&frame, Brightness::Min),
+            render::ansi(&frame, Brightness::Max)
+        );
+    }
+
+    #[test]
+    fn ansi_shades() {
+        let mut frame = Grayscale::dark();
+        frame.set(0, 0, LEVEL_MAX);
+        frame.set(0, 1, 1);
+        frame.set(0, 2, LEVEL_MAX);
+        let lines = render::ansi(&frame, Brightness::Max);
+        let cells: Vec<&str> = lines[0].split("██ ").collect();
+        // Dim differs from both lit and unlit
+        assert_eq!(cells[0], cells[2]);
+        assert_ne!(cells[1], cells[0]);
+        assert_ne!(cells[1], cells[3]);
+        assert_eq!(cells[3], cells[4]);
+    }
+}
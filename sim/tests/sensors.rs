@@ -0,0 +1,489 @@
+//! Sensor drivers and control against emulated I2C sensors.
+
+#[cfg(test)]
+mod tests {
+    use embassy_futures::block_on;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::mutex::Mutex;
+    use embassy_time::Timer;
+    use embedded_hal_async::i2c::I2c;
+    use rustymicrobit_moxi::detect::{self, Part};
+    use rustymicrobit_moxi::diagnostics;
+    use rustymicrobit_moxi::emulator::bmp581::{self, EmulatedBmp581};
+    use rustymicrobit_moxi::emulator::pmsa003i::{self, EmulatedPmsa003i};
+    use rustymicrobit_moxi::emulator::scd4x::{self, EmulatedScd4x, Mode, command};
+    use rustymicrobit_moxi::emulator::sgp4x::{self, EmulatedSgp4x, Variant};
+    use rustymicrobit_moxi::emulator::sps30::{self, EmulatedSps30};
+    use rustymicrobit_moxi::emulator::tca9548a::{self, EmulatedTca9548a};
+    use rustymicrobit_moxi::emulator::{Bus, Device, Faults, Nack};
+    use rustymicrobit_moxi::measurement::{Co2Measurement, PressureMeasurement};
+    use rustymicrobit_moxi::mux::{Channel, MuxedI2cDevice};
+    use rustymicrobit_moxi::power::PowerMode;
+    use rustymicrobit_moxi::sensirion::crc8;
+    use rustymicrobit_moxi::sensor::bmp581::Bmp581Sensor;
+    use rustymicrobit_moxi::sensor::pmsa003i::Pmsa003iSensor;
+    use rustymicrobit_moxi::sensor::scd4x::Scd4xSensor;
+    use rustymicrobit_moxi::sensor::sgp4x::Sgp4xSensor;
+    use rustymicrobit_moxi::sensor::sps30::{OUTPUT_FORMAT_FLOAT, Sps30Sensor};
+    use rustymicrobit_moxi::sensor::{Co2Control, GasControl, PmControl, PressureControl};
+    use rustymicrobit_moxi::settings::Settings;
+    use rustymicrobit_moxi::units::RelativeHumidity;
+
+    const SETTINGS: Settings = Settings::new(PowerMode::High);
+
+    fn started(scd: &EmulatedScd4x) -> Co2Control<Scd4xSensor<&EmulatedScd4x>> {
+        let control = block_on(Co2Control::start(Scd4xSensor::new(scd), SETTINGS)).unwrap();
+        scd.take_commands();
+        control
+    }
+
+    #[test]
+    fn crc8_datasheet_example() {
+        assert_eq!(crc8([0xbe, 0xef]), 0x92);
+    }
+
+    #[test]
+    fn scd4x_responds_with_crcs() {
+        let mut scd = &EmulatedScd4x::new();
+        let command = command::GET_SERIAL_NUMBER.to_be_bytes();
+        block_on(scd.write(scd4x::ADDRESS, &command)).unwrap();
+        block_on(Timer::after_millis(1));
+        let mut response = [0; 9];
+        block_on(scd.read(scd4x::ADDRESS, &mut response)).unwrap();
+        for word in response.chunks(3) {
+            assert_eq!(crc8([word[0], word[1]]), word[2]);
+        }
+
+        // Argument words must carry a valid CRC
+        let [hi, lo] = command::SET_TEMPERATURE_OFFSET.to_be_bytes();
+        let bad_crc = [hi, lo, 0x05, 0x5d, 0x00];
+        assert_eq!(
+            block_on(scd.write(scd4x::ADDRESS, &bad_crc)),
+            Err(Nack::Data)
+        );
+    }
+
+    #[test]
+    fn scd4x_busy_while_executing() {
+        let mut scd = &EmulatedScd4x::new();
+        let stop = command::STOP_PERIODIC_MEASUREMENT.to_be_bytes();
+        block_on(scd.write(scd4x::ADDRESS, &stop)).unwrap();
+        assert_eq!(
+            block_on(scd.write(scd4x::ADDRESS, &stop)),
+            Err(Nack::Address)
+        );
+    }
+
+    #[test]
+    fn scd4x_periodic_rejects_settings() {
+        let mut scd = &EmulatedScd4x::new();
+        let start = command::START_PERIODIC_MEASUREMENT.to_be_bytes();
+        block_on(scd.write(scd4x::ADDRESS, &start)).unwrap();
+        assert_eq!(scd.mode(), Mode::Periodic);
+        let get_offset = command::GET_TEMPERATURE_OFFSET.to_be_bytes();
+        assert_eq!(
+            block_on(scd.write(scd4x::ADDRESS, &get_offset)),
+            Err(Nack::Data)
+        );
+    }
+
+    #[test]
+    fn co2_start_stops_then_measures() {
+        let scd = EmulatedScd4x::new();
+        block_on(Co2Control::start(Scd4xSensor::new(&scd), SETTINGS)).unwrap();
+        let commands = scd.take_commands();
+        assert_eq!(commands.first(), Some(&command::STOP_PERIODIC_MEASUREMENT));
+        assert_eq!(commands.last(), Some(&command::START_PERIODIC_MEASUREMENT));
+        assert_eq!(scd.mode(), Mode::Periodic);
+        assert!((scd.temperature_offset() - SETTINGS.temp_offset_c).abs() < 0.01);
+    }
+
+    #[test]
+    fn co2_start_at_altitude() {
+        let scd = EmulatedScd4x::new();
+        block_on(Co2Control::start_at_altitude(
+            Scd4xSensor::new(&scd),
+            SETTINGS,
+            350,
+        ))
+        .unwrap();
+        assert_eq!(scd.altitude(), 350);
+        assert_eq!(scd.mode(), Mode::Periodic);
+    }
+
+    #[test]
+    fn co2_init_retries_nacks() {
+        let scd = EmulatedScd4x::new();
+        scd.inject(Faults {
+            nacks: 2,
+            ..Faults::default()
+        });
+        started(&scd);
+        assert_eq!(scd.faults(), Faults::default());
+        assert_eq!(scd.mode(), Mode::Periodic);
+    }
+
+    #[test]
+    fn co2_init_gives_up() {
+        let scd = EmulatedScd4x::new();
+        scd.inject(Faults {
+            nacks: u8::MAX,
+            ..Faults::default()
+        });
+        assert!(block_on(Co2Control::start(Scd4xSensor::new(&scd), SETTINGS)).is_err());
+        assert_eq!(scd.mode(), Mode::Idle);
+    }
+
+    #[test]
+    fn co2_poll_reads_and_compensates_pressure() {
+        let scd = EmulatedScd4x::new();
+        let mut control = started(&scd);
+        scd.set_ambient(1250, 55.0, 24.0);
+        scd.sample();
+
+        let m_pa = PressureMeasurement::new(95_000.0, 21.0).unwrap();
+        let m_co2 = block_on(control.poll(Some(&m_pa), None, SETTINGS))
+            .unwrap()
+            .unwrap();
+        assert!((m_co2.co2.into_inner() - 1250.0).abs() < f32::EPSILON);
+        assert!((m_co2.humidity.into_inner() - 55.0).abs() < 0.01);
+        assert!((m_co2.temp_c.into_inner() - (24.0 - SETTINGS.temp_offset_c)).abs() < 0.01);
+        assert_eq!(scd.ambient_pressure(), 950);
+        let commands = scd.take_commands();
+        assert!(commands.contains(&command::READ_MEASUREMENT));
+        assert_eq!(commands.last(), Some(&command::AMBIENT_PRESSURE));
+    }
+
+    #[test]
+    fn co2_poll_stuck_data_ready() {
+        let scd = EmulatedScd4x::new();
+        let mut control = started(&scd);
+        scd.inject(Faults {
+            stuck_data_ready: true,
+            ..Faults::default()
+        });
+        scd.sample();
+        assert!(
+            block_on(control.poll(None, None, SETTINGS))
+                .unwrap()
+                .is_none()
+        );
+        assert!(!scd.take_commands().contains(&command::READ_MEASUREMENT));
+    }
+
+    #[test]
+    fn co2_poll_counts_bad_crc() {
+        let scd = EmulatedScd4x::new();
+        let mut control = started(&scd);
+        scd.sample();
+        scd.inject(Faults {
+            bad_crcs: 1,
+            ..Faults::default()
+        });
+        let errors = diagnostics::sensor_errors();
+        assert!(
+            block_on(control.poll(None, None, SETTINGS))
+                .unwrap()
+                .is_none()
+        );
+        assert!(diagnostics::sensor_errors() > errors);
+    }
+
+    #[test]
+    fn co2_poll_calibrates_while_stopped() {
+        let scd = EmulatedScd4x::new();
+        let mut control = started(&scd);
+        scd.sample();
+        block_on(control.poll(None, Some(420), SETTINGS)).unwrap();
+        let commands = scd.take_commands();
+        let frc = commands
+            .iter()
+            .position(|&c| c == command::PERFORM_FORCED_RECALIBRATION)
+            .unwrap();
+        assert_eq!(commands[frc - 1], command::STOP_PERIODIC_MEASUREMENT);
+        assert_eq!(commands.last(), Some(&command::START_PERIODIC_MEASUREMENT));
+    }
+
+    #[test]
+    fn bmp581_resets_and_identifies() {
+        let mut bmp = &EmulatedBmp581::new();
+        let mut id = [0];
+        block_on(bmp.write_read(bmp581::ADDRESS, &[bmp581::register::CHIP_ID], &mut id)).unwrap();
+        assert_eq!(id, [bmp581::CHIP_ID]);
+
+        // Power-on reset flag clears on read
+        let mut int_status = [0];
+        let int_status_reg = [bmp581::register::INT_STATUS];
+        block_on(bmp.write_read(bmp581::ADDRESS, &int_status_reg, &mut int_status)).unwrap();
+        assert_eq!(bmp.register(bmp581::register::INT_STATUS), 0);
+        assert_ne!(int_status, [0]);
+    }
+
+    #[test]
+    fn pressure_reads_emulated_bmp581() {
+        let bmp = EmulatedBmp581::new();
+        bmp.set_ambient(98_765.0, 19.5);
+        let mut control = block_on(PressureControl::start(Bmp581Sensor::new(
+            &bmp,
+            bmp581::ADDRESS,
+        )))
+        .unwrap();
+        let m_pa = block_on(control.poll()).unwrap();
+        assert!((m_pa.hpa.into_inner() - 987.65).abs() < 0.01);
+        assert!((m_pa.temp_c.into_inner() - 19.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn pressure_out_of_range_skipped() {
+        let bmp = EmulatedBmp581::new();
+        bmp.set_ambient(25_000.0, 19.5);
+        let mut control = block_on(PressureControl::start(Bmp581Sensor::new(
+            &bmp,
+            bmp581::ADDRESS,
+        )))
+        .unwrap();
+        let errors = diagnostics::sensor_errors();
+        assert!(block_on(control.poll()).is_none());
+        assert!(diagnostics::sensor_errors() > errors);
+    }
+
+    #[test]
+    fn pressure_nack_skipped() {
+        let bmp = EmulatedBmp581::new();
+        let mut control = block_on(PressureControl::start(Bmp581Sensor::new(
+            &bmp,
+            bmp581::ADDRESS,
+        )))
+        .unwrap();
+        bmp.inject(Faults {
+            nacks: 1,
+            ..Faults::default()
+        });
+        assert!(block_on(control.poll()).is_none());
+        assert!(block_on(control.poll()).is_some());
+    }
+
+    #[test]
+    fn pm_reads_emulated_pmsa003i() {
+        let pms = EmulatedPmsa003i::new();
+        pms.set_ambient(7, 40, 95);
+        let mut control = block_on(PmControl::start(Pmsa003iSensor::new(&pms))).unwrap();
+        let m_pm = block_on(control.poll(RelativeHumidity::try_new(55.0).ok())).unwrap();
+        assert_eq!((m_pm.pm1, m_pm.pm2_5, m_pm.pm10), (7.0, 40.0, 95.0));
+        assert!(!m_pm.unreliable);
+
+        // Saturated air
+        assert!(
+            block_on(control.poll(RelativeHumidity::try_new(95.0).ok()))
+                .unwrap()
+                .unreliable
+        );
+    }
+
+    #[test]
+    fn pm_counts_bad_checksum() {
+        let pms = EmulatedPmsa003i::new();
+        let mut control = block_on(PmControl::start(Pmsa003iSensor::new(&pms))).unwrap();
+        pms.inject(Faults {
+            bad_crcs: 1,
+            ..Faults::default()
+        });
+        let errors = diagnostics::sensor_errors();
+        assert!(block_on(control.poll(None)).is_none());
+        assert!(diagnostics::sensor_errors() > errors);
+        assert!(block_on(control.poll(None)).is_some());
+    }
+
+    #[test]
+    fn pm_reads_emulated_sps30() {
+        let sps = EmulatedSps30::new();
+        sps.set_ambient(11.0, 22.5, 30.0, 33.0);
+        let mut control = block_on(PmControl::start(Sps30Sensor::new(&sps))).unwrap();
+        assert!(sps.measuring());
+
+        // Zero until the first measurement
+        let m_pm = block_on(control.poll(None)).unwrap();
+        assert_eq!((m_pm.pm1, m_pm.pm2_5, m_pm.pm10), (0.0, 0.0, 0.0));
+        sps.sample();
+        let m_pm = block_on(control.poll(None)).unwrap();
+        assert_eq!((m_pm.pm1, m_pm.pm2_5, m_pm.pm10), (11.0, 22.5, 33.0));
+    }
+
+    #[test]
+    fn pm_restarts_running_sps30() {
+        let sps = EmulatedSps30::new();
+        let mut i2c = &sps;
+        let [hi, lo] = sps30::command::START_MEASUREMENT.to_be_bytes();
+        let [a, b] = OUTPUT_FORMAT_FLOAT.to_be_bytes();
+        let start = [hi, lo, a, b, crc8([a, b])];
+        block_on(i2c.write(sps30::ADDRESS, &start)).unwrap();
+        block_on(Timer::after_millis(20));
+        assert!(sps.measuring());
+        block_on(PmControl::start(Sps30Sensor::new(&sps))).unwrap();
+        assert!(sps.measuring());
+    }
+
+    #[test]
+    fn scan_identifies_both() {
+        let scd = EmulatedScd4x::new();
+        let bmp = EmulatedBmp581::new();
+        let inventory = block_on(detect::scan(&mut &Bus::new([&scd, &bmp])));
+        assert_eq!(inventory.responders, [bmp581::ADDRESS, scd4x::ADDRESS]);
+        assert_eq!(inventory.address(Part::Scd4x), Some(scd4x::ADDRESS));
+        assert_eq!(inventory.address(Part::Bmp581), Some(bmp581::ADDRESS));
+        assert_eq!(inventory.summary(), " SCD4X BMP581");
+        assert_eq!(scd.mode(), Mode::Idle);
+    }
+
+    #[test]
+    fn scan_identifies_bmp581_sdo_low() {
+        let bmp = EmulatedBmp581::at(bmp581::ADDRESS_SDO_LOW);
+        let inventory = block_on(detect::scan(&mut &Bus::new([&bmp])));
+        assert_eq!(
+            inventory.address(Part::Bmp581),
+            Some(bmp581::ADDRESS_SDO_LOW)
+        );
+        assert!(!inventory.contains(Part::Scd4x));
+    }
+
+    #[test]
+    fn scan_stops_running_scd4x() {
+        let scd = EmulatedScd4x::new();
+        let mut bus = &Bus::new([&scd]);
+        let start = command::START_PERIODIC_MEASUREMENT.to_be_bytes();
+        block_on(bus.write(scd4x::ADDRESS, &start)).unwrap();
+        let inventory = block_on(detect::scan(&mut bus));
+        assert!(inventory.contains(Part::Scd4x));
+        assert!(!inventory.contains(Part::Bmp581));
+        assert_eq!(scd.mode(), Mode::Idle);
+    }
+
+    #[test]
+    fn gas_reads_emulated_sgp40() {
+        let sgp = EmulatedSgp4x::new(Variant::Sgp40);
+        let mut control = block_on(GasControl::start(Sgp4xSensor::new(&sgp))).unwrap();
+        assert_eq!(sgp.conditioned(), 0);
+
+        // Defaults (50 %RH, 25 C) compensated for until the CO2 sensor has
+        // measured; indices read 0 while the algorithm settles
+        let m_gas = block_on(control.poll(None)).unwrap();
+        assert_eq!((m_gas.voc_index, m_gas.nox_index), (0, None));
+        assert_eq!(sgp.compensation(), Some((0x7fff, 0x6666)));
+        let m_co2 = Co2Measurement::new(600, 25.0, 42.5).unwrap();
+        block_on(control.poll(Some(&m_co2))).unwrap();
+        assert_eq!(sgp.compensation(), Some((0x3fff, 0x7fff)));
+    }
+
+    #[test]
+    fn gas_conditions_sgp41_before_nox() {
+        let sgp = EmulatedSgp4x::new(Variant::Sgp41);
+        let mut control = block_on(GasControl::start(Sgp4xSensor::new(&sgp))).unwrap();
+        assert_eq!(sgp.conditioned(), 1);
+
+        // NOx reads 0 while conditioning, as while settling
+        for _ in 1..10 {
+            let m_gas = block_on(control.poll(None)).unwrap();
+            assert_eq!(m_gas.nox_index, Some(0));
+        }
+        assert_eq!(sgp.conditioned(), 10);
+        let m_gas = block_on(control.poll(None)).unwrap();
+        assert_eq!(m_gas.nox_index, Some(0));
+        assert_eq!(sgp.conditioned(), 10);
+    }
+
+    #[test]
+    fn gas_counts_bad_crc() {
+        let sgp = EmulatedSgp4x::new(Variant::Sgp40);
+        let mut control = block_on(GasControl::start(Sgp4xSensor::new(&sgp))).unwrap();
+        sgp.inject(Faults {
+            bad_crcs: 1,
+            ..Faults::default()
+        });
+        let errors = diagnostics::sensor_errors();
+        assert!(block_on(control.poll(None)).is_none());
+        assert!(diagnostics::sensor_errors() > errors);
+        assert!(block_on(control.poll(None)).is_some());
+    }
+
+    #[test]
+    fn scan_counts_unknown_devices() {
+        // A BMP581 answering at an SCD4X address is not mistaken for one
+        let bmp = EmulatedBmp581::at(scd4x::ADDRESS);
+        let inventory = block_on(detect::scan(&mut &Bus::new([&bmp])));
+        assert_eq!(inventory.responders, [scd4x::ADDRESS]);
+        assert!(inventory.parts.is_empty());
+        assert_eq!(inventory.summary(), " +1 unknown");
+
+        let empty = block_on(detect::scan(&mut &Bus::<0>::new([])));
+        assert_eq!(empty.summary(), " No sensors");
+    }
+
+    #[test]
+    fn scan_identifies_pm_sensors() {
+        let pms = EmulatedPmsa003i::new();
+        let sps = EmulatedSps30::new();
+        let inventory = block_on(detect::scan(&mut &Bus::new([&sps, &pms])));
+        assert_eq!(inventory.responders, [pmsa003i::ADDRESS, sps30::ADDRESS]);
+        assert_eq!(inventory.address(Part::Pmsa003i), Some(pmsa003i::ADDRESS));
+        assert_eq!(inventory.address(Part::Sps30), Some(sps30::ADDRESS));
+        assert_eq!(inventory.summary(), " PMSA003I SPS30");
+        assert!(!sps.measuring());
+    }
+
+    #[test]
+    fn scan_identifies_sgp4x() {
+        let sgp = EmulatedSgp4x::new(Variant::Sgp41);
+        let inventory = block_on(detect::scan(&mut &Bus::new([&sgp])));
+        assert_eq!(inventory.address(Part::Sgp4x), Some(sgp4x::ADDRESS));
+        assert_eq!(inventory.summary(), " SGP4X");
+        assert_eq!(sgp.conditioned(), 0);
+    }
+
+    #[test]
+    fn scan_identifies_muxed_sensors() {
+        let (scd_a, scd_b) = (EmulatedScd4x::new(), EmulatedScd4x::new());
+        let bmp = EmulatedBmp581::new();
+        let (a, b, none): (&[&dyn Device], &[&dyn Device], _) = (&[&scd_a], &[&scd_b], &[]);
+        let mux = EmulatedTca9548a::new([a, none, b, none, none, none, none, none]);
+        let inventory = block_on(detect::scan(&mut &Bus::new([&bmp, &mux])));
+        assert_eq!(inventory.mux, Some(tca9548a::ADDRESS));
+        assert_eq!(inventory.responders, [bmp581::ADDRESS, tca9548a::ADDRESS]);
+        let channels: Vec<_> = inventory
+            .parts
+            .iter()
+            .map(|found| (found.part, found.channel.map(|channel| channel.channel)))
+            .collect();
+        assert_eq!(
+            channels,
+            [
+                (Part::Bmp581, None),
+                (Part::Scd4x, Some(0)),
+                (Part::Scd4x, Some(2))
+            ]
+        );
+        assert_eq!(inventory.summary(), " MUX BMP581 SCD4X:0 SCD4X:2");
+        assert_eq!(mux.control(), 0);
+    }
+
+    #[test]
+    fn muxed_device_selects_its_channel() {
+        let (scd_a, scd_b) = (EmulatedScd4x::new(), EmulatedScd4x::new());
+        let (a, b, none): (&[&dyn Device], &[&dyn Device], _) = (&[&scd_a], &[&scd_b], &[]);
+        let mux = EmulatedTca9548a::new([a, b, none, none, none, none, none, none]);
+        let devices = Bus::new([&mux]);
+        let bus = Mutex::<NoopRawMutex, _>::new(&devices);
+        let channel = Channel::new(tca9548a::ADDRESS, 1);
+        let i2c = MuxedI2cDevice::new(&bus, channel);
+        block_on(Co2Control::start(Scd4xSensor::new(i2c), SETTINGS)).unwrap();
+        assert_eq!(scd_a.mode(), Mode::Idle);
+        assert_eq!(scd_b.mode(), Mode::Periodic);
+        assert_eq!(mux.control(), 0);
+
+        // Deselected, the channel's sensor is off the main bus
+        let mut main = MuxedI2cDevice::new(&bus, None);
+        assert_eq!(
+            block_on(main.read(scd4x::ADDRESS, &mut [0])),
+            Err(Nack::Address)
+        );
+    }
+}
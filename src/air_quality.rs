@@ -0,0 +1,188 @@
+//! Air quality categories: the US EPA AQI for particulates, comfort bands for
+//! CO2, and an overall verdict combining them.
+
+use core::ops::RangeInclusive;
+
+/// AQI reported for concentrations past the highest breakpoint.
+pub const AQI_MAX: u16 = 500;
+
+/// AQI breakpoint: a concentration range, in the pollutant's truncated
+/// units, and the index range it maps onto.
+struct Breakpoint {
+    concentration: RangeInclusive<u32>,
+    index: RangeInclusive<u16>,
+}
+
+const fn breakpoint(concentration: RangeInclusive<u32>, index: RangeInclusive<u16>) -> Breakpoint {
+    Breakpoint {
+        concentration,
+        index,
+    }
+}
+
+/// PM2.5 breakpoints (0.1 ug/m3), per the EPA's 2024 revision.
+const PM2_5_BREAKPOINTS: [Breakpoint; 6] = [
+    breakpoint(0..=90, 0..=50),
+    breakpoint(91..=354, 51..=100),
+    breakpoint(355..=554, 101..=150),
+    breakpoint(555..=1254, 151..=200),
+    breakpoint(1255..=2254, 201..=300),
+    breakpoint(2255..=3254, 301..=500),
+];
+
+/// PM10 breakpoints (ug/m3), per the EPA's 2024 revision.
+const PM10_BREAKPOINTS: [Breakpoint; 6] = [
+    breakpoint(0..=54, 0..=50),
+    breakpoint(55..=154, 51..=100),
+    breakpoint(155..=254, 101..=150),
+    breakpoint(255..=354, 151..=200),
+    breakpoint(355..=424, 201..=300),
+    breakpoint(425..=604, 301..=500),
+];
+
+/// CO2 comfort band upper bounds (ppm, exclusive): excellent near outdoor
+/// levels, bad past the common 1500 ppm ventilation limit.
+pub const CO2_COMFORT_PPM: [u16; 4] = [600, 800, 1000, 1500];
+
+/// Interpolate the index of a truncated concentration within its
+/// breakpoint, rounding to the nearest integer.
+fn index(breakpoints: &[Breakpoint], concentration: u32) -> u16 {
+    let Some(Breakpoint {
+        concentration: c_range,
+        index: i_range,
+    }) = breakpoints
+        .iter()
+        .find(|breakpoint| concentration <= *breakpoint.concentration.end())
+    else {
+        return AQI_MAX;
+    };
+    let (c_lo, c_hi) = (*c_range.start(), *c_range.end());
+    let (i_lo, i_hi) = (u32::from(*i_range.start()), u32::from(*i_range.end()));
+    let above = concentration.saturating_sub(c_lo);
+    let span = c_hi - c_lo;
+    let index = i_lo + ((i_hi - i_lo) * above * 2 + span) / (span * 2);
+    u16::try_from(index).map_or(AQI_MAX, |index| index.min(AQI_MAX))
+}
+
+/// Truncate a concentration to `scale` units per ug/m3; negative and NaN
+/// concentrations read 0.
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "truncation is the EPA's rounding, and the cast saturates"
+)]
+fn truncated(ugm3: f32, scale: f32) -> u32 {
+    (ugm3 * scale) as u32
+}
+
+/// AQI of a PM2.5 concentration (ug/m3), truncated to 0.1 ug/m3.
+#[must_use]
+pub fn pm2_5_aqi(ugm3: f32) -> u16 {
+    index(&PM2_5_BREAKPOINTS, truncated(ugm3, 10.0))
+}
+
+/// AQI of a PM10 concentration (ug/m3), truncated to 1 ug/m3.
+#[must_use]
+pub fn pm10_aqi(ugm3: f32) -> u16 {
+    index(&PM10_BREAKPOINTS, truncated(ugm3, 1.0))
+}
+
+/// Overall AQI of PM2.5 and PM10 concentrations (ug/m3): the higher of the
+/// two.
+#[must_use]
+pub fn aqi(pm2_5: f32, pm10: f32) -> u16 {
+    pm2_5_aqi(pm2_5).max(pm10_aqi(pm10))
+}
+
+/// EPA AQI category.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, defmt::Format)]
+pub enum AqiCategory {
+    Good,
+    Moderate,
+    UnhealthyForSensitiveGroups,
+    Unhealthy,
+    VeryUnhealthy,
+    Hazardous,
+}
+
+impl AqiCategory {
+    /// Category of an AQI.
+    #[must_use]
+    pub const fn of(aqi: u16) -> Self {
+        match aqi {
+            0..=50 => Self::Good,
+            51..=100 => Self::Moderate,
+            101..=150 => Self::UnhealthyForSensitiveGroups,
+            151..=200 => Self::Unhealthy,
+            201..=300 => Self::VeryUnhealthy,
+            _ => Self::Hazardous,
+        }
+    }
+}
+
+/// CO2 comfort category, bounded by [`CO2_COMFORT_PPM`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, defmt::Format)]
+pub enum Co2Comfort {
+    Excellent,
+    Good,
+    Fair,
+    Poor,
+    Bad,
+}
+
+impl Co2Comfort {
+    /// Category of a CO2 level (ppm).
+    #[must_use]
+    pub const fn of(ppm: u16) -> Self {
+        let [excellent, good, fair, poor] = CO2_COMFORT_PPM;
+        match ppm {
+            ppm if ppm < excellent => Self::Excellent,
+            ppm if ppm < good => Self::Good,
+            ppm if ppm < fair => Self::Fair,
+            ppm if ppm < poor => Self::Poor,
+            _ => Self::Bad,
+        }
+    }
+
+    /// Name, as scrolled.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Excellent => "Excellent",
+            Self::Good => "Good",
+            Self::Fair => "Fair",
+            Self::Poor => "Poor",
+            Self::Bad => "Bad",
+        }
+    }
+}
+
+/// Overall air quality, shown as a glyph.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, defmt::Format)]
+pub enum AirQuality {
+    /// Nothing to do.
+    Good,
+    /// Worth airing the room.
+    Fair,
+    /// Act now.
+    Poor,
+}
+
+impl AirQuality {
+    /// The worse of the CO2 level's (ppm) and the AQI's, of those known;
+    /// `None` if neither is.
+    #[must_use]
+    pub fn overall(co2_ppm: Option<u16>, aqi: Option<u16>) -> Option<Self> {
+        let co2 = co2_ppm.map(|co2_ppm| match Co2Comfort::of(co2_ppm) {
+            Co2Comfort::Excellent | Co2Comfort::Good => Self::Good,
+            Co2Comfort::Fair => Self::Fair,
+            Co2Comfort::Poor | Co2Comfort::Bad => Self::Poor,
+        });
+        let particulates = aqi.map(|aqi| match AqiCategory::of(aqi) {
+            AqiCategory::Good => Self::Good,
+            AqiCategory::Moderate => Self::Fair,
+            _ => Self::Poor,
+        });
+        co2.max(particulates)
+    }
+}
@@ -0,0 +1,62 @@
+//! LED matrix row bitmap: the BSP's type on target, a stand-in with the same
+//! API on the host (for the simulator).
+
+#[cfg(not(target_os = "none"))]
+pub use host::Bitmap;
+#[cfg(target_os = "none")]
+pub use microbit_bsp::display::Bitmap;
+
+#[cfg(not(target_os = "none"))]
+mod host {
+    /// Row of up to 8 LEDs, leftmost LED in the highest used bit.
+    #[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+    #[expect(
+        clippy::derive_partial_eq_without_eq,
+        reason = "matches the BSP bitmap, which is not Eq"
+    )]
+    pub struct Bitmap {
+        pixels: u8,
+        nbits: usize,
+    }
+
+    impl Bitmap {
+        /// Build a row of `nbits` LEDs from the low bits of `input`.
+        #[must_use]
+        pub const fn new(input: u8, nbits: usize) -> Self {
+            Self {
+                pixels: input,
+                nbits,
+            }
+        }
+
+        /// Build a row of `nbits` unlit LEDs.
+        #[must_use]
+        pub const fn empty(nbits: usize) -> Self {
+            Self::new(0, nbits)
+        }
+
+        /// Light the LED in column `bit`.
+        pub fn set(&mut self, bit: usize) {
+            self.pixels |= self.mask(bit);
+        }
+
+        /// Unlight the LED in column `bit`.
+        pub fn clear(&mut self, bit: usize) {
+            self.pixels &= !self.mask(bit);
+        }
+
+        /// Whether the LED in column `bit` is lit.
+        #[must_use]
+        pub fn is_set(&self, bit: usize) -> bool {
+            self.pixels & self.mask(bit) != 0
+        }
+
+        /// Mask for column `bit`, empty past the row.
+        fn mask(&self, bit: usize) -> u8 {
+            self.nbits
+                .checked_sub(bit + 1)
+                .and_then(|shift| 1_u8.checked_shl(u32::try_from(shift).ok()?))
+                .unwrap_or(0)
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! BLE Task: Environmental Sensing Service peripheral or BTHome beacon.
+//!
+//! The peripheral also takes the wall-clock time from a central writing its
+//! Current Time characteristic.
+
+use embassy_futures::join::join;
+use embassy_futures::select::{Either, Either3, select, select3};
+use embassy_sync::watch::DynReceiver;
+use embassy_time::{Duration, Timer};
+use microbit_bsp::ble::{MultiprotocolServiceLayer, SoftdeviceController};
+use rustymicrobit_moxi::ble_mode::{BLE_MODE, BleMode};
+use rustymicrobit_moxi::bthome::{self, Readings};
+use rustymicrobit_moxi::measurement::{Co2Measurement, PressureMeasurement};
+use rustymicrobit_moxi::{clock, cts, ess};
+use trouble_host::prelude::*;
+
+use crate::{sense_co2, sense_mb, sense_pa};
+
+/// Advertised device name.
+const DEVICE_NAME: &str = "Moxi";
+
+/// Concurrent central connections.
+const CONNECTIONS_MAX: usize = 1;
+
+/// L2CAP channels (signal + ATT).
+const L2CAP_CHANNELS_MAX: usize = 2;
+
+/// Wait before advertising again after a failure, so a persistent controller
+/// error doesn't spin.
+const ADVERTISE_RETRY: Duration = Duration::from_secs(1);
+
+#[gatt_server]
+struct Server {
+    ess: EnvironmentalSensingService,
+    cts: CurrentTimeService,
+}
+
+#[gatt_service(uuid = ess::SERVICE_UUID)]
+struct EnvironmentalSensingService {
+    #[characteristic(uuid = ess::TEMPERATURE_UUID, read, notify)]
+    temperature: [u8; 2],
+    #[characteristic(uuid = ess::HUMIDITY_UUID, read, notify)]
+    humidity: [u8; 2],
+    #[characteristic(uuid = ess::PRESSURE_UUID, read, notify)]
+    pressure: [u8; 4],
+    #[characteristic(uuid = ess::CO2_UUID, read, notify)]
+    co2: [u8; 2],
+}
+
+#[gatt_service(uuid = cts::SERVICE_UUID)]
+struct CurrentTimeService {
+    #[characteristic(uuid = cts::CURRENT_TIME_UUID, write)]
+    current_time: [u8; cts::CURRENT_TIME_LEN],
+}
+
+/// Multiprotocol service layer runner.
+#[embassy_executor::task]
+pub async fn mpsl_task(mpsl: &'static MultiprotocolServiceLayer<'static>) -> ! {
+    mpsl.run().await
+}
+
+/// BLE peripheral or beacon task, according to `BLE_MODE`.
+#[embassy_executor::task]
+pub async fn ble_task(controller: SoftdeviceController<'static>) {
+    // Static random address: top two bits set
+    let [sn0, sn1, sn2, sn3] = sense_mb::get_serial_number().to_le_bytes();
+    let address = Address::random([sn0, sn1, sn2, sn3, 0x4d, 0xc3]);
+    defmt::info!("BLE: Address {:?}", address);
+
+    let mut resources: HostResources<DefaultPacketPool, CONNECTIONS_MAX, L2CAP_CHANNELS_MAX> =
+        HostResources::new();
+    let stack = trouble_host::new(controller, &mut resources).set_random_address(address);
+    let Host {
+        mut peripheral,
+        mut runner,
+        ..
+    } = stack.build();
+
+    let server = defmt::unwrap!(Server::new_with_config(GapConfig::Peripheral(
+        PeripheralConfig {
+            name: DEVICE_NAME,
+            appearance: &appearance::sensor::MULTI_SENSOR,
+        }
+    )));
+
+    let mut co2_rx = sense_co2::get_sensor_receiver().or_else(|| {
+        defmt::error!("BLE: Request for co2 rx failed (notifications disabled)");
+        None
+    });
+    let mut pa_rx = sense_pa::get_sensor_receiver().or_else(|| {
+        defmt::error!("BLE: Request for pressure rx failed (notifications disabled)");
+        None
+    });
+
+    join(
+        async {
+            if let Err(e) = runner.run().await {
+                defmt::panic!("BLE: Host runner failed ({:?})", e);
+            }
+        },
+        async {
+            match BLE_MODE {
+                BleMode::Peripheral => loop {
+                    match advertise(&mut peripheral, &server).await {
+                        Ok(conn) => serve(&server, &conn, &mut co2_rx, &mut pa_rx).await,
+                        Err(e) => {
+                            defmt::error!("BLE: Advertising failed ({:?})", e);
+                            Timer::after(ADVERTISE_RETRY).await;
+                        }
+                    }
+                },
+                BleMode::Beacon(interval) => {
+                    beacon(&mut peripheral, interval, &mut co2_rx, &mut pa_rx).await;
+                }
+            }
+        },
+    )
+    .await;
+}
+
+/// Advertise the ESS until a central connects.
+async fn advertise<'values, 'server, C: Controller>(
+    peripheral: &mut Peripheral<'values, C, DefaultPacketPool>,
+    server: &'server Server<'values>,
+) -> Result<GattConnection<'values, 'server, DefaultPacketPool>, BleHostError<C::Error>> {
+    let mut adv_data = [0; 31];
+    let len = AdStructure::encode_slice(
+        &[
+            AdStructure::Flags(LE_GENERAL_DISCOVERABLE | BR_EDR_NOT_SUPPORTED),
+            AdStructure::ServiceUuids16(&[ess::SERVICE_UUID.to_le_bytes()]),
+            AdStructure::CompleteLocalName(DEVICE_NAME.as_bytes()),
+        ],
+        &mut adv_data[..],
+    )?;
+    let advertiser = peripheral
+        .advertise(
+            &AdvertisementParameters::default(),
+            Advertisement::ConnectableScannableUndirected {
+                adv_data: adv_data.get(..len).unwrap_or_default(),
+                scan_data: &[],
+            },
+        )
+        .await?;
+    defmt::info!("BLE: Advertising");
+
+    let conn = advertiser.accept().await?.with_attribute_server(server)?;
+    defmt::info!("BLE: Connected");
+    Ok(conn)
+}
+
+/// Broadcast the latest readings as BTHome service data, forever.
+async fn beacon<C: Controller>(
+    peripheral: &mut Peripheral<'_, C, DefaultPacketPool>,
+    interval: Duration,
+    co2_rx: &mut Option<DynReceiver<'static, Co2Measurement>>,
+    pa_rx: &mut Option<DynReceiver<'static, PressureMeasurement>>,
+) {
+    let params = AdvertisementParameters {
+        interval_min: interval,
+        interval_max: interval,
+        ..AdvertisementParameters::default()
+    };
+    let mut readings = Readings::default();
+
+    loop {
+        let service_data = bthome::service_data(&readings);
+        let mut adv_data = [0; 31];
+        let advertiser = match AdStructure::encode_slice(
+            &[
+                AdStructure::Flags(LE_GENERAL_DISCOVERABLE | BR_EDR_NOT_SUPPORTED),
+                AdStructure::ServiceData16 {
+                    uuid: bthome::SERVICE_UUID.to_le_bytes(),
+                    data: &service_data,
+                },
+                AdStructure::ShortenedLocalName(DEVICE_NAME.as_bytes()),
+            ],
+            &mut adv_data[..],
+        ) {
+            Ok(len) => {
+                peripheral
+                    .advertise(
+                        &params,
+                        Advertisement::NonconnectableNonscannableUndirected {
+                            adv_data: adv_data.get(..len).unwrap_or_default(),
+                        },
+                    )
+                    .await
+            }
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = &advertiser {
+            defmt::error!("BLE: Beacon advertising failed ({:?})", e);
+        }
+
+        // Keep advertising until a reading changes
+        match select(changed(co2_rx), changed(pa_rx)).await {
+            Either::First(m_co2) => {
+                readings.co2 = Some(m_co2.co2.into_inner());
+                readings.humidity = Some(m_co2.humidity.into_inner());
+                readings.temp_c = Some(m_co2.temp_c.into_inner());
+            }
+            Either::Second(m_pa) => readings.hpa = Some(m_pa.hpa.into_inner()),
+        }
+        drop(advertiser);
+    }
+}
+
+/// Wait for a new value, or forever without a receiver.
+async fn changed<T: Clone>(rx: &mut Option<DynReceiver<'static, T>>) -> T {
+    match rx.as_mut() {
+        Some(rx) => rx.changed().await,
+        None => core::future::pending().await,
+    }
+}
+
+/// Handle GATT requests and notify new measurements until disconnect.
+async fn serve(
+    server: &Server<'_>,
+    conn: &GattConnection<'_, '_, DefaultPacketPool>,
+    co2_rx: &mut Option<DynReceiver<'static, Co2Measurement>>,
+    pa_rx: &mut Option<DynReceiver<'static, PressureMeasurement>>,
+) {
+    loop {
+        match select3(conn.next(), changed(co2_rx), changed(pa_rx)).await {
+            Either3::First(GattConnectionEvent::Disconnected { reason }) => {
+                defmt::info!("BLE: Disconnected ({:?})", reason);
+                return;
+            }
+            Either3::First(GattConnectionEvent::Gatt { event }) => {
+                let written = match &event {
+                    GattEvent::Write(write) if write.handle() == server.cts.current_time.handle => {
+                        set_clock(write.data())
+                    }
+                    _ => Ok(()),
+                };
+                let reply = match written {
+                    Ok(()) => event.accept(),
+                    Err(code) => event.reject(code),
+                };
+                match reply {
+                    Ok(reply) => reply.send().await,
+                    Err(e) => defmt::warn!("BLE: Failed to answer GATT event ({:?})", e),
+                }
+            }
+            Either3::First(_) => {}
+            Either3::Second(m_co2) => notify_co2(server, conn, &m_co2).await,
+            Either3::Third(m_pa) => notify_pressure(server, conn, &m_pa).await,
+        }
+    }
+}
+
+/// Set the wall clock from a written Current Time value.
+fn set_clock(value: &[u8]) -> Result<(), AttErrorCode> {
+    match cts::unix_ms(value) {
+        Ok(unix_ms) => {
+            clock::set_unix_ms(unix_ms);
+            defmt::info!("BLE: Clock set");
+            Ok(())
+        }
+        Err(e) => {
+            defmt::warn!("BLE: Current time rejected ({:?})", e);
+            Err(AttErrorCode::VALUE_NOT_ALLOWED)
+        }
+    }
+}
+
+/// Publish an SCD4X reading to the CO2, humidity and temperature
+/// characteristics.
+async fn notify_co2(
+    server: &Server<'_>,
+    conn: &GattConnection<'_, '_, DefaultPacketPool>,
+    m_co2: &Co2Measurement,
+) {
+    let service = &server.ess;
+    let results = [
+        service
+            .co2
+            .notify(conn, &ess::co2(m_co2.co2.into_inner()))
+            .await,
+        service
+            .humidity
+            .notify(conn, &ess::humidity(m_co2.humidity.into_inner()))
+            .await,
+        service
+            .temperature
+            .notify(conn, &ess::temperature(m_co2.temp_c.into_inner()))
+            .await,
+    ];
+    for e in results.into_iter().filter_map(Result::err) {
+        defmt::warn!("BLE: CO2 notification failed ({:?})", e);
+    }
+}
+
+/// Publish a BMP581 reading to the pressure characteristic.
+async fn notify_pressure(
+    server: &Server<'_>,
+    conn: &GattConnection<'_, '_, DefaultPacketPool>,
+    m_pa: &PressureMeasurement,
+) {
+    if let Err(e) = server
+        .ess
+        .pressure
+        .notify(conn, &ess::pressure(m_pa.hpa.into_inner()))
+        .await
+    {
+        defmt::warn!("BLE: Pressure notification failed ({:?})", e);
+    }
+}
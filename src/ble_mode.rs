@@ -0,0 +1,23 @@
+//! Bluetooth policy.
+
+use embassy_time::Duration;
+
+/// Active Bluetooth mode: the beacon with the `ble-beacon` feature, the
+/// peripheral otherwise.
+pub const BLE_MODE: BleMode = if cfg!(feature = "ble-beacon") {
+    BleMode::Beacon(BEACON_INTERVAL)
+} else {
+    BleMode::Peripheral
+};
+
+/// BTHome broadcast interval, the advertising interval in beacon mode.
+pub const BEACON_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Bluetooth operating mode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BleMode {
+    /// Connectable Environmental Sensing Service peripheral.
+    Peripheral,
+    /// Broadcast-only BTHome v2 beacon advertised at the given interval.
+    Beacon(Duration),
+}
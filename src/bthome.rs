@@ -0,0 +1,78 @@
+//! BTHome v2 advertisement payload encoding.
+//!
+//! See <https://bthome.io/format/>. Objects are unencrypted, regularly sent,
+//! and ordered by object id.
+
+use heapless::Vec;
+use libm::roundf;
+
+/// BTHome service data UUID.
+pub const SERVICE_UUID: u16 = 0xFCD2;
+
+/// Device information: unencrypted, regular interval, version 2.
+pub const DEVICE_INFO: u8 = 0b0100_0000;
+
+/// Service data capacity (device info and every supported object).
+pub const SERVICE_DATA_MAX: usize = 1 + 3 + 3 + 4 + 3;
+
+/// Temperature object id (sint16, 0.01 C).
+const OBJECT_TEMPERATURE: u8 = 0x02;
+
+/// Humidity object id (uint16, 0.01 %RH).
+const OBJECT_HUMIDITY: u8 = 0x03;
+
+/// Pressure object id (uint24, 0.01 hPa).
+const OBJECT_PRESSURE: u8 = 0x04;
+
+/// CO2 object id (uint16, ppm).
+const OBJECT_CO2: u8 = 0x12;
+
+/// Latest readings to broadcast, if available.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Readings {
+    pub co2: Option<f32>,
+    pub humidity: Option<f32>,
+    pub hpa: Option<f32>,
+    pub temp_c: Option<f32>,
+}
+
+/// Encode BTHome service data (following the UUID) for the given readings.
+#[must_use]
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "saturating casts into the object ranges"
+)]
+pub fn service_data(readings: &Readings) -> Vec<u8, SERVICE_DATA_MAX> {
+    let mut data = Vec::new();
+    let mut push = |bytes: &[u8]| {
+        // Capacity covers every object once
+        if data.extend_from_slice(bytes).is_err() {
+            defmt::error!("BTHome: Service data overflow");
+        }
+    };
+
+    push(&[DEVICE_INFO]);
+
+    if let Some(temp_c) = readings.temp_c.filter(|t| !t.is_nan()) {
+        let [lo, hi] = (roundf(temp_c * 100.0) as i16).to_le_bytes();
+        push(&[OBJECT_TEMPERATURE, lo, hi]);
+    }
+
+    if let Some(humidity) = readings.humidity.filter(|h| !h.is_nan()) {
+        let [lo, hi] = (roundf(humidity * 100.0) as u16).to_le_bytes();
+        push(&[OBJECT_HUMIDITY, lo, hi]);
+    }
+
+    if let Some(hpa) = readings.hpa.filter(|p| !p.is_nan()) {
+        let [b0, b1, b2, _] = (roundf(hpa * 100.0) as u32).min(0x00FF_FFFF).to_le_bytes();
+        push(&[OBJECT_PRESSURE, b0, b1, b2]);
+    }
+
+    if let Some(co2) = readings.co2.filter(|c| !c.is_nan()) {
+        let [lo, hi] = (roundf(co2) as u16).to_le_bytes();
+        push(&[OBJECT_CO2, lo, hi]);
+    }
+
+    data
+}
@@ -0,0 +1,189 @@
+//! Shared I2C bus supervision: transaction timeouts, and recovery of a bus
+//! held low after a brown-out or a yanked cable.
+//!
+//! Each sensor task wraps its bus handle in [`Supervised`], which times out
+//! transactions (including the wait for the shared bus) and requests recovery
+//! after repeated bus faults. The firmware waits on [`recovery_requested`],
+//! releases SDA with [`release_sda`], reinitializes the controller, and marks
+//! the bus [`recovered`]. Faults until then are the recovery's own, and don't
+//! count towards another.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, with_timeout};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::i2c::{Error, ErrorKind, ErrorType, I2c, Operation};
+
+/// Longest wait for the shared bus and a transaction on it.
+pub const TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Consecutive bus faults before recovery is requested.
+pub const FAULTS_BEFORE_RECOVERY: u8 = 3;
+
+/// Clock pulses to release a target holding SDA: one per remaining bit of a
+/// byte, plus the acknowledge.
+const RECOVERY_PULSES: u8 = 9;
+
+/// Half period of the recovery clock (100 kHz).
+const HALF_PERIOD_US: u32 = 5;
+
+/// Pending bus recovery.
+static RECOVERY: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Recovery requested and not yet finished.
+static RECOVERING: AtomicBool = AtomicBool::new(false);
+
+/// Request bus recovery, as on repeated faults.
+pub fn request_recovery() {
+    RECOVERING.store(true, Ordering::Relaxed);
+    RECOVERY.signal(());
+}
+
+/// Whether recovery has been requested and not yet finished.
+#[must_use]
+pub fn recovering() -> bool {
+    RECOVERING.load(Ordering::Relaxed)
+}
+
+/// Mark recovery finished, counting bus faults again.
+pub fn recovered() {
+    RECOVERING.store(false, Ordering::Relaxed);
+}
+
+/// Whether recovery has been requested and not yet started.
+#[must_use]
+pub fn recovery_pending() -> bool {
+    RECOVERY.signaled()
+}
+
+/// Wait for a recovery request.
+pub async fn recovery_requested() {
+    RECOVERY.wait().await;
+}
+
+/// Supervised bus error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum BusError<E> {
+    /// The bus or the target did not complete in time.
+    Timeout,
+    /// Error from the bus.
+    I2c(E),
+}
+
+impl<E: Error> Error for BusError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Timeout => ErrorKind::Other,
+            Self::I2c(e) => e.kind(),
+        }
+    }
+}
+
+impl<E: Error> BusError<E> {
+    /// Whether the error points at the bus rather than the target. A NACK is
+    /// a busy or absent target, which recovery cannot help.
+    #[must_use]
+    pub fn is_bus_fault(&self) -> bool {
+        !matches!(self.kind(), ErrorKind::NoAcknowledge(_))
+    }
+}
+
+/// Bus handle with transaction timeouts, requesting recovery after
+/// [`FAULTS_BEFORE_RECOVERY`] consecutive bus faults outside a recovery.
+pub struct Supervised<I> {
+    i2c: I,
+    faults: u8,
+}
+
+impl<I> Supervised<I> {
+    #[must_use]
+    pub const fn new(i2c: I) -> Self {
+        Self { i2c, faults: 0 }
+    }
+
+    /// Consecutive bus faults since the last success or recovery request.
+    #[must_use]
+    pub const fn faults(&self) -> u8 {
+        self.faults
+    }
+}
+
+impl<I: I2c> ErrorType for Supervised<I> {
+    type Error = BusError<I::Error>;
+}
+
+impl<I: I2c> I2c for Supervised<I> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let result = with_timeout(TIMEOUT, self.i2c.transaction(address, operations))
+            .await
+            .map_or(Err(BusError::Timeout), |result| {
+                result.map_err(BusError::I2c)
+            });
+        match &result {
+            // Waiting on the recovery, or the controller it's reinitializing
+            Err(e) if e.is_bus_fault() && recovering() => {}
+            Err(e) if e.is_bus_fault() => {
+                self.faults = self.faults.saturating_add(1);
+                if self.faults >= FAULTS_BEFORE_RECOVERY {
+                    defmt::warn!("I2C: {=u8} bus faults, requesting recovery", self.faults);
+                    request_recovery();
+                    self.faults = 0;
+                }
+            }
+            _ => self.faults = 0,
+        }
+        result
+    }
+}
+
+/// Release a target holding SDA low, by clocking SCL until SDA reads high,
+/// then generating a STOP. Both pins must be open drain, and the bus
+/// controller disconnected from them.
+///
+/// Returns whether SDA was released.
+///
+/// # Errors
+/// Returns any pin error.
+pub fn release_sda<Scl, Sda>(
+    scl: &mut Scl,
+    sda: &mut Sda,
+    delay: &mut impl DelayNs,
+) -> Result<bool, Scl::Error>
+where
+    Scl: OutputPin,
+    Sda: InputPin<Error = Scl::Error> + OutputPin,
+{
+    sda.set_high()?;
+    scl.set_high()?;
+    delay.delay_us(HALF_PERIOD_US);
+    for _ in 0..RECOVERY_PULSES {
+        if sda.is_high()? {
+            break;
+        }
+        scl.set_low()?;
+        delay.delay_us(HALF_PERIOD_US);
+        scl.set_high()?;
+        delay.delay_us(HALF_PERIOD_US);
+    }
+    if sda.is_low()? {
+        return Ok(false);
+    }
+
+    // STOP: SDA rises while SCL is high
+    scl.set_low()?;
+    delay.delay_us(HALF_PERIOD_US);
+    sda.set_low()?;
+    delay.delay_us(HALF_PERIOD_US);
+    scl.set_high()?;
+    delay.delay_us(HALF_PERIOD_US);
+    sda.set_high()?;
+    delay.delay_us(HALF_PERIOD_US);
+    sda.is_high()
+}
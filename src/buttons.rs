@@ -5,6 +5,7 @@ use embassy_time::Timer;
 use microbit_bsp::Button;
 use microbit_bsp::embassy_nrf::Peri;
 use microbit_bsp::embassy_nrf::gpio::{AnyPin, Input, Pull};
+use rustymicrobit_moxi::ui::ButtonState;
 
 static BUTTONS_LENS: Channel<ThreadModeRawMutex, ButtonState, 3> = Channel::new();
 
@@ -16,13 +17,6 @@ fn get_buttons_sender() -> DynamicSender<'static, ButtonState> {
     BUTTONS_LENS.dyn_sender()
 }
 
-#[derive(Clone, Copy, Debug)]
-pub enum ButtonState {
-    A,
-    B,
-    C,
-}
-
 #[embassy_executor::task]
 pub async fn buttons_task(
     mut btn_a: Button,
@@ -40,6 +34,12 @@ pub async fn buttons_task(
         )
         .await
         {
+            Either3::First(()) if btn_b.is_low() => {
+                tx.send(ButtonState::AB).await;
+            }
+            Either3::Second(()) if btn_a.is_low() => {
+                tx.send(ButtonState::AB).await;
+            }
             Either3::First(_) => {
                 tx.send(ButtonState::A).await;
             }
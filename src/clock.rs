@@ -0,0 +1,64 @@
+//! Wall-clock time.
+//!
+//! Uptime comes from the embassy time driver (nRF RTC1). Wall-clock time is
+//! uptime plus an epoch offset, unknown until set by a client over serial or
+//! Bluetooth.
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::Instant;
+
+/// Unix time (ms) at uptime zero, once set.
+static EPOCH_OFFSET_MS: Mutex<CriticalSectionRawMutex, Cell<Option<u64>>> =
+    Mutex::new(Cell::new(None));
+
+/// Point in time of a reading or event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub struct Timestamp {
+    /// Milliseconds since power on.
+    pub uptime_ms: u64,
+    /// Milliseconds since the Unix epoch, if the clock has been set.
+    pub unix_ms: Option<u64>,
+}
+
+impl Timestamp {
+    /// Build a timestamp from uptime and an optional epoch offset.
+    #[must_use]
+    pub const fn new(uptime_ms: u64, epoch_offset_ms: Option<u64>) -> Self {
+        let unix_ms = match epoch_offset_ms {
+            Some(offset) => Some(offset.saturating_add(uptime_ms)),
+            None => None,
+        };
+        Self { uptime_ms, unix_ms }
+    }
+
+    /// Unix time (ms) if known, else uptime (ms).
+    #[must_use]
+    pub const fn unix_or_uptime_ms(self) -> u64 {
+        match self.unix_ms {
+            Some(unix_ms) => unix_ms,
+            None => self.uptime_ms,
+        }
+    }
+}
+
+/// Current time.
+#[must_use]
+pub fn now() -> Timestamp {
+    let uptime_ms = Instant::now().as_millis();
+    Timestamp::new(uptime_ms, EPOCH_OFFSET_MS.lock(Cell::get))
+}
+
+/// Set the wall clock from the current Unix time (ms).
+pub fn set_unix_ms(unix_ms: u64) {
+    let offset = unix_ms.saturating_sub(Instant::now().as_millis());
+    EPOCH_OFFSET_MS.lock(|cell| cell.set(Some(offset)));
+}
+
+/// Whether the wall clock has been set.
+#[must_use]
+pub fn is_set() -> bool {
+    EPOCH_OFFSET_MS.lock(Cell::get).is_some()
+}
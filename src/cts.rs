@@ -0,0 +1,104 @@
+//! Bluetooth Current Time Service characteristic decoding.
+//!
+//! A connected client sets the wall clock by writing the Current Time
+//! characteristic (Exact Time 256 format, GATT Specification Supplement),
+//! taken as UTC: the day of week and adjust reason are ignored.
+
+/// Current Time Service UUID.
+pub const SERVICE_UUID: u16 = 0x1805;
+
+/// Current Time characteristic UUID.
+pub const CURRENT_TIME_UUID: u16 = 0x2A2B;
+
+/// Current Time characteristic value length.
+pub const CURRENT_TIME_LEN: usize = 10;
+
+/// Days from 0000-03-01 to the Unix epoch, in the proleptic Gregorian
+/// calendar.
+const EPOCH_DAYS: u64 = 719_468;
+
+/// Days per 400-year era.
+const ERA_DAYS: u64 = 146_097;
+
+/// Current Time decoding failures.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum DecodeError {
+    /// Wrong length for the characteristic.
+    Length,
+    /// Year or month "not known".
+    Unknown,
+    /// Not a valid date and time from the Unix epoch on.
+    Range,
+}
+
+/// Unix time (ms) of a Current Time characteristic value.
+///
+/// # Errors
+/// Returns a [`DecodeError`] if the value is malformed, unknown or before
+/// 1970.
+pub fn unix_ms(value: &[u8]) -> Result<u64, DecodeError> {
+    let &[
+        year_lo,
+        year_hi,
+        month,
+        day,
+        hours,
+        minutes,
+        seconds,
+        _,
+        fractions256,
+        _,
+    ] = value
+    else {
+        return Err(DecodeError::Length);
+    };
+    let year = u16::from_le_bytes([year_lo, year_hi]);
+    if year == 0 || month == 0 {
+        return Err(DecodeError::Unknown);
+    }
+    if year < 1970 || month > 12 || !(1..=31).contains(&day) {
+        return Err(DecodeError::Range);
+    }
+    if hours > 23 || minutes > 59 || seconds > 59 {
+        return Err(DecodeError::Range);
+    }
+    let days = days_from_civil(year.into(), month.into(), day.into());
+    // Reject days past the end of the month, which roll into the next
+    if civil_from_days(days) != (year.into(), month.into(), day.into()) {
+        return Err(DecodeError::Range);
+    }
+
+    let seconds =
+        ((days * 24 + u64::from(hours)) * 60 + u64::from(minutes)) * 60 + u64::from(seconds);
+    Ok(seconds * 1000 + u64::from(fractions256) * 1000 / 256)
+}
+
+/// Days since the Unix epoch of a date from 1970 on.
+const fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    // Years start in March, so the leap day ends them
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * ERA_DAYS + day_of_era - EPOCH_DAYS
+}
+
+/// Year, month and day of days since the Unix epoch.
+const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + EPOCH_DAYS;
+    let era = days / ERA_DAYS;
+    let day_of_era = days % ERA_DAYS;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let march_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * march_month + 2) / 5 + 1;
+    let month = if march_month < 10 {
+        march_month + 3
+    } else {
+        march_month - 9
+    };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
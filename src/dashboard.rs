@@ -1,6 +1,10 @@
 //! Dashboard encoding for the 5x5 Microbit LED matrix.
 
-use microbit_bsp::display::Bitmap;
+use crate::air_quality::AirQuality;
+use crate::bitmap::Bitmap;
+use crate::grayscale::{Grayscale, LEVEL_MAX};
+use crate::history::BUCKETS;
+use crate::units::{Fahrenheit, Ppm, RelativeHumidity};
 
 /// LED matrix column count.
 pub const LED_COLS: usize = 5;
@@ -8,6 +12,13 @@ pub const LED_COLS: usize = 5;
 /// LED matrix row count.
 pub const LED_ROWS: usize = 5;
 
+/// Dashboard animation frame count: out-of-range indicators blink, lit for
+/// the first frame only.
+pub const ANIMATION_FRAMES: usize = 2;
+
+/// Dashboard animation: LED matrix frames, shown in turn.
+pub type Animation = [Grayscale; ANIMATION_FRAMES];
+
 /// Temperature minimum display value (F).
 pub const TEMP_BASE_F: i16 = 50;
 
@@ -42,42 +53,231 @@ pub const HUMIDITY_STEP_PCT: u8 = 20;
 /// Humidity display saturation value (%RH).
 pub const HUMIDITY_SATURATION_PCT: u8 = 90;
 
-/// Encode a dashboard LED matrix frame (top to bottom).
+/// Secondary (substep) column level, dimmer than the primary columns.
+pub const SUBSTEP_LEVEL: u8 = 1;
+
+/// Particulate levels (ug/m3) lighting each LED of a column, bottom to top:
+/// the WHO annual PM2.5 guideline, then the US EPA AQI breakpoints.
+pub const PM_LEVELS_UGM3: [u16; LED_ROWS] = [5, 12, 35, 55, 150];
+
+/// VOC index levels lighting each LED of a column, bottom to top: settled,
+/// typical, then Sensirion's elevated bands.
+pub const VOC_INDEX_LEVELS: [u16; LED_ROWS] = [1, 100, 150, 250, 400];
+
+/// NOx index levels lighting each LED of a column, bottom to top: settled
+/// (typical), then Sensirion's elevated bands.
+pub const NOX_INDEX_LEVELS: [u16; LED_ROWS] = [1, 20, 50, 150, 300];
+
+/// Animation holding a frame (top to bottom) still, at full brightness.
 #[must_use]
-pub fn construct_dashboard_rows(co2: u16, humidity: u8, temp_f: i16) -> [Bitmap; LED_ROWS] {
+pub fn still(rows: [Bitmap; LED_ROWS]) -> Animation {
+    [Grayscale::from(rows); ANIMATION_FRAMES]
+}
+
+/// Encode a dashboard LED matrix animation, in whole units. The columns of
+/// an unknown reading stay dark.
+///
+/// The secondary columns are dimmed to [`SUBSTEP_LEVEL`]. A reading below a
+/// primary column's range blinks the column's bottom LED, and one at or above
+/// its saturation blinks the top LED of the full column, so neither reads as
+/// a plain empty or full column. Humidity has no upper indicator: its top LED
+/// already means over 90%.
+#[must_use]
+pub fn construct_dashboard_rows(
+    co2: Option<Ppm>,
+    humidity: Option<RelativeHumidity>,
+    temp_f: Option<Fahrenheit>,
+) -> Animation {
+    let co2 = co2.map(Ppm::whole);
+    let humidity = humidity.map(RelativeHumidity::whole);
+    let temp_f = temp_f.map(Fahrenheit::whole);
     let mut dash_rows = [Bitmap::empty(LED_COLS); LED_ROWS];
 
     // Primary columns fill bottom to top
     for (i, row) in dash_rows.iter_mut().rev().enumerate() {
-        if temp_f >= TEMP_BASE_F + TEMP_STEP_F * i.saturating_truncate::<u16>().cast_signed() {
+        if temp_f.is_some_and(|temp_f| {
+            temp_f >= TEMP_BASE_F + TEMP_STEP_F * i.saturating_truncate::<u16>().cast_signed()
+        }) {
             row.set(0);
         }
 
-        if co2 >= CO2_BASE_PPM + CO2_STEP_PPM * i.saturating_truncate::<u16>() {
+        if co2
+            .is_some_and(|co2| co2 >= CO2_BASE_PPM + CO2_STEP_PPM * i.saturating_truncate::<u16>())
+        {
             row.set(2);
         }
 
-        if humidity >= HUMIDITY_STEP_PCT * (i.saturating_truncate::<u8>() + 1)
-            || humidity >= HUMIDITY_SATURATION_PCT
-        {
+        if humidity.is_some_and(|humidity| {
+            humidity >= HUMIDITY_STEP_PCT * (i.saturating_truncate::<u8>() + 1)
+                || humidity >= HUMIDITY_SATURATION_PCT
+        }) {
             row.set(4);
         }
     }
 
     // Secondary columns fill top to bottom
     for (i, row) in dash_rows.iter_mut().enumerate() {
-        if temp_f % TEMP_STEP_F > TEMP_SUBSTEP_F * i.saturating_truncate::<u16>().cast_signed()
-            || temp_f >= TEMP_SATURATION_F
-        {
+        if temp_f.is_some_and(|temp_f| {
+            temp_f % TEMP_STEP_F > TEMP_SUBSTEP_F * i.saturating_truncate::<u16>().cast_signed()
+                || temp_f >= TEMP_SATURATION_F
+        }) {
             row.set(1);
         }
 
-        if co2 % CO2_STEP_PPM > CO2_SUBSTEP_PPM * i.saturating_truncate::<u16>()
-            || co2 >= CO2_SATURATION_PPM
-        {
+        if co2.is_some_and(|co2| {
+            co2 % CO2_STEP_PPM > CO2_SUBSTEP_PPM * i.saturating_truncate::<u16>()
+                || co2 >= CO2_SATURATION_PPM
+        }) {
             row.set(3);
         }
     }
 
-    dash_rows
+    let mut frame = Grayscale::from(dash_rows);
+    for col in [1, 3] {
+        frame.dim_column(col, SUBSTEP_LEVEL);
+    }
+    let mut animation = [frame; ANIMATION_FRAMES];
+    for (col, under, over) in [
+        (
+            0,
+            temp_f.is_some_and(|temp_f| temp_f < TEMP_BASE_F),
+            temp_f.is_some_and(|temp_f| temp_f >= TEMP_SATURATION_F),
+        ),
+        (
+            2,
+            co2.is_some_and(|co2| co2 < CO2_BASE_PPM),
+            co2.is_some_and(|co2| co2 >= CO2_SATURATION_PPM),
+        ),
+        (
+            4,
+            humidity.is_some_and(|humidity| humidity < HUMIDITY_STEP_PCT),
+            false,
+        ),
+    ] {
+        blink(&mut animation, col, under, over);
+    }
+    animation
+}
+
+/// Blink a column's bottom LED if under range, or its top LED if over.
+fn blink(animation: &mut Animation, col: usize, under: bool, over: bool) {
+    let [lit, unlit] = animation;
+    if under {
+        lit.set(LED_ROWS - 1, col, LEVEL_MAX);
+    }
+    if over {
+        unlit.set(0, col, 0);
+    }
+}
+
+/// Encode a particulate dashboard LED matrix frame (top to bottom).
+///
+/// PM1.0, PM2.5 and PM10 columns fill bottom to top through
+/// [`PM_LEVELS_UGM3`]; the top of the columns between them is lit if the
+/// reading is unreliable (humid).
+#[must_use]
+pub fn construct_pm_rows(pm1: f32, pm2_5: f32, pm10: f32, unreliable: bool) -> [Bitmap; LED_ROWS] {
+    let mut pm_rows = [Bitmap::empty(LED_COLS); LED_ROWS];
+
+    for (row, level) in pm_rows.iter_mut().rev().zip(PM_LEVELS_UGM3) {
+        for (col, pm) in [(0, pm1), (2, pm2_5), (4, pm10)] {
+            if pm >= f32::from(level) {
+                row.set(col);
+            }
+        }
+    }
+
+    if unreliable && let Some(top) = pm_rows.first_mut() {
+        top.set(1);
+        top.set(3);
+    }
+
+    pm_rows
+}
+
+/// Encode a gas index dashboard LED matrix frame (top to bottom).
+///
+/// The VOC index fills columns 0 and 1, and the NOx index (when measured)
+/// columns 3 and 4, bottom to top through [`VOC_INDEX_LEVELS`] and
+/// [`NOX_INDEX_LEVELS`].
+#[must_use]
+pub fn construct_gas_rows(voc_index: u16, nox_index: Option<u16>) -> [Bitmap; LED_ROWS] {
+    let mut gas_rows = [Bitmap::empty(LED_COLS); LED_ROWS];
+
+    for ((row, voc_level), nox_level) in gas_rows
+        .iter_mut()
+        .rev()
+        .zip(VOC_INDEX_LEVELS)
+        .zip(NOX_INDEX_LEVELS)
+    {
+        if voc_index >= voc_level {
+            row.set(0);
+            row.set(1);
+        }
+        if nox_index.is_some_and(|nox_index| nox_index >= nox_level) {
+            row.set(3);
+            row.set(4);
+        }
+    }
+
+    gas_rows
+}
+
+/// Encode a history bar chart, oldest bucket leftmost.
+///
+/// Bars are scaled to the window's range, from one LED at its minimum to a
+/// full column at its maximum, with the top LED lit brightest; empty buckets
+/// leave their column unlit.
+#[must_use]
+pub fn construct_history_frame(window: [Option<f32>; BUCKETS]) -> Grayscale {
+    let (low, high) = window
+        .iter()
+        .flatten()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), &mean| {
+            (low.min(mean), high.max(mean))
+        });
+    let steps = f32::from((LED_ROWS - 1).saturating_truncate::<u16>());
+    let mut frame = Grayscale::dark();
+
+    for (col, mean) in window.iter().enumerate() {
+        let Some(mean) = mean else {
+            continue;
+        };
+        // Rows above the bottom, rounded; none in a flat window
+        let height = if high > low {
+            (mean - low) / (high - low) * steps
+        } else {
+            0.0
+        };
+        let mut top = None;
+        for (step, row) in (0_u16..).zip((0..LED_ROWS).rev()) {
+            if f32::from(step) <= height + 0.5 {
+                frame.set(row, col, SUBSTEP_LEVEL);
+                top = Some(row);
+            }
+        }
+        if let Some(top) = top {
+            frame.set(top, col, LEVEL_MAX);
+        }
+    }
+
+    frame
+}
+
+/// Encode an overall air quality glyph (top to bottom): a smiley, a neutral
+/// face or a frown.
+#[must_use]
+pub const fn construct_air_quality_rows(quality: AirQuality) -> [Bitmap; LED_ROWS] {
+    let [mouth_top, mouth_bottom] = match quality {
+        AirQuality::Good => [0b10001, 0b01110],
+        AirQuality::Fair => [0b11111, 0b00000],
+        AirQuality::Poor => [0b01110, 0b10001],
+    };
+    [
+        Bitmap::new(0b01010, LED_COLS),
+        Bitmap::new(0b01010, LED_COLS),
+        Bitmap::empty(LED_COLS),
+        Bitmap::new(mouth_top, LED_COLS),
+        Bitmap::new(mouth_bottom, LED_COLS),
+    ]
 }
@@ -0,0 +1,250 @@
+//! Measurement log in flash, downloadable in chunks over the serial protocol.
+//!
+//! Records are numbered from the first ever logged, and each number has a
+//! fixed slot in a ring of erase pages, so a download can resume at an
+//! offset across resets. The page ahead of the newest record is erased
+//! before it is written, dropping its oldest records. A record's number is
+//! written after its fields, so one torn by a reset is never read back.
+
+use core::ops::Range;
+
+use embedded_storage_async::nor_flash::NorFlash;
+use heapless::Vec;
+
+use crate::protocol::{LogChunk, Measurement, Timestamp};
+
+/// Bytes of a record's number.
+const SEQ_SIZE: u32 = 4;
+
+/// Bytes of a record's fields.
+const FIELDS_SIZE: u32 = 32;
+
+/// Bytes per record slot: its number, then its fields.
+pub const RECORD_SIZE: u32 = SEQ_SIZE + FIELDS_SIZE;
+
+/// Erased flash byte.
+const ERASED: u8 = 0xFF;
+
+/// Field value of a missing optional reading.
+const UNSET: u64 = u64::MAX;
+
+/// Flash log failure.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum LogError<E> {
+    /// The region is not whole erase pages holding at least two pages of
+    /// records, or the flash can't write a record's number on its own.
+    Layout,
+    /// Error from the flash.
+    Flash(E),
+}
+
+/// Ring of records in a flash region.
+#[derive(Debug)]
+pub struct Datalog<F> {
+    flash: F,
+    /// Flash offset of the first page.
+    start: u32,
+    /// Erase page bytes.
+    page_size: u32,
+    /// Record slots per page.
+    page_slots: u32,
+    /// Record slots in the region.
+    slots: u32,
+    /// Number of the next record.
+    next: u32,
+}
+
+impl<F: NorFlash> Datalog<F> {
+    /// Open the log in `region` of `flash`, resuming after its newest
+    /// record.
+    ///
+    /// # Errors
+    /// Returns [`LogError::Layout`] if the region doesn't fit the flash's
+    /// pages, or [`LogError::Flash`] if reading fails.
+    pub async fn new(flash: F, region: Range<u32>) -> Result<Self, LogError<F::Error>> {
+        let page_size = u32::try_from(F::ERASE_SIZE).unwrap_or(0);
+        let page_slots = page_size / RECORD_SIZE;
+        let size = region.end.saturating_sub(region.start);
+        if page_slots == 0
+            || !region.start.is_multiple_of(page_size)
+            || !size.is_multiple_of(page_size)
+            || !SEQ_SIZE.is_multiple_of(u32::try_from(F::WRITE_SIZE).unwrap_or(0))
+            || !SEQ_SIZE.is_multiple_of(u32::try_from(F::READ_SIZE).unwrap_or(0))
+        {
+            return Err(LogError::Layout);
+        }
+        let pages = size / page_size;
+        let slots = pages
+            .checked_mul(page_slots)
+            .filter(|_| pages >= 2)
+            .ok_or(LogError::Layout)?;
+        let mut log = Self {
+            flash,
+            start: region.start,
+            page_size,
+            page_slots,
+            slots,
+            next: 0,
+        };
+
+        let mut newest = None;
+        for slot in 0..log.slots {
+            let mut seq = [ERASED; SEQ_SIZE as usize];
+            log.flash
+                .read(log.address(slot), &mut seq)
+                .await
+                .map_err(LogError::Flash)?;
+            let seq = u32::from_le_bytes(seq);
+            // Left by a different layout if not in its own slot
+            if seq != u32::MAX && seq % log.slots == slot && newest.is_none_or(|n| seq > n) {
+                newest = Some(seq);
+            }
+        }
+        log.next = newest.map_or(0, |seq| seq.saturating_add(1));
+        Ok(log)
+    }
+
+    /// Number the next record will take.
+    #[must_use]
+    pub const fn next(&self) -> u32 {
+        self.next
+    }
+
+    /// Append a record, erasing the next page's oldest records on entering
+    /// it. A slot left dirty by a torn write is skipped.
+    ///
+    /// # Errors
+    /// Returns [`LogError::Flash`] if erasing or writing fails.
+    pub async fn push(&mut self, record: &Measurement) -> Result<(), LogError<F::Error>> {
+        loop {
+            let address = self.address(self.next);
+            if (address - self.start).is_multiple_of(self.page_size) {
+                let end = address + self.page_size;
+                self.flash
+                    .erase(address, end)
+                    .await
+                    .map_err(LogError::Flash)?;
+            } else if !self.is_blank(address).await? {
+                self.next = self.next.saturating_add(1);
+                continue;
+            }
+
+            self.flash
+                .write(address + SEQ_SIZE, &encode(record))
+                .await
+                .map_err(LogError::Flash)?;
+            self.flash
+                .write(address, &self.next.to_le_bytes())
+                .await
+                .map_err(LogError::Flash)?;
+            self.next = self.next.saturating_add(1);
+            return Ok(());
+        }
+    }
+
+    /// Consecutive records starting at `offset`, or at the oldest retained
+    /// after it if `offset` was dropped.
+    ///
+    /// The chunk is empty past the end of the log.
+    ///
+    /// # Errors
+    /// Returns [`LogError::Flash`] if reading fails.
+    pub async fn chunk(&mut self, offset: u32) -> Result<LogChunk, LogError<F::Error>> {
+        let mut chunk = LogChunk {
+            offset,
+            records: Vec::new(),
+        };
+        let oldest = self.next.saturating_sub(self.slots);
+        for seq in offset.max(oldest)..self.next {
+            if chunk.records.is_full() {
+                break;
+            }
+            match self.read(seq).await? {
+                Some(record) => {
+                    if chunk.records.is_empty() {
+                        chunk.offset = seq;
+                    }
+                    if chunk.records.push(record).is_err() {
+                        break;
+                    }
+                }
+                None if chunk.records.is_empty() => {}
+                None => break,
+            }
+        }
+        Ok(chunk)
+    }
+
+    /// Record `seq`, if its slot still holds it.
+    async fn read(&mut self, seq: u32) -> Result<Option<Measurement>, LogError<F::Error>> {
+        let mut slot = [ERASED; RECORD_SIZE as usize];
+        self.flash
+            .read(self.address(seq), &mut slot)
+            .await
+            .map_err(LogError::Flash)?;
+        Ok(slot
+            .split_first_chunk::<{ SEQ_SIZE as usize }>()
+            .filter(|(stored, _)| u32::from_le_bytes(**stored) == seq)
+            .and_then(|(_, fields)| decode(fields)))
+    }
+
+    /// Whether the slot at `address` is erased.
+    async fn is_blank(&mut self, address: u32) -> Result<bool, LogError<F::Error>> {
+        let mut slot = [ERASED; RECORD_SIZE as usize];
+        self.flash
+            .read(address, &mut slot)
+            .await
+            .map_err(LogError::Flash)?;
+        Ok(slot.iter().all(|&byte| byte == ERASED))
+    }
+
+    /// Flash offset of record `seq`'s slot.
+    const fn address(&self, seq: u32) -> u32 {
+        let slot = seq % self.slots;
+        self.start
+            + (slot / self.page_slots) * self.page_size
+            + (slot % self.page_slots) * RECORD_SIZE
+    }
+}
+
+/// A record's fields, little-endian, with missing readings all ones.
+fn encode(record: &Measurement) -> [u8; FIELDS_SIZE as usize] {
+    let hpa = record.hpa.map_or(u32::MAX, f32::to_bits);
+    let bytes = record
+        .timestamp
+        .uptime_ms
+        .to_le_bytes()
+        .into_iter()
+        .chain(record.timestamp.unix_ms.unwrap_or(UNSET).to_le_bytes())
+        .chain(record.co2_ppm.to_le_bytes())
+        .chain(record.humidity.to_le_bytes())
+        .chain(record.temp_c.to_le_bytes())
+        .chain(hpa.to_le_bytes());
+    let mut fields = [ERASED; FIELDS_SIZE as usize];
+    for (field, byte) in fields.iter_mut().zip(bytes) {
+        *field = byte;
+    }
+    fields
+}
+
+/// A record from its fields.
+fn decode(fields: &[u8]) -> Option<Measurement> {
+    let (uptime_ms, fields) = fields.split_first_chunk::<8>()?;
+    let (unix_ms, fields) = fields.split_first_chunk::<8>()?;
+    let (co2_ppm, fields) = fields.split_first_chunk::<4>()?;
+    let (humidity, fields) = fields.split_first_chunk::<4>()?;
+    let (temp_c, fields) = fields.split_first_chunk::<4>()?;
+    let (hpa, _) = fields.split_first_chunk::<4>()?;
+    let unix_ms = u64::from_le_bytes(*unix_ms);
+    let hpa = u32::from_le_bytes(*hpa);
+    Some(Measurement {
+        timestamp: Timestamp {
+            uptime_ms: u64::from_le_bytes(*uptime_ms),
+            unix_ms: (unix_ms != UNSET).then_some(unix_ms),
+        },
+        co2_ppm: f32::from_le_bytes(*co2_ppm),
+        humidity: f32::from_le_bytes(*humidity),
+        temp_c: f32::from_le_bytes(*temp_c),
+        hpa: (hpa != u32::MAX).then(|| f32::from_bits(hpa)),
+    })
+}
@@ -0,0 +1,325 @@
+//! Boot-time scan of the Qwiic I2C bus, identifying known sensors, also
+//! behind a TCA9548A mux.
+
+use core::fmt::Write;
+use core::ops::RangeInclusive;
+
+use embassy_time::Timer;
+use embedded_hal_async::i2c::I2c;
+use heapless::{String, Vec};
+
+use crate::mux::{self, Channel};
+use crate::sensirion;
+
+/// Addresses scanned, excluding those reserved by the I2C specification.
+pub const SCAN_ADDRESSES: RangeInclusive<u8> = 0x08..=0x77;
+
+/// Responding addresses recorded.
+pub const RESPONDERS_MAX: usize = 16;
+
+/// Identified parts recorded.
+pub const PARTS_MAX: usize = 8;
+
+/// Inventory scroll text capacity.
+pub const SUMMARY_MAX: usize = 48;
+
+/// TCA9548A: channels selected to test the control register.
+const MUX_TEST_CHANNELS: u8 = 0b1010_0101;
+
+/// SCD4X: stop periodic measurement (500 ms), so it answers other commands.
+const SCD4X_STOP_PERIODIC_MEASUREMENT: [u8; 2] = [0x3f, 0x86];
+const SCD4X_STOP_MS: u64 = 500;
+
+/// SCD4X: get serial number (1 ms), three words.
+const SCD4X_GET_SERIAL_NUMBER: [u8; 2] = [0x36, 0x82];
+const SCD4X_GET_MS: u64 = 1;
+
+/// BMP581: chip ID register and value.
+const BMP581_CHIP_ID_REGISTER: u8 = 0x01;
+const BMP581_CHIP_ID: u8 = 0x50;
+
+/// PMSA003I: frame length and start characters.
+const PMSA003I_FRAME_LEN: usize = 32;
+const PMSA003I_START: [u8; 2] = [0x42, 0x4d];
+
+/// SPS30: read product type, four characters as words.
+const SPS30_READ_PRODUCT_TYPE: [u8; 2] = [0xd0, 0x02];
+const SPS30_PRODUCT_TYPE: [u8; 8] = *b"00080000";
+
+/// SGP4X: get serial number (1 ms), three words.
+const SGP4X_GET_SERIAL_NUMBER: [u8; 2] = [0x36, 0x82];
+const SGP4X_GET_MS: u64 = 1;
+
+/// Supported sensor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Part {
+    /// SCD40/41/43 CO2, humidity, and temperature.
+    Scd4x,
+    /// BMP581 pressure and temperature.
+    Bmp581,
+    /// PMSA003I particulate matter.
+    Pmsa003i,
+    /// SPS30 particulate matter.
+    Sps30,
+    /// SGP40/41 VOC (and NOx).
+    Sgp4x,
+}
+
+impl Part {
+    /// Every supported part, in identification order.
+    pub const ALL: [Self; 5] = [
+        Self::Scd4x,
+        Self::Bmp581,
+        Self::Pmsa003i,
+        Self::Sps30,
+        Self::Sgp4x,
+    ];
+
+    /// Addresses the part can be strapped to.
+    #[must_use]
+    pub const fn addresses(self) -> &'static [u8] {
+        match self {
+            Self::Scd4x => &[0x62],
+            Self::Bmp581 => &[0x47, 0x46],
+            Self::Pmsa003i => &[0x12],
+            Self::Sps30 => &[0x69],
+            Self::Sgp4x => &[0x59],
+        }
+    }
+
+    /// Name, as scrolled.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Scd4x => "SCD4X",
+            Self::Bmp581 => "BMP581",
+            Self::Pmsa003i => "PMSA003I",
+            Self::Sps30 => "SPS30",
+            Self::Sgp4x => "SGP4X",
+        }
+    }
+}
+
+/// Identified part.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub struct Found {
+    pub part: Part,
+    pub address: u8,
+    /// Mux channel the part sits behind, if not on the main bus.
+    pub channel: Option<Channel>,
+}
+
+/// What answered on the bus.
+#[derive(Clone, Debug, Default, PartialEq, Eq, defmt::Format)]
+pub struct Inventory {
+    /// Every address responding on the main bus, ascending.
+    pub responders: Vec<u8, RESPONDERS_MAX>,
+    /// TCA9548A mux address, if one answered. Only the first is used.
+    pub mux: Option<u8>,
+    /// Identified parts: on the main bus, then by mux channel.
+    pub parts: Vec<Found, PARTS_MAX>,
+}
+
+impl Inventory {
+    /// Address of the first identified `part`.
+    #[must_use]
+    pub fn address(&self, part: Part) -> Option<u8> {
+        self.parts
+            .iter()
+            .find_map(|found| (found.part == part).then_some(found.address))
+    }
+
+    /// Whether a part was identified.
+    #[must_use]
+    pub fn contains(&self, part: Part) -> bool {
+        self.address(part).is_some()
+    }
+
+    /// Scroll text naming the mux and identified parts (with their mux
+    /// channel), and counting unknown devices.
+    #[must_use]
+    pub fn summary(&self) -> String<SUMMARY_MAX> {
+        let mut text = String::new();
+        if write_summary(&mut text, self).is_err() {
+            defmt::warn!("I2C: Inventory summary truncated");
+        }
+        text
+    }
+
+    /// Record an identified part.
+    fn found(&mut self, part: Part, address: u8, channel: Option<Channel>) {
+        if let Some(channel) = channel {
+            defmt::info!(
+                "I2C: {=str} at {=u8:#04x} on mux channel {=u8}",
+                part.name(),
+                address,
+                channel.channel
+            );
+        } else {
+            defmt::info!("I2C: {=str} at {=u8:#04x}", part.name(), address);
+        }
+        let found = Found {
+            part,
+            address,
+            channel,
+        };
+        if self.parts.push(found).is_err() {
+            defmt::error!("I2C: Inventory full");
+        }
+    }
+}
+
+fn write_summary(text: &mut impl Write, inventory: &Inventory) -> core::fmt::Result {
+    if inventory.mux.is_some() {
+        text.write_str(" MUX")?;
+    }
+    let mut main_bus = 0;
+    for found in &inventory.parts {
+        if let Some(channel) = found.channel {
+            write!(text, " {}:{}", found.part.name(), channel.channel)?;
+        } else {
+            main_bus += 1;
+            write!(text, " {}", found.part.name())?;
+        }
+    }
+    let known = main_bus + usize::from(inventory.mux.is_some());
+    match inventory.responders.len().saturating_sub(known) {
+        0 if inventory.parts.is_empty() => text.write_str(" No sensors"),
+        0 => Ok(()),
+        unknown => write!(text, " +{unknown} unknown"),
+    }
+}
+
+/// Scan the bus, logging each responding address, then identify known parts
+/// by their ID registers, on the main bus and on each channel of a TCA9548A
+/// mux.
+pub async fn scan<I: I2c>(i2c: &mut I) -> Inventory {
+    let mut inventory = Inventory::default();
+    // Identifying the mux deselects any channel left selected by a reset,
+    // keeping its devices off the main bus scan
+    for address in mux::ADDRESSES {
+        if i2c.read(address, &mut [0]).await.is_ok() && is_mux(i2c, address).await {
+            defmt::info!("I2C: TCA9548A mux at {=u8:#04x}", address);
+            inventory.mux = Some(address);
+            break;
+        }
+    }
+
+    for address in SCAN_ADDRESSES {
+        // A one byte read has no side effects on the supported parts
+        if i2c.read(address, &mut [0]).await.is_ok() {
+            defmt::info!("I2C: Device at {=u8:#04x}", address);
+            if inventory.responders.push(address).is_err() {
+                defmt::warn!("I2C: Too many devices, scan stopped");
+                break;
+            }
+        }
+    }
+
+    for part in Part::ALL {
+        for &address in part.addresses() {
+            if inventory.responders.contains(&address) && identify(i2c, part, address).await {
+                // Each part is identified at most once on the main bus
+                inventory.found(part, address, None);
+                break;
+            }
+        }
+    }
+
+    let Some(mux_address) = inventory.mux else {
+        return inventory;
+    };
+    for channel in (0..mux::CHANNELS).filter_map(|channel| Channel::new(mux_address, channel)) {
+        if i2c.write(mux_address, &[channel.mask()]).await.is_err() {
+            defmt::warn!("I2C: Mux channel {=u8} not selected", channel.channel);
+            continue;
+        }
+        for part in Part::ALL {
+            for &address in part.addresses() {
+                // A part answering on the main bus would clash with this one
+                if !inventory.responders.contains(&address)
+                    && i2c.read(address, &mut [0]).await.is_ok()
+                    && identify(i2c, part, address).await
+                {
+                    inventory.found(part, address, Some(channel));
+                    break;
+                }
+            }
+        }
+    }
+    if i2c.write(mux_address, &[mux::DESELECTED]).await.is_err() {
+        defmt::warn!("I2C: Mux not deselected");
+    }
+    inventory
+}
+
+/// Whether the device at `address` is a TCA9548A: its control register reads
+/// back as written. Leaves every channel deselected.
+async fn is_mux<I: I2c>(i2c: &mut I, address: u8) -> bool {
+    for control in [MUX_TEST_CHANNELS, mux::DESELECTED] {
+        let mut read = [0];
+        if i2c.write(address, &[control]).await.is_err()
+            || i2c.read(address, &mut read).await.is_err()
+            || read != [control]
+        {
+            return false;
+        }
+    }
+    true
+}
+
+/// Whether the device at `address` is `part`.
+async fn identify<I: I2c>(i2c: &mut I, part: Part, address: u8) -> bool {
+    match part {
+        Part::Scd4x => {
+            // Stopping also ends any measurement left running by a reset
+            if i2c
+                .write(address, &SCD4X_STOP_PERIODIC_MEASUREMENT)
+                .await
+                .is_err()
+            {
+                return false;
+            }
+            Timer::after_millis(SCD4X_STOP_MS).await;
+            if i2c.write(address, &SCD4X_GET_SERIAL_NUMBER).await.is_err() {
+                return false;
+            }
+            Timer::after_millis(SCD4X_GET_MS).await;
+            let mut serial = [0; 9];
+            i2c.read(address, &mut serial).await.is_ok() && sensirion::words_valid(&serial)
+        }
+        Part::Bmp581 => {
+            let mut id = [0];
+            i2c.write_read(address, &[BMP581_CHIP_ID_REGISTER], &mut id)
+                .await
+                .is_ok()
+                && id == [BMP581_CHIP_ID]
+        }
+        Part::Pmsa003i => {
+            let mut frame = [0; PMSA003I_FRAME_LEN];
+            i2c.read(address, &mut frame).await.is_ok() && frame.starts_with(&PMSA003I_START)
+        }
+        Part::Sps30 => {
+            let mut product_type = [0; 12];
+            if i2c.write(address, &SPS30_READ_PRODUCT_TYPE).await.is_err()
+                || i2c.read(address, &mut product_type).await.is_err()
+                || !sensirion::words_valid(&product_type)
+            {
+                return false;
+            }
+            let (words, _) = product_type.as_chunks::<3>();
+            words
+                .iter()
+                .flat_map(|&[hi, lo, _]| [hi, lo])
+                .eq(SPS30_PRODUCT_TYPE)
+        }
+        Part::Sgp4x => {
+            if i2c.write(address, &SGP4X_GET_SERIAL_NUMBER).await.is_err() {
+                return false;
+            }
+            Timer::after_millis(SGP4X_GET_MS).await;
+            let mut serial = [0; 9];
+            i2c.read(address, &mut serial).await.is_ok() && sensirion::words_valid(&serial)
+        }
+    }
+}
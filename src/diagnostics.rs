@@ -0,0 +1,17 @@
+//! Health counters.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Failed sensor transactions since power on.
+static SENSOR_ERRORS: AtomicU32 = AtomicU32::new(0);
+
+/// Count a failed sensor transaction.
+pub fn record_sensor_error() {
+    SENSOR_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Failed sensor transactions since power on.
+#[must_use]
+pub fn sensor_errors() -> u32 {
+    SENSOR_ERRORS.load(Ordering::Relaxed)
+}
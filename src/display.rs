@@ -1,91 +1,257 @@
-use core::fmt::Write;
+use core::future::pending;
 
 use defmt::info;
-use embassy_time::Duration;
+use embassy_futures::select::{Either3, select3, select4};
+use embassy_sync::watch::DynReceiver;
+use embassy_time::{Duration, Instant, Ticker};
 use heapless::String;
-use microbit_bsp::display::{Frame, LedMatrix};
-use microbit_bsp::embassy_nrf::gpio::Output;
-use rustymicrobit_moxi::dashboard::{LED_COLS, LED_ROWS, construct_dashboard_rows};
-use rustymicrobit_moxi::measurement::{Co2Measurement, PressureMeasurement, fahrenheit};
-
-use crate::buttons::{ButtonState, get_buttons_receiver};
-use crate::{sense_co2, sense_pa};
-
-async fn display_dash(
-    co2: u16,
-    humidity: u8,
-    temp_f: i16,
-    matrix: &mut LedMatrix<Output<'static>, LED_ROWS, LED_COLS>,
-) {
-    let dash = construct_dashboard_rows(co2, humidity, temp_f);
-    matrix.set_brightness(microbit_bsp::display::Brightness::MIN);
-    matrix
-        .display(Frame::new(dash), Duration::from_millis(1000))
-        .await;
+use microbit_bsp::display::{Brightness, LedMatrix};
+use microbit_bsp::embassy_nrf::Peri;
+use microbit_bsp::embassy_nrf::gpio::{AnyPin, Level, Output, OutputDrive};
+use rustymicrobit_moxi::dashboard::{LED_COLS, LED_ROWS};
+use rustymicrobit_moxi::detect::SUMMARY_MAX;
+use rustymicrobit_moxi::grayscale::{Grayscale, LEVEL_MAX};
+use rustymicrobit_moxi::mesh::MESH_ENABLED;
+use rustymicrobit_moxi::page::Pages;
+use rustymicrobit_moxi::ui::{
+    DASHBOARD_FRAME, GREETING, Gases, Particulates, Pollutants, Readings, Screen, Ui,
+};
+use rustymicrobit_moxi::units::RangeError;
+
+use crate::buttons::get_buttons_receiver;
+use crate::{radio, sense_co2, sense_pa, sense_pm, sense_voc};
+
+/// Readings, or `None` with the range error logged.
+fn checked(readings: Result<Readings, RangeError>) -> Option<Readings> {
+    readings
+        .inspect_err(|e| defmt::warn!("Display: Readings out of range ({:?})", e))
+        .ok()
+}
+
+/// Drive time per level step of a row's drive: whole RTC ticks (30.5us at
+/// 32.768kHz), several so that a tick of wake-up jitter moves an LED's
+/// on-time by a fraction of a level.
+const LEVEL_STEP: Duration = Duration::from_ticks(3);
+
+/// Level steps per row drive, lit for at most a fifth of it as the
+/// dashboard runs dim: 1.8ms per row, scanning the matrix at over 100Hz.
+const ROW_STEPS: u8 = LEVEL_MAX * 5;
+
+/// Matrix pins, owned by the display task and lent to one driver at a time:
+/// the bsp's for scrolling, or [`RowScan`] for grayscale. Dropping a driver
+/// disconnects its pins, so an interrupted screen leaves no LED lit.
+pub struct MatrixPins {
+    rows: [Peri<'static, AnyPin>; LED_ROWS],
+    cols: [Peri<'static, AnyPin>; LED_COLS],
+}
+
+impl MatrixPins {
+    /// Pins of the rows, top first, and columns, left first.
+    pub const fn new(
+        rows: [Peri<'static, AnyPin>; LED_ROWS],
+        cols: [Peri<'static, AnyPin>; LED_COLS],
+    ) -> Self {
+        Self { rows, cols }
+    }
+
+    /// Rows (active high) driven low and columns (active low) high, all LEDs
+    /// off.
+    fn outputs(&mut self) -> ([Output<'_>; LED_ROWS], [Output<'_>; LED_COLS]) {
+        (
+            self.rows
+                .each_mut()
+                .map(|pin| Output::new(pin.reborrow(), Level::Low, OutputDrive::Standard)),
+            self.cols
+                .each_mut()
+                .map(|pin| Output::new(pin.reborrow(), Level::High, OutputDrive::Standard)),
+        )
+    }
+
+    /// The bsp's driver, for scrolling text at full brightness.
+    fn scroller(&mut self) -> LedMatrix<Output<'_>, LED_ROWS, LED_COLS> {
+        let (rows, cols) = self.outputs();
+        let mut matrix = LedMatrix::new(rows, cols);
+        matrix.set_brightness(Brightness::MAX);
+        matrix
+    }
+
+    /// Grayscale driver: the bsp's has one brightness for all LEDs.
+    fn row_scan(&mut self) -> RowScan<'_> {
+        let (rows, cols) = self.outputs();
+        RowScan { rows, cols }
+    }
 }
 
-async fn display_specific(
-    data: u16,
-    display_ms: u64,
-    matrix: &mut LedMatrix<Output<'static>, LED_ROWS, LED_COLS>,
-    units: &str,
-) {
-    let mut disp_txt: String<9> = String::new();
-    write!(&mut disp_txt, " {data} {units}").unwrap();
-    matrix.set_brightness(microbit_bsp::display::Brightness::MAX);
-    matrix
-        .scroll_with_speed(disp_txt.as_str(), Duration::from_millis(display_ms))
-        .await;
-    disp_txt.clear();
+/// Matrix rows and columns driven directly, a row at a time.
+struct RowScan<'d> {
+    rows: [Output<'d>; LED_ROWS],
+    cols: [Output<'d>; LED_COLS],
+}
+
+impl RowScan<'_> {
+    /// Show a grayscale frame for `duration`, each LED lit within its row's
+    /// drive for as many steps as its level. Steps keep to the deadlines of
+    /// one ticker, so a late wake-up shortens the step after it rather than
+    /// stretching the scan.
+    async fn show(&mut self, frame: &Grayscale, duration: Duration) {
+        let end = Instant::now() + duration;
+        let mut ticker = Ticker::every(LEVEL_STEP);
+        while Instant::now() < end {
+            for (row, row_pin) in self.rows.iter_mut().enumerate() {
+                row_pin.set_high();
+                for step in 0..ROW_STEPS {
+                    // None lit past the brightest level
+                    let lit = frame.lit_at(row, step);
+                    for (col, col_pin) in self.cols.iter_mut().enumerate() {
+                        if lit.is_set(col) {
+                            col_pin.set_low();
+                        } else {
+                            col_pin.set_high();
+                        }
+                    }
+                    ticker.next().await;
+                }
+                row_pin.set_low();
+            }
+        }
+    }
+}
+
+/// Show a screen until done, or interrupted by dropping the future.
+async fn show(screen: Screen, pins: &mut MatrixPins) {
+    match screen {
+        Screen::Dashboard(animation) => {
+            let mut scan = pins.row_scan();
+            for dash in animation {
+                scan.show(&dash, DASHBOARD_FRAME).await;
+            }
+        }
+        Screen::Scroll { text, duration } => {
+            let mut matrix = pins.scroller();
+            match duration {
+                Some(duration) => matrix.scroll_with_speed(text.as_str(), duration).await,
+                None => matrix.scroll(text.as_str()).await,
+            }
+        }
+    }
 }
 
 #[embassy_executor::task]
-pub async fn display_task(mut matrix: LedMatrix<Output<'static>, LED_ROWS, LED_COLS>) {
-    matrix.set_brightness(microbit_bsp::display::Brightness::MAX);
-    matrix.scroll(" Power ON!").await;
+pub async fn display_task(mut pins: MatrixPins, inventory: String<SUMMARY_MAX>, pages: Pages) {
+    {
+        let mut matrix = pins.scroller();
+        matrix.scroll(GREETING).await;
+        // Sensors found on the bus at boot
+        matrix.scroll(inventory.as_str()).await;
+    }
 
     let btn_rx = get_buttons_receiver();
-    let mut co2_rx = defmt::unwrap!(
-        sense_co2::get_sensor_receiver(),
-        "unable to get co2 sensor receiver"
-    );
-    let mut pa_rx = defmt::unwrap!(
-        sense_pa::get_sensor_receiver(),
-        "unable to get hpa sensor receiver"
-    );
+    let mut co2_rx = sense_co2::get_sensor_receiver().or_else(|| {
+        defmt::error!("Display: Request for co2 rx failed (CO2 and humidity unknown)");
+        None
+    });
+    // Second CO2 sensor, for its page
+    let mut second_rx = sense_co2::get_sensor_receiver_at(1);
+    let mut pa_rx = sense_pa::get_sensor_receiver().or_else(|| {
+        defmt::error!("Display: Request for pressure rx failed (using SCD4X temperature)");
+        None
+    });
+    let mut pm_rx = sense_pm::get_sensor_receiver().or_else(|| {
+        defmt::error!("Display: Request for pm rx failed (PM page shows local readings)");
+        None
+    });
+    let mut voc_rx = sense_voc::get_sensor_receiver().or_else(|| {
+        defmt::error!("Display: Request for voc rx failed (VOC page shows local readings)");
+        None
+    });
+    let mut worst_rx = if MESH_ENABLED {
+        radio::get_worst_receiver().or_else(|| {
+            defmt::error!("Display: Request for mesh rx failed (worst page disabled)");
+            None
+        })
+    } else {
+        None
+    };
+    let mut ui = Ui::with_pages(pages);
+    // Scroll for the last button press, shown before the dashboard
+    let mut pressed = None;
 
     loop {
-        let Co2Measurement { co2, humidity, .. } = co2_rx.get().await;
-        let PressureMeasurement { temp_c, .. } = pa_rx.get().await;
-
-        #[expect(
-            clippy::cast_possible_truncation,
-            clippy::cast_sign_loss,
-            reason = "values within bounds"
-        )]
-        let (co2_u16, humidity_u8, temp_f_i16) =
-            (co2 as u16, humidity as u8, fahrenheit(temp_c) as i16);
-
-        match btn_rx.try_receive() {
-            Ok(ButtonState::A) => {
-                info!("Button A: Display Temp F");
-                let (display_ms, units) = (2750, "F");
-                display_specific(temp_f_i16.cast_unsigned(), display_ms, &mut matrix, units).await;
-            }
-            Ok(ButtonState::B) => {
-                info!("Button B: Display CO2 PPM");
-                let (display_ms, units) = (4500, "ppm");
-                display_specific(co2_u16, display_ms, &mut matrix, units).await;
-            }
-            Ok(ButtonState::C) => {
-                info!("Button C: Display Humidity %");
-                let (display_ms, units) = (2750, "%");
-                display_specific(humidity_u8.into(), display_ms, &mut matrix, units).await;
+        let m_co2 = co2_rx.as_mut().and_then(|rx| rx.try_get());
+        let m_pa = pa_rx.as_mut().and_then(|rx| rx.try_get());
+        let local = checked(Readings::local(m_co2.as_ref(), m_pa.as_ref())).unwrap_or_default();
+        let taken = m_co2
+            .as_ref()
+            .map(|m_co2| m_co2.timestamp)
+            .or_else(|| m_pa.as_ref().map(|m_pa| m_pa.timestamp));
+        if let Some(taken) = taken {
+            ui.record(taken.uptime_ms, &local);
+        }
+        let worst = worst_rx.as_mut().and_then(|rx| rx.try_get());
+        let second = second_rx.as_mut().and_then(|rx| rx.try_get());
+        let readings = ui.readings(
+            local,
+            worst
+                .as_ref()
+                .and_then(|worst| checked(Readings::try_from(worst))),
+            second
+                .as_ref()
+                .and_then(|second| checked(Readings::try_from(second))),
+        );
+        let pollutants = Pollutants {
+            particulates: pm_rx
+                .as_mut()
+                .and_then(|rx| rx.try_get())
+                .as_ref()
+                .map(Particulates::from),
+            gases: voc_rx
+                .as_mut()
+                .and_then(|rx| rx.try_get())
+                .as_ref()
+                .map(Gases::from),
+        };
+
+        let screen = match pressed.take() {
+            Some(button) => {
+                let screen = ui.press(button, &readings, &pollutants);
+                info!(
+                    "Button {:?}: Display {:?} on page {:?}",
+                    button,
+                    screen,
+                    ui.page()
+                );
+                screen
             }
-            // Only possible error is TryReceiveError, indicating an empty buffer
-            Err(_) => {
-                display_dash(co2_u16, humidity_u8, temp_f_i16, &mut matrix).await;
+            None => ui.dashboard(&readings, &pollutants),
+        };
+        let scrolling = matches!(screen, Screen::Scroll { .. });
+        // The dashboard refreshes once its animation has run, or with a new
+        // measurement from any sensor fitted; scrolls run to the end unless a
+        // button interrupts
+        let measured = async {
+            if scrolling {
+                pending::<()>().await;
             }
+            select4(
+                changed(&mut co2_rx),
+                changed(&mut pa_rx),
+                changed(&mut pm_rx),
+                changed(&mut voc_rx),
+            )
+            .await
+        };
+        match select3(show(screen, &mut pins), btn_rx.receive(), measured).await {
+            Either3::First(()) if scrolling => ui.scrolled(),
+            Either3::Second(button) => pressed = Some(button),
+            Either3::First(()) | Either3::Third(_) => {}
         }
     }
 }
+
+/// Wait for a new value, or forever without a receiver.
+async fn changed<T: Clone>(rx: &mut Option<DynReceiver<'static, T>>) -> T {
+    match rx.as_mut() {
+        Some(rx) => rx.changed().await,
+        None => pending().await,
+    }
+}
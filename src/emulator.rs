@@ -0,0 +1,106 @@
+//! Emulated I2C sensors, for exercising drivers and sensor control without
+//! hardware.
+//!
+//! Each emulator answers on its own address and implements the
+//! `embedded-hal-async` [`I2c`] trait by shared reference, so a test can hand
+//! `&emulator` to a driver and still inspect or fault the device. A [`Bus`]
+//! shares several emulators, as on the Qwiic connector, including any behind
+//! an emulated mux.
+
+pub mod bmp581;
+pub mod pmsa003i;
+pub mod scd4x;
+pub mod sgp4x;
+pub mod sps30;
+pub mod tca9548a;
+
+use embedded_hal_async::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+/// Emulated I2C device.
+pub trait Device {
+    /// Address the device answers at.
+    fn address(&self) -> u8;
+
+    /// Handle a transaction addressed to the device.
+    ///
+    /// # Errors
+    /// Returns a NACK as the device would.
+    fn transaction(&self, operations: &mut [Operation<'_>]) -> Result<(), Nack>;
+
+    /// Device answering at `address` behind this one, as behind a mux.
+    fn downstream(&self, _address: u8) -> Option<&dyn Device> {
+        None
+    }
+}
+
+/// Device answering at `address` among `devices`, or behind one of them.
+fn route<'a>(devices: &[&'a dyn Device], address: u8) -> Option<&'a dyn Device> {
+    devices.iter().find_map(|&device| {
+        if device.address() == address {
+            Some(device)
+        } else {
+            device.downstream(address)
+        }
+    })
+}
+
+/// Emulated devices sharing a bus. Unanswered addresses NACK.
+pub struct Bus<'a, const N: usize> {
+    devices: [&'a dyn Device; N],
+}
+
+impl<'a, const N: usize> Bus<'a, N> {
+    #[must_use]
+    pub const fn new(devices: [&'a dyn Device; N]) -> Self {
+        Self { devices }
+    }
+}
+
+impl<const N: usize> ErrorType for &Bus<'_, N> {
+    type Error = Nack;
+}
+
+#[expect(
+    clippy::unused_async_trait_impl,
+    reason = "emulated devices answer immediately"
+)]
+impl<const N: usize> I2c for &Bus<'_, N> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Nack> {
+        route(&self.devices, address)
+            .map_or(Err(Nack::Address), |device| device.transaction(operations))
+    }
+}
+
+/// Injected faults, consumed as they trigger.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, defmt::Format)]
+pub struct Faults {
+    /// Transactions to NACK before answering again.
+    pub nacks: u8,
+    /// Responses to send with a corrupt CRC on the first word (SCD4X, SGP4X,
+    /// SPS30) or checksum (PMSA003I).
+    pub bad_crcs: u8,
+    /// Never report new data as ready.
+    pub stuck_data_ready: bool,
+}
+
+/// Emulated bus error: the device did not acknowledge.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Nack {
+    /// Address NACK: wrong address, busy, or an injected fault.
+    Address,
+    /// Data NACK: unknown or disallowed command, or bad argument CRC.
+    Data,
+}
+
+impl embedded_hal_async::i2c::Error for Nack {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::NoAcknowledge(match self {
+            Self::Address => NoAcknowledgeSource::Address,
+            Self::Data => NoAcknowledgeSource::Data,
+        })
+    }
+}
@@ -0,0 +1,341 @@
+//! Emulated BMP581 pressure and temperature sensor.
+//!
+//! Models the register map over I2C: auto-incrementing register access,
+//! soft reset (the sensor NACKs while resetting), power-on-reset and
+//! data-ready interrupt flags (cleared on read), and the power modes. A
+//! forced measurement updates the data registers once and returns to
+//! standby; normal and continuous modes measure before every transaction.
+
+use core::cell::RefCell;
+
+use embassy_time::{Duration, Instant};
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+
+use super::{Device, Faults, Nack};
+
+/// I2C address with SDO high (the Qwiic board's default).
+pub const ADDRESS: u8 = 0x47;
+
+/// I2C address with SDO low.
+pub const ADDRESS_SDO_LOW: u8 = 0x46;
+
+/// Chip ID of a BMP581.
+pub const CHIP_ID: u8 = 0x50;
+
+/// Register addresses.
+pub mod register {
+    pub const CHIP_ID: u8 = 0x01;
+    pub const REV_ID: u8 = 0x02;
+    pub const CHIP_STATUS: u8 = 0x11;
+    pub const TEMP_DATA_XLSB: u8 = 0x1d;
+    pub const PRESS_DATA_XLSB: u8 = 0x20;
+    pub const INT_STATUS: u8 = 0x27;
+    pub const STATUS: u8 = 0x28;
+    pub const DSP_CONFIG: u8 = 0x30;
+    pub const DSP_IIR: u8 = 0x31;
+    pub const OSR_CONFIG: u8 = 0x36;
+    pub const ODR_CONFIG: u8 = 0x37;
+    pub const CMD: u8 = 0x7e;
+}
+
+/// `INT_STATUS` flags.
+const INT_DRDY: u8 = 0x01;
+const INT_POR: u8 = 0x10;
+
+/// `STATUS`: NVM ready.
+const STATUS_NVM_RDY: u8 = 0x02;
+
+/// `OSR_CONFIG`: pressure measurement enabled.
+const OSR_PRESS_EN: u8 = 0x40;
+
+/// `ODR_CONFIG` power mode field.
+const ODR_PWR_MODE: u8 = 0x03;
+
+/// `CMD`: soft reset.
+const CMD_SOFT_RESET: u8 = 0xb6;
+
+/// Time to complete a soft reset.
+const SOFT_RESET: Duration = Duration::from_millis(2);
+
+/// Registers with reset values other than zero.
+const RESET_VALUES: [(u8, u8); 6] = [
+    (register::CHIP_ID, CHIP_ID),
+    (register::REV_ID, 0x32),
+    (register::INT_STATUS, INT_POR),
+    (register::STATUS, STATUS_NVM_RDY),
+    (register::DSP_CONFIG, 0x30),
+    (register::ODR_CONFIG, 0x70),
+];
+
+/// Registers the host cannot write.
+const READ_ONLY: [u8; 5] = [
+    register::CHIP_ID,
+    register::REV_ID,
+    register::CHIP_STATUS,
+    register::INT_STATUS,
+    register::STATUS,
+];
+
+/// Power mode, from `ODR_CONFIG`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum PowerMode {
+    Standby,
+    Normal,
+    Forced,
+    Continuous,
+}
+
+impl PowerMode {
+    const fn from_odr_config(odr_config: u8) -> Self {
+        match odr_config & ODR_PWR_MODE {
+            0 => Self::Standby,
+            1 => Self::Normal,
+            2 => Self::Forced,
+            _ => Self::Continuous,
+        }
+    }
+}
+
+struct State {
+    address: u8,
+    registers: [u8; 0x80],
+    /// Register for the next access.
+    pointer: u8,
+    /// No response until a soft reset has completed.
+    busy_until: Instant,
+    pa: f32,
+    temp_c: f32,
+    faults: Faults,
+}
+
+/// Emulated BMP581.
+pub struct EmulatedBmp581 {
+    state: RefCell<State>,
+}
+
+impl Default for EmulatedBmp581 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmulatedBmp581 {
+    /// Sensor at [`ADDRESS`] after power on, measuring 101 325 Pa and 21 C.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::at(ADDRESS)
+    }
+
+    /// Sensor at an address, after power on.
+    #[must_use]
+    pub fn at(address: u8) -> Self {
+        let mut state = State {
+            address,
+            registers: [0; 0x80],
+            pointer: 0,
+            busy_until: Instant::MIN,
+            pa: 101_325.0,
+            temp_c: 21.0,
+            faults: Faults::default(),
+        };
+        state.reset();
+        Self {
+            state: RefCell::new(state),
+        }
+    }
+
+    /// Set the conditions measured from now on.
+    pub fn set_ambient(&self, pa: f32, temp_c: f32) {
+        let mut state = self.state.borrow_mut();
+        state.pa = pa;
+        state.temp_c = temp_c;
+    }
+
+    /// Inject faults, replacing any not yet triggered.
+    pub fn inject(&self, faults: Faults) {
+        self.state.borrow_mut().faults = faults;
+    }
+
+    /// Faults not yet triggered.
+    #[must_use]
+    pub fn faults(&self) -> Faults {
+        self.state.borrow().faults
+    }
+
+    #[must_use]
+    pub fn power_mode(&self) -> PowerMode {
+        PowerMode::from_odr_config(self.register(register::ODR_CONFIG))
+    }
+
+    /// Register value, without read side effects.
+    #[must_use]
+    pub fn register(&self, address: u8) -> u8 {
+        self.state.borrow().get(address)
+    }
+}
+
+impl ErrorType for &EmulatedBmp581 {
+    type Error = Nack;
+}
+
+#[expect(
+    clippy::unused_async_trait_impl,
+    reason = "the emulated device answers immediately"
+)]
+impl I2c for &EmulatedBmp581 {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Nack> {
+        if address == self.address() {
+            Device::transaction(*self, operations)
+        } else {
+            Err(Nack::Address)
+        }
+    }
+}
+
+impl Device for EmulatedBmp581 {
+    fn address(&self) -> u8 {
+        self.state.borrow().address
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        self.state.borrow_mut().transaction(operations)
+    }
+}
+
+impl State {
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        if Instant::now() < self.busy_until {
+            return Err(Nack::Address);
+        }
+        if self.faults.nacks > 0 {
+            self.faults.nacks -= 1;
+            return Err(Nack::Address);
+        }
+        if matches!(
+            PowerMode::from_odr_config(self.get(register::ODR_CONFIG)),
+            PowerMode::Normal | PowerMode::Continuous
+        ) {
+            self.measure();
+        }
+
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    let [register, data @ ..] = &**bytes else {
+                        return Err(Nack::Data);
+                    };
+                    self.pointer = *register;
+                    for &value in data {
+                        self.write(value)?;
+                    }
+                }
+                Operation::Read(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        *byte = self.read()?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, address: u8) -> u8 {
+        self.registers
+            .get(usize::from(address))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn set(&mut self, address: u8, value: u8) {
+        if let Some(register) = self.registers.get_mut(usize::from(address)) {
+            *register = value;
+        }
+    }
+
+    /// Write the register at the pointer, then advance.
+    fn write(&mut self, value: u8) -> Result<(), Nack> {
+        let address = self.pointer;
+        if usize::from(address) >= self.registers.len() {
+            return Err(Nack::Data);
+        }
+        self.pointer = address.wrapping_add(1);
+        match address {
+            register::CMD => {
+                if value == CMD_SOFT_RESET {
+                    self.reset();
+                    self.busy_until = Instant::now() + SOFT_RESET;
+                }
+            }
+            register::ODR_CONFIG => {
+                self.set(address, value);
+                if PowerMode::from_odr_config(value) == PowerMode::Forced {
+                    self.measure();
+                    self.set(address, value & !ODR_PWR_MODE);
+                }
+            }
+            _ if READ_ONLY.contains(&address) => {}
+            _ => self.set(address, value),
+        }
+        Ok(())
+    }
+
+    /// Read the register at the pointer, then advance.
+    fn read(&mut self) -> Result<u8, Nack> {
+        let address = self.pointer;
+        if usize::from(address) >= self.registers.len() {
+            return Err(Nack::Data);
+        }
+        self.pointer = address.wrapping_add(1);
+        let value = self.get(address);
+        if address == register::INT_STATUS {
+            self.set(address, 0);
+        }
+        Ok(value)
+    }
+
+    fn reset(&mut self) {
+        self.registers = [0; 0x80];
+        for (address, value) in RESET_VALUES {
+            self.set(address, value);
+        }
+    }
+
+    /// Update the data registers and raise data-ready, unless stuck.
+    fn measure(&mut self) {
+        if self.faults.stuck_data_ready {
+            return;
+        }
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "in range of the 24-bit data registers"
+        )]
+        let temperature = libm::roundf(self.temp_c * 65536.0) as i32;
+        let pressure = if self.get(register::OSR_CONFIG) & OSR_PRESS_EN == 0 {
+            0
+        } else {
+            #[expect(
+                clippy::cast_possible_truncation,
+                reason = "in range of the 24-bit data registers"
+            )]
+            let pressure = libm::roundf(self.pa * 64.0) as i32;
+            pressure
+        };
+        for (first, value) in [
+            (register::TEMP_DATA_XLSB, temperature),
+            (register::PRESS_DATA_XLSB, pressure),
+        ] {
+            let [xlsb, lsb, msb, _] = value.to_le_bytes();
+            for (address, byte) in (first..).zip([xlsb, lsb, msb]) {
+                self.set(address, byte);
+            }
+        }
+        self.set(
+            register::INT_STATUS,
+            self.get(register::INT_STATUS) | INT_DRDY,
+        );
+    }
+}
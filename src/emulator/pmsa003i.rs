@@ -0,0 +1,166 @@
+//! Emulated PMSA003I particulate matter sensor.
+//!
+//! Models the I2C interface: the sensor measures from power on, and every
+//! read returns its latest 32-byte frame from the start. Writes are ignored.
+
+use core::cell::RefCell;
+
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+
+use super::{Device, Faults, Nack};
+
+/// I2C address.
+pub const ADDRESS: u8 = 0x12;
+
+/// Frame length, in bytes.
+pub const FRAME_LEN: usize = 32;
+
+/// Frame start characters.
+const START: [u8; 2] = [0x42, 0x4d];
+
+/// Frame length field: the data words and checksum that follow it.
+const LENGTH: u16 = 28;
+
+/// Ambient conditions the sensor measures (ug/m3).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+struct Ambient {
+    pm1: u16,
+    pm2_5: u16,
+    pm10: u16,
+}
+
+struct State {
+    ambient: Ambient,
+    faults: Faults,
+}
+
+/// Emulated PMSA003I, at [`ADDRESS`].
+pub struct EmulatedPmsa003i {
+    state: RefCell<State>,
+}
+
+impl Default for EmulatedPmsa003i {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmulatedPmsa003i {
+    /// Sensor after power on, measuring 3, 5 and 8 ug/m3 of PM1.0, PM2.5 and
+    /// PM10.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            state: RefCell::new(State {
+                ambient: Ambient {
+                    pm1: 3,
+                    pm2_5: 5,
+                    pm10: 8,
+                },
+                faults: Faults::default(),
+            }),
+        }
+    }
+
+    /// Set the concentrations (ug/m3) measured from now on.
+    pub fn set_ambient(&self, pm1: u16, pm2_5: u16, pm10: u16) {
+        self.state.borrow_mut().ambient = Ambient { pm1, pm2_5, pm10 };
+    }
+
+    /// Inject faults, replacing any not yet triggered. Bad CRCs corrupt the
+    /// frame checksum.
+    pub fn inject(&self, faults: Faults) {
+        self.state.borrow_mut().faults = faults;
+    }
+
+    /// Faults not yet triggered.
+    #[must_use]
+    pub fn faults(&self) -> Faults {
+        self.state.borrow().faults
+    }
+}
+
+impl ErrorType for &EmulatedPmsa003i {
+    type Error = Nack;
+}
+
+#[expect(
+    clippy::unused_async_trait_impl,
+    reason = "the emulated device answers immediately"
+)]
+impl I2c for &EmulatedPmsa003i {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Nack> {
+        if address == ADDRESS {
+            Device::transaction(*self, operations)
+        } else {
+            Err(Nack::Address)
+        }
+    }
+}
+
+impl Device for EmulatedPmsa003i {
+    fn address(&self) -> u8 {
+        ADDRESS
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        self.state.borrow_mut().transaction(operations)
+    }
+}
+
+impl State {
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        if self.faults.nacks > 0 {
+            self.faults.nacks -= 1;
+            return Err(Nack::Address);
+        }
+        for operation in operations {
+            if let Operation::Read(buffer) = operation {
+                // Past the frame's end, the idle bus reads high
+                let frame = self.frame();
+                let mut frame = frame.iter();
+                for byte in buffer.iter_mut() {
+                    *byte = frame.next().copied().unwrap_or(0xff);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Latest frame, with a corrupt checksum if injected.
+    fn frame(&mut self) -> [u8; FRAME_LEN] {
+        let Ambient { pm1, pm2_5, pm10 } = self.ambient;
+        // Standard particle then atmospheric environment concentrations; the
+        // particle counts, version and error code read zero
+        let words = [
+            u16::from_be_bytes(START),
+            LENGTH,
+            pm1,
+            pm2_5,
+            pm10,
+            pm1,
+            pm2_5,
+            pm10,
+        ];
+        let mut frame = [0; FRAME_LEN];
+        for (bytes, word) in frame.as_chunks_mut::<2>().0.iter_mut().zip(words) {
+            *bytes = word.to_be_bytes();
+        }
+        let [body @ .., hi, lo] = &mut frame;
+        let sum = body
+            .iter()
+            .fold(0_u16, |sum, &byte| sum.wrapping_add(u16::from(byte)));
+        let sum = if self.faults.bad_crcs > 0 {
+            self.faults.bad_crcs -= 1;
+            !sum
+        } else {
+            sum
+        };
+        [*hi, *lo] = sum.to_be_bytes();
+        frame
+    }
+}
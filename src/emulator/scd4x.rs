@@ -0,0 +1,460 @@
+//! Emulated SCD4X CO2, humidity, and temperature sensor.
+//!
+//! Models the I2C command set: 16-bit commands with CRC-8 protected argument
+//! and response words, command execution times (the sensor NACKs while
+//! busy), the commands allowed during periodic measurement, and the
+//! data-ready flag raised once per measurement interval.
+
+use core::cell::RefCell;
+
+use embassy_time::{Duration, Instant};
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+use heapless::{CapacityError, HistoryBuf, Vec};
+
+use super::{Device, Faults, Nack};
+use crate::sensirion::crc8;
+
+/// I2C address.
+pub const ADDRESS: u8 = 0x62;
+
+/// Commands kept for [`EmulatedScd4x::take_commands`].
+pub const COMMAND_LOG: usize = 32;
+
+/// Temperature offset after a factory reset (C).
+const DEFAULT_TEMP_OFFSET_C: f32 = 4.0;
+
+/// Ambient pressure after a factory reset (hPa).
+const DEFAULT_AMBIENT_HPA: u16 = 1013;
+
+/// Serial number words.
+const SERIAL: [u16; 3] = [0xbe5f, 0x3b07, 0x1f61];
+
+/// SCD41 in the sensor variant word.
+const VARIANT_SCD41: u16 = 0x1000;
+
+/// Data-ready status words: any of the low 11 bits set means ready.
+const DATA_READY: u16 = 0x8006;
+const DATA_NOT_READY: u16 = 0x8000;
+
+/// Forced recalibration response for a failed recalibration.
+const FRC_FAILED: u16 = 0xffff;
+
+/// Command codes.
+pub mod command {
+    pub const START_PERIODIC_MEASUREMENT: u16 = 0x21b1;
+    pub const START_LOW_POWER_PERIODIC_MEASUREMENT: u16 = 0x21ac;
+    pub const STOP_PERIODIC_MEASUREMENT: u16 = 0x3f86;
+    pub const READ_MEASUREMENT: u16 = 0xec05;
+    pub const GET_DATA_READY_STATUS: u16 = 0xe4b8;
+    pub const SET_TEMPERATURE_OFFSET: u16 = 0x241d;
+    pub const GET_TEMPERATURE_OFFSET: u16 = 0x2318;
+    pub const SET_SENSOR_ALTITUDE: u16 = 0x2427;
+    pub const GET_SENSOR_ALTITUDE: u16 = 0x2322;
+    /// Set with an argument, get without.
+    pub const AMBIENT_PRESSURE: u16 = 0xe000;
+    pub const PERFORM_FORCED_RECALIBRATION: u16 = 0x362f;
+    pub const GET_SERIAL_NUMBER: u16 = 0x3682;
+    pub const GET_SENSOR_VARIANT: u16 = 0x202f;
+    pub const PERSIST_SETTINGS: u16 = 0x3615;
+    pub const REINIT: u16 = 0x3646;
+    pub const PERFORM_FACTORY_RESET: u16 = 0x3632;
+}
+
+/// Commands the sensor accepts during periodic measurement.
+const PERIODIC_COMMANDS: [u16; 4] = [
+    command::READ_MEASUREMENT,
+    command::GET_DATA_READY_STATUS,
+    command::STOP_PERIODIC_MEASUREMENT,
+    command::AMBIENT_PRESSURE,
+];
+
+/// Measurement state.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Mode {
+    Idle,
+    /// Periodic measurement every 5 s.
+    Periodic,
+    /// Low power periodic measurement every 30 s.
+    LowPowerPeriodic,
+}
+
+impl Mode {
+    const fn interval(self) -> Option<Duration> {
+        match self {
+            Self::Idle => None,
+            Self::Periodic => Some(Duration::from_secs(5)),
+            Self::LowPowerPeriodic => Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+/// Ambient conditions the sensor measures.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+struct Ambient {
+    co2: u16,
+    humidity: f32,
+    temp_c: f32,
+}
+
+struct State {
+    mode: Mode,
+    /// No response until the last command has executed.
+    busy_until: Instant,
+    /// Response words with CRCs, for the next read.
+    response: Vec<u8, 9>,
+    ambient: Ambient,
+    /// Next measurement while measuring.
+    next_sample: Instant,
+    /// A measurement was taken and not yet read.
+    unread: bool,
+    /// A measurement was taken since power on (needed to recalibrate).
+    measured: bool,
+    temp_offset: u16,
+    altitude_m: u16,
+    ambient_hpa: u16,
+    commands: HistoryBuf<u16, COMMAND_LOG>,
+    faults: Faults,
+}
+
+/// Emulated SCD4X (an SCD41), at [`ADDRESS`].
+pub struct EmulatedScd4x {
+    state: RefCell<State>,
+}
+
+impl Default for EmulatedScd4x {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmulatedScd4x {
+    /// Idle sensor, with factory settings, measuring 612 ppm, 41.5 %, 25.25 C
+    /// (21.25 C after the default offset).
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            state: RefCell::new(State {
+                mode: Mode::Idle,
+                busy_until: Instant::MIN,
+                response: Vec::new(),
+                ambient: Ambient {
+                    co2: 612,
+                    humidity: 41.5,
+                    temp_c: 25.25,
+                },
+                next_sample: Instant::MIN,
+                unread: false,
+                measured: false,
+                temp_offset: encode_temperature_offset(DEFAULT_TEMP_OFFSET_C),
+                altitude_m: 0,
+                ambient_hpa: DEFAULT_AMBIENT_HPA,
+                commands: HistoryBuf::new(),
+                faults: Faults::default(),
+            }),
+        }
+    }
+
+    /// Set the conditions measured from now on; temperature is before the
+    /// sensor's offset is subtracted.
+    pub fn set_ambient(&self, co2: u16, humidity: f32, temp_c: f32) {
+        self.state.borrow_mut().ambient = Ambient {
+            co2,
+            humidity,
+            temp_c,
+        };
+    }
+
+    /// Take a measurement now, as if the interval had elapsed, if measuring.
+    pub fn sample(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.mode != Mode::Idle {
+            state.sample(Instant::now());
+        }
+    }
+
+    /// Inject faults, replacing any not yet triggered.
+    pub fn inject(&self, faults: Faults) {
+        self.state.borrow_mut().faults = faults;
+    }
+
+    /// Faults not yet triggered.
+    #[must_use]
+    pub fn faults(&self) -> Faults {
+        self.state.borrow().faults
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> Mode {
+        self.state.borrow().mode
+    }
+
+    /// Temperature offset (C), as stored (to the sensor's resolution).
+    #[must_use]
+    pub fn temperature_offset(&self) -> f32 {
+        decode_temperature_offset(self.state.borrow().temp_offset)
+    }
+
+    /// Altitude compensation (m).
+    #[must_use]
+    pub fn altitude(&self) -> u16 {
+        self.state.borrow().altitude_m
+    }
+
+    /// Ambient pressure compensation (hPa).
+    #[must_use]
+    pub fn ambient_pressure(&self) -> u16 {
+        self.state.borrow().ambient_hpa
+    }
+
+    /// Commands received since last taken, oldest first; only the latest
+    /// [`COMMAND_LOG`] are kept.
+    #[must_use]
+    pub fn take_commands(&self) -> Vec<u16, COMMAND_LOG> {
+        let mut state = self.state.borrow_mut();
+        let commands = state.commands.oldest_ordered().copied().collect();
+        state.commands.clear();
+        commands
+    }
+}
+
+impl ErrorType for &EmulatedScd4x {
+    type Error = Nack;
+}
+
+#[expect(
+    clippy::unused_async_trait_impl,
+    reason = "the emulated device answers immediately"
+)]
+impl I2c for &EmulatedScd4x {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Nack> {
+        if address == ADDRESS {
+            Device::transaction(*self, operations)
+        } else {
+            Err(Nack::Address)
+        }
+    }
+}
+
+impl Device for EmulatedScd4x {
+    fn address(&self) -> u8 {
+        ADDRESS
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        self.state.borrow_mut().transaction(operations)
+    }
+}
+
+impl State {
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        let now = Instant::now();
+        if now < self.busy_until {
+            return Err(Nack::Address);
+        }
+        if self.faults.nacks > 0 {
+            self.faults.nacks -= 1;
+            return Err(Nack::Address);
+        }
+        if self.next_sample <= now && self.mode != Mode::Idle {
+            self.sample(now);
+        }
+
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => self.write(bytes, now)?,
+                Operation::Read(buffer) => self.read(buffer),
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute a command, with an optional argument word.
+    fn write(&mut self, bytes: &[u8], now: Instant) -> Result<(), Nack> {
+        let (command, argument) = match *bytes {
+            [hi, lo] => (u16::from_be_bytes([hi, lo]), None),
+            [hi, lo, a, b, crc] if crc8([a, b]) == crc => (
+                u16::from_be_bytes([hi, lo]),
+                Some(u16::from_be_bytes([a, b])),
+            ),
+            _ => return Err(Nack::Data),
+        };
+        self.commands.write(command);
+        self.response.clear();
+        if self.mode != Mode::Idle && !PERIODIC_COMMANDS.contains(&command) {
+            return Err(Nack::Data);
+        }
+
+        let (execution_ms, response) = self.execute(command, argument, now)?;
+        self.busy_until = now + Duration::from_millis(execution_ms);
+        for word in response {
+            let [hi, lo] = word.to_be_bytes();
+            self.response
+                .extend_from_slice(&[hi, lo, crc8([hi, lo])])
+                .map_err(|CapacityError { .. }| Nack::Data)?;
+        }
+        if self.faults.bad_crcs > 0
+            && let Some(crc) = self.response.get_mut(2)
+        {
+            self.faults.bad_crcs -= 1;
+            *crc ^= 0xff;
+        }
+        Ok(())
+    }
+
+    /// Apply a command, returning its execution time (ms) and response.
+    fn execute(
+        &mut self,
+        command: u16,
+        argument: Option<u16>,
+        now: Instant,
+    ) -> Result<(u64, Vec<u16, 3>), Nack> {
+        let mut response = Vec::new();
+        let mut respond = |words: &[u16]| {
+            response
+                .extend_from_slice(words)
+                .map_err(|CapacityError { .. }| Nack::Data)
+        };
+        let execution_ms = match (command, argument) {
+            (command::START_PERIODIC_MEASUREMENT, None) => {
+                self.start(Mode::Periodic, now);
+                0
+            }
+            (command::START_LOW_POWER_PERIODIC_MEASUREMENT, None) => {
+                self.start(Mode::LowPowerPeriodic, now);
+                0
+            }
+            (command::STOP_PERIODIC_MEASUREMENT, None) => {
+                self.mode = Mode::Idle;
+                500
+            }
+            (command::READ_MEASUREMENT, None) => {
+                if !self.measured {
+                    return Err(Nack::Data);
+                }
+                self.unread = false;
+                respond(&self.measurement())?;
+                1
+            }
+            (command::GET_DATA_READY_STATUS, None) => {
+                let ready = self.unread && !self.faults.stuck_data_ready;
+                respond(&[if ready { DATA_READY } else { DATA_NOT_READY }])?;
+                1
+            }
+            (command::SET_TEMPERATURE_OFFSET, Some(offset)) => {
+                self.temp_offset = offset;
+                1
+            }
+            (command::GET_TEMPERATURE_OFFSET, None) => {
+                respond(&[self.temp_offset])?;
+                1
+            }
+            (command::SET_SENSOR_ALTITUDE, Some(altitude_m)) => {
+                self.altitude_m = altitude_m;
+                1
+            }
+            (command::GET_SENSOR_ALTITUDE, None) => {
+                respond(&[self.altitude_m])?;
+                1
+            }
+            (command::AMBIENT_PRESSURE, Some(hpa)) => {
+                self.ambient_hpa = hpa;
+                1
+            }
+            (command::AMBIENT_PRESSURE, None) => {
+                respond(&[self.ambient_hpa])?;
+                1
+            }
+            (command::PERFORM_FORCED_RECALIBRATION, Some(ppm)) => {
+                respond(&[self.recalibrate(ppm)])?;
+                400
+            }
+            (command::GET_SERIAL_NUMBER, None) => {
+                respond(&SERIAL)?;
+                1
+            }
+            (command::GET_SENSOR_VARIANT, None) => {
+                respond(&[VARIANT_SCD41])?;
+                1
+            }
+            (command::PERSIST_SETTINGS, None) => 800,
+            (command::REINIT, None) => 30,
+            (command::PERFORM_FACTORY_RESET, None) => {
+                self.temp_offset = encode_temperature_offset(DEFAULT_TEMP_OFFSET_C);
+                self.altitude_m = 0;
+                self.ambient_hpa = DEFAULT_AMBIENT_HPA;
+                1200
+            }
+            _ => return Err(Nack::Data),
+        };
+        Ok((execution_ms, response))
+    }
+
+    /// Read the response; past its end, the idle bus reads high.
+    fn read(&mut self, buffer: &mut [u8]) {
+        let mut response = self.response.iter();
+        for byte in buffer {
+            *byte = response.next().copied().unwrap_or(0xff);
+        }
+        self.response.clear();
+    }
+
+    fn start(&mut self, mode: Mode, now: Instant) {
+        self.mode = mode;
+        self.unread = false;
+        if let Some(interval) = mode.interval() {
+            self.next_sample = now + interval;
+        }
+    }
+
+    /// Take a measurement, scheduling the next.
+    fn sample(&mut self, now: Instant) {
+        self.unread = true;
+        self.measured = true;
+        if let Some(interval) = self.mode.interval() {
+            self.next_sample = now + interval;
+        }
+    }
+
+    /// CO2, temperature, and humidity words of the latest measurement.
+    fn measurement(&self) -> [u16; 3] {
+        let temp_c = self.ambient.temp_c - decode_temperature_offset(self.temp_offset);
+        [
+            self.ambient.co2,
+            scale(temp_c + 45.0, 175.0),
+            scale(self.ambient.humidity, 100.0),
+        ]
+    }
+
+    /// Forced recalibration response: the correction (ppm) offset by 0x8000.
+    fn recalibrate(&self, ppm: u16) -> u16 {
+        if self.measured {
+            let correction = i32::from(ppm) - i32::from(self.ambient.co2);
+            u16::try_from(correction + 0x8000).unwrap_or(FRC_FAILED)
+        } else {
+            FRC_FAILED
+        }
+    }
+}
+
+/// Word for `value` in `[0, full_scale]`.
+fn scale(value: f32, full_scale: f32) -> u16 {
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "clamped to the word's range"
+    )]
+    let word = libm::roundf((value / full_scale).clamp(0.0, 1.0) * f32::from(u16::MAX)) as u16;
+    word
+}
+
+fn encode_temperature_offset(offset_c: f32) -> u16 {
+    scale(offset_c, 175.0)
+}
+
+fn decode_temperature_offset(word: u16) -> f32 {
+    f32::from(word) * 175.0 / f32::from(u16::MAX)
+}
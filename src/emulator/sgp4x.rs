@@ -0,0 +1,241 @@
+//! Emulated SGP40 and SGP41 gas sensors.
+//!
+//! Models the I2C command set: 16-bit commands with CRC-8 protected argument
+//! and response words, the execution times (the sensor NACKs while busy), and
+//! the commands each variant accepts. Raw signals are set directly rather
+//! than derived from gas concentrations.
+
+use core::cell::RefCell;
+
+use embassy_time::{Duration, Instant};
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+use heapless::{CapacityError, Vec};
+
+use super::{Device, Faults, Nack};
+use crate::sensirion::crc8;
+
+/// I2C address.
+pub const ADDRESS: u8 = 0x59;
+
+/// Serial number words, as read.
+pub const SERIAL_NUMBER: [u16; 3] = [0x0000, 0x0426, 0x7b1c];
+
+/// Command codes.
+pub mod command {
+    /// SGP40 only.
+    pub const MEASURE_RAW_SIGNAL: u16 = 0x260f;
+    /// SGP41 only.
+    pub const EXECUTE_CONDITIONING: u16 = 0x2612;
+    /// SGP41 only.
+    pub const MEASURE_RAW_SIGNALS: u16 = 0x2619;
+    pub const GET_SERIAL_NUMBER: u16 = 0x3682;
+}
+
+/// SGP40 measurement execution time.
+const SGP40_MEASURE: Duration = Duration::from_millis(30);
+
+/// SGP41 conditioning and measurement execution time.
+const SGP41_MEASURE: Duration = Duration::from_millis(50);
+
+/// Get serial number execution time.
+const GET_SERIAL_NUMBER: Duration = Duration::from_millis(1);
+
+/// Emulated variant.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Variant {
+    /// VOC.
+    Sgp40,
+    /// VOC and NOx.
+    Sgp41,
+}
+
+struct State {
+    variant: Variant,
+    /// No response until the last command has executed.
+    busy_until: Instant,
+    /// Response words with CRCs, for the next read.
+    response: Vec<u8, 9>,
+    voc_raw: u16,
+    nox_raw: u16,
+    /// Humidity and temperature ticks of the last measurement.
+    compensation: Option<(u16, u16)>,
+    /// Conditioning commands executed.
+    conditioned: u8,
+    faults: Faults,
+}
+
+/// Emulated SGP40 or SGP41, at [`ADDRESS`].
+pub struct EmulatedSgp4x {
+    state: RefCell<State>,
+}
+
+impl EmulatedSgp4x {
+    /// Sensor after power on, reading raw signals of 28000 (VOC) and 16000
+    /// (NOx, SGP41 only).
+    #[must_use]
+    pub fn new(variant: Variant) -> Self {
+        Self {
+            state: RefCell::new(State {
+                variant,
+                busy_until: Instant::MIN,
+                response: Vec::new(),
+                voc_raw: 28_000,
+                nox_raw: 16_000,
+                compensation: None,
+                conditioned: 0,
+                faults: Faults::default(),
+            }),
+        }
+    }
+
+    /// Set the raw signals read from now on.
+    pub fn set_raw(&self, voc_raw: u16, nox_raw: u16) {
+        let mut state = self.state.borrow_mut();
+        state.voc_raw = voc_raw;
+        state.nox_raw = nox_raw;
+    }
+
+    /// Humidity and temperature ticks passed with the last measurement or
+    /// conditioning command.
+    #[must_use]
+    pub fn compensation(&self) -> Option<(u16, u16)> {
+        self.state.borrow().compensation
+    }
+
+    /// Conditioning commands executed (SGP41).
+    #[must_use]
+    pub fn conditioned(&self) -> u8 {
+        self.state.borrow().conditioned
+    }
+
+    /// Inject faults, replacing any not yet triggered.
+    pub fn inject(&self, faults: Faults) {
+        self.state.borrow_mut().faults = faults;
+    }
+
+    /// Faults not yet triggered.
+    #[must_use]
+    pub fn faults(&self) -> Faults {
+        self.state.borrow().faults
+    }
+}
+
+impl ErrorType for &EmulatedSgp4x {
+    type Error = Nack;
+}
+
+#[expect(
+    clippy::unused_async_trait_impl,
+    reason = "the emulated device answers immediately"
+)]
+impl I2c for &EmulatedSgp4x {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Nack> {
+        if address == ADDRESS {
+            Device::transaction(*self, operations)
+        } else {
+            Err(Nack::Address)
+        }
+    }
+}
+
+impl Device for EmulatedSgp4x {
+    fn address(&self) -> u8 {
+        ADDRESS
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        self.state.borrow_mut().transaction(operations)
+    }
+}
+
+impl State {
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        let now = Instant::now();
+        if now < self.busy_until {
+            return Err(Nack::Address);
+        }
+        if self.faults.nacks > 0 {
+            self.faults.nacks -= 1;
+            return Err(Nack::Address);
+        }
+
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => self.write(bytes, now)?,
+                Operation::Read(buffer) => self.read(buffer),
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute a command, with the humidity and temperature arguments for a
+    /// measurement.
+    fn write(&mut self, bytes: &[u8], now: Instant) -> Result<(), Nack> {
+        let (command, compensation) = match *bytes {
+            [hi, lo] => (u16::from_be_bytes([hi, lo]), None),
+            [hi, lo, rh_hi, rh_lo, rh_crc, t_hi, t_lo, t_crc]
+                if crc8([rh_hi, rh_lo]) == rh_crc && crc8([t_hi, t_lo]) == t_crc =>
+            {
+                (
+                    u16::from_be_bytes([hi, lo]),
+                    Some((
+                        u16::from_be_bytes([rh_hi, rh_lo]),
+                        u16::from_be_bytes([t_hi, t_lo]),
+                    )),
+                )
+            }
+            _ => return Err(Nack::Data),
+        };
+        self.response.clear();
+
+        let (words, execution): (&[u16], Duration) = match (command, compensation, self.variant) {
+            (command::GET_SERIAL_NUMBER, None, _) => (&SERIAL_NUMBER, GET_SERIAL_NUMBER),
+            (command::MEASURE_RAW_SIGNAL, Some(_), Variant::Sgp40) => {
+                (&[self.voc_raw], SGP40_MEASURE)
+            }
+            (command::EXECUTE_CONDITIONING, Some(_), Variant::Sgp41) => {
+                self.conditioned = self.conditioned.saturating_add(1);
+                (&[self.voc_raw], SGP41_MEASURE)
+            }
+            (command::MEASURE_RAW_SIGNALS, Some(_), Variant::Sgp41) => {
+                (&[self.voc_raw, self.nox_raw], SGP41_MEASURE)
+            }
+            _ => return Err(Nack::Data),
+        };
+        if compensation.is_some() {
+            self.compensation = compensation;
+        }
+        for &word in words {
+            self.respond(word)?;
+        }
+        if self.faults.bad_crcs > 0
+            && let Some(crc) = self.response.get_mut(2)
+        {
+            self.faults.bad_crcs -= 1;
+            *crc ^= 0xff;
+        }
+        self.busy_until = now + execution;
+        Ok(())
+    }
+
+    /// Append a response word and its CRC.
+    fn respond(&mut self, word: u16) -> Result<(), Nack> {
+        let [hi, lo] = word.to_be_bytes();
+        self.response
+            .extend_from_slice(&[hi, lo, crc8([hi, lo])])
+            .map_err(|CapacityError { .. }| Nack::Data)
+    }
+
+    /// Read the response; past its end, the idle bus reads high.
+    fn read(&mut self, buffer: &mut [u8]) {
+        let mut response = self.response.iter();
+        for byte in buffer {
+            *byte = response.next().copied().unwrap_or(0xff);
+        }
+        self.response.clear();
+    }
+}
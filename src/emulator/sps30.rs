@@ -0,0 +1,272 @@
+//! Emulated SPS30 particulate matter sensor.
+//!
+//! Models the I2C command set: 16-bit commands with CRC-8 protected argument
+//! and response words, the start and stop execution times (the sensor NACKs
+//! while busy), the commands allowed in each mode, and a new measurement
+//! every second while measuring. Only float output is supported.
+
+use core::cell::RefCell;
+
+use embassy_time::{Duration, Instant};
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+use heapless::{CapacityError, Vec};
+
+use super::{Device, Faults, Nack};
+use crate::sensirion::crc8;
+
+/// I2C address.
+pub const ADDRESS: u8 = 0x69;
+
+/// Product type, as read.
+pub const PRODUCT_TYPE: [u8; 8] = *b"00080000";
+
+/// Start measurement argument: big-endian IEEE 754 float output.
+const OUTPUT_FORMAT_FLOAT: u16 = 0x0300;
+
+/// Measurement interval.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Start and stop measurement execution time.
+const MODE_CHANGE: Duration = Duration::from_millis(20);
+
+/// Command codes.
+pub mod command {
+    pub const START_MEASUREMENT: u16 = 0x0010;
+    pub const STOP_MEASUREMENT: u16 = 0x0104;
+    pub const READ_MEASURED_VALUES: u16 = 0x0300;
+    pub const READ_PRODUCT_TYPE: u16 = 0xd002;
+}
+
+/// Ambient conditions the sensor measures (ug/m3).
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+struct Ambient {
+    pm1: f32,
+    pm2_5: f32,
+    pm4_0: f32,
+    pm10: f32,
+}
+
+struct State {
+    measuring: bool,
+    /// No response until the last command has executed.
+    busy_until: Instant,
+    /// Response words with CRCs, for the next read: ten floats at most.
+    response: Vec<u8, 60>,
+    ambient: Ambient,
+    /// Latest measurement: zero until the first while measuring.
+    measured: Ambient,
+    /// Next measurement while measuring.
+    next_sample: Instant,
+    faults: Faults,
+}
+
+/// Emulated SPS30, at [`ADDRESS`].
+pub struct EmulatedSps30 {
+    state: RefCell<State>,
+}
+
+impl Default for EmulatedSps30 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmulatedSps30 {
+    /// Idle sensor, measuring 4, 6.5, 7 and 9 ug/m3 of PM1.0, PM2.5, PM4.0
+    /// and PM10.
+    #[must_use]
+    pub fn new() -> Self {
+        let zero = Ambient {
+            pm1: 0.0,
+            pm2_5: 0.0,
+            pm4_0: 0.0,
+            pm10: 0.0,
+        };
+        Self {
+            state: RefCell::new(State {
+                measuring: false,
+                busy_until: Instant::MIN,
+                response: Vec::new(),
+                ambient: Ambient {
+                    pm1: 4.0,
+                    pm2_5: 6.5,
+                    pm4_0: 7.0,
+                    pm10: 9.0,
+                },
+                measured: zero,
+                next_sample: Instant::MIN,
+                faults: Faults::default(),
+            }),
+        }
+    }
+
+    /// Set the concentrations (ug/m3) measured from now on.
+    pub fn set_ambient(&self, pm1: f32, pm2_5: f32, pm4_0: f32, pm10: f32) {
+        self.state.borrow_mut().ambient = Ambient {
+            pm1,
+            pm2_5,
+            pm4_0,
+            pm10,
+        };
+    }
+
+    /// Take a measurement now, as if the interval had elapsed, if measuring.
+    pub fn sample(&self) {
+        let mut state = self.state.borrow_mut();
+        if state.measuring {
+            state.sample(Instant::now());
+        }
+    }
+
+    /// Inject faults, replacing any not yet triggered.
+    pub fn inject(&self, faults: Faults) {
+        self.state.borrow_mut().faults = faults;
+    }
+
+    /// Faults not yet triggered.
+    #[must_use]
+    pub fn faults(&self) -> Faults {
+        self.state.borrow().faults
+    }
+
+    #[must_use]
+    pub fn measuring(&self) -> bool {
+        self.state.borrow().measuring
+    }
+}
+
+impl ErrorType for &EmulatedSps30 {
+    type Error = Nack;
+}
+
+#[expect(
+    clippy::unused_async_trait_impl,
+    reason = "the emulated device answers immediately"
+)]
+impl I2c for &EmulatedSps30 {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Nack> {
+        if address == ADDRESS {
+            Device::transaction(*self, operations)
+        } else {
+            Err(Nack::Address)
+        }
+    }
+}
+
+impl Device for EmulatedSps30 {
+    fn address(&self) -> u8 {
+        ADDRESS
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        self.state.borrow_mut().transaction(operations)
+    }
+}
+
+impl State {
+    fn transaction(&mut self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        let now = Instant::now();
+        if now < self.busy_until {
+            return Err(Nack::Address);
+        }
+        if self.faults.nacks > 0 {
+            self.faults.nacks -= 1;
+            return Err(Nack::Address);
+        }
+        if self.measuring && self.next_sample <= now {
+            self.sample(now);
+        }
+
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => self.write(bytes, now)?,
+                Operation::Read(buffer) => self.read(buffer),
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute a command, with an optional argument word.
+    fn write(&mut self, bytes: &[u8], now: Instant) -> Result<(), Nack> {
+        let (command, argument) = match *bytes {
+            [hi, lo] => (u16::from_be_bytes([hi, lo]), None),
+            [hi, lo, a, b, crc] if crc8([a, b]) == crc => (
+                u16::from_be_bytes([hi, lo]),
+                Some(u16::from_be_bytes([a, b])),
+            ),
+            _ => return Err(Nack::Data),
+        };
+        self.response.clear();
+
+        let floats: &[f32] = match (command, argument, self.measuring) {
+            (command::START_MEASUREMENT, Some(OUTPUT_FORMAT_FLOAT), false) => {
+                self.measuring = true;
+                self.next_sample = now + INTERVAL;
+                self.busy_until = now + MODE_CHANGE;
+                &[]
+            }
+            (command::STOP_MEASUREMENT, None, true) => {
+                self.measuring = false;
+                self.busy_until = now + MODE_CHANGE;
+                &[]
+            }
+            (command::READ_MEASURED_VALUES, None, true) => {
+                let Ambient {
+                    pm1,
+                    pm2_5,
+                    pm4_0,
+                    pm10,
+                } = self.measured;
+                // Number concentrations and typical particle size read zero
+                &[pm1, pm2_5, pm4_0, pm10, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+            }
+            (command::READ_PRODUCT_TYPE, None, _) => {
+                let (pairs, _) = PRODUCT_TYPE.as_chunks::<2>();
+                for &pair in pairs {
+                    self.respond(u16::from_be_bytes(pair))?;
+                }
+                &[]
+            }
+            _ => return Err(Nack::Data),
+        };
+        for float in floats {
+            let [a, b, c, d] = float.to_be_bytes();
+            self.respond(u16::from_be_bytes([a, b]))?;
+            self.respond(u16::from_be_bytes([c, d]))?;
+        }
+        if self.faults.bad_crcs > 0
+            && let Some(crc) = self.response.get_mut(2)
+        {
+            self.faults.bad_crcs -= 1;
+            *crc ^= 0xff;
+        }
+        Ok(())
+    }
+
+    /// Append a response word and its CRC.
+    fn respond(&mut self, word: u16) -> Result<(), Nack> {
+        let [hi, lo] = word.to_be_bytes();
+        self.response
+            .extend_from_slice(&[hi, lo, crc8([hi, lo])])
+            .map_err(|CapacityError { .. }| Nack::Data)
+    }
+
+    /// Read the response; past its end, the idle bus reads high.
+    fn read(&mut self, buffer: &mut [u8]) {
+        let mut response = self.response.iter();
+        for byte in buffer {
+            *byte = response.next().copied().unwrap_or(0xff);
+        }
+        self.response.clear();
+    }
+
+    /// Take a measurement, scheduling the next.
+    fn sample(&mut self, now: Instant) {
+        self.measured = self.ambient;
+        self.next_sample = now + INTERVAL;
+    }
+}
@@ -0,0 +1,75 @@
+//! Emulated TCA9548A I2C multiplexer.
+//!
+//! The control register selects any of eight downstream channels; devices on
+//! selected channels answer on the upstream bus. Each byte written replaces
+//! the register, and reads return it.
+
+use core::cell::Cell;
+
+use embedded_hal_async::i2c::Operation;
+
+use super::{Device, Nack, route};
+use crate::mux::CHANNELS;
+
+/// I2C address with A0-A2 low (the Qwiic board's default).
+pub const ADDRESS: u8 = 0x70;
+
+/// Emulated TCA9548A, with the devices on each channel.
+pub struct EmulatedTca9548a<'a> {
+    address: u8,
+    channels: [&'a [&'a dyn Device]; CHANNELS as usize],
+    control: Cell<u8>,
+}
+
+impl<'a> EmulatedTca9548a<'a> {
+    /// Mux at [`ADDRESS`] after power on, with no channel selected.
+    #[must_use]
+    pub const fn new(channels: [&'a [&'a dyn Device]; CHANNELS as usize]) -> Self {
+        Self::at(ADDRESS, channels)
+    }
+
+    /// Mux at an address, after power on.
+    #[must_use]
+    pub const fn at(address: u8, channels: [&'a [&'a dyn Device]; CHANNELS as usize]) -> Self {
+        Self {
+            address,
+            channels,
+            control: Cell::new(0),
+        }
+    }
+
+    /// Control register: a bit per selected channel.
+    #[must_use]
+    pub const fn control(&self) -> u8 {
+        self.control.get()
+    }
+}
+
+impl Device for EmulatedTca9548a<'_> {
+    fn address(&self) -> u8 {
+        self.address
+    }
+
+    fn transaction(&self, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    if let Some(&control) = bytes.last() {
+                        self.control.set(control);
+                    }
+                }
+                Operation::Read(buffer) => buffer.fill(self.control.get()),
+            }
+        }
+        Ok(())
+    }
+
+    fn downstream(&self, address: u8) -> Option<&dyn Device> {
+        let control = self.control.get();
+        self.channels
+            .iter()
+            .enumerate()
+            .filter(|&(channel, _)| control & (1 << channel) != 0)
+            .find_map(|(_, devices)| route(devices, address))
+    }
+}
@@ -0,0 +1,90 @@
+//! Bluetooth Environmental Sensing Service characteristic encoding.
+//!
+//! Value formats follow the GATT Specification Supplement; all values are
+//! little-endian.
+
+use libm::roundf;
+
+/// Environmental Sensing Service UUID.
+pub const SERVICE_UUID: u16 = 0x181A;
+
+/// Pressure characteristic UUID (uint32, 0.1 Pa).
+pub const PRESSURE_UUID: u16 = 0x2A6D;
+
+/// Temperature characteristic UUID (sint16, 0.01 C).
+pub const TEMPERATURE_UUID: u16 = 0x2A6E;
+
+/// Humidity characteristic UUID (uint16, 0.01 %RH).
+pub const HUMIDITY_UUID: u16 = 0x2A6F;
+
+/// CO2 Concentration characteristic UUID (uint16, ppm).
+pub const CO2_UUID: u16 = 0x2B8C;
+
+/// Temperature "value is not known".
+pub const TEMPERATURE_UNKNOWN: i16 = i16::MIN;
+
+/// Humidity "value is not known".
+pub const HUMIDITY_UNKNOWN: u16 = u16::MAX;
+
+/// CO2 "value is not known".
+pub const CO2_UNKNOWN: u16 = u16::MAX;
+
+/// CO2 "value is 65534 ppm or greater".
+pub const CO2_SATURATED: u16 = u16::MAX - 1;
+
+/// Encode a temperature characteristic value.
+#[must_use]
+#[expect(clippy::cast_possible_truncation, reason = "clamped to i16 range")]
+pub fn temperature(celsius: f32) -> [u8; 2] {
+    let value = if celsius.is_nan() {
+        TEMPERATURE_UNKNOWN
+    } else {
+        roundf(celsius * 100.0).clamp(-27315.0, f32::from(i16::MAX)) as i16
+    };
+    value.to_le_bytes()
+}
+
+/// Encode a humidity characteristic value.
+#[must_use]
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "clamped to [0, 10000]"
+)]
+pub fn humidity(percent: f32) -> [u8; 2] {
+    let value = if percent.is_nan() {
+        HUMIDITY_UNKNOWN
+    } else {
+        roundf(percent * 100.0).clamp(0.0, 10_000.0) as u16
+    };
+    value.to_le_bytes()
+}
+
+/// Encode a pressure characteristic value from hPa.
+#[must_use]
+pub fn pressure(hpa: f32) -> [u8; 4] {
+    // 1 hPa = 1000 deci-pascals
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "saturating cast, NaN maps to 0"
+    )]
+    let deci_pa = roundf(hpa * 1000.0) as u32;
+    deci_pa.to_le_bytes()
+}
+
+/// Encode a CO2 concentration characteristic value.
+#[must_use]
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "clamped to [0, CO2_SATURATED]"
+)]
+pub fn co2(ppm: f32) -> [u8; 2] {
+    let value = if ppm.is_nan() {
+        CO2_UNKNOWN
+    } else {
+        roundf(ppm).clamp(0.0, f32::from(CO2_SATURATED)) as u16
+    };
+    value.to_le_bytes()
+}
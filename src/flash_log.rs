@@ -0,0 +1,178 @@
+//! Log Task: the first CO2 sensor's measurements, in flash reserved at its
+//! top.
+//!
+//! While the Bluetooth controller runs, the flash is erased and written
+//! through the MPSL, between radio events, as the controller forbids direct
+//! NVMC access. Otherwise, the radio mesh included, the NVMC is used directly.
+
+use core::ops::Range;
+
+use embassy_embedded_hal::adapter::BlockingAsync;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::mutex::Mutex;
+use embedded_storage_async::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+#[cfg(feature = "uart")]
+use heapless::Vec;
+use microbit_bsp::embassy_nrf::nvmc::Nvmc;
+use rustymicrobit_moxi::datalog::Datalog;
+#[cfg(feature = "uart")]
+use rustymicrobit_moxi::protocol::LogChunk;
+use rustymicrobit_moxi::protocol::Measurement;
+
+/// Flash written through the MPSL.
+#[cfg(feature = "ble")]
+type MpslFlash = nrf_mpsl::Flash<'static>;
+
+/// Flash written through the NVMC directly.
+type NvmcFlash = BlockingAsync<Nvmc<'static>>;
+
+/// Flash the log is kept in, according to whether the Bluetooth controller
+/// runs.
+pub enum LogFlash {
+    #[cfg(feature = "ble")]
+    Mpsl(MpslFlash),
+    Nvmc(NvmcFlash),
+}
+
+/// Error from either flash.
+#[derive(Debug, defmt::Format)]
+pub enum LogFlashError {
+    #[cfg(feature = "ble")]
+    Mpsl(<MpslFlash as ErrorType>::Error),
+    Nvmc(<NvmcFlash as ErrorType>::Error),
+}
+
+impl NorFlashError for LogFlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            #[cfg(feature = "ble")]
+            Self::Mpsl(e) => e.kind(),
+            Self::Nvmc(e) => e.kind(),
+        }
+    }
+}
+
+impl ErrorType for LogFlash {
+    type Error = LogFlashError;
+}
+
+// Both drive the same NVMC
+#[cfg(feature = "ble")]
+const _: () = assert!(
+    MpslFlash::READ_SIZE == NvmcFlash::READ_SIZE
+        && MpslFlash::WRITE_SIZE == NvmcFlash::WRITE_SIZE
+        && MpslFlash::ERASE_SIZE == NvmcFlash::ERASE_SIZE
+);
+
+impl ReadNorFlash for LogFlash {
+    const READ_SIZE: usize = NvmcFlash::READ_SIZE;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), LogFlashError> {
+        match self {
+            #[cfg(feature = "ble")]
+            Self::Mpsl(flash) => flash.read(offset, bytes).await.map_err(LogFlashError::Mpsl),
+            Self::Nvmc(flash) => flash.read(offset, bytes).await.map_err(LogFlashError::Nvmc),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        match self {
+            #[cfg(feature = "ble")]
+            Self::Mpsl(flash) => flash.capacity(),
+            Self::Nvmc(flash) => flash.capacity(),
+        }
+    }
+}
+
+impl NorFlash for LogFlash {
+    const WRITE_SIZE: usize = NvmcFlash::WRITE_SIZE;
+    const ERASE_SIZE: usize = NvmcFlash::ERASE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), LogFlashError> {
+        match self {
+            #[cfg(feature = "ble")]
+            Self::Mpsl(flash) => flash.erase(from, to).await.map_err(LogFlashError::Mpsl),
+            Self::Nvmc(flash) => flash.erase(from, to).await.map_err(LogFlashError::Nvmc),
+        }
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), LogFlashError> {
+        match self {
+            #[cfg(feature = "ble")]
+            Self::Mpsl(flash) => flash
+                .write(offset, bytes)
+                .await
+                .map_err(LogFlashError::Mpsl),
+            Self::Nvmc(flash) => flash
+                .write(offset, bytes)
+                .await
+                .map_err(LogFlashError::Nvmc),
+        }
+    }
+}
+
+/// The top 32 KiB of flash, left out of `FLASH` in memory.x: 8 pages of
+/// 113 records.
+const REGION: Range<u32> = 0x7_8000..0x8_0000;
+
+/// Measurements awaiting a write.
+const PENDING_MAX: usize = 4;
+
+/// Measurements to append, from the CO2 sensor's task.
+static PENDING: Channel<ThreadModeRawMutex, Measurement, PENDING_MAX> = Channel::new();
+
+/// The log, once opened.
+static LOG: Mutex<ThreadModeRawMutex, Option<Datalog<LogFlash>>> = Mutex::new(None);
+
+/// Queue a measurement for the log, dropping it if writes are behind.
+pub fn record(measurement: Measurement) {
+    if PENDING.try_send(measurement).is_err() {
+        defmt::warn!("Log: Writes behind, measurement dropped");
+    }
+}
+
+/// Logged records starting at `offset`; none if the log isn't open or
+/// can't be read.
+#[cfg(feature = "uart")]
+pub async fn chunk(offset: u32) -> LogChunk {
+    let empty = LogChunk {
+        offset,
+        records: Vec::new(),
+    };
+    let mut log = LOG.lock().await;
+    let Some(log) = log.as_mut() else {
+        return empty;
+    };
+    log.chunk(offset)
+        .await
+        .inspect_err(|e| defmt::warn!("Log: Read failed ({:?})", e))
+        .unwrap_or(empty)
+}
+
+/// Open the log, resuming after its newest record, and append queued
+/// measurements.
+#[embassy_executor::task]
+pub async fn log_task(flash: LogFlash) {
+    match Datalog::new(flash, REGION).await {
+        Ok(log) => {
+            defmt::info!("Log: Opened at record {=u32}", log.next());
+            *LOG.lock().await = Some(log);
+        }
+        Err(e) => {
+            defmt::error!("Log: Open failed ({:?})", e);
+            return;
+        }
+    }
+
+    loop {
+        let measurement = PENDING.receive().await;
+        if let Some(log) = LOG.lock().await.as_mut()
+            && let Err(e) = log.push(&measurement).await
+        {
+            defmt::warn!("Log: Write failed ({:?})", e);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Sensirion gas index algorithm: VOC and NOx indices from SGP4X raw signals,
+//! sampled every second.
+//!
+//! The raw signal is normalized against a mean and deviation learned over the
+//! past hours, so an index tells how the air compares to its recent history:
+//! 100 (VOC) or 1 (NOx) is typical, up to 500 for strong events, and VOC
+//! below 100 for cleaner than usual air. The first 45 samples read 0 while
+//! the sensor settles.
+
+use libm::{expf, roundf, sqrtf};
+
+/// Sampling interval (s).
+pub const SAMPLING_INTERVAL_S: f32 = 1.0;
+
+/// Samples after start reading 0.
+const INITIAL_BLACKOUT_S: f32 = 45.0;
+
+const INDEX_GAIN: f32 = 230.0;
+const SRAW_STD_INITIAL: f32 = 50.0;
+const SRAW_STD_BONUS_VOC: f32 = 220.0;
+const SRAW_STD_NOX: f32 = 2000.0;
+const TAU_MEAN_HOURS: f32 = 12.0;
+const TAU_VARIANCE_HOURS: f32 = 12.0;
+const TAU_INITIAL_MEAN_VOC: f32 = 20.0;
+const TAU_INITIAL_MEAN_NOX: f32 = 1200.0;
+const INIT_DURATION_MEAN_VOC: f32 = 3600.0 * 0.75;
+const INIT_DURATION_MEAN_NOX: f32 = 3600.0 * 4.75;
+const INIT_TRANSITION_MEAN: f32 = 0.01;
+const TAU_INITIAL_VARIANCE: f32 = 2500.0;
+const INIT_DURATION_VARIANCE_VOC: f32 = 3600.0 * 1.45;
+const INIT_DURATION_VARIANCE_NOX: f32 = 3600.0 * 5.70;
+const INIT_TRANSITION_VARIANCE: f32 = 0.01;
+const GATING_THRESHOLD_VOC: f32 = 340.0;
+const GATING_THRESHOLD_NOX: f32 = 30.0;
+const GATING_THRESHOLD_INITIAL: f32 = 510.0;
+const GATING_THRESHOLD_TRANSITION: f32 = 0.09;
+const GATING_VOC_MAX_DURATION_MINUTES: f32 = 60.0 * 3.0;
+const GATING_NOX_MAX_DURATION_MINUTES: f32 = 60.0 * 12.0;
+const GATING_MAX_RATIO: f32 = 0.3;
+const SIGMOID_L: f32 = 500.0;
+const SIGMOID_K_VOC: f32 = -0.0065;
+const SIGMOID_X0_VOC: f32 = 213.0;
+const SIGMOID_K_NOX: f32 = -0.0101;
+const SIGMOID_X0_NOX: f32 = 614.0;
+const VOC_INDEX_OFFSET: f32 = 100.0;
+const NOX_INDEX_OFFSET: f32 = 1.0;
+const LP_TAU_FAST: f32 = 20.0;
+const LP_TAU_SLOW: f32 = 500.0;
+const LP_ALPHA: f32 = -0.2;
+const VOC_SRAW_MINIMUM: u16 = 20_000;
+const NOX_SRAW_MINIMUM: u16 = 10_000;
+const GAMMA_SCALING: f32 = 64.0;
+const ADDITIONAL_GAMMA_MEAN_SCALING: f32 = 8.0;
+const FIX16_MAX: f32 = 32767.0;
+
+/// Index computed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Gas {
+    Voc,
+    Nox,
+}
+
+impl Gas {
+    const fn sraw_minimum(self) -> u16 {
+        match self {
+            Self::Voc => VOC_SRAW_MINIMUM,
+            Self::Nox => NOX_SRAW_MINIMUM,
+        }
+    }
+
+    const fn index_offset(self) -> f32 {
+        match self {
+            Self::Voc => VOC_INDEX_OFFSET,
+            Self::Nox => NOX_INDEX_OFFSET,
+        }
+    }
+
+    const fn gating_threshold(self) -> f32 {
+        match self {
+            Self::Voc => GATING_THRESHOLD_VOC,
+            Self::Nox => GATING_THRESHOLD_NOX,
+        }
+    }
+
+    const fn gating_max_duration_minutes(self) -> f32 {
+        match self {
+            Self::Voc => GATING_VOC_MAX_DURATION_MINUTES,
+            Self::Nox => GATING_NOX_MAX_DURATION_MINUTES,
+        }
+    }
+
+    const fn init_duration_mean(self) -> f32 {
+        match self {
+            Self::Voc => INIT_DURATION_MEAN_VOC,
+            Self::Nox => INIT_DURATION_MEAN_NOX,
+        }
+    }
+
+    const fn init_duration_variance(self) -> f32 {
+        match self {
+            Self::Voc => INIT_DURATION_VARIANCE_VOC,
+            Self::Nox => INIT_DURATION_VARIANCE_NOX,
+        }
+    }
+
+    const fn tau_initial_mean(self) -> f32 {
+        match self {
+            Self::Voc => TAU_INITIAL_MEAN_VOC,
+            Self::Nox => TAU_INITIAL_MEAN_NOX,
+        }
+    }
+}
+
+/// Logistic function `1 / (1 + e^(k (x - x0)))`, saturated far from `x0`.
+fn sigmoid(x: f32, x0: f32, k: f32) -> f32 {
+    let exponent = k * (x - x0);
+    if exponent < -50.0 {
+        1.0
+    } else if exponent > 50.0 {
+        0.0
+    } else {
+        1.0 / (1.0 + expf(exponent))
+    }
+}
+
+/// Gas index algorithm state, for one gas.
+#[derive(Clone, Debug)]
+pub struct GasIndex {
+    gas: Gas,
+    uptime_s: f32,
+    /// Raw signal less the gas's minimum.
+    sraw: f32,
+    index: f32,
+    estimator: MeanVarianceEstimator,
+    lowpass: Option<AdaptiveLowpass>,
+}
+
+impl GasIndex {
+    #[must_use]
+    pub fn new(gas: Gas) -> Self {
+        Self {
+            gas,
+            uptime_s: 0.0,
+            sraw: 0.0,
+            index: 0.0,
+            estimator: MeanVarianceEstimator::new(gas),
+            lowpass: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn gas(&self) -> Gas {
+        self.gas
+    }
+
+    /// Process a raw signal sampled [`SAMPLING_INTERVAL_S`] after the last,
+    /// returning the index (0 to 500).
+    pub fn process(&mut self, sraw: u16) -> u16 {
+        if self.uptime_s <= INITIAL_BLACKOUT_S {
+            self.uptime_s += SAMPLING_INTERVAL_S;
+        } else {
+            // Out of range signals are ignored, the last is used
+            if (1..65_000).contains(&sraw) {
+                let minimum = self.gas.sraw_minimum();
+                let sraw = sraw.clamp(minimum + 1, minimum.saturating_add(32_767));
+                self.sraw = f32::from(sraw - minimum);
+            }
+            let index = if self.gas == Gas::Voc || self.estimator.initialized {
+                let mox = self.mox_model(self.sraw);
+                self.sigmoid_scaled(mox)
+            } else {
+                self.gas.index_offset()
+            };
+            self.index = self
+                .lowpass
+                .get_or_insert_with(|| AdaptiveLowpass::new(index))
+                .process(index)
+                .max(0.5);
+            if self.sraw > 0.0 {
+                self.estimator.process(self.sraw, self.index);
+            }
+        }
+        #[expect(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            reason = "index is [0, 500]"
+        )]
+        let index = roundf(self.index) as u16;
+        index
+    }
+
+    /// Signal deviation from the learned mean, scaled by the learned
+    /// deviation (VOC) or a fixed deviation (NOx).
+    fn mox_model(&self, sraw: f32) -> f32 {
+        let deviation = sraw - self.estimator.mean();
+        match self.gas {
+            Gas::Voc => deviation / -(self.estimator.std + SRAW_STD_BONUS_VOC) * INDEX_GAIN,
+            Gas::Nox => deviation / SRAW_STD_NOX * INDEX_GAIN,
+        }
+    }
+
+    /// Map the model output onto the index scale, with typical air at the
+    /// gas's offset.
+    fn sigmoid_scaled(&self, sample: f32) -> f32 {
+        let (x0, k) = match self.gas {
+            Gas::Voc => (SIGMOID_X0_VOC, SIGMOID_K_VOC),
+            Gas::Nox => (SIGMOID_X0_NOX, SIGMOID_K_NOX),
+        };
+        let offset = self.gas.index_offset();
+        let exponent = k * (sample - x0);
+        if exponent < -50.0 {
+            SIGMOID_L
+        } else if exponent > 50.0 {
+            0.0
+        } else if sample >= 0.0 {
+            let shift = match self.gas {
+                Gas::Voc => (SIGMOID_L - 5.0 * offset) / 4.0,
+                Gas::Nox => (500.0 / 499.0) * (1.0 - offset),
+            };
+            (SIGMOID_L + shift) / (1.0 + expf(exponent)) - shift
+        } else {
+            SIGMOID_L / (1.0 + expf(exponent))
+        }
+    }
+}
+
+/// Running mean and deviation of the raw signal, learning fast at first and
+/// pausing while the index is high (gating), so events don't become the norm.
+#[derive(Clone, Debug)]
+struct MeanVarianceEstimator {
+    gas: Gas,
+    initialized: bool,
+    mean: f32,
+    sraw_offset: f32,
+    std: f32,
+    gamma_mean: f32,
+    gamma_variance: f32,
+    gamma_initial_mean: f32,
+    gamma_initial_variance: f32,
+    uptime_gamma: f32,
+    uptime_gating: f32,
+    gating_duration_minutes: f32,
+}
+
+impl MeanVarianceEstimator {
+    fn new(gas: Gas) -> Self {
+        let interval_h = SAMPLING_INTERVAL_S / 3600.0;
+        Self {
+            gas,
+            initialized: false,
+            mean: 0.0,
+            sraw_offset: 0.0,
+            std: SRAW_STD_INITIAL,
+            gamma_mean: ADDITIONAL_GAMMA_MEAN_SCALING * GAMMA_SCALING * interval_h
+                / (TAU_MEAN_HOURS + interval_h),
+            gamma_variance: GAMMA_SCALING * interval_h / (TAU_VARIANCE_HOURS + interval_h),
+            gamma_initial_mean: ADDITIONAL_GAMMA_MEAN_SCALING * GAMMA_SCALING * SAMPLING_INTERVAL_S
+                / (gas.tau_initial_mean() + SAMPLING_INTERVAL_S),
+            gamma_initial_variance: GAMMA_SCALING * SAMPLING_INTERVAL_S
+                / (TAU_INITIAL_VARIANCE + SAMPLING_INTERVAL_S),
+            uptime_gamma: 0.0,
+            uptime_gating: 0.0,
+            gating_duration_minutes: 0.0,
+        }
+    }
+
+    fn mean(&self) -> f32 {
+        self.mean + self.sraw_offset
+    }
+
+    /// Learning rates of the mean and variance for this sample.
+    fn gammas(&mut self, gas_index: f32) -> (f32, f32) {
+        let uptime_limit = FIX16_MAX - SAMPLING_INTERVAL_S;
+        if self.uptime_gamma < uptime_limit {
+            self.uptime_gamma += SAMPLING_INTERVAL_S;
+        }
+        if self.uptime_gating < uptime_limit {
+            self.uptime_gating += SAMPLING_INTERVAL_S;
+        }
+        let gating_threshold = self.gas.gating_threshold();
+
+        let init_mean =
+            |uptime| sigmoid(uptime, self.gas.init_duration_mean(), INIT_TRANSITION_MEAN);
+        let sigmoid_gamma_mean = init_mean(self.uptime_gamma);
+        let gamma_mean =
+            self.gamma_mean + (self.gamma_initial_mean - self.gamma_mean) * sigmoid_gamma_mean;
+        let gating_threshold_mean = gating_threshold
+            + (GATING_THRESHOLD_INITIAL - gating_threshold) * init_mean(self.uptime_gating);
+        let sigmoid_gating_mean = sigmoid(
+            gas_index,
+            gating_threshold_mean,
+            GATING_THRESHOLD_TRANSITION,
+        );
+
+        let init_variance = |uptime| {
+            sigmoid(
+                uptime,
+                self.gas.init_duration_variance(),
+                INIT_TRANSITION_VARIANCE,
+            )
+        };
+        let sigmoid_gamma_variance = init_variance(self.uptime_gamma);
+        let gamma_variance = self.gamma_variance
+            + (self.gamma_initial_variance - self.gamma_variance)
+                * (sigmoid_gamma_variance - sigmoid_gamma_mean);
+        let gating_threshold_variance = gating_threshold
+            + (GATING_THRESHOLD_INITIAL - gating_threshold) * init_variance(self.uptime_gating);
+        let sigmoid_gating_variance = sigmoid(
+            gas_index,
+            gating_threshold_variance,
+            GATING_THRESHOLD_TRANSITION,
+        );
+
+        // Gating for too long restarts the fast initial learning
+        self.gating_duration_minutes = (self.gating_duration_minutes
+            + SAMPLING_INTERVAL_S / 60.0
+                * ((1.0 - sigmoid_gating_mean) * (1.0 + GATING_MAX_RATIO) - GATING_MAX_RATIO))
+            .max(0.0);
+        if self.gating_duration_minutes > self.gas.gating_max_duration_minutes() {
+            self.uptime_gating = 0.0;
+        }
+
+        (
+            sigmoid_gating_mean * gamma_mean,
+            sigmoid_gating_variance * gamma_variance,
+        )
+    }
+
+    fn process(&mut self, sraw: f32, gas_index: f32) {
+        if !self.initialized {
+            self.initialized = true;
+            self.sraw_offset = sraw;
+            self.mean = 0.0;
+            return;
+        }
+        if self.mean >= 100.0 || self.mean <= -100.0 {
+            self.sraw_offset += self.mean;
+            self.mean = 0.0;
+        }
+        let sraw = sraw - self.sraw_offset;
+        let (gamma_mean, gamma_variance) = self.gammas(gas_index);
+        let delta_sgp = (sraw - self.mean) / GAMMA_SCALING;
+        let c = self.std + delta_sgp.abs();
+        let additional_scaling = if c > 1440.0 {
+            (c / 1440.0) * (c / 1440.0)
+        } else {
+            1.0
+        };
+        self.std = sqrtf(additional_scaling * (GAMMA_SCALING - gamma_variance))
+            * sqrtf(
+                self.std * (self.std / (GAMMA_SCALING * additional_scaling))
+                    + gamma_variance * delta_sgp / additional_scaling * delta_sgp,
+            );
+        self.mean += gamma_mean * delta_sgp / ADDITIONAL_GAMMA_MEAN_SCALING;
+    }
+}
+
+/// Low-pass filter, fast to follow large changes and slow otherwise.
+#[derive(Clone, Debug)]
+struct AdaptiveLowpass {
+    fast: f32,
+    slow: f32,
+    output: f32,
+}
+
+impl AdaptiveLowpass {
+    const fn new(sample: f32) -> Self {
+        Self {
+            fast: sample,
+            slow: sample,
+            output: sample,
+        }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        let a1 = SAMPLING_INTERVAL_S / (LP_TAU_FAST + SAMPLING_INTERVAL_S);
+        let a2 = SAMPLING_INTERVAL_S / (LP_TAU_SLOW + SAMPLING_INTERVAL_S);
+        self.fast = (1.0 - a1) * self.fast + a1 * sample;
+        self.slow = (1.0 - a2) * self.slow + a2 * sample;
+        let f1 = expf(LP_ALPHA * (self.fast - self.slow).abs());
+        let tau = (LP_TAU_SLOW - LP_TAU_FAST) * f1 + LP_TAU_FAST;
+        let a3 = SAMPLING_INTERVAL_S / (SAMPLING_INTERVAL_S + tau);
+        self.output = (1.0 - a3) * self.output + a3 * sample;
+        self.output
+    }
+}
@@ -0,0 +1,94 @@
+//! Grayscale LED matrix frames: an intensity level per LED.
+//!
+//! The matrix is scanned a row at a time, and each row's drive is split into
+//! [`LEVEL_MAX`] steps: an LED is lit from the start of the drive for as many
+//! steps as its level, so its on-time within every scan follows its level.
+
+use crate::bitmap::Bitmap;
+use crate::dashboard::{LED_COLS, LED_ROWS};
+
+/// Brightest level; 0 is unlit.
+pub const LEVEL_MAX: u8 = 4;
+
+/// LED matrix frame with an intensity level per LED, top row first.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, defmt::Format)]
+pub struct Grayscale {
+    levels: [[u8; LED_COLS]; LED_ROWS],
+}
+
+impl Grayscale {
+    /// Frame with every LED unlit.
+    #[must_use]
+    pub const fn dark() -> Self {
+        Self {
+            levels: [[0; LED_COLS]; LED_ROWS],
+        }
+    }
+
+    /// Level of the LED at `row`, `col`; unlit past the matrix.
+    #[must_use]
+    pub fn level(&self, row: usize, col: usize) -> u8 {
+        self.levels
+            .get(row)
+            .and_then(|levels| levels.get(col))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Set the LED at `row`, `col` to `level`, at most [`LEVEL_MAX`];
+    /// ignored past the matrix.
+    pub fn set(&mut self, row: usize, col: usize, level: u8) {
+        if let Some(led) = self
+            .levels
+            .get_mut(row)
+            .and_then(|levels| levels.get_mut(col))
+        {
+            *led = level.min(LEVEL_MAX);
+        }
+    }
+
+    /// Dim the lit LEDs of column `col` to at most `level`.
+    pub fn dim_column(&mut self, col: usize, level: u8) {
+        for led in self
+            .levels
+            .iter_mut()
+            .filter_map(|levels| levels.get_mut(col))
+        {
+            *led = (*led).min(level);
+        }
+    }
+
+    /// LEDs lit at any level.
+    #[must_use]
+    pub fn lit(&self) -> [Bitmap; LED_ROWS] {
+        core::array::from_fn(|row| self.lit_at(row, 0))
+    }
+
+    /// LEDs of `row` lit `step` steps into its drive: those with a greater
+    /// level.
+    #[must_use]
+    pub fn lit_at(&self, row: usize, step: u8) -> Bitmap {
+        let mut lit = Bitmap::empty(LED_COLS);
+        for col in 0..LED_COLS {
+            if self.level(row, col) > step {
+                lit.set(col);
+            }
+        }
+        lit
+    }
+}
+
+impl From<[Bitmap; LED_ROWS]> for Grayscale {
+    /// Lit LEDs at [`LEVEL_MAX`].
+    fn from(rows: [Bitmap; LED_ROWS]) -> Self {
+        let mut frame = Self::dark();
+        for (levels, row) in frame.levels.iter_mut().zip(rows) {
+            for (col, led) in levels.iter_mut().enumerate() {
+                if row.is_set(col) {
+                    *led = LEVEL_MAX;
+                }
+            }
+        }
+        frame
+    }
+}
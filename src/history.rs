@@ -0,0 +1,249 @@
+//! Reading history for the history page: for each span, the mean readings
+//! of its latest [`BUCKETS`] time buckets.
+//!
+//! Buckets are aligned to multiples of their length in uptime, and the
+//! window ends at the latest reading's bucket, so a gap in the readings
+//! shows as empty buckets.
+
+use crate::dashboard::LED_COLS;
+use crate::readout::DEGREES_F;
+use crate::ui::Readings;
+use crate::units::{Fahrenheit, Ppm, RelativeHumidity};
+
+/// Buckets shown, one per matrix column.
+pub const BUCKETS: usize = LED_COLS;
+
+/// Spans kept.
+const SPANS: usize = 3;
+
+/// Metrics kept.
+const METRICS: usize = 3;
+
+/// Reading charted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, defmt::Format)]
+pub enum Metric {
+    #[default]
+    Co2,
+    Temperature,
+    Humidity,
+}
+
+impl Metric {
+    /// Following metric, wrapping around.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Co2 => Self::Temperature,
+            Self::Temperature => Self::Humidity,
+            Self::Humidity => Self::Co2,
+        }
+    }
+
+    /// Title scrolled when the metric is selected.
+    #[must_use]
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::Co2 => "CO2",
+            Self::Temperature => "Temp",
+            Self::Humidity => "RH",
+        }
+    }
+
+    /// Value in `readings`, if known.
+    fn of(self, readings: &Readings) -> Option<f32> {
+        match self {
+            Self::Co2 => readings.co2.map(Ppm::into_inner),
+            Self::Temperature => readings.temp_f.map(Fahrenheit::into_inner),
+            Self::Humidity => readings.humidity.map(RelativeHumidity::into_inner),
+        }
+    }
+
+    /// Index among the metrics kept.
+    const fn index(self) -> usize {
+        match self {
+            Self::Co2 => 0,
+            Self::Temperature => 1,
+            Self::Humidity => 2,
+        }
+    }
+
+    /// Units scrolled after a value.
+    #[must_use]
+    pub const fn units(self) -> &'static str {
+        match self {
+            Self::Co2 => "ppm",
+            Self::Temperature => DEGREES_F,
+            Self::Humidity => "%",
+        }
+    }
+}
+
+/// Time charted, across all buckets.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, defmt::Format)]
+pub enum Span {
+    #[default]
+    FiveMinutes,
+    Hour,
+    Day,
+}
+
+impl Span {
+    /// Following span, wrapping around.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::FiveMinutes => Self::Hour,
+            Self::Hour => Self::Day,
+            Self::Day => Self::FiveMinutes,
+        }
+    }
+
+    /// Title scrolled when the span is selected.
+    #[must_use]
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::FiveMinutes => "5 min",
+            Self::Hour => "1 h",
+            Self::Day => "24 h",
+        }
+    }
+
+    /// Bucket length (ms).
+    #[must_use]
+    pub const fn bucket_ms(self) -> u64 {
+        let span_ms = match self {
+            Self::FiveMinutes => 5 * 60 * 1000,
+            Self::Hour => 60 * 60 * 1000,
+            Self::Day => 24 * 60 * 60 * 1000,
+        };
+        span_ms / BUCKETS as u64
+    }
+
+    /// Index among the spans kept.
+    const fn index(self) -> usize {
+        match self {
+            Self::FiveMinutes => 0,
+            Self::Hour => 1,
+            Self::Day => 2,
+        }
+    }
+}
+
+/// Sums of the readings in one bucket.
+#[derive(Clone, Copy, Debug, Default, PartialEq, defmt::Format)]
+struct Bucket {
+    /// Uptime over bucket length.
+    index: u64,
+    /// Sum of each metric's known readings, by [`Metric::index`].
+    sums: [f32; METRICS],
+    /// Known readings of each metric summed.
+    counts: [u16; METRICS],
+}
+
+impl Bucket {
+    const EMPTY: Self = Self {
+        index: 0,
+        sums: [0.0; METRICS],
+        counts: [0; METRICS],
+    };
+
+    fn add(&mut self, readings: &Readings) {
+        for metric in [Metric::Co2, Metric::Temperature, Metric::Humidity] {
+            if let (Some(value), Some(sum), Some(count)) = (
+                metric.of(readings),
+                self.sums.get_mut(metric.index()),
+                self.counts.get_mut(metric.index()),
+            ) {
+                *sum += value;
+                *count = count.saturating_add(1);
+            }
+        }
+    }
+
+    /// Whether no readings were summed.
+    fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&count| count == 0)
+    }
+
+    /// Mean of `metric`, if any of its readings were summed.
+    fn mean(&self, metric: Metric) -> Option<f32> {
+        let sum = self.sums.get(metric.index())?;
+        let count = *self.counts.get(metric.index())?;
+        (count > 0).then(|| sum / f32::from(count))
+    }
+}
+
+/// Latest buckets of local readings, oldest first, for each span.
+#[derive(Clone, Debug, Default, PartialEq, defmt::Format)]
+pub struct History {
+    buckets: [[Bucket; BUCKETS]; SPANS],
+    /// Uptime (ms) of the latest reading.
+    latest_ms: Option<u64>,
+}
+
+impl History {
+    /// Build an empty history.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            buckets: [[Bucket::EMPTY; BUCKETS]; SPANS],
+            latest_ms: None,
+        }
+    }
+
+    /// Add readings taken at `uptime_ms`. Readings no later than the latest
+    /// are ignored, so the same measurement can be offered repeatedly, as
+    /// are readings with nothing known.
+    pub fn record(&mut self, uptime_ms: u64, readings: &Readings) {
+        if *readings == Readings::default()
+            || self
+                .latest_ms
+                .is_some_and(|latest_ms| uptime_ms <= latest_ms)
+        {
+            return;
+        }
+        self.latest_ms = Some(uptime_ms);
+        for (span, buckets) in [Span::FiveMinutes, Span::Hour, Span::Day]
+            .into_iter()
+            .zip(&mut self.buckets)
+        {
+            let index = uptime_ms / span.bucket_ms();
+            if buckets
+                .last()
+                .is_none_or(|last| last.index != index || last.is_empty())
+            {
+                buckets.rotate_left(1);
+                if let Some(last) = buckets.last_mut() {
+                    *last = Bucket {
+                        index,
+                        ..Bucket::EMPTY
+                    };
+                }
+            }
+            if let Some(last) = buckets.last_mut() {
+                last.add(readings);
+            }
+        }
+    }
+
+    /// Mean of `metric` in each of the latest buckets of `span`, oldest
+    /// first, ending with the latest reading's bucket; `None` where empty.
+    #[must_use]
+    pub fn window(&self, metric: Metric, span: Span) -> [Option<f32>; BUCKETS] {
+        let Some(latest_ms) = self.latest_ms else {
+            return [None; BUCKETS];
+        };
+        let buckets = self.buckets.get(span.index());
+        let latest = latest_ms / span.bucket_ms();
+        let mut window = [None; BUCKETS];
+        for (age, mean) in (0_u64..).zip(window.iter_mut().rev()) {
+            *mean = latest.checked_sub(age).and_then(|index| {
+                buckets?
+                    .iter()
+                    .find(|bucket| !bucket.is_empty() && bucket.index == index)?
+                    .mean(metric)
+            });
+        }
+        window
+    }
+}
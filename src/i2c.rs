@@ -0,0 +1,151 @@
+//! Qwiic I2C bus: configuration, and recovery when held low.
+//!
+//! Sensor tasks share the bus through [`Supervised`] handles, which request
+//! recovery after repeated bus faults (see [`rustymicrobit_moxi::bus`]).
+
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_time::Delay;
+use embedded_hal_async::i2c::{ErrorKind, ErrorType, I2c, Operation};
+use microbit_bsp::embassy_nrf::gpio::{Flex, OutputDrive, Pull};
+use microbit_bsp::embassy_nrf::peripherals::{P0_26, P1_00, TWISPI0};
+use microbit_bsp::embassy_nrf::twim::{self, Frequency, Twim};
+use microbit_bsp::embassy_nrf::{Peri, bind_interrupts};
+use rustymicrobit_moxi::bus::{self, Supervised};
+use rustymicrobit_moxi::mux::{Channel, MuxedI2cDevice};
+use static_cell::StaticCell;
+
+/// Bus clock. The SCD4X and BMP581 both support fast mode, but long or
+/// daisy-chained Qwiic cables are more reliable at standard mode, the
+/// default.
+const FREQUENCY: Frequency = if cfg!(feature = "i2c-fast-mode") {
+    Frequency::K400
+} else {
+    Frequency::K100
+};
+
+/// Enable the nRF's internal pull-ups (~13k), for breakouts without their
+/// own. SparkFun Qwiic boards fit 2.2k pull-ups.
+const PULLUPS: bool = cfg!(feature = "i2c-pullups");
+
+/// Longest write: the SGP4X's measure command with its humidity and
+/// temperature arguments, each with a CRC.
+const WRITE_MAX: usize = 8;
+
+bind_interrupts!(struct Irqs{
+    TWISPI0 => twim::InterruptHandler<TWISPI0>;
+});
+
+/// Shared bus.
+pub type I2cBus = Mutex<NoopRawMutex, Controller>;
+
+/// Sensor task's handle on the shared bus, or a mux channel.
+pub type SensorI2c = Supervised<MuxedI2cDevice<'static, NoopRawMutex, Controller>>;
+
+static I2C_BUS: StaticCell<I2cBus> = StaticCell::new();
+
+/// TWIM copies writes from flash through RAM, failing longer ones.
+static mut RAM_I2C_BUFFER: [u8; WRITE_MAX] = [0; WRITE_MAX];
+
+/// Bus controller, taken only while recovery holds the bus.
+pub struct Controller(Option<Twim<'static>>);
+
+/// Controller error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Error {
+    /// Error from the controller.
+    Twim(twim::Error),
+    /// The controller is being reinitialized.
+    Recovering,
+}
+
+impl embedded_hal_async::i2c::Error for Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Twim(e) => e.kind(),
+            Self::Recovering => ErrorKind::Other,
+        }
+    }
+}
+
+impl ErrorType for Controller {
+    type Error = Error;
+}
+
+impl I2c for Controller {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        match &mut self.0 {
+            Some(twim) => twim
+                .transaction(address, operations)
+                .await
+                .map_err(Error::Twim),
+            None => Err(Error::Recovering),
+        }
+    }
+}
+
+pub fn init(
+    scl: Peri<'static, P0_26>,
+    sda: Peri<'static, P1_00>,
+    twi: Peri<'static, TWISPI0>,
+) -> &'static I2cBus {
+    I2C_BUS.init(Mutex::new(Controller(Some(new_twim(scl, sda, twi)))))
+}
+
+/// New handle on the shared bus, or a mux channel, for a sensor task.
+pub const fn device(i2c_bus: &'static I2cBus, channel: Option<Channel>) -> SensorI2c {
+    Supervised::new(MuxedI2cDevice::new(i2c_bus, channel))
+}
+
+fn new_twim(
+    scl: Peri<'static, P0_26>,
+    sda: Peri<'static, P1_00>,
+    twi: Peri<'static, TWISPI0>,
+) -> Twim<'static> {
+    let mut config = twim::Config::default();
+    config.frequency = FREQUENCY;
+    config.scl_pullup = PULLUPS;
+    config.sda_pullup = PULLUPS;
+    // SAFETY: only one Twim exists at a time, and recovery drops the old one
+    // before creating the next
+    let ram_buffer = unsafe { &mut *(&raw mut RAM_I2C_BUFFER) };
+    Twim::new(twi, Irqs, sda, scl, config, ram_buffer)
+}
+
+/// Bus recovery task: on request, clock SCL to release SDA, then
+/// reinitialize TWIM.
+#[embassy_executor::task]
+pub async fn recovery_task(i2c_bus: &'static I2cBus) {
+    loop {
+        bus::recovery_requested().await;
+        let mut controller = i2c_bus.lock().await;
+        defmt::warn!("I2C: Recovering bus");
+
+        // Dropping the controller releases TWISPI0 and its pins
+        drop(controller.0.take());
+
+        // SAFETY (all): the pins were released by the dropped controller
+        let mut scl = Flex::new(unsafe { P0_26::steal() });
+        let mut sda = Flex::new(unsafe { P1_00::steal() });
+        scl.set_as_input_output(Pull::None, OutputDrive::Standard0Disconnect1);
+        sda.set_as_input_output(Pull::None, OutputDrive::Standard0Disconnect1);
+        match bus::release_sda(&mut scl, &mut sda, &mut Delay) {
+            Ok(true) => defmt::info!("I2C: SDA released"),
+            Ok(false) => defmt::error!("I2C: SDA still held low"),
+            Err(e) => match e {},
+        }
+        drop((scl, sda));
+
+        // SAFETY (all): the pins were released above, and TWISPI0 by the
+        // dropped controller
+        let scl = unsafe { P0_26::steal() };
+        let sda = unsafe { P1_00::steal() };
+        let twi = unsafe { TWISPI0::steal() };
+        controller.0 = Some(new_twim(scl, sda, twi));
+        bus::recovered();
+    }
+}
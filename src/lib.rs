@@ -1,7 +1,39 @@
 //! Testable model api.
 #![no_std]
 #![feature(integer_widen_truncate, const_trait_impl)]
+#![cfg_attr(
+    any(feature = "scd4x", feature = "bmp581"),
+    feature(impl_trait_in_assoc_type)
+)]
 
+pub mod air_quality;
+pub mod bitmap;
+pub mod ble_mode;
+pub mod bthome;
+pub mod bus;
+pub mod clock;
+pub mod cts;
 pub mod dashboard;
+pub mod datalog;
+pub mod detect;
+pub mod diagnostics;
+#[cfg(feature = "emulator")]
+pub mod emulator;
+pub mod ess;
+pub mod gas_index;
+pub mod grayscale;
+pub mod history;
 pub mod measurement;
+pub mod mesh;
+pub mod modbus;
+pub mod mux;
+pub mod page;
 pub mod power;
+pub mod protocol;
+pub mod readout;
+pub mod sensirion;
+pub mod sensor;
+pub mod serial_mode;
+pub mod settings;
+pub mod ui;
+pub mod units;
@@ -1,62 +1,206 @@
 #![no_std]
 #![no_main]
 
+#[cfg(feature = "ble")]
+mod ble;
 mod buttons;
 mod display;
+#[cfg(feature = "flash-log")]
+mod flash_log;
+mod i2c;
+mod radio;
 mod sense_co2;
 mod sense_mb;
 mod sense_pa;
+mod sense_pm;
+mod sense_voc;
+#[cfg(feature = "uart")]
+mod serial;
 
 use defmt::info;
 use defmt_rtt as _;
-use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+#[cfg(feature = "flash-log")]
+use embassy_embedded_hal::adapter::BlockingAsync;
 use embassy_executor::Spawner;
-use embassy_sync::blocking_mutex::raw::NoopRawMutex;
-use embassy_sync::mutex::Mutex;
+use embassy_time::Timer;
 use microbit_bsp::Microbit;
-use microbit_bsp::embassy_nrf::peripherals::{P0_26, P1_00, P1_04, TEMP, TWISPI0};
-use microbit_bsp::embassy_nrf::twim::Twim;
-use microbit_bsp::embassy_nrf::{Peri, bind_interrupts, twim};
+use microbit_bsp::embassy_nrf::Peri;
+use microbit_bsp::embassy_nrf::gpio::AnyPin;
+#[cfg(feature = "flash-log")]
+use microbit_bsp::embassy_nrf::nvmc::Nvmc;
+#[cfg(feature = "flash-log")]
+use microbit_bsp::embassy_nrf::peripherals::NVMC;
+#[cfg(feature = "onboard-temp")]
+use microbit_bsp::embassy_nrf::peripherals::TEMP;
+#[cfg(feature = "uart")]
+use microbit_bsp::embassy_nrf::peripherals::{P0_06, P1_08, PPI_CH0, PPI_CH1, TIMER1, UARTE0};
+use microbit_bsp::embassy_nrf::peripherals::{
+    P0_11, P0_15, P0_19, P0_21, P0_22, P0_24, P0_28, P0_30, P0_31, P1_04, P1_05, RADIO,
+};
 use panic_probe as _;
-use static_cell::{ConstStaticCell, StaticCell};
+use rustymicrobit_moxi::dashboard::{LED_COLS, LED_ROWS};
+use rustymicrobit_moxi::mesh::MESH_ENABLED;
+use rustymicrobit_moxi::page::Pages;
+#[cfg(feature = "scd4x")]
+use rustymicrobit_moxi::sensor;
+use rustymicrobit_moxi::{clock, detect};
 
-static I2C_BUS: StaticCell<Mutex<NoopRawMutex, Twim<'static>>> = StaticCell::new();
+// Wall-clock once set, uptime (from 1970-01-01) until then
+defmt::timestamp!("{=u64:iso8601ms}", clock::now().unix_or_uptime_ms());
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
     info!("Power ON!");
     let b = Microbit::default();
 
-    spawner.spawn(display::display_task(b.display).unwrap());
+    // Identify what's on the Qwiic bus, once sensors have powered on (30ms
+    // for the SCD4X per datasheet, 50ms for margin)
+    let i2c_bus = i2c::init(b.p19, b.p20, b.twispi0);
+    spawner.spawn(i2c::recovery_task(i2c_bus).unwrap());
+    Timer::after_millis(50).await;
+    let inventory = detect::scan(&mut i2c::device(i2c_bus, None)).await;
 
-    let pin_temp = unsafe { TEMP::steal() };
-    spawner.spawn(sense_mb::sense_mb_task(pin_temp).unwrap());
+    let pages = Pages::found(&inventory, MESH_ENABLED);
+    // The bsp's matrix driver has one brightness for all LEDs: drop it, so
+    // that the display task owns its pins and drives them for grayscale too
+    drop(b.display);
+    // SAFETY (all): the bsp's matrix driver, their only other owner, was
+    // dropped above
+    let rows: [Peri<'static, AnyPin>; LED_ROWS] = [
+        unsafe { P0_21::steal() }.into(),
+        unsafe { P0_22::steal() }.into(),
+        unsafe { P0_15::steal() }.into(),
+        unsafe { P0_24::steal() }.into(),
+        unsafe { P0_19::steal() }.into(),
+    ];
+    let cols: [Peri<'static, AnyPin>; LED_COLS] = [
+        unsafe { P0_28::steal() }.into(),
+        unsafe { P0_11::steal() }.into(),
+        unsafe { P0_31::steal() }.into(),
+        unsafe { P1_05::steal() }.into(),
+        unsafe { P0_30::steal() }.into(),
+    ];
+    let pins = display::MatrixPins::new(rows, cols);
+    spawner.spawn(display::display_task(pins, inventory.summary(), pages).unwrap());
+
+    #[cfg(feature = "onboard-temp")]
+    {
+        let pin_temp = unsafe { TEMP::steal() };
+        spawner.spawn(sense_mb::sense_mb_task(pin_temp).unwrap());
+    }
 
     let btn_touch = unsafe { P1_04::steal() };
     spawner.spawn(buttons::buttons_task(b.btn_a, b.btn_b, btn_touch.into()).unwrap());
 
-    // I2C Tasks
-    let i2c_bus = i2c_init(b.p19, b.p20, b.twispi0);
+    #[cfg(feature = "uart")]
+    {
+        // SAFETY (all): the bsp doesn't expose the interface UART, its idle
+        // timer or PPI channels, and they are unused elsewhere
+        let serial_p = serial::SerialPeripherals {
+            uarte: unsafe { UARTE0::steal() },
+            rxd: unsafe { P1_08::steal() }.into(),
+            txd: unsafe { P0_06::steal() }.into(),
+            timer: unsafe { TIMER1::steal() },
+            ppi_ch0: unsafe { PPI_CH0::steal() },
+            ppi_ch1: unsafe { PPI_CH1::steal() },
+        };
+        spawner.spawn(serial::serial_task(serial_p).unwrap());
+    }
 
-    let i2c_co2 = I2cDevice::new(i2c_bus);
-    spawner.spawn(sense_co2::sense_co2_task(i2c_co2).unwrap());
+    // Radio Tasks
+    if MESH_ENABLED {
+        // SAFETY: the BLE controller owning RADIO is never initialized
+        let p_radio = unsafe { RADIO::steal() };
+        spawner.spawn(radio::radio_task(p_radio).unwrap());
+    }
+    #[cfg(feature = "ble")]
+    if !MESH_ENABLED {
+        let (sdc, mpsl) = defmt::unwrap!(b.ble.init(b.timer0, b.rng));
+        spawner.spawn(ble::mpsl_task(mpsl).unwrap());
+        spawner.spawn(ble::ble_task(sdc).unwrap());
 
-    let i2c_hpa = I2cDevice::new(i2c_bus);
-    spawner.spawn(sense_pa::sense_pa_task(i2c_hpa).unwrap());
-}
+        #[cfg(feature = "flash-log")]
+        {
+            // SAFETY: the bsp doesn't expose the NVMC, and it's unused elsewhere
+            let p_nvmc = unsafe { NVMC::steal() };
+            let flash = flash_log::LogFlash::Mpsl(nrf_mpsl::Flash::take(mpsl, p_nvmc));
+            spawner.spawn(flash_log::log_task(flash).unwrap());
+        }
+    }
+    // Without the Bluetooth controller, the NVMC directly
+    #[cfg(feature = "flash-log")]
+    if !cfg!(feature = "ble") || MESH_ENABLED {
+        // SAFETY: the bsp doesn't expose the NVMC, and it's unused elsewhere
+        let p_nvmc = unsafe { NVMC::steal() };
+        let flash = flash_log::LogFlash::Nvmc(BlockingAsync::new(Nvmc::new(p_nvmc)));
+        spawner.spawn(flash_log::log_task(flash).unwrap());
+    }
 
-fn i2c_init(
-    scl: Peri<'static, P0_26>,
-    sda: Peri<'static, P1_00>,
-    twi: Peri<'static, TWISPI0>,
-) -> &'static mut Mutex<NoopRawMutex, Twim<'static>> {
-    bind_interrupts!(struct Irqs{
-        TWISPI0 => twim::InterruptHandler<TWISPI0>;
-    });
-    static RAM_I2C_BUFFER: ConstStaticCell<[u8; 4]> = ConstStaticCell::new([0; 4]);
-
-    let i2c_config = twim::Config::default();
-    let i2c = Twim::new(twi, Irqs, sda, scl, i2c_config, RAM_I2C_BUFFER.take());
-    let i2c_mutex = Mutex::new(i2c);
-    I2C_BUS.init(i2c_mutex)
+    // I2C Tasks, for the sensors found: up to CO2_SENSORS_MAX SCD4Xs, the
+    // first BMP581, the first particulate sensor, and the first SGP4X
+    #[cfg(feature = "scd4x")]
+    let mut co2_sensors = 0..sensor::CO2_SENSORS_MAX;
+    #[cfg(feature = "bmp581")]
+    let mut pressure_started = false;
+    #[cfg(any(feature = "pmsa003i", feature = "sps30"))]
+    let mut pm_started = false;
+    #[cfg(feature = "sgp4x")]
+    let mut voc_started = false;
+    for &found in &inventory.parts {
+        match found.part {
+            #[cfg(feature = "scd4x")]
+            detect::Part::Scd4x => match co2_sensors.next() {
+                Some(index) => {
+                    let pressure =
+                        cfg!(feature = "bmp581") && inventory.contains(detect::Part::Bmp581);
+                    let i2c_co2 = i2c::device(i2c_bus, found.channel);
+                    spawner.spawn(sense_co2::sense_co2_task(i2c_co2, index, pressure).unwrap());
+                }
+                None => info!("I2C: Extra {} not started", found),
+            },
+            #[cfg(feature = "bmp581")]
+            detect::Part::Bmp581 if !pressure_started => {
+                pressure_started = true;
+                let i2c_hpa = i2c::device(i2c_bus, found.channel);
+                spawner.spawn(sense_pa::sense_pa_task(i2c_hpa, found.address).unwrap());
+            }
+            #[cfg(feature = "bmp581")]
+            detect::Part::Bmp581 => info!("I2C: Extra {} not started", found),
+            #[cfg(feature = "pmsa003i")]
+            detect::Part::Pmsa003i if !pm_started => {
+                pm_started = true;
+                let i2c_pm = i2c::device(i2c_bus, found.channel);
+                spawner.spawn(sense_pm::sense_pmsa003i_task(i2c_pm).unwrap());
+            }
+            #[cfg(feature = "sps30")]
+            detect::Part::Sps30 if !pm_started => {
+                pm_started = true;
+                let i2c_pm = i2c::device(i2c_bus, found.channel);
+                spawner.spawn(sense_pm::sense_sps30_task(i2c_pm).unwrap());
+            }
+            #[cfg(feature = "pmsa003i")]
+            detect::Part::Pmsa003i => info!("I2C: Extra {} not started", found),
+            #[cfg(feature = "sps30")]
+            detect::Part::Sps30 => info!("I2C: Extra {} not started", found),
+            #[cfg(feature = "sgp4x")]
+            detect::Part::Sgp4x if !voc_started => {
+                voc_started = true;
+                let i2c_voc = i2c::device(i2c_bus, found.channel);
+                spawner.spawn(sense_voc::sense_voc_task(i2c_voc).unwrap());
+            }
+            #[cfg(feature = "sgp4x")]
+            detect::Part::Sgp4x => info!("I2C: Extra {} not started", found),
+            #[cfg_attr(
+                all(
+                    feature = "scd4x",
+                    feature = "bmp581",
+                    feature = "pmsa003i",
+                    feature = "sps30",
+                    feature = "sgp4x"
+                ),
+                expect(unreachable_patterns, reason = "every part is built in")
+            )]
+            _ => info!("I2C: {} not built in", found),
+        }
+    }
 }
@@ -1,45 +1,112 @@
 //! Sensor measurement types.
 
+use crate::clock::{self, Timestamp};
+use crate::dashboard::HUMIDITY_SATURATION_PCT;
+use crate::units::{Celsius, Hectopascal, Ppm, RangeError, RelativeHumidity};
+
 /// SCD41 reading.
 #[derive(Clone, Copy, Debug)]
 pub struct Co2Measurement {
-    pub co2: f32,
-    pub humidity: f32,
-    pub temp_c: f32,
+    pub co2: Ppm,
+    pub humidity: RelativeHumidity,
+    pub temp_c: Celsius,
+    pub timestamp: Timestamp,
 }
 
 impl Co2Measurement {
-    /// Build a measurement from read SCD41 values.
-    #[must_use]
-    pub fn new(co2: u16, humidity: f32, temp_c: f32) -> Self {
-        Self {
-            co2: f32::from(co2),
-            humidity,
-            temp_c,
-        }
+    /// Build a measurement from read SCD41 values, stamped now.
+    ///
+    /// # Errors
+    /// Returns the [`RangeError`] of the first value out of range.
+    pub fn new(co2: u16, humidity: f32, temp_c: f32) -> Result<Self, RangeError> {
+        Ok(Self {
+            co2: Ppm::try_new(f32::from(co2))?,
+            humidity: RelativeHumidity::try_new(humidity)?,
+            temp_c: Celsius::try_new(temp_c)?,
+            timestamp: clock::now(),
+        })
     }
 }
 
 /// BMP581 reading.
 #[derive(Clone, Copy, Debug)]
 pub struct PressureMeasurement {
-    pub hpa: f32,
-    pub temp_c: f32,
+    pub hpa: Hectopascal,
+    pub temp_c: Celsius,
+    pub timestamp: Timestamp,
 }
 
 impl PressureMeasurement {
-    /// Build a measurement from read BMP581 values (pressure in pascals).
+    /// Build a measurement from read BMP581 values (pressure in pascals),
+    /// stamped now.
+    ///
+    /// # Errors
+    /// Returns the [`RangeError`] of the first value out of range.
+    pub fn new(pa: f32, temp_c: f32) -> Result<Self, RangeError> {
+        Ok(Self {
+            hpa: Hectopascal::from_pa(pa)?,
+            temp_c: Celsius::try_new(temp_c)?,
+            timestamp: clock::now(),
+        })
+    }
+}
+
+/// Particulate sensor (PMSA003I, SPS30) reading: mass concentrations (ug/m3).
+#[derive(Clone, Copy, Debug)]
+pub struct PmMeasurement {
+    pub pm1: f32,
+    pub pm2_5: f32,
+    pub pm10: f32,
+    /// Taken above the humidity saturation threshold, where water droplets
+    /// read as particles.
+    pub unreliable: bool,
+    pub timestamp: Timestamp,
+}
+
+impl PmMeasurement {
+    /// Build a measurement from read mass concentrations, stamped now.
+    #[must_use]
+    pub fn new(pm1: f32, pm2_5: f32, pm10: f32) -> Self {
+        Self {
+            pm1,
+            pm2_5,
+            pm10,
+            unreliable: false,
+            timestamp: clock::now(),
+        }
+    }
+
+    /// Flag the measurement unreliable if relative humidity (%RH), when
+    /// known, is above [`HUMIDITY_SATURATION_PCT`].
     #[must_use]
-    pub fn new(pa: f32, temp_c: f32) -> Self {
+    pub fn gated(self, humidity: Option<RelativeHumidity>) -> Self {
         Self {
-            hpa: pa / 100.0,
-            temp_c,
+            unreliable: humidity
+                .is_some_and(|humidity| humidity.into_inner() > f32::from(HUMIDITY_SATURATION_PCT)),
+            ..self
         }
     }
 }
 
-/// Convert from degrees Celsius to degrees Fahrenheit.
-#[must_use]
-pub fn fahrenheit(celsius: f32) -> f32 {
-    celsius * 9.0 / 5.0 + 32.0
+/// Gas sensor (SGP40, SGP41) reading: gas index algorithm outputs, 0 while
+/// the algorithm settles.
+#[derive(Clone, Copy, Debug)]
+pub struct GasMeasurement {
+    /// VOC index: 100 is typical, higher is worse.
+    pub voc_index: u16,
+    /// NOx index: 1 is typical, higher is worse. `None` on an SGP40.
+    pub nox_index: Option<u16>,
+    pub timestamp: Timestamp,
+}
+
+impl GasMeasurement {
+    /// Build a measurement from computed indices, stamped now.
+    #[must_use]
+    pub fn new(voc_index: u16, nox_index: Option<u16>) -> Self {
+        Self {
+            voc_index,
+            nox_index,
+            timestamp: clock::now(),
+        }
+    }
 }
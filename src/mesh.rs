@@ -0,0 +1,223 @@
+//! Multi-unit radio mesh: packet format and peer table.
+//!
+//! Each unit broadcasts its latest readings tagged with its FICR serial
+//! number and keeps a table of recently heard peers. The frames carry no
+//! MAC header, so a packet is only taken as a peer's if it starts with the
+//! mesh's magic and ends with a CRC-16 over the rest: other 802.15.4 traffic
+//! on the channel is rejected.
+
+use libm::roundf;
+
+use crate::measurement::{Co2Measurement, PressureMeasurement};
+use crate::modbus::crc16;
+
+/// Whether the IEEE 802.15.4 radio mesh replaces Bluetooth.
+pub const MESH_ENABLED: bool = false;
+
+/// IEEE 802.15.4 channel shared by all units.
+pub const MESH_CHANNEL: u8 = 20;
+
+/// Packet marker, "MX".
+const MAGIC: [u8; 2] = [0x4d, 0x58];
+
+/// Packet format version.
+const VERSION: u8 = 2;
+
+/// Flag: pressure field is valid.
+const FLAG_PRESSURE: u8 = 0b0000_0001;
+
+/// Encoded packet length.
+pub const PACKET_LEN: usize = 19;
+
+/// Packet decoding failures.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum DecodeError {
+    /// Wrong length for the packet version.
+    Length,
+    /// Not a mesh packet.
+    Magic,
+    /// Corrupt, or foreign traffic starting with the magic.
+    Crc,
+    /// Unsupported packet version.
+    Version,
+}
+
+/// A unit's latest readings, as broadcast.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct PeerReading {
+    pub serial: u32,
+    pub co2: u16,
+    pub humidity: f32,
+    pub temp_c: f32,
+    pub hpa: Option<f32>,
+}
+
+impl PeerReading {
+    /// Build a reading from local measurements.
+    #[must_use]
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "saturating cast, CO2 is [0, 40000] ppm"
+    )]
+    pub fn new(serial: u32, m_co2: &Co2Measurement, m_pa: Option<&PressureMeasurement>) -> Self {
+        Self {
+            serial,
+            co2: roundf(m_co2.co2.into_inner()) as u16,
+            humidity: m_co2.humidity.into_inner(),
+            temp_c: m_co2.temp_c.into_inner(),
+            hpa: m_pa.map(|m| m.hpa.into_inner()),
+        }
+    }
+
+    /// Encode as a radio packet payload.
+    #[must_use]
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "saturating casts into the field ranges"
+    )]
+    pub fn encode(&self, seq: u8) -> [u8; PACKET_LEN] {
+        let flags = if self.hpa.is_some() { FLAG_PRESSURE } else { 0 };
+        let [s0, s1, s2, s3] = self.serial.to_le_bytes();
+        let [c0, c1] = self.co2.to_le_bytes();
+        let [t0, t1] = (roundf(self.temp_c * 100.0) as i16).to_le_bytes();
+        let [h0, h1] = (roundf(self.humidity * 100.0) as u16).to_le_bytes();
+        let [p0, p1] = (roundf(self.hpa.unwrap_or_default() * 10.0) as u16).to_le_bytes();
+        let [m0, m1] = MAGIC;
+
+        let mut packet = [
+            m0, m1, VERSION, flags, seq, s0, s1, s2, s3, c0, c1, t0, t1, h0, h1, p0, p1, 0, 0,
+        ];
+        if let Some((body, crc)) = packet.split_last_chunk_mut() {
+            *crc = crc16(body).to_le_bytes();
+        }
+        packet
+    }
+
+    /// Decode a radio packet payload into a sequence number and reading.
+    ///
+    /// # Errors
+    /// Returns a [`DecodeError`] for foreign, malformed, or newer packets.
+    pub fn decode(packet: &[u8]) -> Result<(u8, Self), DecodeError> {
+        if !packet.starts_with(&MAGIC) {
+            return Err(DecodeError::Magic);
+        }
+        let Ok(packet) = <&[u8; PACKET_LEN]>::try_from(packet) else {
+            return Err(DecodeError::Length);
+        };
+        if packet
+            .split_last_chunk()
+            .is_none_or(|(body, crc)| *crc != crc16(body).to_le_bytes())
+        {
+            return Err(DecodeError::Crc);
+        }
+        let &[
+            _,
+            _,
+            version,
+            flags,
+            seq,
+            s0,
+            s1,
+            s2,
+            s3,
+            c0,
+            c1,
+            t0,
+            t1,
+            h0,
+            h1,
+            p0,
+            p1,
+            _,
+            _,
+        ] = packet;
+        if version != VERSION {
+            return Err(DecodeError::Version);
+        }
+
+        let reading = Self {
+            serial: u32::from_le_bytes([s0, s1, s2, s3]),
+            co2: u16::from_le_bytes([c0, c1]),
+            temp_c: f32::from(i16::from_le_bytes([t0, t1])) / 100.0,
+            humidity: f32::from(u16::from_le_bytes([h0, h1])) / 100.0,
+            hpa: (flags & FLAG_PRESSURE != 0)
+                .then(|| f32::from(u16::from_le_bytes([p0, p1])) / 10.0),
+        };
+        Ok((seq, reading))
+    }
+}
+
+/// Table entry for a heard unit.
+#[derive(Clone, Copy, Debug, defmt::Format)]
+struct Peer {
+    reading: PeerReading,
+    seq: u8,
+    seen_ms: u64,
+}
+
+/// Recently heard units, keyed by serial number.
+#[derive(Debug)]
+pub struct PeerTable<const N: usize> {
+    peers: heapless::Vec<Peer, N>,
+    ttl_ms: u64,
+}
+
+impl<const N: usize> PeerTable<N> {
+    /// Build an empty table forgetting peers unheard for `ttl_ms`.
+    #[must_use]
+    pub const fn new(ttl_ms: u64) -> Self {
+        Self {
+            peers: heapless::Vec::new(),
+            ttl_ms,
+        }
+    }
+
+    /// Record a reading heard at `now_ms`, evicting the stalest peer when full.
+    ///
+    /// Returns `false` for a repeated sequence number from a known peer.
+    pub fn update(&mut self, seq: u8, reading: PeerReading, now_ms: u64) -> bool {
+        let peer = Peer {
+            reading,
+            seq,
+            seen_ms: now_ms,
+        };
+
+        if let Some(known) = self
+            .peers
+            .iter_mut()
+            .find(|p| p.reading.serial == reading.serial)
+        {
+            if known.seq == seq {
+                return false;
+            }
+            *known = peer;
+            return true;
+        }
+
+        if let Err(peer) = self.peers.push(peer)
+            && let Some(stalest) = self.peers.iter_mut().min_by_key(|p| p.seen_ms)
+        {
+            *stalest = peer;
+        }
+        true
+    }
+
+    /// Forget peers unheard since `now_ms - ttl`.
+    pub fn expire(&mut self, now_ms: u64) {
+        let ttl_ms = self.ttl_ms;
+        self.peers
+            .retain(|p| now_ms.saturating_sub(p.seen_ms) <= ttl_ms);
+    }
+
+    /// Reading with the highest CO2 among peers and `local`.
+    #[must_use]
+    pub fn worst(&self, local: Option<PeerReading>) -> Option<PeerReading> {
+        self.peers
+            .iter()
+            .map(|p| p.reading)
+            .chain(local)
+            .max_by_key(|r| r.co2)
+    }
+}
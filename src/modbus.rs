@@ -0,0 +1,291 @@
+//! Modbus RTU slave: framing, CRC-16 and register map.
+//!
+//! Input registers (function 0x04, read-only):
+//!
+//! | Address | Value                 | Unit    |
+//! |---------|-----------------------|---------|
+//! | 0       | CO2                   | ppm     |
+//! | 1       | Temperature (signed)  | 0.01 C  |
+//! | 2       | Relative humidity     | 0.01 %  |
+//! | 3       | Pressure              | 0.1 hPa |
+//! | 4       | Sensor status bits    |         |
+//!
+//! Status bits: 0 the CO2 sensor has reported, 1 the pressure sensor has
+//! reported, 2 CO2 is at or above the alarm threshold.
+//!
+//! Holding registers (functions 0x03, 0x06, 0x10):
+//!
+//! | Address | Setting                     | Range        |
+//! |---------|-----------------------------|--------------|
+//! | 0       | CO2 alarm threshold (ppm)   | 400..=5000   |
+//! | 1       | Power mode (0 high, 1 low)  | 0..=1        |
+//! | 2       | Temperature offset (0.01 C) | 0..=2000     |
+//! | 3       | Unit id                     | 1..=247      |
+//!
+//! Settings are kept in RAM only: a reset restores the defaults.
+
+use heapless::Vec;
+use libm::roundf;
+
+use crate::measurement::{Co2Measurement, PressureMeasurement};
+use crate::power::PowerMode;
+use crate::settings::Settings;
+
+/// Maximum RTU frame length.
+pub const FRAME_MAX: usize = 256;
+
+/// Input register count.
+pub const INPUT_COUNT: usize = 5;
+
+/// Holding register count.
+pub const HOLDING_COUNT: usize = 4;
+
+/// Status bit: CO2 sensor has reported.
+pub const STATUS_CO2: u16 = 0b01;
+
+/// Status bit: pressure sensor has reported.
+pub const STATUS_PRESSURE: u16 = 0b10;
+
+/// Status bit: CO2 is at or above the alarm threshold.
+pub const STATUS_CO2_ALARM: u16 = 0b100;
+
+/// Broadcast address (writes only, never answered).
+const BROADCAST: u8 = 0;
+
+const READ_HOLDING: u8 = 0x03;
+const READ_INPUT: u8 = 0x04;
+const WRITE_SINGLE: u8 = 0x06;
+const WRITE_MULTIPLE: u8 = 0x10;
+
+/// Registers per read request limit.
+const READ_COUNT_MAX: u16 = 125;
+
+/// Modbus exception codes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+#[repr(u8)]
+pub enum Exception {
+    IllegalFunction = 0x01,
+    IllegalDataAddress = 0x02,
+    IllegalDataValue = 0x03,
+}
+
+/// Modbus CRC-16 (polynomial 0xA001 reflected, initial 0xFFFF).
+#[must_use]
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ u16::from(byte), |crc, _| {
+            if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xA001
+            }
+        })
+    })
+}
+
+/// Input register values for the latest measurements, alarming at the
+/// threshold in `settings`.
+#[must_use]
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "saturating casts into register ranges"
+)]
+pub fn input_registers(
+    m_co2: Option<&Co2Measurement>,
+    m_pa: Option<&PressureMeasurement>,
+    settings: &Settings,
+) -> [u16; INPUT_COUNT] {
+    let (co2, temp, humidity) = m_co2.map_or((0, 0, 0), |m| {
+        (
+            roundf(m.co2.into_inner()) as u16,
+            (roundf(m.temp_c.into_inner() * 100.0) as i16).cast_unsigned(),
+            roundf(m.humidity.into_inner() * 100.0) as u16,
+        )
+    });
+    let pressure = m_pa.map_or(0, |m| roundf(m.hpa.into_inner() * 10.0) as u16);
+
+    let mut status = 0;
+    if m_co2.is_some() {
+        status |= STATUS_CO2;
+    }
+    if m_pa.is_some() {
+        status |= STATUS_PRESSURE;
+    }
+    if m_co2.is_some() && co2 >= settings.co2_alarm_ppm {
+        status |= STATUS_CO2_ALARM;
+    }
+
+    [co2, temp, humidity, pressure, status]
+}
+
+/// Holding register values for the given settings.
+#[must_use]
+#[expect(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "offset is [0, 20] C"
+)]
+pub fn holding_registers(settings: &Settings) -> [u16; HOLDING_COUNT] {
+    [
+        settings.co2_alarm_ppm,
+        match settings.power_mode {
+            PowerMode::High => 0,
+            PowerMode::Low => 1,
+        },
+        roundf(settings.temp_offset_c * 100.0) as u16,
+        u16::from(settings.unit_id),
+    ]
+}
+
+/// Write one holding register, validating its range.
+///
+/// # Errors
+/// Returns an [`Exception`] for unknown addresses or out-of-range values.
+pub fn write_holding(settings: &mut Settings, address: u16, value: u16) -> Result<(), Exception> {
+    let mut staged = *settings;
+    match address {
+        0 => staged.co2_alarm_ppm = value,
+        1 => {
+            staged.power_mode = match value {
+                0 => PowerMode::High,
+                1 => PowerMode::Low,
+                _ => return Err(Exception::IllegalDataValue),
+            }
+        }
+        2 => staged.temp_offset_c = f32::from(value) / 100.0,
+        3 => staged.unit_id = value.try_into().ok().ok_or(Exception::IllegalDataValue)?,
+        _ => return Err(Exception::IllegalDataAddress),
+    }
+
+    if !staged.is_valid() {
+        return Err(Exception::IllegalDataValue);
+    }
+    *settings = staged;
+    Ok(())
+}
+
+/// Answer a request frame addressed to `settings.unit_id`.
+///
+/// Writes update `settings`. Returns `None` for frames that must not be
+/// answered: bad CRC, other units, or broadcasts.
+#[must_use]
+pub fn respond(
+    frame: &[u8],
+    inputs: &[u16; INPUT_COUNT],
+    settings: &mut Settings,
+) -> Option<Vec<u8, FRAME_MAX>> {
+    let (body, crc) = frame.split_last_chunk::<2>()?;
+    if body.len() < 2 || crc16(body) != u16::from_le_bytes(*crc) {
+        return None;
+    }
+    let (&unit, pdu) = body.split_first()?;
+    if unit != settings.unit_id && unit != BROADCAST {
+        return None;
+    }
+    let (&function, data) = pdu.split_first()?;
+
+    let mut response = Vec::new();
+    let mut push = |bytes: &[u8]| {
+        // Register counts are bounded to fit a frame
+        if response.extend_from_slice(bytes).is_err() {
+            defmt::error!("Modbus: Response overflow");
+        }
+    };
+    push(&[unit, function]);
+
+    let result = match function {
+        READ_HOLDING => read(data, &holding_registers(settings), &mut push),
+        READ_INPUT => read(data, inputs, &mut push),
+        WRITE_SINGLE => write_single(data, settings, &mut push),
+        WRITE_MULTIPLE => write_multiple(data, settings, &mut push),
+        _ => Err(Exception::IllegalFunction),
+    };
+
+    if unit == BROADCAST {
+        return None;
+    }
+
+    if let Err(exception) = result {
+        response.truncate(1);
+        response
+            .extend_from_slice(&[function | 0x80, exception as u8])
+            .ok()?;
+    }
+    let crc = crc16(&response).to_le_bytes();
+    response.extend_from_slice(&crc).ok()?;
+    Some(response)
+}
+
+/// Big-endian register pair (address, count or value) at the start of `data`.
+const fn words(data: &[u8]) -> Result<(u16, u16), Exception> {
+    match data {
+        [a0, a1, b0, b1, ..] => Ok((
+            u16::from_be_bytes([*a0, *a1]),
+            u16::from_be_bytes([*b0, *b1]),
+        )),
+        _ => Err(Exception::IllegalDataValue),
+    }
+}
+
+/// Read `count` registers from `address`.
+fn read(data: &[u8], registers: &[u16], push: &mut impl FnMut(&[u8])) -> Result<(), Exception> {
+    let (address, count) = words(data)?;
+    if count == 0 || count > READ_COUNT_MAX {
+        return Err(Exception::IllegalDataValue);
+    }
+    let values = registers
+        .get(usize::from(address)..usize::from(address) + usize::from(count))
+        .ok_or(Exception::IllegalDataAddress)?;
+
+    push(&[(count * 2).saturating_truncate()]);
+    for value in values {
+        push(&value.to_be_bytes());
+    }
+    Ok(())
+}
+
+/// Write one register, echoing the request.
+fn write_single(
+    data: &[u8],
+    settings: &mut Settings,
+    push: &mut impl FnMut(&[u8]),
+) -> Result<(), Exception> {
+    let (address, value) = words(data)?;
+    write_holding(settings, address, value)?;
+    push(data.get(..4).unwrap_or_default());
+    Ok(())
+}
+
+/// Write consecutive registers, all or nothing.
+fn write_multiple(
+    data: &[u8],
+    settings: &mut Settings,
+    push: &mut impl FnMut(&[u8]),
+) -> Result<(), Exception> {
+    let (address, count) = words(data)?;
+    let values = match data.get(4..) {
+        Some([byte_count, values @ ..])
+            if count > 0
+                && usize::from(*byte_count) == usize::from(count) * 2
+                && values.len() == usize::from(*byte_count) =>
+        {
+            values
+        }
+        _ => return Err(Exception::IllegalDataValue),
+    };
+
+    let mut staged = *settings;
+    let (pairs, _) = values.as_chunks::<2>();
+    for (offset, pair) in (0..).zip(pairs) {
+        write_holding(
+            &mut staged,
+            address.saturating_add(offset),
+            u16::from_be_bytes(*pair),
+        )?;
+    }
+    *settings = staged;
+
+    push(data.get(..4).unwrap_or_default());
+    Ok(())
+}
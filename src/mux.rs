@@ -0,0 +1,86 @@
+//! TCA9548A I2C multiplexer: eight downstream channels behind one address, so
+//! sensors with fixed addresses (two SCD4Xs, say) can share the bus.
+//!
+//! A [`MuxedI2cDevice`] selects its channel for each transaction and
+//! deselects it after, holding the shared bus throughout, so devices on the
+//! main bus and on other channels never see it.
+
+use core::ops::RangeInclusive;
+
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::Mutex;
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation};
+
+/// Addresses the mux can be strapped to (0x70 on the Qwiic board).
+pub const ADDRESSES: RangeInclusive<u8> = 0x70..=0x77;
+
+/// Downstream channels.
+pub const CHANNELS: u8 = 8;
+
+/// Control register value with no channel selected.
+pub const DESELECTED: u8 = 0;
+
+/// Mux channel a device sits behind.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub struct Channel {
+    /// Mux address.
+    pub mux: u8,
+    /// Channel number, 0 to 7.
+    pub channel: u8,
+}
+
+impl Channel {
+    /// Channel of the mux at `mux`, if in range.
+    #[must_use]
+    pub const fn new(mux: u8, channel: u8) -> Option<Self> {
+        if channel < CHANNELS {
+            Some(Self { mux, channel })
+        } else {
+            None
+        }
+    }
+
+    /// Control register value selecting only this channel.
+    #[must_use]
+    pub const fn mask(self) -> u8 {
+        1 << (self.channel % CHANNELS)
+    }
+}
+
+/// Handle on a shared bus for one device, on the main bus or behind a mux
+/// channel.
+pub struct MuxedI2cDevice<'a, M: RawMutex, BUS> {
+    bus: &'a Mutex<M, BUS>,
+    channel: Option<Channel>,
+}
+
+impl<'a, M: RawMutex, BUS> MuxedI2cDevice<'a, M, BUS> {
+    /// Device on the main bus if `channel` is `None`.
+    #[must_use]
+    pub const fn new(bus: &'a Mutex<M, BUS>, channel: Option<Channel>) -> Self {
+        Self { bus, channel }
+    }
+}
+
+impl<M: RawMutex, BUS: ErrorType> ErrorType for MuxedI2cDevice<'_, M, BUS> {
+    type Error = BUS::Error;
+}
+
+impl<M: RawMutex, BUS: I2c> I2c for MuxedI2cDevice<'_, M, BUS> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut bus = self.bus.lock().await;
+        let Some(channel) = self.channel else {
+            return bus.transaction(address, operations).await;
+        };
+        bus.write(channel.mux, &[channel.mask()]).await?;
+        let result = bus.transaction(address, operations).await;
+        // Deselect even if the device failed, leaving the main bus clear
+        let deselected = bus.write(channel.mux, &[DESELECTED]).await;
+        drop(bus);
+        result.and(deselected)
+    }
+}
@@ -0,0 +1,150 @@
+//! Display pages, cycled with buttons A and B pressed together, skipping
+//! those without a data source on this unit.
+
+use crate::detect::{Inventory, Part};
+use crate::sensor::CO2_SENSORS_MAX;
+
+/// Dashboard page shown on the LED matrix.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, defmt::Format)]
+pub enum Page {
+    /// Local readings.
+    #[default]
+    Dashboard,
+    /// Worst (highest CO2) readings among this unit and its mesh peers.
+    Worst,
+    /// Second CO2 sensor's readings, as behind a mux.
+    Second,
+    /// Particulate matter readings.
+    Particulates,
+    /// VOC and NOx gas index readings.
+    Gases,
+    /// Overall air quality glyph.
+    AirQuality,
+    /// History bar chart of a local reading.
+    History,
+}
+
+impl Page {
+    /// Following page, wrapping around.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Dashboard => Self::Worst,
+            Self::Worst => Self::Second,
+            Self::Second => Self::Particulates,
+            Self::Particulates => Self::Gases,
+            Self::Gases => Self::AirQuality,
+            Self::AirQuality => Self::History,
+            Self::History => Self::Dashboard,
+        }
+    }
+
+    /// Following page among `pages`, wrapping around.
+    #[must_use]
+    pub const fn next_in(self, pages: Pages) -> Self {
+        let mut page = self.next();
+        while !pages.contains(page) {
+            page = page.next();
+        }
+        page
+    }
+
+    /// Title scrolled when the page is selected.
+    #[must_use]
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::Dashboard => "Home",
+            Self::Worst => "Worst",
+            Self::Second => "Sensor B",
+            Self::Particulates => "PM",
+            Self::Gases => "VOC",
+            Self::AirQuality => "Air",
+            Self::History => "History",
+        }
+    }
+}
+
+/// Data sources fitted, deciding which pages are shown.
+#[expect(clippy::struct_excessive_bools, reason = "independent sources")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub struct Pages {
+    /// Local readings, from a CO2 or pressure sensor.
+    pub local: bool,
+    /// Local CO2 readings.
+    pub co2: bool,
+    /// Mesh peers' readings.
+    pub mesh: bool,
+    /// Second CO2 sensor.
+    pub second: bool,
+    /// Particulate matter sensor.
+    pub particulates: bool,
+    /// VOC sensor.
+    pub gases: bool,
+}
+
+impl Pages {
+    /// Every page shown.
+    pub const ALL: Self = Self {
+        local: true,
+        co2: true,
+        mesh: true,
+        second: true,
+        particulates: true,
+        gases: true,
+    };
+
+    /// Sources started for the parts of a boot `inventory` that are built
+    /// in, and the mesh when `mesh` is enabled.
+    #[must_use]
+    pub fn found(inventory: &Inventory, mesh: bool) -> Self {
+        let built_in = |part| {
+            inventory.contains(part)
+                && match part {
+                    Part::Scd4x => cfg!(feature = "scd4x"),
+                    Part::Bmp581 => cfg!(feature = "bmp581"),
+                    Part::Pmsa003i => cfg!(feature = "pmsa003i"),
+                    Part::Sps30 => cfg!(feature = "sps30"),
+                    Part::Sgp4x => cfg!(feature = "sgp4x"),
+                }
+        };
+        let co2_sensors = if built_in(Part::Scd4x) {
+            inventory
+                .parts
+                .iter()
+                .filter(|found| found.part == Part::Scd4x)
+                .count()
+                .min(CO2_SENSORS_MAX)
+        } else {
+            0
+        };
+        Self {
+            local: co2_sensors > 0 || built_in(Part::Bmp581),
+            co2: co2_sensors > 0,
+            mesh,
+            second: co2_sensors > 1,
+            particulates: built_in(Part::Pmsa003i) || built_in(Part::Sps30),
+            gases: built_in(Part::Sgp4x),
+        }
+    }
+
+    /// Whether `page` has a data source. The dashboard always does, as the
+    /// home page.
+    #[must_use]
+    pub const fn contains(self, page: Page) -> bool {
+        match page {
+            Page::Dashboard => true,
+            Page::Worst => self.mesh,
+            Page::Second => self.second,
+            Page::Particulates => self.particulates,
+            Page::Gases => self.gases,
+            Page::AirQuality => self.co2 || self.particulates,
+            Page::History => self.local,
+        }
+    }
+}
+
+impl Default for Pages {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
@@ -6,7 +6,7 @@ use embassy_time::Duration;
 pub const POWER_MODE: PowerMode = PowerMode::High;
 
 /// Operating power mode.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
 pub enum PowerMode {
     High,
     Low,
@@ -21,4 +21,13 @@ impl PowerMode {
             Self::Low => Duration::from_secs(30),
         }
     }
+
+    /// SCD4X temperature offset (C), calibrated against the BMP581.
+    #[must_use]
+    pub const fn temp_offset_c(self) -> f32 {
+        match self {
+            Self::High => 2.949,
+            Self::Low => 0.0,
+        }
+    }
 }
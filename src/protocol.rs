@@ -0,0 +1,76 @@
+//! Framed serial protocol (schema and codec in `moxi-protocol`).
+
+pub use moxi_protocol::*;
+
+use crate::measurement::{Co2Measurement, PressureMeasurement};
+use crate::settings::Settings;
+use crate::{clock, power};
+
+impl From<clock::Timestamp> for Timestamp {
+    fn from(timestamp: clock::Timestamp) -> Self {
+        Self {
+            uptime_ms: timestamp.uptime_ms,
+            unix_ms: timestamp.unix_ms,
+        }
+    }
+}
+
+impl From<power::PowerMode> for PowerMode {
+    fn from(mode: power::PowerMode) -> Self {
+        match mode {
+            power::PowerMode::High => Self::High,
+            power::PowerMode::Low => Self::Low,
+        }
+    }
+}
+
+impl From<PowerMode> for power::PowerMode {
+    fn from(mode: PowerMode) -> Self {
+        match mode {
+            PowerMode::High => Self::High,
+            PowerMode::Low => Self::Low,
+        }
+    }
+}
+
+impl From<&Settings> for Config {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            co2_alarm_ppm: settings.co2_alarm_ppm,
+            power_mode: settings.power_mode.into(),
+            temp_offset_c: settings.temp_offset_c,
+            unit_id: settings.unit_id,
+        }
+    }
+}
+
+impl TryFrom<Config> for Settings {
+    type Error = NackReason;
+
+    fn try_from(config: Config) -> Result<Self, Self::Error> {
+        let settings = Self {
+            co2_alarm_ppm: config.co2_alarm_ppm,
+            power_mode: config.power_mode.into(),
+            temp_offset_c: config.temp_offset_c,
+            unit_id: config.unit_id,
+        };
+        if settings.is_valid() {
+            Ok(settings)
+        } else {
+            Err(NackReason::Invalid)
+        }
+    }
+}
+
+/// Wire measurement from the latest sensor readings, stamped by the CO2
+/// reading.
+#[must_use]
+pub fn measurement(m_co2: &Co2Measurement, m_pa: Option<&PressureMeasurement>) -> Measurement {
+    Measurement {
+        timestamp: m_co2.timestamp.into(),
+        co2_ppm: m_co2.co2.into_inner(),
+        humidity: m_co2.humidity.into_inner(),
+        temp_c: m_co2.temp_c.into_inner(),
+        hpa: m_pa.map(|m| m.hpa.into_inner()),
+    }
+}
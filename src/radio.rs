@@ -0,0 +1,98 @@
+//! Radio Task: Multi-unit mesh over IEEE 802.15.4 packets.
+
+use embassy_futures::select::{Either, select};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::watch::{DynReceiver, Watch};
+use embassy_time::{Duration, Instant, Timer};
+use microbit_bsp::embassy_nrf::peripherals::RADIO;
+use microbit_bsp::embassy_nrf::radio::ieee802154::{Packet, Radio};
+use microbit_bsp::embassy_nrf::{Peri, bind_interrupts, radio};
+use rustymicrobit_moxi::mesh::{MESH_CHANNEL, PeerReading, PeerTable};
+use rustymicrobit_moxi::settings;
+
+use crate::{sense_co2, sense_mb, sense_pa};
+
+/// Peers tracked at once.
+const PEERS_MAX: usize = 8;
+
+/// Forget peers unheard for this long.
+const PEER_TTL: Duration = Duration::from_secs(120);
+
+/// Count of receiving tasks [`display`].
+const MESH_CONSUMERS: usize = 1;
+
+/// SPMC for the worst reading among this unit and its peers.
+static MESH_LENS: Watch<ThreadModeRawMutex, PeerReading, MESH_CONSUMERS> = Watch::new();
+
+pub fn get_worst_receiver() -> Option<DynReceiver<'static, PeerReading>> {
+    MESH_LENS.dyn_receiver()
+}
+
+/// Broadcast local readings and aggregate peers' readings.
+#[embassy_executor::task]
+pub async fn radio_task(p_radio: Peri<'static, RADIO>) {
+    bind_interrupts!(struct Irqs {
+        RADIO => radio::InterruptHandler<RADIO>;
+    });
+    let mut radio = Radio::new(p_radio, Irqs);
+    radio.set_channel(MESH_CHANNEL);
+
+    let serial = sense_mb::get_serial_number();
+    defmt::info!(
+        "Mesh: Joined channel {=u8} as {=u32:x}",
+        MESH_CHANNEL,
+        serial
+    );
+
+    let mut co2_rx = sense_co2::get_sensor_receiver().or_else(|| {
+        defmt::error!("Mesh: Request for co2 rx failed (listening only)");
+        None
+    });
+    let mut pa_rx = sense_pa::get_sensor_receiver().or_else(|| {
+        defmt::error!("Mesh: Request for pressure rx failed (pressure disabled)");
+        None
+    });
+
+    let worst_tx = MESH_LENS.sender();
+    let mut peers = PeerTable::<PEERS_MAX>::new(PEER_TTL.as_millis());
+    let mut packet = Packet::new();
+    let mut seq: u8 = 0;
+    let mut report_at = Instant::now();
+
+    loop {
+        match select(Timer::at(report_at), radio.receive(&mut packet)).await {
+            Either::First(()) => {
+                // At the polling interval of the current settings
+                report_at += settings::get().power_mode.interval();
+                // Without a CO2 reading of its own, a unit only listens
+                let m_pa = pa_rx.as_mut().and_then(|rx| rx.try_get());
+                let local = co2_rx
+                    .as_mut()
+                    .and_then(|rx| rx.try_get())
+                    .map(|m_co2| PeerReading::new(serial, &m_co2, m_pa.as_ref()));
+                if let Some(local) = local {
+                    packet.copy_from_slice(&local.encode(seq));
+                    seq = seq.wrapping_add(1);
+                    if let Err(e) = radio.try_send(&mut packet).await {
+                        defmt::warn!("Mesh: Send failed ({:?})", e);
+                    }
+                }
+
+                peers.expire(Instant::now().as_millis());
+                if let Some(worst) = peers.worst(local) {
+                    worst_tx.send(worst);
+                }
+            }
+            Either::Second(Ok(())) => match PeerReading::decode(&packet) {
+                Ok((peer_seq, reading)) if reading.serial != serial => {
+                    if peers.update(peer_seq, reading, Instant::now().as_millis()) {
+                        defmt::debug!("Mesh: Heard {:?}", reading);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => defmt::trace!("Mesh: Ignored packet ({:?})", e),
+            },
+            Either::Second(Err(e)) => defmt::warn!("Mesh: Receive failed ({:?})", e),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Scrolled readouts: a value after an optional label, with units.
+//!
+//! Fractional values are rounded to a number of decimal places and formatted
+//! as integers, so formatting pulls in no float formatting; values that are
+//! not finite, or too large to show, read `--`. Readouts too long for their
+//! text are cut short rather than failing.
+
+use core::fmt::{self, Display, Formatter, Write};
+
+use heapless::String;
+use libm::roundf;
+
+/// Temperature units, as scrolled. The matrix font is printable ASCII only,
+/// so there's no degree sign, matching the `C` readout.
+pub const DEGREES_F: &str = "F";
+
+/// Value shown when unknown or out of range.
+pub const UNKNOWN: &str = "--";
+
+/// Decimal places shown at most.
+pub const DECIMALS_MAX: u8 = 3;
+
+/// 2^31, exact as `f32`: the magnitude bounding the `i32` range.
+const I32_BOUND: f32 = 65_536.0 * 32_768.0;
+
+/// Value rounded to a number of decimal places, for display.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Fixed {
+    value: f32,
+    decimals: u8,
+}
+
+impl Fixed {
+    /// `value` rounded to `decimals` places, at most [`DECIMALS_MAX`].
+    #[must_use]
+    pub fn new(value: f32, decimals: u8) -> Self {
+        Self {
+            value,
+            decimals: decimals.min(DECIMALS_MAX),
+        }
+    }
+
+    /// `value` rounded to a whole number.
+    #[must_use]
+    pub fn whole(value: f32) -> Self {
+        Self::new(value, 0)
+    }
+
+    /// Value scaled to whole units of the last decimal place, if it fits.
+    fn scaled(self) -> Option<i32> {
+        let scaled = roundf(self.value * f32::from(self.scale()));
+        if !(-I32_BOUND..I32_BOUND).contains(&scaled) {
+            return None;
+        }
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "checked to be within the i32 range"
+        )]
+        let scaled = scaled as i32;
+        Some(scaled)
+    }
+
+    /// Divisor of the scaled value.
+    const fn scale(self) -> u16 {
+        match self.decimals {
+            0 => 1,
+            1 => 10,
+            2 => 100,
+            _ => 1000,
+        }
+    }
+}
+
+impl Display for Fixed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Some(scaled) = self.scaled() else {
+            return f.write_str(UNKNOWN);
+        };
+        // Signed even when the whole part rounds to 0
+        let sign = if scaled < 0 { "-" } else { "" };
+        let magnitude = scaled.unsigned_abs();
+        let scale = u32::from(self.scale());
+        let (whole, fraction) = (magnitude / scale, magnitude % scale);
+        match usize::from(self.decimals) {
+            0 => write!(f, "{sign}{whole}"),
+            decimals => write!(f, "{sign}{whole}.{fraction:0decimals$}"),
+        }
+    }
+}
+
+/// Value after an optional label (e.g. the sensor's), with units if any.
+#[derive(Clone, Copy, Debug)]
+pub struct Readout<'a, V> {
+    pub label: Option<&'a str>,
+    pub value: V,
+    pub units: &'a str,
+}
+
+impl<V: Display> Display for Readout<'_, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self {
+            label,
+            value,
+            units,
+        } = self;
+        if let Some(label) = label {
+            write!(f, " {label}")?;
+        }
+        write!(f, " {value}")?;
+        if units.is_empty() {
+            Ok(())
+        } else {
+            write!(f, " {units}")
+        }
+    }
+}
+
+/// Text of at most `N` bytes, cut short at a character boundary if need be.
+#[must_use]
+pub fn truncated<const N: usize>(text: impl Display) -> String<N> {
+    let mut writer = Truncating(String::new());
+    if write!(&mut writer, "{text}").is_err() {
+        defmt::warn!("Display: Readout cut short at {=usize} bytes", N);
+    }
+    writer.0
+}
+
+/// Writer keeping what fits, then failing.
+struct Truncating<const N: usize>(String<N>);
+
+impl<const N: usize> Write for Truncating<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.0.push(c).ok().ok_or(fmt::Error)?;
+        }
+        Ok(())
+    }
+}
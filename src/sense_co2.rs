@@ -1,174 +1,136 @@
 //! Sense Task: SCD4X CO2, Humidity, and Temperature.
-
-use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
-use embassy_sync::blocking_mutex::raw::{NoopRawMutex, ThreadModeRawMutex};
-use embassy_sync::watch::{DynReceiver, Sender, Watch};
-use embassy_time::{Delay, Timer};
-use libscd::asynchronous::scd4x::Scd4x;
-use microbit_bsp::embassy_nrf::twim::Twim;
-use rustymicrobit_moxi::measurement::{Co2Measurement, PressureMeasurement, fahrenheit};
-use rustymicrobit_moxi::power::{POWER_MODE, PowerMode};
-
+//!
+//! Measurements are compensated for ambient pressure from the BMP581, or
+//! without one (feature or sensor) for the site's altitude. Up to
+//! `CO2_SENSORS_MAX` sensors run at once, each behind its own mux channel.
+
+#[cfg(feature = "uart")]
+use core::ops::RangeInclusive;
+
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+#[cfg(any(feature = "scd4x", feature = "uart"))]
+use embassy_sync::signal::Signal;
+use embassy_sync::watch::{DynReceiver, Watch};
+use rustymicrobit_moxi::measurement::Co2Measurement;
+#[cfg(all(feature = "scd4x", feature = "flash-log"))]
+use rustymicrobit_moxi::protocol;
+#[cfg(feature = "scd4x")]
+use rustymicrobit_moxi::sensor::CO2_LABELS;
+use rustymicrobit_moxi::sensor::CO2_SENSORS_MAX;
+#[cfg(feature = "scd4x")]
+use rustymicrobit_moxi::sensor::scd4x::Scd4xSensor;
+#[cfg(feature = "scd4x")]
+use rustymicrobit_moxi::sensor::{self, Co2Control};
+#[cfg(feature = "scd4x")]
+use rustymicrobit_moxi::settings;
+
+#[cfg(all(feature = "scd4x", feature = "flash-log"))]
+use crate::flash_log;
+#[cfg(feature = "scd4x")]
+use crate::i2c::SensorI2c;
+#[cfg(feature = "scd4x")]
 use crate::sense_pa;
 
-/// Retries before panic.
-const INIT_ATTEMPTS_MAX: u8 = 3;
+/// Count of receiving tasks [`display`, `ble`, `radio`, `serial`, `sense_pm`
+/// and `sense_voc`] of the first sensor; the others feed only the display.
+const CO2_CONSUMERS: usize = 6;
 
-/// Count of receiving tasks [`display`].
-const CO2_CONSUMERS: usize = 1;
+/// SPMC for each sensor's measurements, in detection order.
+static CO2_LENSES: [Watch<ThreadModeRawMutex, Co2Measurement, CO2_CONSUMERS>; CO2_SENSORS_MAX] =
+    [const { Watch::new() }; CO2_SENSORS_MAX];
 
-/// SPMC for pressure measurements.
-static CO2_LENS: Watch<ThreadModeRawMutex, Co2Measurement, CO2_CONSUMERS> = Watch::new();
+/// Valid forced recalibration references (ppm).
+#[cfg(feature = "uart")]
+pub const CALIBRATION_PPM: RangeInclusive<u16> = 400..=2000;
 
+/// Pending forced recalibration reference (ppm).
+#[cfg(any(feature = "scd4x", feature = "uart"))]
+static CALIBRATION: Signal<ThreadModeRawMutex, u16> = Signal::new();
+
+/// First sensor's measurements.
 pub fn get_sensor_receiver() -> Option<DynReceiver<'static, Co2Measurement>> {
-    CO2_LENS.dyn_receiver()
+    get_sensor_receiver_at(0)
 }
 
-/// SCD4X CO2, humidity, and temperature sensing task.
-#[embassy_executor::task]
-pub async fn sense_co2_task(i2c: I2cDevice<'static, NoopRawMutex, Twim<'static>>) {
-    let mut scd = Scd4x::new(i2c, Delay);
-
-    // Power on delay (30ms per datasheet, 50ms for margin)
-    Timer::after_millis(50).await;
-
-    for init_attempt in 1..=INIT_ATTEMPTS_MAX {
-        match scd.stop_periodic_measurement().await {
-            Ok(()) => break,
-            Err(e) => {
-                defmt::error!(
-                    "CO2 Sensor: Stop/Init attempt {=u8} failed ({:?})",
-                    init_attempt,
-                    e
-                );
-
-                if init_attempt == INIT_ATTEMPTS_MAX {
-                    defmt::panic!(
-                        "CO2 Sensor: Failed to stop/initialize after {=u8} attempts",
-                        INIT_ATTEMPTS_MAX
-                    );
-                }
-                Timer::after_millis(250).await;
-            }
-        }
-    }
-
-    get_device_info(&mut scd).await;
-    set_temp_offset(&mut scd).await;
-    set_polling(&mut scd).await;
+/// Measurements of the sensor labelled `CO2_LABELS[index]`.
+pub fn get_sensor_receiver_at(index: usize) -> Option<DynReceiver<'static, Co2Measurement>> {
+    CO2_LENSES.get(index)?.dyn_receiver()
+}
 
-    let co2_tx = CO2_LENS.sender();
-    let mut pa_rx = sense_pa::get_sensor_receiver().or_else(|| {
-        defmt::error!("CO2 Sensor: Request for pressure rx failed (tracking disabled)");
-        None
-    });
+/// First sensor's latest measurement, if any, without consuming a receiver.
+#[cfg(feature = "uart")]
+pub fn get_latest() -> Option<Co2Measurement> {
+    CO2_LENSES.first()?.try_get()
+}
 
-    loop {
-        match scd.data_ready().await {
-            Ok(true) => get_measurement(&co2_tx, &mut pa_rx, &mut scd).await,
-            Ok(false) => defmt::trace!("CO2 Sensor: No unread data"),
-            Err(e) => defmt::error!("CO2 Sensor: Failed device ready probe ({:?})", e),
-        }
-        Timer::after(POWER_MODE.interval()).await;
-    }
+/// Request a forced recalibration against a reference level, applied at the
+/// next poll.
+#[cfg(feature = "uart")]
+pub fn request_calibration(ppm: u16) {
+    CALIBRATION.signal(ppm);
 }
 
-/// Query SCD4X variant and serial number.
-async fn get_device_info(scd: &mut Scd4x<I2cDevice<'static, NoopRawMutex, Twim<'static>>, Delay>) {
-    if let Ok(Some(variant)) = scd.sensor_variant().await {
-        match variant {
-            libscd::SensorVariant::Scd40 => defmt::info!("CO2 Sensor: SCD-40"),
-            libscd::SensorVariant::Scd41 => defmt::info!("CO2 Sensor: SCD-41"),
-            libscd::SensorVariant::Scd43 => defmt::info!("CO2 Sensor: SCD-43"),
-            _ => defmt::info!("CO2 Sensor: Unknown"),
-        }
-        match scd.serial_number().await {
-            Ok(sn) => defmt::info!("CO2 Sensor SN: {:?}", sn),
-            Err(e) => defmt::error!("CO2 Sensor: Failed to read SN ({:?})", e),
-        }
+/// SCD4X CO2, humidity, and temperature sensing task, one per sensor. The
+/// first (index 0) is logged and takes recalibration requests.
+#[cfg(feature = "scd4x")]
+#[embassy_executor::task(pool_size = CO2_SENSORS_MAX)]
+pub async fn sense_co2_task(i2c: SensorI2c, index: usize, pressure: bool) {
+    let (Some(lens), Some(&label)) = (CO2_LENSES.get(index), CO2_LABELS.get(index)) else {
+        defmt::panic!("CO2 Sensor: No sensor {=usize}", index);
+    };
+    let primary = index == 0;
+
+    defmt::info!("CO2 Sensor {=str}: SCD4X", label);
+    let scd = Scd4xSensor::new(i2c);
+    let started = if pressure {
+        Co2Control::start(scd, settings::get()).await
     } else {
-        defmt::error!("CO2 Sensor: Failed to read sensor");
-    }
-}
+        Co2Control::start_at_altitude(scd, settings::get(), sensor::ALTITUDE_M).await
+    };
+    let mut control = match started {
+        Ok(control) => control,
+        Err(e) => defmt::panic!("CO2 Sensor {=str}: Failed to start ({:?})", label, e),
+    };
 
-/// Read SCD4X sensor data.
-async fn get_measurement(
-    co2_tx: &Sender<'_, ThreadModeRawMutex, Co2Measurement, CO2_CONSUMERS>,
-    pa_rx: &mut Option<DynReceiver<'_, PressureMeasurement>>,
-    scd: &mut Scd4x<I2cDevice<'static, NoopRawMutex, Twim<'static>>, Delay>,
-) {
-    match scd.read_measurement().await {
-        Ok(m) => {
-            let m_co2 = Co2Measurement::new(m.co2, m.humidity, m.temperature);
-            defmt::info!(
-                "CO2: {=f32}, Humidity: {=f32}, Temperature: {=f32} C ({=f32} F)",
-                m_co2.co2,
-                m_co2.humidity,
-                m_co2.temp_c,
-                fahrenheit(m_co2.temp_c)
+    let co2_tx = lens.sender();
+    let mut pa_rx = if pressure {
+        sense_pa::get_sensor_receiver().or_else(|| {
+            defmt::error!(
+                "CO2 Sensor {=str}: Request for pressure rx failed (tracking disabled)",
+                label
             );
+            None
+        })
+    } else {
+        None
+    };
 
-            if let Some(m_pa) = pa_rx.as_mut().and_then(|rx| rx.try_get()) {
-                #[expect(
-                    clippy::cast_possible_truncation,
-                    clippy::cast_sign_loss,
-                    reason = "hPa is [300, 1250]"
-                )]
-                let hpa_ambient = m_pa.hpa as u16;
-                if let Err(e) = scd.set_ambient_pressure(hpa_ambient).await {
-                    defmt::error!("CO2 Sensor: Failed to set pressure ({:?})", e);
-                }
-            }
-            co2_tx.send(m_co2);
-        }
-        Err(e) => defmt::error!("CO2 Sensor: Read failed ({:?})", e),
-    }
-}
-
-/// Set SCD4X polling according to `POWER_MODE`.
-async fn set_polling(scd: &mut Scd4x<I2cDevice<'static, NoopRawMutex, Twim<'static>>, Delay>) {
-    match POWER_MODE {
-        PowerMode::High => match scd.start_periodic_measurement().await {
-            Ok(()) => {
-                defmt::info!("CO2 Sensor: Initiated periodic measurement mode");
+    #[cfg_attr(
+        not(feature = "flash-log"),
+        expect(unused_variables, reason = "the pressure is only logged")
+    )]
+    let e = sensor::run_co2(
+        &mut control,
+        || pa_rx.as_mut().and_then(|rx| rx.try_get()),
+        || {
+            if primary {
+                CALIBRATION.try_take()
+            } else {
+                None
             }
-            Err(e) => defmt::panic!(
-                "CO2 Sensor: Failed to start periodic measurement mode ({:?})",
-                e
-            ),
         },
-        PowerMode::Low => match scd.start_low_power_periodic_measurement().await {
-            Ok(()) => {
-                defmt::info!("CO2 Sensor: Initiated low-power periodic measurement mode");
+        |m_co2, m_pa| {
+            #[cfg(feature = "flash-log")]
+            if primary {
+                flash_log::record(protocol::measurement(&m_co2, m_pa.as_ref()));
             }
-            Err(e) => defmt::panic!(
-                "CO2 Sensor: Failed to start low-power periodic measurement mode ({:?})",
-                e
-            ),
+            co2_tx.send(m_co2);
         },
-    }
-}
-
-/// Set SCD4X temperature reading offset.
-async fn set_temp_offset(scd: &mut Scd4x<I2cDevice<'static, NoopRawMutex, Twim<'static>>, Delay>) {
-    /// Offset from BMP581 in High Power mode.
-    const OFFSET_BMP581: f32 = 2.949;
-
-    let offset = match POWER_MODE {
-        PowerMode::High => OFFSET_BMP581,
-        PowerMode::Low => 0.0,
-    };
-
-    match scd.get_temperature_offset().await {
-        Ok(previous_offset) => defmt::debug!(
-            "CO2 Sensor: Setting temperature offset (old: {=f32} C, new: {=f32} C)",
-            previous_offset,
-            offset
-        ),
-        Err(e) => defmt::panic!("CO2 Sensor: Failed to probe temperature offset ({:?})", e),
-    }
-
-    if let Err(e) = scd.set_temperature_offset(offset).await {
-        defmt::panic!("CO2 Sensor: Failed to set temperature offset ({:?})", e);
-    }
+    )
+    .await;
+    defmt::panic!(
+        "CO2 Sensor {=str}: Failed to restart measurement ({:?})",
+        label,
+        e
+    );
 }
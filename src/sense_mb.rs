@@ -1,25 +1,33 @@
 //! Sense Task: Microbit Temperature.
 
+#[cfg(feature = "onboard-temp")]
 use embassy_time::Timer;
+#[cfg(feature = "onboard-temp")]
 use microbit_bsp::embassy_nrf::peripherals::TEMP;
+#[cfg(feature = "onboard-temp")]
 use microbit_bsp::embassy_nrf::temp::Temp;
+#[cfg(feature = "onboard-temp")]
 use microbit_bsp::embassy_nrf::{Peri, bind_interrupts, temp};
-use rustymicrobit_moxi::measurement::fahrenheit;
-use rustymicrobit_moxi::power::POWER_MODE;
+#[cfg(feature = "onboard-temp")]
+use rustymicrobit_moxi::settings;
+#[cfg(feature = "onboard-temp")]
+use rustymicrobit_moxi::units::{Celsius, RangeError};
 
 /// Temperature offset wrt BMP581.
+#[cfg(feature = "onboard-temp")]
 const OFFSET_BMP581: f32 = 1.969;
 
 /// Lower 32 bits of FICR.
 const FICR_DEVICEID_0: *const u32 = core::ptr::with_exposed_provenance(0x1000_0060);
 
 /// Read microbit serial number (lower 32 bits of FICR).
-fn get_serial_number() -> u32 {
+pub fn get_serial_number() -> u32 {
     // SAFETY: FICR is read-only at a fixed address
     unsafe { core::ptr::read_volatile(FICR_DEVICEID_0) }
 }
 
 /// Microbit temperature sensing task.
+#[cfg(feature = "onboard-temp")]
 #[embassy_executor::task]
 pub async fn sense_mb_task(p_temp: Peri<'static, TEMP>) {
     let serial_num = get_serial_number();
@@ -34,7 +42,13 @@ pub async fn sense_mb_task(p_temp: Peri<'static, TEMP>) {
         let value = mb_temp.read().await;
         let temp_c = value.to_num::<f32>() - OFFSET_BMP581;
 
-        defmt::info!("Microbit: {=f32} ({=f32})", temp_c, fahrenheit(temp_c));
-        Timer::after(POWER_MODE.interval()).await;
+        match Celsius::try_new(temp_c)
+            .map_err(RangeError::from)
+            .and_then(Celsius::to_fahrenheit)
+        {
+            Ok(temp_f) => defmt::info!("Microbit: {=f32} ({})", temp_c, temp_f),
+            Err(e) => defmt::error!("Microbit: {=f32} ({:?})", temp_c, e),
+        }
+        Timer::after(settings::get().power_mode.interval()).await;
     }
 }
@@ -1,81 +1,50 @@
 //! Sense Task: BMP581 Pressure and Temperature.
+//!
+//! Without the `bmp581` feature, or a BMP581 found at boot, nothing is
+//! published and consumers go without pressure.
 
-use bmp5::i2c::{BMP5_ADDRESS, Bmp5};
-use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
-use embassy_sync::blocking_mutex::raw::{NoopRawMutex, ThreadModeRawMutex};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
 use embassy_sync::watch::{DynReceiver, Watch};
-use embassy_time::{Delay, Timer};
-use microbit_bsp::embassy_nrf::twim::Twim;
-use rustymicrobit_moxi::measurement::{PressureMeasurement, fahrenheit};
-use rustymicrobit_moxi::power::POWER_MODE;
+use rustymicrobit_moxi::measurement::PressureMeasurement;
+use rustymicrobit_moxi::sensor::CO2_SENSORS_MAX;
+#[cfg(feature = "bmp581")]
+use rustymicrobit_moxi::sensor::bmp581::Bmp581Sensor;
+#[cfg(feature = "bmp581")]
+use rustymicrobit_moxi::sensor::{self, PressureControl};
 
-/// Retries before panic.
-const INIT_ATTEMPTS_MAX: u8 = 3;
+#[cfg(feature = "bmp581")]
+use crate::i2c::SensorI2c;
 
-/// Count of receiving tasks [`display` and `sense_co2`].
-const PRESSURE_CONSUMERS: usize = 2;
+/// Count of receiving tasks [`display`, `ble`, `radio`, `serial`, and each
+/// `sense_co2`].
+const PRESSURE_CONSUMERS: usize = 4 + CO2_SENSORS_MAX;
 
 /// SPMC for pressure measurements.
 static PRESSURE_LENS: Watch<ThreadModeRawMutex, PressureMeasurement, PRESSURE_CONSUMERS> =
     Watch::new();
 
-const BMP5_CONFIG: bmp5::Config = bmp5::Config {
-    temperature_oversampling: bmp5::Oversampling::Oversampling8X,
-    temperature_iir_filter: bmp5::IIRFilter::Bypass,
-    pressure_oversampling: bmp5::Oversampling::Oversampling8X,
-    pressure_iir_filter: bmp5::IIRFilter::Bypass,
-    output_data_rate: bmp5::OutputDataRate::OutputDataRate0_250Hz,
-};
-
 pub fn get_sensor_receiver() -> Option<DynReceiver<'static, PressureMeasurement>> {
     PRESSURE_LENS.dyn_receiver()
 }
 
+/// Latest measurement, if any, without consuming a receiver.
+#[cfg(feature = "uart")]
+pub fn get_latest() -> Option<PressureMeasurement> {
+    PRESSURE_LENS.try_get()
+}
+
 /// BMP581 pressure and temperature sensing task.
+#[cfg(feature = "bmp581")]
 #[embassy_executor::task]
-pub async fn sense_pa_task(i2c: I2cDevice<'static, NoopRawMutex, Twim<'static>>) {
-    let mut bmp = Bmp5::new(i2c, Delay, BMP5_ADDRESS, BMP5_CONFIG);
-
-    defmt::info!("Pressure Sensor: BMP581");
+pub async fn sense_pa_task(i2c: SensorI2c, address: u8) {
+    let bmp = Bmp581Sensor::new(i2c, address);
 
-    for init_attempt in 1..=INIT_ATTEMPTS_MAX {
-        match bmp.init().await {
-            Ok(()) => {
-                defmt::info!("Pressure Sensor: Initialized successfully");
-                break;
-            }
-            Err(e) => {
-                defmt::error!(
-                    "Pressure Sensor: Init attempt {=u8} failed ({:?})",
-                    init_attempt,
-                    e
-                );
-                if init_attempt == INIT_ATTEMPTS_MAX {
-                    defmt::panic!(
-                        "Pressure Sensor: Failed to initialize after {=u8} attempts.",
-                        INIT_ATTEMPTS_MAX
-                    );
-                }
-                Timer::after_millis(10).await;
-            }
-        }
-    }
+    defmt::info!("Pressure Sensor: BMP581 at {=u8:#04x}", address);
+    let mut control = match PressureControl::start(bmp).await {
+        Ok(control) => control,
+        Err(e) => defmt::panic!("Pressure Sensor: Failed to start ({:?})", e),
+    };
 
     let tx = PRESSURE_LENS.sender();
-    loop {
-        match bmp.measure().await {
-            Ok(m) => {
-                let m_pa = PressureMeasurement::new(m.pressure, m.temperature);
-                defmt::info!(
-                    "Pressure: {=f32} hPa, Temperature: {=f32} C ({=f32} F)",
-                    m_pa.hpa,
-                    m_pa.temp_c,
-                    fahrenheit(m_pa.temp_c)
-                );
-                tx.send(m_pa);
-            }
-            Err(e) => defmt::error!("Pressure Sensor: Measurement failed ({:?})", e),
-        }
-        Timer::after(POWER_MODE.interval()).await;
-    }
+    sensor::run_pressure(&mut control, |m_pa| tx.send(m_pa)).await
 }
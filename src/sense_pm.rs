@@ -0,0 +1,72 @@
+//! Sense Task: PMSA003I or SPS30 Particulate Matter.
+//!
+//! Readings taken while the CO2 sensor reports humidity above the dashboard's
+//! saturation threshold are flagged unreliable: water droplets read as
+//! particles. Without a particulate sensor feature, or one found at boot,
+//! nothing is published and the PM page shows local readings.
+
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::watch::{DynReceiver, Watch};
+use rustymicrobit_moxi::measurement::PmMeasurement;
+#[cfg(feature = "pmsa003i")]
+use rustymicrobit_moxi::sensor::pmsa003i::Pmsa003iSensor;
+#[cfg(feature = "sps30")]
+use rustymicrobit_moxi::sensor::sps30::Sps30Sensor;
+#[cfg(any(feature = "pmsa003i", feature = "sps30"))]
+use rustymicrobit_moxi::sensor::{self, PmControl, PmSensor};
+
+#[cfg(any(feature = "pmsa003i", feature = "sps30"))]
+use crate::i2c::SensorI2c;
+#[cfg(any(feature = "pmsa003i", feature = "sps30"))]
+use crate::sense_co2;
+
+/// Count of receiving tasks [`display`].
+const PM_CONSUMERS: usize = 1;
+
+/// SPMC for particulate measurements.
+static PM_LENS: Watch<ThreadModeRawMutex, PmMeasurement, PM_CONSUMERS> = Watch::new();
+
+pub fn get_sensor_receiver() -> Option<DynReceiver<'static, PmMeasurement>> {
+    PM_LENS.dyn_receiver()
+}
+
+/// PMSA003I particulate sensing task.
+#[cfg(feature = "pmsa003i")]
+#[embassy_executor::task]
+pub async fn sense_pmsa003i_task(i2c: SensorI2c) {
+    defmt::info!("PM Sensor: PMSA003I");
+    run(Pmsa003iSensor::new(i2c)).await
+}
+
+/// SPS30 particulate sensing task.
+#[cfg(feature = "sps30")]
+#[embassy_executor::task]
+pub async fn sense_sps30_task(i2c: SensorI2c) {
+    defmt::info!("PM Sensor: SPS30");
+    run(Sps30Sensor::new(i2c)).await
+}
+
+#[cfg(any(feature = "pmsa003i", feature = "sps30"))]
+async fn run<S: PmSensor>(pm_sensor: S) -> ! {
+    let mut control = match PmControl::start(pm_sensor).await {
+        Ok(control) => control,
+        Err(e) => defmt::panic!("PM Sensor: Failed to start ({:?})", e),
+    };
+
+    let mut co2_rx = sense_co2::get_sensor_receiver().or_else(|| {
+        defmt::error!("PM Sensor: Request for co2 rx failed (humidity gating disabled)");
+        None
+    });
+    let tx = PM_LENS.sender();
+    sensor::run_pm(
+        &mut control,
+        || {
+            co2_rx
+                .as_mut()
+                .and_then(|rx| rx.try_get())
+                .map(|m| m.humidity)
+        },
+        |m_pm| tx.send(m_pm),
+    )
+    .await
+}
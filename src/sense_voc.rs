@@ -0,0 +1,51 @@
+//! Sense Task: SGP40 VOC or SGP41 VOC and NOx gas indices.
+//!
+//! Each measurement is compensated for the first CO2 sensor's humidity and
+//! temperature, and fed to the gas index algorithm every second. Without the
+//! `sgp4x` feature, or a sensor found at boot, nothing is published and the
+//! VOC page shows local readings.
+
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::watch::{DynReceiver, Watch};
+use rustymicrobit_moxi::measurement::GasMeasurement;
+#[cfg(feature = "sgp4x")]
+use rustymicrobit_moxi::sensor::sgp4x::Sgp4xSensor;
+#[cfg(feature = "sgp4x")]
+use rustymicrobit_moxi::sensor::{self, GasControl};
+
+#[cfg(feature = "sgp4x")]
+use crate::i2c::SensorI2c;
+#[cfg(feature = "sgp4x")]
+use crate::sense_co2;
+
+/// Count of receiving tasks [`display`].
+const VOC_CONSUMERS: usize = 1;
+
+/// SPMC for gas index measurements.
+static VOC_LENS: Watch<ThreadModeRawMutex, GasMeasurement, VOC_CONSUMERS> = Watch::new();
+
+pub fn get_sensor_receiver() -> Option<DynReceiver<'static, GasMeasurement>> {
+    VOC_LENS.dyn_receiver()
+}
+
+/// SGP40/41 gas sensing task.
+#[cfg(feature = "sgp4x")]
+#[embassy_executor::task]
+pub async fn sense_voc_task(i2c: SensorI2c) {
+    let mut control = match GasControl::start(Sgp4xSensor::new(i2c)).await {
+        Ok(control) => control,
+        Err(e) => defmt::panic!("Gas Sensor: Failed to start ({:?})", e),
+    };
+
+    let mut co2_rx = sense_co2::get_sensor_receiver().or_else(|| {
+        defmt::error!("Gas Sensor: Request for co2 rx failed (compensating for defaults)");
+        None
+    });
+    let tx = VOC_LENS.sender();
+    sensor::run_gas(
+        &mut control,
+        || co2_rx.as_mut().and_then(|rx| rx.try_get()),
+        |m_gas| tx.send(m_gas),
+    )
+    .await
+}
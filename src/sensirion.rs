@@ -0,0 +1,22 @@
+//! Sensirion I2C conventions: 16-bit data words, each followed by a CRC-8.
+
+/// Sensirion CRC-8 of a data word (polynomial 0x31, initial 0xFF).
+#[must_use]
+pub fn crc8(word: [u8; 2]) -> u8 {
+    word.into_iter().fold(0xff, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x31
+            }
+        })
+    })
+}
+
+/// Whether a response is whole words, each with a valid CRC.
+#[must_use]
+pub fn words_valid(response: &[u8]) -> bool {
+    let (words, remainder) = response.as_chunks::<3>();
+    remainder.is_empty() && words.iter().all(|&[hi, lo, crc]| crc8([hi, lo]) == crc)
+}
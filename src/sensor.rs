@@ -0,0 +1,602 @@
+//! Sensor driver traits, and the sensor control loops generic over them.
+
+#[cfg(feature = "bmp581")]
+pub mod bmp581;
+#[cfg(feature = "pmsa003i")]
+pub mod pmsa003i;
+#[cfg(feature = "scd4x")]
+pub mod scd4x;
+#[cfg(feature = "sgp4x")]
+pub mod sgp4x;
+#[cfg(feature = "sps30")]
+pub mod sps30;
+
+use embassy_time::{Duration, Timer};
+
+use crate::diagnostics;
+use crate::gas_index::{Gas, GasIndex};
+use crate::measurement::{Co2Measurement, GasMeasurement, PmMeasurement, PressureMeasurement};
+use crate::power::PowerMode;
+use crate::settings::{self, Settings};
+use crate::units::{Celsius, RangeError, RelativeHumidity};
+
+/// Site altitude (m), compensated for by the CO2 sensor on units without a
+/// pressure sensor.
+pub const ALTITUDE_M: u16 = 0;
+
+/// CO2 sensors run at once, on the main bus or behind a TCA9548A mux. The
+/// first feeds the dashboard, radio and log; the rest have their own page.
+pub const CO2_SENSORS_MAX: usize = 2;
+
+/// Label of each CO2 sensor, in detection order.
+pub const CO2_LABELS: [&str; CO2_SENSORS_MAX] = ["A", "B"];
+
+/// Init attempts before giving up.
+pub const INIT_ATTEMPTS_MAX: u8 = 3;
+
+/// Delay between CO2 sensor init attempts.
+const CO2_INIT_RETRY: Duration = Duration::from_millis(250);
+
+/// Delay between pressure sensor init attempts.
+const PRESSURE_INIT_RETRY: Duration = Duration::from_millis(10);
+
+/// Delay between particulate sensor init attempts.
+const PM_INIT_RETRY: Duration = Duration::from_millis(100);
+
+/// Delay between gas sensor init attempts.
+const GAS_INIT_RETRY: Duration = Duration::from_millis(100);
+
+/// Gas sensor sampling interval, whatever the power mode: the gas index
+/// algorithm's [`SAMPLING_INTERVAL_S`](crate::gas_index::SAMPLING_INTERVAL_S).
+pub const GAS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Relative humidity (%RH) and temperature (C) a gas sensor compensates for
+/// before the CO2 sensor has measured.
+pub const GAS_DEFAULT_COMPENSATION: (f32, f32) = (50.0, 25.0);
+
+/// Error of a sensor wrapping a third-party driver (SCD4X, BMP581).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum DriverError<E> {
+    Driver(E),
+    /// Reading outside its quantity's range.
+    Range(RangeError),
+}
+
+/// CO2, humidity, and temperature sensor (e.g. SCD4X).
+#[expect(
+    async_fn_in_trait,
+    reason = "sensor tasks run on a thread-mode executor"
+)]
+pub trait CO2Sensor {
+    type Error: defmt::Format;
+
+    /// Ready the sensor after power on.
+    async fn init(&mut self) -> Result<(), Self::Error> {
+        self.stop().await
+    }
+
+    /// Start periodic measurement at the power mode's rate.
+    async fn start(&mut self, power_mode: PowerMode) -> Result<(), Self::Error>;
+
+    /// Stop periodic measurement.
+    async fn stop(&mut self) -> Result<(), Self::Error>;
+
+    /// Whether an unread measurement is available.
+    async fn data_ready(&mut self) -> Result<bool, Self::Error>;
+
+    /// Read the latest measurement.
+    async fn read(&mut self) -> Result<Co2Measurement, Self::Error>;
+
+    /// Temperature offset (C).
+    async fn temperature_offset(&mut self) -> Result<f32, Self::Error>;
+
+    /// Set the temperature offset (C), while stopped.
+    async fn set_temperature_offset(&mut self, offset_c: f32) -> Result<(), Self::Error>;
+
+    /// Compensate for the altitude (m) instead of measured pressure, while
+    /// stopped.
+    async fn set_altitude(&mut self, altitude_m: u16) -> Result<(), Self::Error>;
+
+    /// Compensate for ambient pressure (hPa).
+    async fn set_ambient_pressure(&mut self, hpa: u16) -> Result<(), Self::Error>;
+
+    /// Force recalibration against a reference level (ppm), while stopped.
+    /// Returns the applied correction (ppm), or `None` if the sensor
+    /// rejected it.
+    async fn calibrate(&mut self, ppm: u16) -> Result<Option<i16>, Self::Error>;
+}
+
+/// Pressure and temperature sensor (e.g. BMP581).
+#[expect(
+    async_fn_in_trait,
+    reason = "sensor tasks run on a thread-mode executor"
+)]
+pub trait PressureSensor {
+    type Error: defmt::Format;
+
+    /// Ready the sensor after power on.
+    async fn init(&mut self) -> Result<(), Self::Error>;
+
+    /// Take a measurement.
+    async fn read(&mut self) -> Result<PressureMeasurement, Self::Error>;
+}
+
+/// Particulate matter sensor (e.g. PMSA003I, SPS30).
+#[expect(
+    async_fn_in_trait,
+    reason = "sensor tasks run on a thread-mode executor"
+)]
+pub trait PmSensor {
+    type Error: defmt::Format;
+
+    /// Ready the sensor after power on, and start measuring.
+    async fn init(&mut self) -> Result<(), Self::Error>;
+
+    /// Read the latest measurement.
+    async fn read(&mut self) -> Result<PmMeasurement, Self::Error>;
+}
+
+/// Gas sensor measuring VOCs, and possibly NOx, as raw signals for the gas
+/// index algorithm (e.g. SGP40, SGP41).
+#[expect(
+    async_fn_in_trait,
+    reason = "sensor tasks run on a thread-mode executor"
+)]
+pub trait GasSensor {
+    type Error: defmt::Format;
+
+    /// Ready the sensor after power on, returning whether it measures NOx.
+    async fn init(&mut self) -> Result<bool, Self::Error>;
+
+    /// Measure the raw VOC signal, and the raw NOx signal once available,
+    /// compensated for relative humidity (%RH) and temperature (C).
+    async fn read_raw(
+        &mut self,
+        humidity: f32,
+        temp_c: f32,
+    ) -> Result<(u16, Option<u16>), Self::Error>;
+}
+
+/// Temperature (F) to log, NaN if it overflows.
+fn fahrenheit(temp: Celsius) -> f32 {
+    temp.to_fahrenheit().map_or(f32::NAN, f32::from)
+}
+
+/// Run `init` until it succeeds, up to [`INIT_ATTEMPTS_MAX`] times.
+async fn init_with_retries<E: defmt::Format>(
+    name: &str,
+    retry: Duration,
+    mut init: impl AsyncFnMut() -> Result<(), E>,
+) -> Result<(), E> {
+    let mut attempt = 1;
+    loop {
+        match init().await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                defmt::error!("{=str}: Init attempt {=u8} failed ({:?})", name, attempt, e);
+                if attempt == INIT_ATTEMPTS_MAX {
+                    defmt::error!(
+                        "{=str}: Failed to initialize after {=u8} attempts",
+                        name,
+                        INIT_ATTEMPTS_MAX
+                    );
+                    return Err(e);
+                }
+                attempt += 1;
+                Timer::after(retry).await;
+            }
+        }
+    }
+}
+
+/// CO2 sensor control: polling, settings, and calibration.
+pub struct Co2Control<S> {
+    sensor: S,
+    applied: Settings,
+}
+
+impl<S: CO2Sensor> Co2Control<S> {
+    /// Initialize the sensor, apply settings, and start measuring.
+    ///
+    /// # Errors
+    /// Returns the sensor error if init fails [`INIT_ATTEMPTS_MAX`] times or
+    /// measurement cannot be started.
+    pub async fn start(mut sensor: S, settings: Settings) -> Result<Self, S::Error> {
+        init_with_retries("CO2 Sensor", CO2_INIT_RETRY, async || sensor.init().await).await?;
+        Self::configured(sensor, settings).await
+    }
+
+    /// As [`start`](Self::start), compensating for a fixed altitude (m) on
+    /// units without a pressure sensor.
+    ///
+    /// # Errors
+    /// Returns the sensor error if init fails [`INIT_ATTEMPTS_MAX`] times,
+    /// the altitude cannot be set, or measurement cannot be started.
+    pub async fn start_at_altitude(
+        mut sensor: S,
+        settings: Settings,
+        altitude_m: u16,
+    ) -> Result<Self, S::Error> {
+        init_with_retries("CO2 Sensor", CO2_INIT_RETRY, async || sensor.init().await).await?;
+        if let Err(e) = sensor.set_altitude(altitude_m).await {
+            defmt::error!("CO2 Sensor: Failed to set altitude ({:?})", e);
+            return Err(e);
+        }
+        defmt::info!("CO2 Sensor: Compensating for {=u16} m altitude", altitude_m);
+        Self::configured(sensor, settings).await
+    }
+
+    /// Apply settings to an initialized sensor, and start measuring.
+    async fn configured(sensor: S, settings: Settings) -> Result<Self, S::Error> {
+        let mut control = Self {
+            sensor,
+            applied: settings,
+        };
+        control.configure().await?;
+        Ok(control)
+    }
+
+    /// Sensor being controlled.
+    #[must_use]
+    pub const fn sensor(&self) -> &S {
+        &self.sensor
+    }
+
+    /// Delay until the next poll.
+    #[must_use]
+    pub const fn interval(&self) -> Duration {
+        self.applied.power_mode.interval()
+    }
+
+    /// Read any new measurement, compensated for ambient pressure, then apply
+    /// a requested recalibration (ppm) and changed settings.
+    ///
+    /// Read failures are logged and counted as sensor errors.
+    ///
+    /// # Errors
+    /// Returns the sensor error if measurement cannot be restarted.
+    pub async fn poll(
+        &mut self,
+        pressure: Option<&PressureMeasurement>,
+        calibration: Option<u16>,
+        settings: Settings,
+    ) -> Result<Option<Co2Measurement>, S::Error> {
+        let m_co2 = match self.sensor.data_ready().await {
+            Ok(true) => self.measure(pressure).await,
+            Ok(false) => {
+                defmt::trace!("CO2 Sensor: No unread data");
+                None
+            }
+            Err(e) => {
+                diagnostics::record_sensor_error();
+                defmt::error!("CO2 Sensor: Failed device ready probe ({:?})", e);
+                None
+            }
+        };
+
+        if let Some(ppm) = calibration {
+            self.calibrate(ppm).await?;
+        }
+
+        let restart = (settings.power_mode, settings.temp_offset_c)
+            != (self.applied.power_mode, self.applied.temp_offset_c);
+        self.applied = settings;
+        if restart {
+            defmt::info!("CO2 Sensor: Applying settings ({:?})", settings);
+            self.stop().await;
+            self.configure().await?;
+        }
+
+        Ok(m_co2)
+    }
+
+    /// Read a measurement, then compensate the next for ambient pressure.
+    async fn measure(&mut self, pressure: Option<&PressureMeasurement>) -> Option<Co2Measurement> {
+        let m_co2 = match self.sensor.read().await {
+            Ok(m_co2) => m_co2,
+            Err(e) => {
+                diagnostics::record_sensor_error();
+                defmt::error!("CO2 Sensor: Read failed ({:?})", e);
+                return None;
+            }
+        };
+        defmt::info!(
+            "CO2: {}, Humidity: {}, Temperature: {} C ({=f32} F)",
+            m_co2.co2,
+            m_co2.humidity,
+            m_co2.temp_c,
+            fahrenheit(m_co2.temp_c)
+        );
+
+        if let Some(m_pa) = pressure
+            && let Err(e) = self.sensor.set_ambient_pressure(m_pa.hpa.whole()).await
+        {
+            defmt::error!("CO2 Sensor: Failed to set pressure ({:?})", e);
+        }
+        Some(m_co2)
+    }
+
+    /// Force recalibration, then resume measuring.
+    ///
+    /// The sensor should have been running at the reference level for at
+    /// least three minutes.
+    async fn calibrate(&mut self, ppm: u16) -> Result<(), S::Error> {
+        defmt::info!("CO2 Sensor: Recalibrating to {=u16} ppm", ppm);
+        self.stop().await;
+        match self.sensor.calibrate(ppm).await {
+            Ok(Some(correction)) => {
+                defmt::info!(
+                    "CO2 Sensor: Recalibrated (correction {=i16} ppm)",
+                    correction
+                );
+            }
+            Ok(None) => defmt::error!("CO2 Sensor: Recalibration failed"),
+            Err(e) => {
+                diagnostics::record_sensor_error();
+                defmt::error!("CO2 Sensor: Recalibration failed ({:?})", e);
+            }
+        }
+        self.start_measurement().await
+    }
+
+    /// Stop measuring, logging failures.
+    async fn stop(&mut self) {
+        if let Err(e) = self.sensor.stop().await {
+            defmt::error!("CO2 Sensor: Failed to stop periodic measurement ({:?})", e);
+        }
+    }
+
+    /// Apply the temperature offset, then start measuring.
+    async fn configure(&mut self) -> Result<(), S::Error> {
+        let offset = self.applied.temp_offset_c;
+        match self.sensor.temperature_offset().await {
+            Ok(previous_offset) => defmt::debug!(
+                "CO2 Sensor: Setting temperature offset (old: {=f32} C, new: {=f32} C)",
+                previous_offset,
+                offset
+            ),
+            Err(e) => {
+                defmt::error!("CO2 Sensor: Failed to probe temperature offset ({:?})", e);
+                return Err(e);
+            }
+        }
+        if let Err(e) = self.sensor.set_temperature_offset(offset).await {
+            defmt::error!("CO2 Sensor: Failed to set temperature offset ({:?})", e);
+            return Err(e);
+        }
+        self.start_measurement().await
+    }
+
+    /// Start measuring in the applied power mode.
+    async fn start_measurement(&mut self) -> Result<(), S::Error> {
+        let power_mode = self.applied.power_mode;
+        match self.sensor.start(power_mode).await {
+            Ok(()) => {
+                defmt::info!(
+                    "CO2 Sensor: Initiated {:?} power periodic measurement",
+                    power_mode
+                );
+                Ok(())
+            }
+            Err(e) => {
+                defmt::error!(
+                    "CO2 Sensor: Failed to start {:?} power periodic measurement ({:?})",
+                    power_mode,
+                    e
+                );
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Poll the CO2 sensor at the power mode's interval, until measurement
+/// cannot be restarted.
+///
+/// `pressure` gives the latest pressure measurement, `calibration` any
+/// requested recalibration (ppm), and `publish` receives each measurement
+/// with the pressure it was compensated for.
+pub async fn run_co2<S: CO2Sensor>(
+    control: &mut Co2Control<S>,
+    mut pressure: impl FnMut() -> Option<PressureMeasurement>,
+    mut calibration: impl FnMut() -> Option<u16>,
+    mut publish: impl FnMut(Co2Measurement, Option<PressureMeasurement>),
+) -> S::Error {
+    loop {
+        let m_pa = pressure();
+        match control
+            .poll(m_pa.as_ref(), calibration(), settings::get())
+            .await
+        {
+            Ok(Some(m_co2)) => publish(m_co2, m_pa),
+            Ok(None) => {}
+            Err(e) => return e,
+        }
+        Timer::after(control.interval()).await;
+    }
+}
+
+/// Pressure sensor control.
+pub struct PressureControl<S> {
+    sensor: S,
+}
+
+impl<S: PressureSensor> PressureControl<S> {
+    /// Initialize the sensor.
+    ///
+    /// # Errors
+    /// Returns the sensor error if init fails [`INIT_ATTEMPTS_MAX`] times.
+    pub async fn start(mut sensor: S) -> Result<Self, S::Error> {
+        init_with_retries("Pressure Sensor", PRESSURE_INIT_RETRY, async || {
+            sensor.init().await
+        })
+        .await?;
+        defmt::info!("Pressure Sensor: Initialized successfully");
+        Ok(Self { sensor })
+    }
+
+    /// Take a measurement; failures are logged and counted as sensor errors.
+    pub async fn poll(&mut self) -> Option<PressureMeasurement> {
+        match self.sensor.read().await {
+            Ok(m_pa) => {
+                defmt::info!(
+                    "Pressure: {} hPa, Temperature: {} C ({=f32} F)",
+                    m_pa.hpa,
+                    m_pa.temp_c,
+                    fahrenheit(m_pa.temp_c)
+                );
+                Some(m_pa)
+            }
+            Err(e) => {
+                diagnostics::record_sensor_error();
+                defmt::error!("Pressure Sensor: Measurement failed ({:?})", e);
+                None
+            }
+        }
+    }
+}
+
+/// Poll the pressure sensor at the power mode's interval, publishing each
+/// measurement.
+pub async fn run_pressure<S: PressureSensor>(
+    control: &mut PressureControl<S>,
+    mut publish: impl FnMut(PressureMeasurement),
+) -> ! {
+    loop {
+        if let Some(m_pa) = control.poll().await {
+            publish(m_pa);
+        }
+        Timer::after(settings::get().power_mode.interval()).await;
+    }
+}
+
+/// Particulate sensor control.
+pub struct PmControl<S> {
+    sensor: S,
+}
+
+impl<S: PmSensor> PmControl<S> {
+    /// Initialize the sensor and start measuring.
+    ///
+    /// # Errors
+    /// Returns the sensor error if init fails [`INIT_ATTEMPTS_MAX`] times.
+    pub async fn start(mut sensor: S) -> Result<Self, S::Error> {
+        init_with_retries("PM Sensor", PM_INIT_RETRY, async || sensor.init().await).await?;
+        defmt::info!("PM Sensor: Initialized successfully");
+        Ok(Self { sensor })
+    }
+
+    /// Read a measurement, flagged unreliable above the humidity saturation
+    /// threshold (%RH); failures are logged and counted as sensor errors.
+    pub async fn poll(&mut self, humidity: Option<RelativeHumidity>) -> Option<PmMeasurement> {
+        match self.sensor.read().await {
+            Ok(m_pm) => {
+                let m_pm = m_pm.gated(humidity);
+                defmt::info!(
+                    "PM1.0: {=f32}, PM2.5: {=f32}, PM10: {=f32} ug/m3{=str}",
+                    m_pm.pm1,
+                    m_pm.pm2_5,
+                    m_pm.pm10,
+                    if m_pm.unreliable { " (humid)" } else { "" }
+                );
+                Some(m_pm)
+            }
+            Err(e) => {
+                diagnostics::record_sensor_error();
+                defmt::error!("PM Sensor: Read failed ({:?})", e);
+                None
+            }
+        }
+    }
+}
+
+/// Poll the particulate sensor at the power mode's interval, publishing each
+/// measurement.
+///
+/// `humidity` gives the latest relative humidity (%RH), if known.
+pub async fn run_pm<S: PmSensor>(
+    control: &mut PmControl<S>,
+    mut humidity: impl FnMut() -> Option<RelativeHumidity>,
+    mut publish: impl FnMut(PmMeasurement),
+) -> ! {
+    loop {
+        if let Some(m_pm) = control.poll(humidity()).await {
+            publish(m_pm);
+        }
+        Timer::after(settings::get().power_mode.interval()).await;
+    }
+}
+
+/// Gas sensor control: the gas index algorithm for each measured gas.
+pub struct GasControl<S> {
+    sensor: S,
+    voc: GasIndex,
+    nox: Option<GasIndex>,
+}
+
+impl<S: GasSensor> GasControl<S> {
+    /// Initialize the sensor.
+    ///
+    /// # Errors
+    /// Returns the sensor error if init fails [`INIT_ATTEMPTS_MAX`] times.
+    pub async fn start(mut sensor: S) -> Result<Self, S::Error> {
+        let mut has_nox = false;
+        init_with_retries("Gas Sensor", GAS_INIT_RETRY, async || {
+            has_nox = sensor.init().await?;
+            Ok(())
+        })
+        .await?;
+        defmt::info!(
+            "Gas Sensor: Initialized successfully ({=str})",
+            if has_nox { "VOC, NOx" } else { "VOC" }
+        );
+        Ok(Self {
+            sensor,
+            voc: GasIndex::new(Gas::Voc),
+            nox: has_nox.then(|| GasIndex::new(Gas::Nox)),
+        })
+    }
+
+    /// Measure, compensated for the latest CO2 sensor humidity and
+    /// temperature, and update the indices; failures are logged and counted
+    /// as sensor errors.
+    pub async fn poll(&mut self, m_co2: Option<&Co2Measurement>) -> Option<GasMeasurement> {
+        let (humidity, temp_c) = m_co2.map_or(GAS_DEFAULT_COMPENSATION, |m| {
+            (m.humidity.into_inner(), m.temp_c.into_inner())
+        });
+        match self.sensor.read_raw(humidity, temp_c).await {
+            Ok((voc_raw, nox_raw)) => {
+                let voc_index = self.voc.process(voc_raw);
+                // NOx reads 0 until its signal is available, as while settling
+                let nox_index = self
+                    .nox
+                    .as_mut()
+                    .map(|nox| nox_raw.map_or(0, |nox_raw| nox.process(nox_raw)));
+                defmt::debug!("VOC index: {=u16}, NOx index: {=?}", voc_index, nox_index);
+                Some(GasMeasurement::new(voc_index, nox_index))
+            }
+            Err(e) => {
+                diagnostics::record_sensor_error();
+                defmt::error!("Gas Sensor: Measurement failed ({:?})", e);
+                None
+            }
+        }
+    }
+}
+
+/// Poll the gas sensor every [`GAS_INTERVAL`], publishing each measurement.
+///
+/// `m_co2` gives the latest CO2 sensor measurement, if any, for humidity and
+/// temperature compensation.
+pub async fn run_gas<S: GasSensor>(
+    control: &mut GasControl<S>,
+    mut m_co2: impl FnMut() -> Option<Co2Measurement>,
+    mut publish: impl FnMut(GasMeasurement),
+) -> ! {
+    loop {
+        if let Some(m_gas) = control.poll(m_co2().as_ref()).await {
+            publish(m_gas);
+        }
+        Timer::after(GAS_INTERVAL).await;
+    }
+}
@@ -0,0 +1,44 @@
+//! BMP581 driver, over any async I2C bus.
+
+use bmp5::i2c::Bmp5;
+use embassy_time::Delay;
+use embedded_hal_async::i2c::I2c;
+
+use super::{DriverError, PressureSensor};
+use crate::measurement::PressureMeasurement;
+
+const BMP5_CONFIG: bmp5::Config = bmp5::Config {
+    temperature_oversampling: bmp5::Oversampling::Oversampling8X,
+    temperature_iir_filter: bmp5::IIRFilter::Bypass,
+    pressure_oversampling: bmp5::Oversampling::Oversampling8X,
+    pressure_iir_filter: bmp5::IIRFilter::Bypass,
+    output_data_rate: bmp5::OutputDataRate::OutputDataRate0_250Hz,
+};
+
+/// BMP581 pressure and temperature sensor.
+pub struct Bmp581Sensor<I: I2c>(Bmp5<I, Delay>);
+
+impl<I: I2c> Bmp581Sensor<I> {
+    /// Sensor at `address`, 0x47 or 0x46 depending on the SDO strap.
+    #[must_use]
+    pub fn new(i2c: I, address: u8) -> Self {
+        Self(Bmp5::new(i2c, Delay, address, BMP5_CONFIG))
+    }
+}
+
+impl<I> PressureSensor for Bmp581Sensor<I>
+where
+    I: I2c,
+    I::Error: defmt::Format,
+{
+    type Error = DriverError<impl defmt::Format>;
+
+    async fn init(&mut self) -> Result<(), Self::Error> {
+        self.0.init().await.map_err(DriverError::Driver)
+    }
+
+    async fn read(&mut self) -> Result<PressureMeasurement, Self::Error> {
+        let m = self.0.measure().await.map_err(DriverError::Driver)?;
+        PressureMeasurement::new(m.pressure, m.temperature).map_err(DriverError::Range)
+    }
+}
@@ -0,0 +1,93 @@
+//! PMSA003I driver, over any async I2C bus.
+//!
+//! The sensor measures continuously; each read returns its latest 32-byte
+//! frame: two start characters, the frame length, thirteen big-endian data
+//! words, and a checksum of the preceding bytes.
+
+use embedded_hal_async::i2c::I2c;
+
+use super::PmSensor;
+use crate::measurement::PmMeasurement;
+
+/// I2C address.
+pub const ADDRESS: u8 = 0x12;
+
+/// Frame length, in bytes.
+pub const FRAME_LEN: usize = 32;
+
+/// Frame start characters.
+pub const START: [u8; 2] = [0x42, 0x4d];
+
+/// Words of the PM1.0, PM2.5 and PM10 concentrations under atmospheric
+/// environment, counting the start characters and length as words.
+const ATMOSPHERIC_WORDS: core::ops::Range<usize> = 5..8;
+
+/// PMSA003I driver error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Error<E> {
+    I2c(E),
+    /// Bad start characters or checksum.
+    Frame,
+}
+
+/// PMSA003I particulate matter sensor.
+pub struct Pmsa003iSensor<I: I2c> {
+    i2c: I,
+}
+
+impl<I: I2c> Pmsa003iSensor<I> {
+    #[must_use]
+    pub const fn new(i2c: I) -> Self {
+        Self { i2c }
+    }
+
+    /// Read and check the latest frame.
+    async fn frame(&mut self) -> Result<[u8; FRAME_LEN], Error<I::Error>> {
+        let mut frame = [0; FRAME_LEN];
+        self.i2c
+            .read(ADDRESS, &mut frame)
+            .await
+            .map_err(Error::I2c)?;
+        if frame_valid(&frame) {
+            Ok(frame)
+        } else {
+            Err(Error::Frame)
+        }
+    }
+}
+
+/// Whether a frame has the start characters and a valid checksum.
+#[must_use]
+pub fn frame_valid(frame: &[u8; FRAME_LEN]) -> bool {
+    let [body @ .., hi, lo] = *frame;
+    let sum = body
+        .iter()
+        .fold(0_u16, |sum, &byte| sum.wrapping_add(u16::from(byte)));
+    body.starts_with(&START) && sum == u16::from_be_bytes([hi, lo])
+}
+
+impl<I> PmSensor for Pmsa003iSensor<I>
+where
+    I: I2c,
+    I::Error: defmt::Format,
+{
+    type Error = Error<I::Error>;
+
+    async fn init(&mut self) -> Result<(), Self::Error> {
+        // Measuring from power on; a valid frame shows it is ready
+        self.frame().await.map(|_| ())
+    }
+
+    async fn read(&mut self) -> Result<PmMeasurement, Self::Error> {
+        let frame = self.frame().await?;
+        let (words, _) = frame.as_chunks::<2>();
+        match words.get(ATMOSPHERIC_WORDS) {
+            Some(&[pm1, pm2_5, pm10]) => Ok(PmMeasurement::new(
+                f32::from(u16::from_be_bytes(pm1)),
+                f32::from(u16::from_be_bytes(pm2_5)),
+                f32::from(u16::from_be_bytes(pm10)),
+            )),
+            _ => Err(Error::Frame),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! SCD4X driver, over any async I2C bus.
+
+use embassy_time::Delay;
+use embedded_hal_async::i2c::I2c;
+use libscd::asynchronous::scd4x::Scd4x;
+
+use super::{CO2Sensor, DriverError};
+use crate::measurement::Co2Measurement;
+use crate::power::PowerMode;
+
+/// SCD4X CO2, humidity, and temperature sensor.
+pub struct Scd4xSensor<I: I2c>(Scd4x<I, Delay>);
+
+impl<I: I2c> Scd4xSensor<I> {
+    #[must_use]
+    pub fn new(i2c: I) -> Self {
+        Self(Scd4x::new(i2c, Delay))
+    }
+
+    /// Log SCD4X variant and serial number.
+    async fn log_device_info(&mut self)
+    where
+        I::Error: defmt::Format,
+    {
+        if let Ok(Some(variant)) = self.0.sensor_variant().await {
+            match variant {
+                libscd::SensorVariant::Scd40 => defmt::info!("CO2 Sensor: SCD-40"),
+                libscd::SensorVariant::Scd41 => defmt::info!("CO2 Sensor: SCD-41"),
+                libscd::SensorVariant::Scd43 => defmt::info!("CO2 Sensor: SCD-43"),
+                _ => defmt::info!("CO2 Sensor: Unknown"),
+            }
+            match self.0.serial_number().await {
+                Ok(sn) => defmt::info!("CO2 Sensor SN: {:?}", sn),
+                Err(e) => defmt::error!("CO2 Sensor: Failed to read SN ({:?})", e),
+            }
+        } else {
+            defmt::error!("CO2 Sensor: Failed to read sensor");
+        }
+    }
+}
+
+impl<I> CO2Sensor for Scd4xSensor<I>
+where
+    I: I2c,
+    I::Error: defmt::Format,
+{
+    type Error = DriverError<impl defmt::Format>;
+
+    async fn init(&mut self) -> Result<(), Self::Error> {
+        let stopped = self.0.stop_periodic_measurement().await;
+        if stopped.is_ok() {
+            self.log_device_info().await;
+        }
+        stopped.map_err(DriverError::Driver)
+    }
+
+    async fn start(&mut self, power_mode: PowerMode) -> Result<(), Self::Error> {
+        match power_mode {
+            PowerMode::High => self.0.start_periodic_measurement().await,
+            PowerMode::Low => self.0.start_low_power_periodic_measurement().await,
+        }
+        .map_err(DriverError::Driver)
+    }
+
+    async fn stop(&mut self) -> Result<(), Self::Error> {
+        self.0
+            .stop_periodic_measurement()
+            .await
+            .map_err(DriverError::Driver)
+    }
+
+    async fn data_ready(&mut self) -> Result<bool, Self::Error> {
+        self.0.data_ready().await.map_err(DriverError::Driver)
+    }
+
+    async fn read(&mut self) -> Result<Co2Measurement, Self::Error> {
+        let m = self
+            .0
+            .read_measurement()
+            .await
+            .map_err(DriverError::Driver)?;
+        Co2Measurement::new(m.co2, m.humidity, m.temperature).map_err(DriverError::Range)
+    }
+
+    async fn temperature_offset(&mut self) -> Result<f32, Self::Error> {
+        self.0
+            .get_temperature_offset()
+            .await
+            .map_err(DriverError::Driver)
+    }
+
+    async fn set_temperature_offset(&mut self, offset_c: f32) -> Result<(), Self::Error> {
+        self.0
+            .set_temperature_offset(offset_c)
+            .await
+            .map_err(DriverError::Driver)
+    }
+
+    async fn set_altitude(&mut self, altitude_m: u16) -> Result<(), Self::Error> {
+        self.0
+            .set_sensor_altitude(altitude_m)
+            .await
+            .map_err(DriverError::Driver)
+    }
+
+    async fn set_ambient_pressure(&mut self, hpa: u16) -> Result<(), Self::Error> {
+        self.0
+            .set_ambient_pressure(hpa)
+            .await
+            .map_err(DriverError::Driver)
+    }
+
+    async fn calibrate(&mut self, ppm: u16) -> Result<Option<i16>, Self::Error> {
+        self.0
+            .perform_forced_recalibration(ppm)
+            .await
+            .map_err(DriverError::Driver)
+    }
+}
@@ -0,0 +1,232 @@
+//! SGP40 and SGP41 driver, over any async I2C bus.
+//!
+//! Sensirion command set: 16-bit commands with CRC-8 protected argument and
+//! response words. Each measurement takes the relative humidity and
+//! temperature to compensate for, as ticks. The SGP41 also measures NOx, once
+//! its NOx pixel has been conditioned for ten seconds after power on.
+
+use embassy_time::Timer;
+use embedded_hal_async::i2c::I2c;
+
+use super::GasSensor;
+use crate::sensirion::{self, crc8};
+
+/// I2C address.
+pub const ADDRESS: u8 = 0x59;
+
+/// Command codes.
+pub mod command {
+    /// SGP40 only.
+    pub const MEASURE_RAW_SIGNAL: u16 = 0x260f;
+    /// SGP41 only.
+    pub const EXECUTE_CONDITIONING: u16 = 0x2612;
+    /// SGP41 only.
+    pub const MEASURE_RAW_SIGNALS: u16 = 0x2619;
+    pub const GET_SERIAL_NUMBER: u16 = 0x3682;
+}
+
+/// SGP41 NOx pixel conditioning after power on, in one second samples.
+pub const CONDITIONING_SAMPLES: u8 = 10;
+
+/// SGP40 measurement execution time.
+const SGP40_MEASURE_MS: u64 = 30;
+
+/// SGP41 conditioning and measurement execution time.
+const SGP41_MEASURE_MS: u64 = 50;
+
+/// Get serial number execution time.
+const GET_SERIAL_NUMBER_MS: u64 = 1;
+
+/// Relative humidity (%RH) as a measurement argument.
+#[must_use]
+pub fn humidity_ticks(humidity: f32) -> u16 {
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "clamped to the tick range"
+    )]
+    let ticks = (humidity.clamp(0.0, 100.0) * 65535.0 / 100.0) as u16;
+    ticks
+}
+
+/// Temperature (C) as a measurement argument.
+#[must_use]
+pub fn temperature_ticks(temp_c: f32) -> u16 {
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "clamped to the tick range"
+    )]
+    let ticks = ((temp_c.clamp(-45.0, 130.0) + 45.0) * 65535.0 / 175.0) as u16;
+    ticks
+}
+
+/// SGP4X driver error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Error<E> {
+    I2c(E),
+    /// Response word with a bad CRC.
+    Crc,
+}
+
+/// Sensor variant, told apart by the commands it accepts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Variant {
+    /// VOC.
+    Sgp40,
+    /// VOC and NOx.
+    Sgp41,
+}
+
+/// SGP40 or SGP41 gas sensor.
+pub struct Sgp4xSensor<I: I2c> {
+    i2c: I,
+    variant: Variant,
+    /// SGP41 samples left conditioning the NOx pixel.
+    conditioning: u8,
+}
+
+impl<I: I2c> Sgp4xSensor<I> {
+    #[must_use]
+    pub const fn new(i2c: I) -> Self {
+        Self {
+            i2c,
+            variant: Variant::Sgp40,
+            conditioning: 0,
+        }
+    }
+
+    /// Variant found by [`init`](GasSensor::init).
+    #[must_use]
+    pub const fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Send a command with the humidity and temperature arguments, wait for
+    /// it to execute, and read `N` response words.
+    async fn measure<const N: usize>(
+        &mut self,
+        command: u16,
+        humidity: f32,
+        temp_c: f32,
+        execution_ms: u64,
+    ) -> Result<[u16; N], Error<I::Error>> {
+        let [hi, lo] = command.to_be_bytes();
+        let [rh_hi, rh_lo] = humidity_ticks(humidity).to_be_bytes();
+        let [t_hi, t_lo] = temperature_ticks(temp_c).to_be_bytes();
+        self.i2c
+            .write(
+                ADDRESS,
+                &[
+                    hi,
+                    lo,
+                    rh_hi,
+                    rh_lo,
+                    crc8([rh_hi, rh_lo]),
+                    t_hi,
+                    t_lo,
+                    crc8([t_hi, t_lo]),
+                ],
+            )
+            .await
+            .map_err(Error::I2c)?;
+        Timer::after_millis(execution_ms).await;
+        self.read_words().await
+    }
+
+    /// Read `N` response words, checking their CRCs.
+    async fn read_words<const N: usize>(&mut self) -> Result<[u16; N], Error<I::Error>> {
+        // Three words at most: the serial number
+        let mut response = [0; 9];
+        let response = response.get_mut(..N * 3).ok_or(Error::Crc)?;
+        self.i2c.read(ADDRESS, response).await.map_err(Error::I2c)?;
+        if !sensirion::words_valid(response) {
+            return Err(Error::Crc);
+        }
+        let (words, _) = response.as_chunks::<3>();
+        let mut values = [0; N];
+        for (value, &[hi, lo, _]) in values.iter_mut().zip(words) {
+            *value = u16::from_be_bytes([hi, lo]);
+        }
+        Ok(values)
+    }
+}
+
+impl<I> GasSensor for Sgp4xSensor<I>
+where
+    I: I2c,
+    I::Error: defmt::Format,
+{
+    type Error = Error<I::Error>;
+
+    async fn init(&mut self) -> Result<bool, Self::Error> {
+        let [hi, lo] = command::GET_SERIAL_NUMBER.to_be_bytes();
+        self.i2c
+            .write(ADDRESS, &[hi, lo])
+            .await
+            .map_err(Error::I2c)?;
+        Timer::after_millis(GET_SERIAL_NUMBER_MS).await;
+        self.read_words::<3>().await?;
+
+        // Conditioning starts the SGP41's; the SGP40 NACKs it as unknown
+        let (humidity, temp_c) = super::GAS_DEFAULT_COMPENSATION;
+        let conditioned = self
+            .measure::<1>(
+                command::EXECUTE_CONDITIONING,
+                humidity,
+                temp_c,
+                SGP41_MEASURE_MS,
+            )
+            .await;
+        (self.variant, self.conditioning) = match conditioned {
+            Ok(_) => (Variant::Sgp41, CONDITIONING_SAMPLES - 1),
+            Err(Error::I2c(_)) => (Variant::Sgp40, 0),
+            Err(e) => return Err(e),
+        };
+        defmt::info!("Gas Sensor: {:?}", self.variant);
+        Ok(self.variant == Variant::Sgp41)
+    }
+
+    async fn read_raw(
+        &mut self,
+        humidity: f32,
+        temp_c: f32,
+    ) -> Result<(u16, Option<u16>), Self::Error> {
+        match self.variant {
+            Variant::Sgp40 => {
+                let [voc] = self
+                    .measure(
+                        command::MEASURE_RAW_SIGNAL,
+                        humidity,
+                        temp_c,
+                        SGP40_MEASURE_MS,
+                    )
+                    .await?;
+                Ok((voc, None))
+            }
+            Variant::Sgp41 if self.conditioning > 0 => {
+                let [voc] = self
+                    .measure(
+                        command::EXECUTE_CONDITIONING,
+                        humidity,
+                        temp_c,
+                        SGP41_MEASURE_MS,
+                    )
+                    .await?;
+                self.conditioning -= 1;
+                Ok((voc, None))
+            }
+            Variant::Sgp41 => {
+                let [voc, nox] = self
+                    .measure(
+                        command::MEASURE_RAW_SIGNALS,
+                        humidity,
+                        temp_c,
+                        SGP41_MEASURE_MS,
+                    )
+                    .await?;
+                Ok((voc, Some(nox)))
+            }
+        }
+    }
+}
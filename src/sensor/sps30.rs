@@ -0,0 +1,110 @@
+//! SPS30 driver, over any async I2C bus.
+//!
+//! Sensirion command set: 16-bit commands with CRC-8 protected argument and
+//! response words. Measurements are read as floats (ug/m3), keeping the
+//! tenths the PM2.5 AQI breakpoints are specified to.
+
+use embassy_time::Timer;
+use embedded_hal_async::i2c::I2c;
+
+use super::PmSensor;
+use crate::measurement::PmMeasurement;
+use crate::sensirion::{self, crc8};
+
+/// I2C address.
+pub const ADDRESS: u8 = 0x69;
+
+/// Command codes.
+pub mod command {
+    pub const START_MEASUREMENT: u16 = 0x0010;
+    pub const STOP_MEASUREMENT: u16 = 0x0104;
+    pub const READ_MEASURED_VALUES: u16 = 0x0300;
+}
+
+/// Start measurement argument: big-endian IEEE 754 float output.
+pub const OUTPUT_FORMAT_FLOAT: u16 = 0x0300;
+
+/// Start and stop measurement execution time.
+const MODE_CHANGE_MS: u64 = 20;
+
+/// PM1.0, PM2.5, PM4.0 and PM10 mass concentrations, two words each, with
+/// CRCs.
+const MASS_CONCENTRATIONS_LEN: usize = 24;
+
+/// SPS30 driver error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Error<E> {
+    I2c(E),
+    /// Response word with a bad CRC.
+    Crc,
+}
+
+/// SPS30 particulate matter sensor.
+pub struct Sps30Sensor<I: I2c> {
+    i2c: I,
+}
+
+impl<I: I2c> Sps30Sensor<I> {
+    #[must_use]
+    pub const fn new(i2c: I) -> Self {
+        Self { i2c }
+    }
+
+    /// Send a command, with an optional argument word.
+    async fn command(&mut self, command: u16, argument: Option<u16>) -> Result<(), I::Error> {
+        let [hi, lo] = command.to_be_bytes();
+        match argument {
+            Some(argument) => {
+                let [a, b] = argument.to_be_bytes();
+                self.i2c.write(ADDRESS, &[hi, lo, a, b, crc8([a, b])]).await
+            }
+            None => self.i2c.write(ADDRESS, &[hi, lo]).await,
+        }
+    }
+}
+
+impl<I> PmSensor for Sps30Sensor<I>
+where
+    I: I2c,
+    I::Error: defmt::Format,
+{
+    type Error = Error<I::Error>;
+
+    async fn init(&mut self) -> Result<(), Self::Error> {
+        // Stopping ends any measurement left running by a reset; idle, the
+        // sensor NACKs it
+        if self.command(command::STOP_MEASUREMENT, None).await.is_ok() {
+            Timer::after_millis(MODE_CHANGE_MS).await;
+        }
+        self.command(command::START_MEASUREMENT, Some(OUTPUT_FORMAT_FLOAT))
+            .await
+            .map_err(Error::I2c)?;
+        Timer::after_millis(MODE_CHANGE_MS).await;
+        Ok(())
+    }
+
+    async fn read(&mut self) -> Result<PmMeasurement, Self::Error> {
+        self.command(command::READ_MEASURED_VALUES, None)
+            .await
+            .map_err(Error::I2c)?;
+        let mut response = [0; MASS_CONCENTRATIONS_LEN];
+        self.i2c
+            .read(ADDRESS, &mut response)
+            .await
+            .map_err(Error::I2c)?;
+        if !sensirion::words_valid(&response) {
+            return Err(Error::Crc);
+        }
+        let (floats, _) = response.as_chunks::<6>();
+        match *floats {
+            // PM4.0 is not reported
+            [pm1, pm2_5, _, pm10] => Ok(PmMeasurement::new(float(pm1), float(pm2_5), float(pm10))),
+            _ => Err(Error::Crc),
+        }
+    }
+}
+
+/// Float from its two response words, each followed by its CRC.
+const fn float([a, b, _, c, d, _]: [u8; 6]) -> f32 {
+    f32::from_be_bytes([a, b, c, d])
+}
@@ -0,0 +1,207 @@
+//! Serial Task: Framed binary protocol or Modbus RTU slave over the UART.
+
+use embassy_futures::join::join;
+use embassy_futures::select::{Either, select};
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::watch::DynReceiver;
+use embassy_time::Instant;
+use microbit_bsp::embassy_nrf::peripherals::{P0_06, P1_08, PPI_CH0, PPI_CH1, TIMER1, UARTE0};
+use microbit_bsp::embassy_nrf::uarte::{self, Baudrate, Parity, Uarte, UarteRxWithIdle, UarteTx};
+use microbit_bsp::embassy_nrf::{Peri, bind_interrupts};
+use rustymicrobit_moxi::protocol::{self, Decoder, Frame, Message, NackReason, Status};
+use rustymicrobit_moxi::serial_mode::{SERIAL_MODE, SerialMode};
+use rustymicrobit_moxi::settings::{self, Settings};
+use rustymicrobit_moxi::{clock, diagnostics, modbus};
+
+#[cfg(feature = "flash-log")]
+use crate::flash_log;
+use crate::{sense_co2, sense_mb, sense_pa};
+
+/// Pending replies awaiting transmission.
+const REPLIES_MAX: usize = 2;
+
+/// UART peripherals and pins (interface MCU's USB serial).
+pub struct SerialPeripherals {
+    pub uarte: Peri<'static, UARTE0>,
+    pub rxd: Peri<'static, P1_08>,
+    pub txd: Peri<'static, P0_06>,
+    pub timer: Peri<'static, TIMER1>,
+    pub ppi_ch0: Peri<'static, PPI_CH0>,
+    pub ppi_ch1: Peri<'static, PPI_CH1>,
+}
+
+/// Serial protocol task, according to `SERIAL_MODE`.
+#[embassy_executor::task]
+pub async fn serial_task(p: SerialPeripherals) {
+    bind_interrupts!(struct Irqs {
+        UARTE0 => uarte::InterruptHandler<UARTE0>;
+    });
+
+    let mut config = uarte::Config::default();
+    match SERIAL_MODE {
+        SerialMode::Framed => config.baudrate = Baudrate::BAUD115200,
+        SerialMode::Modbus => {
+            config.baudrate = Baudrate::BAUD19200;
+            config.parity = Parity::INCLUDED;
+        }
+    }
+
+    let uart = Uarte::new(p.uarte, p.rxd, p.txd, Irqs, config);
+    let (tx, rx) = uart.split_with_idle(p.timer, p.ppi_ch0, p.ppi_ch1);
+
+    match SERIAL_MODE {
+        SerialMode::Framed => run_framed(tx, rx).await,
+        SerialMode::Modbus => run_modbus(tx, rx).await,
+    }
+}
+
+/// Answer framed requests and stream new measurements.
+async fn run_framed(mut tx: UarteTx<'static>, mut rx: UarteRxWithIdle<'static>) {
+    let replies: Channel<NoopRawMutex, Frame, REPLIES_MAX> = Channel::new();
+    let mut co2_rx = sense_co2::get_sensor_receiver().or_else(|| {
+        defmt::error!("Serial: Request for co2 rx failed (measurements not streamed)");
+        None
+    });
+
+    let receive = async {
+        let mut decoder = Decoder::new();
+        let mut chunk = [0; 32];
+        loop {
+            let len = match rx.read_until_idle(&mut chunk).await {
+                Ok(len) => len,
+                Err(e) => {
+                    defmt::warn!("Serial: Read failed ({:?})", e);
+                    continue;
+                }
+            };
+            for &byte in chunk.get(..len).unwrap_or_default() {
+                if let Some(request) = decoder.feed(byte) {
+                    replies.send(reply(request).await).await;
+                }
+            }
+        }
+    };
+
+    let transmit = async {
+        let mut seq: u16 = 0;
+        let mut encoded = [0; protocol::FRAME_MAX];
+        loop {
+            let frame = match select(replies.receive(), changed(&mut co2_rx)).await {
+                Either::First(reply) => reply,
+                Either::Second(m_co2) => {
+                    seq = seq.wrapping_add(1);
+                    let m_pa = sense_pa::get_latest();
+                    Frame {
+                        seq,
+                        message: Message::Measurement(protocol::measurement(&m_co2, m_pa.as_ref())),
+                    }
+                }
+            };
+
+            match protocol::encode(&frame, &mut encoded) {
+                Ok(len) => {
+                    if let Err(e) = tx.write(encoded.get(..len).unwrap_or_default()).await {
+                        defmt::warn!("Serial: Write failed ({:?})", e);
+                    }
+                }
+                Err(e) => defmt::error!("Serial: Encode failed ({:?})", e),
+            }
+        }
+    };
+
+    join(receive, transmit).await;
+}
+
+/// Wait for a new value, or forever without a receiver.
+async fn changed<T: Clone>(rx: &mut Option<DynReceiver<'static, T>>) -> T {
+    match rx.as_mut() {
+        Some(rx) => rx.changed().await,
+        None => core::future::pending().await,
+    }
+}
+
+/// Handle one decoded request.
+#[cfg_attr(
+    not(feature = "flash-log"),
+    expect(clippy::unused_async, reason = "only log reads wait")
+)]
+async fn reply(request: Result<Frame, protocol::Error>) -> Frame {
+    let Frame { seq, message } = match request {
+        Ok(frame) => frame,
+        Err(e) => {
+            defmt::warn!("Serial: Malformed frame ({:?})", e);
+            return Frame {
+                seq: 0,
+                message: Message::Nack(0, NackReason::Malformed),
+            };
+        }
+    };
+
+    let message = match message {
+        Message::GetStatus => Message::Status(Status {
+            serial: sense_mb::get_serial_number(),
+            uptime_ms: Instant::now().as_millis(),
+            clock_set: clock::is_set(),
+            co2_ok: sense_co2::get_latest().is_some(),
+            pressure_ok: sense_pa::get_latest().is_some(),
+            sensor_errors: diagnostics::sensor_errors(),
+        }),
+        Message::GetConfig => Message::Config((&settings::get()).into()),
+        Message::SetConfig(config) => match Settings::try_from(config) {
+            Ok(updated) => {
+                defmt::info!("Serial: Settings updated ({:?})", updated);
+                settings::set(updated);
+                Message::Ack(seq)
+            }
+            Err(reason) => Message::Nack(seq, reason),
+        },
+        Message::SetTime(unix_ms) => {
+            clock::set_unix_ms(unix_ms);
+            defmt::info!("Serial: Clock set");
+            Message::Ack(seq)
+        }
+        #[cfg(feature = "flash-log")]
+        Message::GetLog(offset) => Message::LogChunk(flash_log::chunk(offset).await),
+        Message::Calibrate(ppm) if sense_co2::CALIBRATION_PPM.contains(&ppm) => {
+            sense_co2::request_calibration(ppm);
+            Message::Ack(seq)
+        }
+        Message::Calibrate(_) => Message::Nack(seq, NackReason::Invalid),
+        _ => Message::Nack(seq, NackReason::Unsupported),
+    };
+    Frame { seq, message }
+}
+
+/// Answer Modbus RTU requests.
+async fn run_modbus(mut tx: UarteTx<'static>, mut rx: UarteRxWithIdle<'static>) {
+    let mut frame = [0; modbus::FRAME_MAX];
+    loop {
+        // RTU frames are delimited by line idle
+        let len = match rx.read_until_idle(&mut frame).await {
+            Ok(len) => len,
+            Err(e) => {
+                defmt::warn!("Serial: Read failed ({:?})", e);
+                continue;
+            }
+        };
+
+        let m_co2 = sense_co2::get_latest();
+        let m_pa = sense_pa::get_latest();
+        let current = settings::get();
+        let inputs = modbus::input_registers(m_co2.as_ref(), m_pa.as_ref(), &current);
+
+        let mut updated = current;
+        let response = modbus::respond(frame.get(..len).unwrap_or_default(), &inputs, &mut updated);
+        if updated != current {
+            defmt::info!("Serial: Settings updated ({:?})", updated);
+            settings::set(updated);
+        }
+
+        if let Some(response) = response
+            && let Err(e) = tx.write(&response).await
+        {
+            defmt::warn!("Serial: Write failed ({:?})", e);
+        }
+    }
+}
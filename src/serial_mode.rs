@@ -0,0 +1,13 @@
+//! Serial port policy.
+
+/// Active serial protocol.
+pub const SERIAL_MODE: SerialMode = SerialMode::Framed;
+
+/// Serial port protocol.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SerialMode {
+    /// COBS-framed binary protocol for host tools (115200 baud, 8N1).
+    Framed,
+    /// Modbus RTU slave for building management (19200 baud, 8E1).
+    Modbus,
+}
@@ -0,0 +1,70 @@
+//! Runtime settings, adjustable by connected clients.
+//!
+//! Settings are kept in RAM only: a reset restores the defaults.
+
+use core::cell::Cell;
+use core::ops::RangeInclusive;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+
+use crate::power::{POWER_MODE, PowerMode};
+
+/// Current settings.
+static SETTINGS: Mutex<CriticalSectionRawMutex, Cell<Settings>> =
+    Mutex::new(Cell::new(Settings::new(POWER_MODE)));
+
+/// Valid CO2 alarm thresholds (ppm).
+pub const CO2_ALARM_PPM: RangeInclusive<u16> = 400..=5000;
+
+/// Valid SCD4X temperature offsets (C).
+pub const TEMP_OFFSET_C: RangeInclusive<f32> = 0.0..=20.0;
+
+/// Valid Modbus slave addresses.
+pub const UNIT_ID: RangeInclusive<u8> = 1..=247;
+
+/// Adjustable unit configuration.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Settings {
+    /// CO2 level considered poor air (ppm), flagged in the Modbus status
+    /// register.
+    pub co2_alarm_ppm: u16,
+    /// Sensor polling power mode.
+    pub power_mode: PowerMode,
+    /// SCD4X temperature offset (C).
+    pub temp_offset_c: f32,
+    /// Modbus slave address.
+    pub unit_id: u8,
+}
+
+impl Settings {
+    /// Default settings for a power mode.
+    #[must_use]
+    pub const fn new(power_mode: PowerMode) -> Self {
+        Self {
+            co2_alarm_ppm: 1000,
+            power_mode,
+            temp_offset_c: power_mode.temp_offset_c(),
+            unit_id: 1,
+        }
+    }
+
+    /// Whether every setting is within its valid range.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        CO2_ALARM_PPM.contains(&self.co2_alarm_ppm)
+            && TEMP_OFFSET_C.contains(&self.temp_offset_c)
+            && UNIT_ID.contains(&self.unit_id)
+    }
+}
+
+/// Current settings.
+#[must_use]
+pub fn get() -> Settings {
+    SETTINGS.lock(Cell::get)
+}
+
+/// Replace the current settings.
+pub fn set(settings: Settings) {
+    SETTINGS.lock(|cell| cell.set(settings));
+}
@@ -0,0 +1,452 @@
+//! Display logic, shared by the firmware and the host simulator.
+//!
+//! The matrix shows the dashboard for the current page; buttons A, B and the
+//! logo scroll temperature, CO2 and humidity (PM1.0, PM2.5 and PM10 on the
+//! particulate page, gas indices on the VOC page, the AQI and CO2 comfort on
+//! the air quality page), and A+B cycles the pages with data. On the history
+//! page, A and B cycle the charted reading and span instead.
+
+use embassy_time::Duration;
+use heapless::String;
+
+use crate::air_quality::{self, AirQuality, Co2Comfort};
+use crate::bitmap::Bitmap;
+use crate::dashboard::{
+    ANIMATION_FRAMES, Animation, LED_COLS, LED_ROWS, construct_air_quality_rows,
+    construct_dashboard_rows, construct_gas_rows, construct_history_frame, construct_pm_rows,
+    still,
+};
+use crate::history::{History, Metric, Span};
+use crate::measurement::{Co2Measurement, GasMeasurement, PmMeasurement, PressureMeasurement};
+use crate::mesh::PeerReading;
+use crate::page::{Page, Pages};
+use crate::readout::{self, DEGREES_F, Fixed, Readout, UNKNOWN};
+use crate::sensor::CO2_LABELS;
+use crate::units::{Celsius, Fahrenheit, Ppm, RangeError, RelativeHumidity};
+
+/// Text scrolled at power on.
+pub const GREETING: &str = " Power ON!";
+
+/// Scrolled text capacity.
+pub const TEXT_MAX: usize = 20;
+
+/// Dashboard animation frame display time.
+pub const DASHBOARD_FRAME: Duration = Duration::from_millis(500);
+
+/// Button presses.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum ButtonState {
+    A,
+    B,
+    /// Logo (touch).
+    C,
+    /// A and B pressed together.
+    AB,
+}
+
+/// Readings shown on the matrix; `None` where no sensor provides one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, defmt::Format)]
+pub struct Readings {
+    pub co2: Option<Ppm>,
+    pub humidity: Option<RelativeHumidity>,
+    pub temp_f: Option<Fahrenheit>,
+}
+
+impl Readings {
+    /// Readings from CO2 (ppm), relative humidity (%RH) and temperature (C).
+    ///
+    /// # Errors
+    /// Returns the [`RangeError`] of the first value out of range.
+    pub fn new(co2: f32, humidity: f32, temp_c: f32) -> Result<Self, RangeError> {
+        Ok(Self {
+            co2: Some(Ppm::try_new(co2)?),
+            humidity: Some(RelativeHumidity::try_new(humidity)?),
+            temp_f: Some(Celsius::try_new(temp_c)?.to_fahrenheit()?),
+        })
+    }
+
+    /// This unit's readings, from whichever sensors are fitted: CO2 and
+    /// humidity from the CO2 sensor, and the temperature from the pressure
+    /// sensor, as it reads truer, or else the CO2 sensor.
+    ///
+    /// # Errors
+    /// Returns [`RangeError::Fahrenheit`] if the temperature overflows.
+    pub fn local(
+        m_co2: Option<&Co2Measurement>,
+        m_pa: Option<&PressureMeasurement>,
+    ) -> Result<Self, RangeError> {
+        let temp_c = m_pa
+            .map(|m_pa| m_pa.temp_c)
+            .or_else(|| m_co2.map(|m_co2| m_co2.temp_c));
+        Ok(Self {
+            co2: m_co2.map(|m_co2| m_co2.co2),
+            humidity: m_co2.map(|m_co2| m_co2.humidity),
+            temp_f: temp_c.map(Celsius::to_fahrenheit).transpose()?,
+        })
+    }
+}
+
+impl TryFrom<&Co2Measurement> for Readings {
+    type Error = RangeError;
+
+    fn try_from(m_co2: &Co2Measurement) -> Result<Self, RangeError> {
+        Self::local(Some(m_co2), None)
+    }
+}
+
+impl TryFrom<&PeerReading> for Readings {
+    type Error = RangeError;
+
+    fn try_from(reading: &PeerReading) -> Result<Self, RangeError> {
+        Self::new(f32::from(reading.co2), reading.humidity, reading.temp_c)
+    }
+}
+
+/// Particulate readings (ug/m3) shown on [`Page::Particulates`].
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Particulates {
+    pub pm1: f32,
+    pub pm2_5: f32,
+    pub pm10: f32,
+    /// Taken in saturated air.
+    pub unreliable: bool,
+}
+
+impl Particulates {
+    /// US EPA AQI of the PM2.5 and PM10 concentrations.
+    #[must_use]
+    pub fn aqi(&self) -> u16 {
+        air_quality::aqi(self.pm2_5, self.pm10)
+    }
+}
+
+impl From<&PmMeasurement> for Particulates {
+    fn from(m_pm: &PmMeasurement) -> Self {
+        Self {
+            pm1: m_pm.pm1,
+            pm2_5: m_pm.pm2_5,
+            pm10: m_pm.pm10,
+            unreliable: m_pm.unreliable,
+        }
+    }
+}
+
+/// Gas index readings shown on [`Page::Gases`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub struct Gases {
+    pub voc_index: u16,
+    /// `None` without a NOx sensor (SGP40).
+    pub nox_index: Option<u16>,
+}
+
+impl From<&GasMeasurement> for Gases {
+    fn from(m_gas: &GasMeasurement) -> Self {
+        Self {
+            voc_index: m_gas.voc_index,
+            nox_index: m_gas.nox_index,
+        }
+    }
+}
+
+/// Latest readings of the optional sensors, each with its own page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, defmt::Format)]
+pub struct Pollutants {
+    pub particulates: Option<Particulates>,
+    pub gases: Option<Gases>,
+}
+
+/// What the matrix shows next.
+#[derive(Clone, Debug, Eq, PartialEq, defmt::Format)]
+pub enum Screen {
+    /// Dashboard animation at minimum brightness, [`DASHBOARD_FRAME`] per
+    /// frame.
+    Dashboard(Animation),
+    /// Text scrolled at maximum brightness, over `duration` if given.
+    Scroll {
+        text: String<TEXT_MAX>,
+        duration: Option<Duration>,
+    },
+}
+
+/// Display state.
+#[derive(Clone, Debug, Default, PartialEq, defmt::Format)]
+pub struct Ui {
+    page: Page,
+    /// Pages cycled through.
+    pages: Pages,
+    /// Button whose readout is scrolling, until done or cancelled.
+    readout: Option<ButtonState>,
+    /// Local readings, for [`Page::History`].
+    history: History,
+    /// Reading charted on [`Page::History`].
+    metric: Metric,
+    /// Time charted on [`Page::History`].
+    span: Span,
+}
+
+impl Ui {
+    /// Start on the default page, with every page shown.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_pages(Pages::ALL)
+    }
+
+    /// Start on the default page, showing only `pages`.
+    #[must_use]
+    pub const fn with_pages(pages: Pages) -> Self {
+        Self {
+            page: Page::Dashboard,
+            pages,
+            readout: None,
+            history: History::new(),
+            metric: Metric::Co2,
+            span: Span::FiveMinutes,
+        }
+    }
+
+    /// Record local readings taken at `uptime_ms` in the history.
+    pub fn record(&mut self, uptime_ms: u64, local: &Readings) {
+        self.history.record(uptime_ms, local);
+    }
+
+    /// The last scroll finished: its button scrolls the readout again.
+    pub const fn scrolled(&mut self) {
+        self.readout = None;
+    }
+
+    /// Current page.
+    #[must_use]
+    pub const fn page(&self) -> Page {
+        self.page
+    }
+
+    /// Readings for the current page: the mesh's worst on [`Page::Worst`],
+    /// or the second CO2 sensor's on [`Page::Second`], when known, otherwise
+    /// local.
+    #[must_use]
+    pub const fn readings(
+        &self,
+        local: Readings,
+        worst: Option<Readings>,
+        second: Option<Readings>,
+    ) -> Readings {
+        match (self.page, worst, second) {
+            (Page::Worst, Some(worst), _) => worst,
+            (Page::Second, _, Some(second)) => second,
+            _ => local,
+        }
+    }
+
+    /// Dashboard animation for the readings, or still frame for the
+    /// pollutants on their pages when known, or the history chart.
+    #[must_use]
+    pub fn dashboard(&self, readings: &Readings, pollutants: &Pollutants) -> Screen {
+        match (self.page, pollutants) {
+            (
+                Page::Particulates,
+                Pollutants {
+                    particulates: Some(pm),
+                    ..
+                },
+            ) => Screen::Dashboard(still(construct_pm_rows(
+                pm.pm1,
+                pm.pm2_5,
+                pm.pm10,
+                pm.unreliable,
+            ))),
+            (
+                Page::Gases,
+                Pollutants {
+                    gases: Some(gases), ..
+                },
+            ) => Screen::Dashboard(still(construct_gas_rows(gases.voc_index, gases.nox_index))),
+            (Page::History, _) => Screen::Dashboard(
+                [construct_history_frame(self.history.window(self.metric, self.span));
+                    ANIMATION_FRAMES],
+            ),
+            (Page::AirQuality, _) => {
+                // Humid readings overstate particulates
+                let aqi = pollutants
+                    .particulates
+                    .filter(|pm| !pm.unreliable)
+                    .as_ref()
+                    .map(Particulates::aqi);
+                let rows = AirQuality::overall(readings.co2.map(Ppm::whole), aqi).map_or(
+                    [Bitmap::empty(LED_COLS); LED_ROWS],
+                    construct_air_quality_rows,
+                );
+                Screen::Dashboard(still(rows))
+            }
+            _ => Screen::Dashboard(construct_dashboard_rows(
+                readings.co2,
+                readings.humidity,
+                readings.temp_f,
+            )),
+        }
+    }
+
+    /// Handle a button press, returning the text to scroll. Readouts on
+    /// [`Page::Second`] are labelled with the sensor, and on the pollutant
+    /// pages are of the pollutants when known: on [`Page::Gases`], A and the
+    /// logo scroll the VOC index, and B the NOx index when measured. On
+    /// [`Page::AirQuality`], A scrolls the AQI when known (marked approximate
+    /// if humid) and B the CO2 comfort category. Readings not known scroll
+    /// [`UNKNOWN`].
+    ///
+    /// On [`Page::History`], A and B cycle the charted reading and span,
+    /// scrolling its title, and the logo scrolls the latest bucket's mean.
+    ///
+    /// Pressing a readout's button again while it scrolls cancels it, back
+    /// to the dashboard.
+    pub fn press(
+        &mut self,
+        button: ButtonState,
+        readings: &Readings,
+        pollutants: &Pollutants,
+    ) -> Screen {
+        if self.readout.take() == Some(button) {
+            return self.dashboard(readings, pollutants);
+        }
+        let cycles = match (button, self.page) {
+            (ButtonState::AB, _) | (ButtonState::A | ButtonState::B, Page::History) => true,
+            (ButtonState::A | ButtonState::B | ButtonState::C, _) => false,
+        };
+        if !cycles {
+            self.readout = Some(button);
+        }
+        let co2 = readings.co2.map(Ppm::whole);
+        let humidity = readings.humidity.map(RelativeHumidity::whole);
+        let [_, second] = CO2_LABELS;
+        let label = match self.page {
+            Page::Second => Some(second),
+            Page::Dashboard
+            | Page::Worst
+            | Page::Particulates
+            | Page::Gases
+            | Page::AirQuality
+            | Page::History => None,
+        };
+        let Pollutants {
+            particulates,
+            gases,
+        } = *pollutants;
+        match (button, self.page, particulates, gases) {
+            (ButtonState::AB, ..) => {
+                self.page = self.page.next_in(self.pages);
+                Screen::Scroll {
+                    text: self.page.title().try_into().unwrap_or_default(),
+                    duration: None,
+                }
+            }
+            (_, Page::History, ..) => self.press_history(button),
+            (ButtonState::A, Page::Particulates, Some(pm), _) => {
+                pm_readout("PM1.0", pm.pm1, pm.unreliable)
+            }
+            (ButtonState::B, Page::Particulates, Some(pm), _) => {
+                pm_readout("PM2.5", pm.pm2_5, pm.unreliable)
+            }
+            (ButtonState::C, Page::Particulates, Some(pm), _) => {
+                pm_readout("PM10", pm.pm10, pm.unreliable)
+            }
+            (
+                ButtonState::B,
+                Page::Gases,
+                _,
+                Some(Gases {
+                    nox_index: Some(nox_index),
+                    ..
+                }),
+            ) => readout(Some("NOx"), nox_index, "", 2750),
+            (ButtonState::A | ButtonState::B | ButtonState::C, Page::Gases, _, Some(gases)) => {
+                readout(Some("VOC"), gases.voc_index, "", 2750)
+            }
+            (ButtonState::A, Page::AirQuality, Some(pm), _) => {
+                let approximate = if pm.unreliable { "~" } else { "" };
+                readout(
+                    Some("AQI"),
+                    format_args!("{approximate}{}", pm.aqi()),
+                    "",
+                    2750,
+                )
+            }
+            (ButtonState::B, Page::AirQuality, ..) => readout(
+                Some("CO2"),
+                co2.map_or(UNKNOWN, |co2| Co2Comfort::of(co2).name()),
+                "",
+                2750,
+            ),
+            (ButtonState::A, ..) => readout(
+                label,
+                Fixed::new(readings.temp_f.map_or(f32::NAN, Fahrenheit::into_inner), 1),
+                DEGREES_F,
+                2750,
+            ),
+            (ButtonState::B, ..) => readout(label, whole(co2.map(f32::from)), "ppm", 4500),
+            (ButtonState::C, ..) => readout(label, whole(humidity.map(f32::from)), "%", 2750),
+        }
+    }
+
+    /// Handle a button press on [`Page::History`], other than A+B.
+    fn press_history(&mut self, button: ButtonState) -> Screen {
+        let title = match button {
+            ButtonState::A => {
+                self.metric = self.metric.next();
+                self.metric.title()
+            }
+            ButtonState::B => {
+                self.span = self.span.next();
+                self.span.title()
+            }
+            ButtonState::C | ButtonState::AB => {
+                let [.., latest] = self.history.window(self.metric, self.span);
+                let decimals = match self.metric {
+                    Metric::Temperature => 1,
+                    Metric::Co2 | Metric::Humidity => 0,
+                };
+                return readout(
+                    Some(self.metric.title()),
+                    Fixed::new(latest.unwrap_or(f32::NAN), decimals),
+                    self.metric.units(),
+                    4500,
+                );
+            }
+        };
+        Screen::Scroll {
+            text: title.try_into().unwrap_or_default(),
+            duration: None,
+        }
+    }
+}
+
+/// Whole value, or [`UNKNOWN`] if `None`.
+fn whole(value: Option<f32>) -> Fixed {
+    Fixed::whole(value.unwrap_or(f32::NAN))
+}
+
+/// Scrolled particulate concentration, marked approximate if unreliable.
+fn pm_readout(label: &str, value: f32, unreliable: bool) -> Screen {
+    let approximate = if unreliable { "~" } else { "" };
+    readout(
+        Some(label),
+        format_args!("{approximate}{}", Fixed::whole(value)),
+        "ug/m3",
+        4500,
+    )
+}
+
+/// Scrolled value with units (if any), after the sensor's label if given.
+fn readout(
+    label: Option<&str>,
+    value: impl core::fmt::Display,
+    units: &str,
+    duration_ms: u64,
+) -> Screen {
+    Screen::Scroll {
+        text: readout::truncated(Readout {
+            label,
+            value,
+            units,
+        }),
+        duration: Some(Duration::from_millis(duration_ms)),
+    }
+}
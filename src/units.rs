@@ -0,0 +1,163 @@
+//! Physical quantities, validated on construction.
+//!
+//! Each quantity wraps an `f32` that is finite and within the quantity's
+//! range, so conversions between units and into display integers are
+//! checked once, where a reading enters the system.
+
+use nutype::nutype;
+
+/// CO2 concentration (ppm), as read: [0, 65535].
+#[nutype(
+    validate(finite, greater_or_equal = 0.0, less_or_equal = 65535.0),
+    derive(Debug, Clone, Copy, PartialEq, PartialOrd, Display, TryFrom, Into)
+)]
+pub struct Ppm(f32);
+
+/// Relative humidity (%RH): [0, 100].
+#[nutype(
+    validate(finite, greater_or_equal = 0.0, less_or_equal = 100.0),
+    derive(Debug, Clone, Copy, PartialEq, PartialOrd, Display, TryFrom, Into)
+)]
+pub struct RelativeHumidity(f32);
+
+/// Temperature (C), no colder than absolute zero.
+#[nutype(
+    validate(finite, greater_or_equal = -273.15),
+    derive(Debug, Clone, Copy, PartialEq, PartialOrd, Display, TryFrom, Into)
+)]
+pub struct Celsius(f32);
+
+/// Temperature (F), no colder than absolute zero.
+#[nutype(
+    validate(finite, greater_or_equal = -459.67),
+    derive(Debug, Clone, Copy, PartialEq, PartialOrd, Display, TryFrom, Into)
+)]
+pub struct Fahrenheit(f32);
+
+/// Atmospheric pressure (hPa), within the BMP581's range and the SCD4X's
+/// compensation: [300, 1250].
+#[nutype(
+    validate(finite, greater_or_equal = 300.0, less_or_equal = 1250.0),
+    derive(Debug, Clone, Copy, PartialEq, PartialOrd, Display, TryFrom, Into)
+)]
+pub struct Hectopascal(f32);
+
+/// Value not finite or outside its quantity's range.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, defmt::Format)]
+pub enum RangeError {
+    Ppm,
+    RelativeHumidity,
+    Celsius,
+    Fahrenheit,
+    Hectopascal,
+}
+
+impl From<PpmError> for RangeError {
+    fn from(_: PpmError) -> Self {
+        Self::Ppm
+    }
+}
+
+impl From<RelativeHumidityError> for RangeError {
+    fn from(_: RelativeHumidityError) -> Self {
+        Self::RelativeHumidity
+    }
+}
+
+impl From<CelsiusError> for RangeError {
+    fn from(_: CelsiusError) -> Self {
+        Self::Celsius
+    }
+}
+
+impl From<FahrenheitError> for RangeError {
+    fn from(_: FahrenheitError) -> Self {
+        Self::Fahrenheit
+    }
+}
+
+impl From<HectopascalError> for RangeError {
+    fn from(_: HectopascalError) -> Self {
+        Self::Hectopascal
+    }
+}
+
+impl Ppm {
+    /// Whole ppm, truncated.
+    #[must_use]
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "validated to [0, 65535]"
+    )]
+    pub fn whole(self) -> u16 {
+        self.into_inner() as u16
+    }
+}
+
+impl RelativeHumidity {
+    /// Whole %RH, truncated.
+    #[must_use]
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "validated to [0, 100]"
+    )]
+    pub fn whole(self) -> u8 {
+        self.into_inner() as u8
+    }
+}
+
+impl Celsius {
+    /// Convert to degrees Fahrenheit.
+    ///
+    /// # Errors
+    /// Returns [`RangeError::Fahrenheit`] if the result overflows `f32`.
+    pub fn to_fahrenheit(self) -> Result<Fahrenheit, RangeError> {
+        Ok(Fahrenheit::try_new(self.into_inner() * 9.0 / 5.0 + 32.0)?)
+    }
+}
+
+impl Fahrenheit {
+    /// Whole degrees, truncated, saturating at the `i16` range.
+    #[must_use]
+    #[expect(clippy::cast_possible_truncation, reason = "clamped to the i16 range")]
+    pub fn whole(self) -> i16 {
+        self.into_inner()
+            .clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16
+    }
+}
+
+impl Hectopascal {
+    /// Convert from pascals.
+    ///
+    /// # Errors
+    /// Returns [`RangeError::Hectopascal`] outside [300, 1250] hPa.
+    pub fn from_pa(pa: f32) -> Result<Self, RangeError> {
+        Ok(Self::try_new(pa / 100.0)?)
+    }
+
+    /// Whole hPa, truncated.
+    #[must_use]
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "validated to [300, 1250]"
+    )]
+    pub fn whole(self) -> u16 {
+        self.into_inner() as u16
+    }
+}
+
+/// Log a quantity by its value.
+macro_rules! format_as_value {
+    ($($quantity:ty),*) => {
+        $(impl defmt::Format for $quantity {
+            fn format(&self, f: defmt::Formatter<'_>) {
+                defmt::write!(f, "{=f32}", self.into_inner());
+            }
+        })*
+    };
+}
+
+format_as_value!(Ppm, RelativeHumidity, Celsius, Fahrenheit, Hectopascal);
@@ -0,0 +1,109 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::air_quality::{
+        AQI_MAX, AirQuality, AqiCategory, Co2Comfort, aqi, pm2_5_aqi, pm10_aqi,
+    };
+
+    #[test]
+    fn pm2_5_breakpoints() {
+        defmt::assert_eq!(pm2_5_aqi(0.0), 0);
+        defmt::assert_eq!(pm2_5_aqi(9.0), 50);
+        defmt::assert_eq!(pm2_5_aqi(12.0), 56);
+        defmt::assert_eq!(pm2_5_aqi(35.9), 102);
+        defmt::assert_eq!(pm2_5_aqi(325.4), 500);
+    }
+
+    #[test]
+    fn pm2_5_truncates_to_tenths() {
+        // 9.09 truncates to 9.0, not rounding up into the next breakpoint
+        defmt::assert_eq!(pm2_5_aqi(9.09), 50);
+        defmt::assert_eq!(pm2_5_aqi(9.1), 51);
+        defmt::assert_eq!(pm2_5_aqi(150.4), 226);
+    }
+
+    #[test]
+    fn pm2_5_breakpoint_edges() {
+        // Each breakpoint's last tenth, and the next's first
+        for (ugm3, index) in [
+            (9.0, 50),
+            (9.1, 51),
+            (35.4, 100),
+            (35.5, 101),
+            (55.4, 150),
+            (55.5, 151),
+            (125.4, 200),
+            (125.5, 201),
+            (225.4, 300),
+            (225.5, 301),
+            (325.4, 500),
+            (325.5, AQI_MAX),
+        ] {
+            defmt::assert_eq!(pm2_5_aqi(ugm3), index);
+        }
+    }
+
+    #[test]
+    fn pm10_breakpoints() {
+        defmt::assert_eq!(pm10_aqi(54.9), 50);
+        defmt::assert_eq!(pm10_aqi(60.0), 53);
+        defmt::assert_eq!(pm10_aqi(155.0), 101);
+        defmt::assert_eq!(pm10_aqi(604.0), 500);
+    }
+
+    #[test]
+    fn beyond_the_index() {
+        defmt::assert_eq!(pm2_5_aqi(600.0), AQI_MAX);
+        defmt::assert_eq!(pm10_aqi(f32::INFINITY), AQI_MAX);
+        defmt::assert_eq!(pm2_5_aqi(-1.0), 0);
+        defmt::assert_eq!(pm2_5_aqi(f32::NAN), 0);
+    }
+
+    #[test]
+    fn overall_aqi_is_the_worse() {
+        defmt::assert_eq!(aqi(5.0, 160.0), 103);
+        defmt::assert_eq!(
+            AqiCategory::of(103),
+            AqiCategory::UnhealthyForSensitiveGroups
+        );
+        defmt::assert_eq!(AqiCategory::of(AQI_MAX), AqiCategory::Hazardous);
+    }
+
+    #[test]
+    fn co2_comfort() {
+        defmt::assert_eq!(Co2Comfort::of(420), Co2Comfort::Excellent);
+        defmt::assert_eq!(Co2Comfort::of(600), Co2Comfort::Good);
+        defmt::assert_eq!(Co2Comfort::of(950), Co2Comfort::Fair);
+        defmt::assert_eq!(Co2Comfort::of(1200), Co2Comfort::Poor);
+        defmt::assert_eq!(Co2Comfort::of(2000), Co2Comfort::Bad);
+    }
+
+    #[test]
+    fn overall_air_quality() {
+        defmt::assert_eq!(AirQuality::overall(None, None), None);
+        defmt::assert_eq!(AirQuality::overall(Some(500), None), Some(AirQuality::Good));
+        defmt::assert_eq!(AirQuality::overall(None, Some(75)), Some(AirQuality::Fair));
+        defmt::assert_eq!(
+            AirQuality::overall(Some(900), Some(20)),
+            Some(AirQuality::Fair)
+        );
+        defmt::assert_eq!(
+            AirQuality::overall(Some(500), Some(75)),
+            Some(AirQuality::Fair)
+        );
+        defmt::assert_eq!(
+            AirQuality::overall(Some(500), Some(160)),
+            Some(AirQuality::Poor)
+        );
+        defmt::assert_eq!(
+            AirQuality::overall(Some(1600), Some(20)),
+            Some(AirQuality::Poor)
+        );
+    }
+}
@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::bthome::{DEVICE_INFO, Readings, service_data};
+
+    #[test]
+    fn no_readings() {
+        let data = service_data(&Readings::default());
+        defmt::assert_eq!(data.as_slice(), &[DEVICE_INFO]);
+    }
+
+    // Object vectors from https://bthome.io/format/
+    #[test]
+    fn spec_vectors() {
+        let readings = Readings {
+            co2: Some(1250.0),
+            humidity: Some(50.55),
+            hpa: Some(1008.83),
+            temp_c: Some(25.0),
+        };
+        #[rustfmt::skip]
+        let expected = [
+            0x40,
+            0x02, 0xc4, 0x09,
+            0x03, 0xbf, 0x13,
+            0x04, 0x13, 0x8a, 0x01,
+            0x12, 0xe2, 0x04,
+        ];
+        defmt::assert_eq!(service_data(&readings).as_slice(), &expected);
+    }
+
+    #[test]
+    fn negative_temperature_without_pressure() {
+        let readings = Readings {
+            co2: Some(842.0),
+            humidity: Some(41.7),
+            hpa: None,
+            temp_c: Some(-5.5),
+        };
+        #[rustfmt::skip]
+        let expected = [
+            0x40,
+            0x02, 0xda, 0xfd,
+            0x03, 0x4a, 0x10,
+            0x12, 0x4a, 0x03,
+        ];
+        defmt::assert_eq!(service_data(&readings).as_slice(), &expected);
+    }
+
+    #[test]
+    fn nan_readings_are_omitted() {
+        let readings = Readings {
+            co2: Some(f32::NAN),
+            ..Readings::default()
+        };
+        defmt::assert_eq!(service_data(&readings).as_slice(), &[DEVICE_INFO]);
+    }
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::clock::{self, Timestamp};
+    use rustymicrobit_moxi::measurement::Co2Measurement;
+
+    #[test]
+    fn timestamp_without_offset_is_uptime_only() {
+        let ts = Timestamp::new(1_500, None);
+        defmt::assert_eq!(ts.unix_ms, None);
+        defmt::assert_eq!(ts.unix_or_uptime_ms(), 1_500);
+    }
+
+    #[test]
+    fn timestamp_with_offset() {
+        let ts = Timestamp::new(1_500, Some(1_700_000_000_000));
+        defmt::assert_eq!(ts.unix_ms, Some(1_700_000_001_500));
+        defmt::assert_eq!(ts.unix_or_uptime_ms(), 1_700_000_001_500);
+    }
+
+    #[test]
+    fn set_clock_stamps_measurements() {
+        let unix_ms = 1_700_000_000_000;
+        clock::set_unix_ms(unix_ms);
+        defmt::assert!(clock::is_set());
+
+        let m = defmt::unwrap!(Co2Measurement::new(842, 41.7, 22.5));
+        let stamped = defmt::unwrap!(m.timestamp.unix_ms);
+        defmt::assert!(stamped >= unix_ms && stamped < unix_ms + 1_000);
+    }
+}
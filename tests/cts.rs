@@ -0,0 +1,77 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::cts::{self, DecodeError};
+
+    /// Current Time value: year, month, day, hours, minutes, seconds and
+    /// 1/256 s, on a Monday, manually adjusted.
+    const fn value(year: u16, date_time: [u8; 5], fractions256: u8) -> [u8; cts::CURRENT_TIME_LEN] {
+        let [year_lo, year_hi] = year.to_le_bytes();
+        let [month, day, hours, minutes, seconds] = date_time;
+        [
+            year_lo,
+            year_hi,
+            month,
+            day,
+            hours,
+            minutes,
+            seconds,
+            1,
+            fractions256,
+            0b0001,
+        ]
+    }
+
+    #[test]
+    fn unix_ms_of_current_time() {
+        defmt::assert_eq!(cts::unix_ms(&value(1970, [1, 1, 0, 0, 0], 0)), Ok(0));
+        defmt::assert_eq!(
+            cts::unix_ms(&value(2000, [3, 1, 0, 0, 0], 0)),
+            Ok(951_868_800_000)
+        );
+        // Leap day, with half a second
+        defmt::assert_eq!(
+            cts::unix_ms(&value(2024, [2, 29, 12, 34, 56], 128)),
+            Ok(1_709_210_096_500)
+        );
+        defmt::assert_eq!(
+            cts::unix_ms(&[0xea, 0x07, 10, 19, 8, 0, 0, 1, 0, 0]),
+            Ok(1_792_396_800_000)
+        );
+    }
+
+    #[test]
+    fn invalid_current_time() {
+        defmt::assert_eq!(
+            cts::unix_ms(&value(2024, [2, 29, 12, 0, 0], 0)[..9]),
+            Err(DecodeError::Length)
+        );
+        defmt::assert_eq!(
+            cts::unix_ms(&value(0, [2, 29, 12, 0, 0], 0)),
+            Err(DecodeError::Unknown)
+        );
+        defmt::assert_eq!(
+            cts::unix_ms(&value(2024, [0, 29, 12, 0, 0], 0)),
+            Err(DecodeError::Unknown)
+        );
+        for (year, date_time) in [
+            (1969, [12, 31, 23, 59, 59]),
+            (2023, [2, 29, 12, 0, 0]),
+            (2024, [4, 31, 12, 0, 0]),
+            (2024, [13, 1, 12, 0, 0]),
+            (2024, [1, 1, 24, 0, 0]),
+            (2024, [1, 1, 0, 60, 0]),
+        ] {
+            defmt::assert_eq!(
+                cts::unix_ms(&value(year, date_time, 0)),
+                Err(DecodeError::Range)
+            );
+        }
+    }
+}
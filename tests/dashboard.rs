@@ -8,22 +8,63 @@ use microbit_bsp as _;
 #[embedded_test::tests]
 mod tests {
     use microbit_bsp::display::Bitmap;
+    use rustymicrobit_moxi::air_quality::AirQuality;
     use rustymicrobit_moxi::dashboard::{
-        CO2_SATURATION_PPM, HUMIDITY_SATURATION_PCT, LED_COLS, LED_ROWS, TEMP_SATURATION_F,
-        construct_dashboard_rows,
+        ANIMATION_FRAMES, Animation, CO2_BASE_PPM, CO2_SATURATION_PPM, HUMIDITY_SATURATION_PCT,
+        LED_COLS, LED_ROWS, NOX_INDEX_LEVELS, PM_LEVELS_UGM3, SUBSTEP_LEVEL, TEMP_BASE_F,
+        TEMP_SATURATION_F, VOC_INDEX_LEVELS, construct_air_quality_rows, construct_dashboard_rows,
+        construct_gas_rows, construct_history_frame, construct_pm_rows,
     };
+    use rustymicrobit_moxi::grayscale::{Grayscale, LEVEL_MAX};
+    use rustymicrobit_moxi::units::{Fahrenheit, Ppm, RelativeHumidity};
+
+    /// Dashboard animation for readings in whole units.
+    fn animation(co2: u16, humidity: u8, temp_f: i16) -> Animation {
+        construct_dashboard_rows(
+            Some(defmt::unwrap!(Ppm::try_new(f32::from(co2)).ok())),
+            Some(defmt::unwrap!(
+                RelativeHumidity::try_new(f32::from(humidity)).ok()
+            )),
+            Some(defmt::unwrap!(Fahrenheit::try_new(f32::from(temp_f)).ok())),
+        )
+    }
+
+    /// LEDs lit in each dashboard animation frame, for readings in whole
+    /// units.
+    fn rows(co2: u16, humidity: u8, temp_f: i16) -> [[Bitmap; LED_ROWS]; ANIMATION_FRAMES] {
+        animation(co2, humidity, temp_f).map(|frame| frame.lit())
+    }
+
+    /// Full matrix, with the temperature and CO2 columns' top LEDs unlit.
+    #[rustfmt::skip]
+    const OVER: [Bitmap; LED_ROWS] = [
+        Bitmap::new(0b01011, LED_COLS),
+        Bitmap::new(0b11111, LED_COLS),
+        Bitmap::new(0b11111, LED_COLS),
+        Bitmap::new(0b11111, LED_COLS),
+        Bitmap::new(0b11111, LED_COLS),
+    ];
 
     #[test]
     fn dashboard_encoding_0_0_0() {
-        let expected = [Bitmap::empty(LED_COLS); LED_ROWS];
-        defmt::assert_eq!(construct_dashboard_rows(0, 0, 0), expected);
+        // Bottom LEDs of the primary columns blink
+        #[rustfmt::skip]
+        let lit = [
+            Bitmap::empty(LED_COLS),
+            Bitmap::empty(LED_COLS),
+            Bitmap::empty(LED_COLS),
+            Bitmap::empty(LED_COLS),
+            Bitmap::new(0b10101, LED_COLS),
+        ];
+        let expected = [lit, [Bitmap::empty(LED_COLS); LED_ROWS]];
+        defmt::assert_eq!(rows(0, 0, 0), expected);
     }
 
     #[test]
     fn dashboard_encoding_saturated() {
-        let expected = [Bitmap::new(0b11111, LED_COLS); LED_ROWS];
+        let expected = [[Bitmap::new(0b11111, LED_COLS); LED_ROWS], OVER];
         defmt::assert_eq!(
-            construct_dashboard_rows(
+            rows(
                 CO2_SATURATION_PPM,
                 HUMIDITY_SATURATION_PCT,
                 TEMP_SATURATION_F
@@ -32,6 +73,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dashboard_encoding_under_range() {
+        #[rustfmt::skip]
+        let lit = [
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b00010, LED_COLS),
+            Bitmap::new(0b10111, LED_COLS),
+        ];
+        #[rustfmt::skip]
+        let unlit = [
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b00010, LED_COLS),
+            Bitmap::new(0b00011, LED_COLS),
+        ];
+        defmt::assert_eq!(rows(CO2_BASE_PPM - 1, 20, TEMP_BASE_F - 5), [lit, unlit]);
+
+        // Just in range
+        let [lit, unlit] = rows(CO2_BASE_PPM, 20, TEMP_BASE_F);
+        defmt::assert_eq!(lit, unlit);
+    }
+
     #[test]
     fn dashboard_encoding_intent_twins() {
         #[rustfmt::skip]
@@ -42,14 +108,63 @@ mod tests {
             Bitmap::new(0b10101, LED_COLS),
             Bitmap::new(0b10101, LED_COLS),
         ];
-        defmt::assert_eq!(construct_dashboard_rows(601, 41, 72), expected);
-        defmt::assert_eq!(construct_dashboard_rows(639, 59, 71), expected);
+        defmt::assert_eq!(rows(601, 41, 72), [expected; ANIMATION_FRAMES]);
+        defmt::assert_eq!(rows(639, 59, 71), [expected; ANIMATION_FRAMES]);
+
+        // Whole units, truncated
+        let fractional = construct_dashboard_rows(
+            Some(defmt::unwrap!(Ppm::try_new(639.9).ok())),
+            Some(defmt::unwrap!(RelativeHumidity::try_new(59.9).ok())),
+            Some(defmt::unwrap!(Fahrenheit::try_new(71.9).ok())),
+        );
+        defmt::assert_eq!(
+            fractional.map(|frame| frame.lit()),
+            [expected; ANIMATION_FRAMES]
+        );
+    }
+
+    #[test]
+    fn dashboard_unknown_readings_dark() {
+        let temperature_only = construct_dashboard_rows(
+            None,
+            None,
+            Some(defmt::unwrap!(Fahrenheit::try_new(72.0).ok())),
+        );
+        #[rustfmt::skip]
+        let expected = [
+            Bitmap::new(0b01000, LED_COLS),
+            Bitmap::new(0b00000, LED_COLS),
+            Bitmap::new(0b10000, LED_COLS),
+            Bitmap::new(0b10000, LED_COLS),
+            Bitmap::new(0b10000, LED_COLS),
+        ];
+        defmt::assert_eq!(
+            temperature_only.map(|frame| frame.lit()),
+            [expected; ANIMATION_FRAMES]
+        );
+
+        let unknown = construct_dashboard_rows(None, None, None);
+        defmt::assert_eq!(
+            unknown.map(|frame| frame.lit()),
+            [[Bitmap::empty(LED_COLS); LED_ROWS]; ANIMATION_FRAMES]
+        );
+    }
+
+    #[test]
+    fn dashboard_substeps_dimmed() {
+        let [frame, _] = animation(601, 41, 72);
+        // Temperature and CO2 primary, then substep columns
+        defmt::assert_eq!(frame.level(4, 0), LEVEL_MAX);
+        defmt::assert_eq!(frame.level(0, 1), SUBSTEP_LEVEL);
+        defmt::assert_eq!(frame.level(4, 2), LEVEL_MAX);
+        defmt::assert_eq!(frame.level(0, 3), SUBSTEP_LEVEL);
+        defmt::assert_eq!(frame.level(1, 1), 0);
     }
 
     #[test]
     fn dashboard_encoding_intent_saturation_min() {
-        let expected = [Bitmap::new(0b11111, LED_COLS); LED_ROWS];
-        defmt::assert_eq!(construct_dashboard_rows(1361, 90, 99), expected);
+        let expected = [[Bitmap::new(0b11111, LED_COLS); LED_ROWS], OVER];
+        defmt::assert_eq!(rows(1361, 90, 99), expected);
     }
 
     #[test]
@@ -62,6 +177,119 @@ mod tests {
             Bitmap::new(0b11111, LED_COLS),
             Bitmap::new(0b10101, LED_COLS),
         ];
-        defmt::assert_eq!(construct_dashboard_rows(1360, 89, 98), expected);
+        defmt::assert_eq!(rows(1360, 89, 98), [expected; ANIMATION_FRAMES]);
+    }
+
+    #[test]
+    fn pm_encoding_clean_and_saturated() {
+        let expected = [Bitmap::empty(LED_COLS); LED_ROWS];
+        defmt::assert_eq!(construct_pm_rows(0.0, 4.0, 4.0, false), expected);
+
+        let [.., max] = PM_LEVELS_UGM3;
+        let expected = [Bitmap::new(0b10101, LED_COLS); LED_ROWS];
+        defmt::assert_eq!(
+            construct_pm_rows(f32::from(max), f32::from(max), f32::from(max), false),
+            expected
+        );
+    }
+
+    #[test]
+    fn pm_encoding_humid() {
+        #[rustfmt::skip]
+        let expected = [
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b00001, LED_COLS),
+            Bitmap::new(0b00101, LED_COLS),
+            Bitmap::new(0b00101, LED_COLS),
+            Bitmap::new(0b00101, LED_COLS),
+        ];
+        defmt::assert_eq!(construct_pm_rows(4.0, 38.0, 60.0, true), expected);
+    }
+
+    #[test]
+    fn gas_encoding() {
+        #[rustfmt::skip]
+        let expected = [
+            Bitmap::new(0b00000, LED_COLS),
+            Bitmap::new(0b00000, LED_COLS),
+            Bitmap::new(0b11000, LED_COLS),
+            Bitmap::new(0b11000, LED_COLS),
+            Bitmap::new(0b11011, LED_COLS),
+        ];
+        defmt::assert_eq!(construct_gas_rows(160, Some(3)), expected);
+
+        let [.., voc_max] = VOC_INDEX_LEVELS;
+        let [.., nox_max] = NOX_INDEX_LEVELS;
+        let expected = [Bitmap::new(0b11011, LED_COLS); LED_ROWS];
+        defmt::assert_eq!(construct_gas_rows(voc_max, Some(nox_max)), expected);
+    }
+
+    #[test]
+    fn gas_encoding_without_nox() {
+        let expected = [Bitmap::empty(LED_COLS); LED_ROWS];
+        defmt::assert_eq!(construct_gas_rows(0, None), expected);
+
+        let expected = [Bitmap::new(0b11000, LED_COLS); LED_ROWS];
+        defmt::assert_eq!(construct_gas_rows(500, None), expected);
+    }
+
+    #[test]
+    fn history_bars_scaled() {
+        let frame =
+            construct_history_frame([Some(400.0), Some(500.0), None, Some(600.0), Some(800.0)]);
+        #[rustfmt::skip]
+        let expected = [
+            Bitmap::new(0b00001, LED_COLS),
+            Bitmap::new(0b00001, LED_COLS),
+            Bitmap::new(0b00011, LED_COLS),
+            Bitmap::new(0b01011, LED_COLS),
+            Bitmap::new(0b11011, LED_COLS),
+        ];
+        defmt::assert_eq!(frame.lit(), expected);
+        // Bar tops brightest
+        defmt::assert_eq!(frame.level(4, 0), LEVEL_MAX);
+        defmt::assert_eq!(frame.level(4, 1), SUBSTEP_LEVEL);
+        defmt::assert_eq!(frame.level(3, 1), LEVEL_MAX);
+        defmt::assert_eq!(frame.level(2, 3), LEVEL_MAX);
+        defmt::assert_eq!(frame.level(0, 4), LEVEL_MAX);
+        defmt::assert_eq!(frame.level(1, 4), SUBSTEP_LEVEL);
+    }
+
+    #[test]
+    fn history_flat_and_empty() {
+        let frame = construct_history_frame([None, None, Some(21.5), Some(21.5), Some(21.5)]);
+        let mut expected = [Bitmap::empty(LED_COLS); LED_ROWS];
+        if let Some(bottom) = expected.last_mut() {
+            *bottom = Bitmap::new(0b00111, LED_COLS);
+        }
+        defmt::assert_eq!(frame.lit(), expected);
+        defmt::assert_eq!(construct_history_frame([None; LED_COLS]), Grayscale::dark());
+    }
+
+    #[test]
+    fn air_quality_glyphs() {
+        #[rustfmt::skip]
+        let smiley = [
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b01010, LED_COLS),
+            Bitmap::new(0b00000, LED_COLS),
+            Bitmap::new(0b10001, LED_COLS),
+            Bitmap::new(0b01110, LED_COLS),
+        ];
+        defmt::assert_eq!(construct_air_quality_rows(AirQuality::Good), smiley);
+
+        let [.., fair_mouth, fair_chin] = construct_air_quality_rows(AirQuality::Fair);
+        defmt::assert_eq!(
+            [fair_mouth, fair_chin],
+            [Bitmap::new(0b11111, LED_COLS), Bitmap::empty(LED_COLS)]
+        );
+        let [.., poor_mouth, poor_chin] = construct_air_quality_rows(AirQuality::Poor);
+        defmt::assert_eq!(
+            [poor_mouth, poor_chin],
+            [
+                Bitmap::new(0b01110, LED_COLS),
+                Bitmap::new(0b10001, LED_COLS)
+            ]
+        );
     }
 }
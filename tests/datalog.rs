@@ -0,0 +1,215 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use embassy_futures::block_on;
+    use embedded_storage_async::nor_flash::{
+        ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+    };
+    use rustymicrobit_moxi::datalog::{Datalog, LogError, RECORD_SIZE};
+    use rustymicrobit_moxi::protocol::{LOG_CHUNK_RECORDS, LogChunk, Measurement, Timestamp};
+
+    /// Erase page: 3 records.
+    const PAGE: u32 = 128;
+
+    /// Flash of 3 pages: 9 records, 6 retained after erasing ahead.
+    const FLASH: usize = 3 * PAGE as usize;
+
+    /// Out of bounds or misaligned access.
+    #[derive(Debug, defmt::Format)]
+    struct Misaccess;
+
+    impl NorFlashError for Misaccess {
+        fn kind(&self) -> NorFlashErrorKind {
+            NorFlashErrorKind::OutOfBounds
+        }
+    }
+
+    /// RAM with NOR semantics: writes only clear bits.
+    struct RamFlash<'a>(&'a mut [u8; FLASH]);
+
+    impl ErrorType for RamFlash<'_> {
+        type Error = Misaccess;
+    }
+
+    #[expect(clippy::unused_async_trait_impl, reason = "RAM answers immediately")]
+    impl ReadNorFlash for RamFlash<'_> {
+        const READ_SIZE: usize = 1;
+
+        async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Misaccess> {
+            let start = offset as usize;
+            let stored = self.0.get(start..start + bytes.len()).ok_or(Misaccess)?;
+            bytes.copy_from_slice(stored);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            FLASH
+        }
+    }
+
+    #[expect(clippy::unused_async_trait_impl, reason = "RAM answers immediately")]
+    impl NorFlash for RamFlash<'_> {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = PAGE as usize;
+
+        async fn erase(&mut self, from: u32, to: u32) -> Result<(), Misaccess> {
+            if !from.is_multiple_of(PAGE) || !to.is_multiple_of(PAGE) {
+                return Err(Misaccess);
+            }
+            let pages = self
+                .0
+                .get_mut(from as usize..to as usize)
+                .ok_or(Misaccess)?;
+            pages.fill(0xFF);
+            Ok(())
+        }
+
+        async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Misaccess> {
+            let start = offset as usize;
+            if !start.is_multiple_of(4) || !bytes.len().is_multiple_of(4) {
+                return Err(Misaccess);
+            }
+            let stored = self
+                .0
+                .get_mut(start..start + bytes.len())
+                .ok_or(Misaccess)?;
+            for (cell, byte) in stored.iter_mut().zip(bytes) {
+                *cell &= byte;
+            }
+            Ok(())
+        }
+    }
+
+    const fn record(uptime_ms: u64) -> Measurement {
+        Measurement {
+            timestamp: Timestamp {
+                uptime_ms,
+                unix_ms: None,
+            },
+            co2_ppm: 600.0,
+            humidity: 40.0,
+            temp_c: 21.0,
+            hpa: None,
+        }
+    }
+
+    fn open(flash: &mut [u8; FLASH]) -> Datalog<RamFlash<'_>> {
+        defmt::unwrap!(block_on(Datalog::new(RamFlash(flash), 0..3 * PAGE)))
+    }
+
+    fn push(log: &mut Datalog<RamFlash<'_>>, uptimes: core::ops::Range<u64>) {
+        for uptime_ms in uptimes {
+            defmt::unwrap!(block_on(log.push(&record(uptime_ms))));
+        }
+    }
+
+    fn chunk_at(log: &mut Datalog<RamFlash<'_>>, offset: u32) -> LogChunk {
+        defmt::unwrap!(block_on(log.chunk(offset)))
+    }
+
+    fn uptimes(chunk: &LogChunk) -> heapless::Vec<u64, LOG_CHUNK_RECORDS> {
+        chunk
+            .records
+            .iter()
+            .map(|r| r.timestamp.uptime_ms)
+            .collect()
+    }
+
+    #[test]
+    fn chunks_from_offset() {
+        let mut flash = [0xFF; FLASH];
+        let mut log = open(&mut flash);
+        push(&mut log, 0..3);
+
+        defmt::assert_eq!(log.next(), 3);
+        let chunk = chunk_at(&mut log, 0);
+        defmt::assert_eq!(chunk.offset, 0);
+        defmt::assert_eq!(uptimes(&chunk), [0, 1]);
+        let chunk = chunk_at(&mut log, 2);
+        defmt::assert_eq!(chunk.offset, 2);
+        defmt::assert_eq!(uptimes(&chunk), [2]);
+        defmt::assert!(chunk_at(&mut log, 3).records.is_empty());
+    }
+
+    #[test]
+    fn fields_round_trip() {
+        let mut flash = [0xFF; FLASH];
+        let mut log = open(&mut flash);
+        let full = Measurement {
+            timestamp: Timestamp {
+                uptime_ms: 5_000,
+                unix_ms: Some(1_760_000_000_000),
+            },
+            hpa: Some(1013.25),
+            ..record(0)
+        };
+        for m in [full, record(6_000)] {
+            defmt::unwrap!(block_on(log.push(&m)));
+        }
+
+        defmt::assert_eq!(chunk_at(&mut log, 0).records, [full, record(6_000)]);
+    }
+
+    #[test]
+    fn full_log_drops_oldest() {
+        let mut flash = [0xFF; FLASH];
+        let mut log = open(&mut flash);
+        push(&mut log, 0..10);
+
+        // Record 9 took the first page back, erasing records 0 to 2, so
+        // dropped offsets resume at the oldest retained record
+        let chunk = chunk_at(&mut log, 0);
+        defmt::assert_eq!(chunk.offset, 3);
+        defmt::assert_eq!(uptimes(&chunk), [3, 4]);
+        let chunk = chunk_at(&mut log, 9);
+        defmt::assert_eq!(chunk.offset, 9);
+        defmt::assert_eq!(uptimes(&chunk), [9]);
+        defmt::assert!(chunk_at(&mut log, 10).records.is_empty());
+    }
+
+    #[test]
+    fn survives_reset() {
+        let mut flash = [0xFF; FLASH];
+        push(&mut open(&mut flash), 0..7);
+
+        let mut log = open(&mut flash);
+        defmt::assert_eq!(log.next(), 7);
+        push(&mut log, 7..8);
+        let chunk = chunk_at(&mut log, 6);
+        defmt::assert_eq!(chunk.offset, 6);
+        defmt::assert_eq!(uptimes(&chunk), [6, 7]);
+    }
+
+    #[test]
+    fn torn_record_skipped() {
+        let mut flash = [0xFF; FLASH];
+        push(&mut open(&mut flash), 0..2);
+        // Reset while writing record 2: fields written, number not
+        flash[2 * RECORD_SIZE as usize + 4] = 0;
+
+        let mut log = open(&mut flash);
+        defmt::assert_eq!(log.next(), 2);
+        push(&mut log, 3..4);
+        defmt::assert_eq!(log.next(), 4);
+        defmt::assert_eq!(uptimes(&chunk_at(&mut log, 0)), [0, 1]);
+        // A download past the gap resumes after it
+        let chunk = chunk_at(&mut log, 2);
+        defmt::assert_eq!(chunk.offset, 3);
+        defmt::assert_eq!(uptimes(&chunk), [3]);
+    }
+
+    #[test]
+    fn region_checked() {
+        let mut flash = [0xFF; FLASH];
+        let one_page = block_on(Datalog::new(RamFlash(&mut flash), 0..PAGE));
+        defmt::assert!(matches!(one_page, Err(LogError::Layout)));
+        let misaligned = block_on(Datalog::new(RamFlash(&mut flash), 4..3 * PAGE));
+        defmt::assert!(matches!(misaligned, Err(LogError::Layout)));
+    }
+}
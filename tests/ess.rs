@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::ess;
+
+    #[test]
+    fn temperature_centidegrees_le() {
+        defmt::assert_eq!(ess::temperature(22.5), 2250_i16.to_le_bytes());
+        defmt::assert_eq!(ess::temperature(-10.01), [0x17, 0xfc]);
+        defmt::assert_eq!(ess::temperature(f32::NAN), [0x00, 0x80]);
+    }
+
+    #[test]
+    fn temperature_clamped() {
+        defmt::assert_eq!(ess::temperature(1000.0), i16::MAX.to_le_bytes());
+        defmt::assert_eq!(ess::temperature(-500.0), (-27315_i16).to_le_bytes());
+    }
+
+    #[test]
+    fn humidity_centipercent_le() {
+        defmt::assert_eq!(ess::humidity(41.7), [0x4a, 0x10]);
+        defmt::assert_eq!(ess::humidity(120.0), 10_000_u16.to_le_bytes());
+        defmt::assert_eq!(ess::humidity(-1.0), [0x00, 0x00]);
+        defmt::assert_eq!(ess::humidity(f32::NAN), [0xff, 0xff]);
+    }
+
+    #[test]
+    fn pressure_decipascals_le() {
+        defmt::assert_eq!(ess::pressure(1013.25), 1_013_250_u32.to_le_bytes());
+        defmt::assert_eq!(ess::pressure(1013.25), [0x02, 0x76, 0x0f, 0x00]);
+    }
+
+    #[test]
+    fn co2_ppm_le() {
+        defmt::assert_eq!(ess::co2(842.0), [0x4a, 0x03]);
+        defmt::assert_eq!(ess::co2(70_000.0), ess::CO2_SATURATED.to_le_bytes());
+        defmt::assert_eq!(ess::co2(f32::NAN), ess::CO2_UNKNOWN.to_le_bytes());
+    }
+}
@@ -0,0 +1,52 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::gas_index::{Gas, GasIndex};
+
+    /// Samples read 0 while the algorithm settles.
+    const BLACKOUT: usize = 46;
+
+    /// Feed `samples` of a constant raw signal, returning the last index.
+    fn settle(index: &mut GasIndex, sraw: u16, samples: usize) -> u16 {
+        (0..samples).fold(0, |_, _| index.process(sraw))
+    }
+
+    #[test]
+    fn reads_zero_while_settling() {
+        let mut voc = GasIndex::new(Gas::Voc);
+        defmt::assert_eq!(settle(&mut voc, 28_000, BLACKOUT), 0);
+        defmt::assert_ne!(voc.process(28_000), 0);
+    }
+
+    #[test]
+    fn steady_air_is_typical() {
+        let mut voc = GasIndex::new(Gas::Voc);
+        defmt::assert_eq!(settle(&mut voc, 28_000, BLACKOUT + 300), 100);
+        let mut nox = GasIndex::new(Gas::Nox);
+        defmt::assert_eq!(settle(&mut nox, 16_000, BLACKOUT + 300), 1);
+    }
+
+    #[test]
+    fn events_raise_the_index() {
+        // VOCs lower the VOC signal, NOx raises the NOx signal
+        let mut voc = GasIndex::new(Gas::Voc);
+        settle(&mut voc, 28_000, BLACKOUT + 300);
+        defmt::assert!(settle(&mut voc, 26_000, 60) > 150);
+        let mut nox = GasIndex::new(Gas::Nox);
+        settle(&mut nox, 16_000, BLACKOUT + 300);
+        defmt::assert!(settle(&mut nox, 22_000, 60) > 20);
+    }
+
+    #[test]
+    fn cleaner_air_lowers_the_voc_index() {
+        let mut voc = GasIndex::new(Gas::Voc);
+        settle(&mut voc, 28_000, BLACKOUT + 300);
+        defmt::assert!(settle(&mut voc, 29_000, 60) < 100);
+    }
+}
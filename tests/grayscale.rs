@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use microbit_bsp::display::Bitmap;
+    use rustymicrobit_moxi::dashboard::{LED_COLS, LED_ROWS};
+    use rustymicrobit_moxi::grayscale::{Grayscale, LEVEL_MAX};
+
+    #[test]
+    fn levels_as_row_on_time() {
+        let mut frame = Grayscale::dark();
+        frame.set(0, 0, LEVEL_MAX);
+        frame.set(0, 2, 1);
+        frame.set(4, 4, 2);
+
+        let top = [0, 1, 2, 3].map(|step| frame.lit_at(0, step));
+        defmt::assert_eq!(
+            top,
+            [
+                Bitmap::new(0b10100, LED_COLS),
+                Bitmap::new(0b10000, LED_COLS),
+                Bitmap::new(0b10000, LED_COLS),
+                Bitmap::new(0b10000, LED_COLS),
+            ]
+        );
+        // Lit for as many steps of its row's drive as its level
+        let on_steps = (0..LEVEL_MAX)
+            .filter(|&step| frame.lit_at(4, step).is_set(4))
+            .count();
+        defmt::assert_eq!(on_steps, 2);
+        defmt::assert_eq!(frame.lit_at(2, 0), Bitmap::empty(LED_COLS));
+        defmt::assert_eq!(frame.lit_at(LED_ROWS, 0), Bitmap::empty(LED_COLS));
+    }
+
+    #[test]
+    fn levels_clamped() {
+        let mut frame = Grayscale::dark();
+        frame.set(1, 1, LEVEL_MAX + 3);
+        defmt::assert_eq!(frame.level(1, 1), LEVEL_MAX);
+        // Past the matrix
+        frame.set(LED_ROWS, 0, 1);
+        defmt::assert_eq!(frame.level(LED_ROWS, 0), 0);
+        defmt::assert_eq!(frame.lit(), Grayscale::from(frame.lit()).lit());
+    }
+
+    #[test]
+    fn from_bitmaps() {
+        let rows = [Bitmap::new(0b10001, LED_COLS); LED_ROWS];
+        let mut frame = Grayscale::from(rows);
+        defmt::assert_eq!(frame.level(2, 0), LEVEL_MAX);
+        defmt::assert_eq!(frame.level(2, 1), 0);
+        defmt::assert_eq!(frame.lit(), rows);
+
+        frame.dim_column(4, 1);
+        defmt::assert_eq!(frame.level(2, 4), 1);
+        defmt::assert_eq!(frame.level(2, 1), 0);
+        defmt::assert_eq!(frame.lit(), rows);
+    }
+}
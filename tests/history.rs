@@ -0,0 +1,124 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::history::{BUCKETS, History, Metric, Span};
+    use rustymicrobit_moxi::ui::Readings;
+
+    /// Readings of `co2` ppm, 40 %RH and 20 C (68 F).
+    fn readings(co2: f32) -> Readings {
+        defmt::unwrap!(Readings::new(co2, 40.0, 20.0))
+    }
+
+    const MINUTE_MS: u64 = 60 * 1000;
+
+    #[test]
+    fn buckets_averaged() {
+        let mut history = History::new();
+        defmt::assert_eq!(
+            history.window(Metric::Co2, Span::FiveMinutes),
+            [None; BUCKETS]
+        );
+        history.record(1000, &readings(600.0));
+        history.record(31_000, &readings(800.0));
+        defmt::assert_eq!(
+            history.window(Metric::Co2, Span::FiveMinutes),
+            [None, None, None, None, Some(700.0)]
+        );
+        defmt::assert_eq!(
+            history.window(Metric::Temperature, Span::Day),
+            [None, None, None, None, Some(68.0)]
+        );
+        defmt::assert_eq!(
+            history.window(Metric::Humidity, Span::Hour),
+            [None, None, None, None, Some(40.0)]
+        );
+    }
+
+    #[test]
+    fn unknown_readings_not_charted() {
+        let mut history = History::new();
+        history.record(1000, &Readings::default());
+        defmt::assert_eq!(
+            history.window(Metric::Temperature, Span::FiveMinutes),
+            [None; BUCKETS]
+        );
+
+        // Temperature alone, as without a CO2 sensor
+        let temperature_only = Readings {
+            temp_f: readings(600.0).temp_f,
+            ..Readings::default()
+        };
+        history.record(2000, &temperature_only);
+        history.record(MINUTE_MS + 2000, &readings(600.0));
+        defmt::assert_eq!(
+            history.window(Metric::Temperature, Span::FiveMinutes),
+            [None, None, None, Some(68.0), Some(68.0)]
+        );
+        defmt::assert_eq!(
+            history.window(Metric::Co2, Span::FiveMinutes),
+            [None, None, None, None, Some(600.0)]
+        );
+    }
+
+    #[test]
+    fn stale_readings_ignored() {
+        let mut history = History::new();
+        history.record(MINUTE_MS, &readings(600.0));
+        history.record(MINUTE_MS, &readings(600.0));
+        history.record(MINUTE_MS - 1, &readings(1000.0));
+        defmt::assert_eq!(
+            history.window(Metric::Co2, Span::FiveMinutes),
+            [None, None, None, None, Some(600.0)]
+        );
+    }
+
+    #[test]
+    fn gaps_left_empty() {
+        let mut history = History::new();
+        history.record(1, &readings(500.0));
+        history.record(3 * MINUTE_MS, &readings(900.0));
+        defmt::assert_eq!(
+            history.window(Metric::Co2, Span::FiveMinutes),
+            [None, Some(500.0), None, None, Some(900.0)]
+        );
+        // One 12 minute bucket
+        defmt::assert_eq!(
+            history.window(Metric::Co2, Span::Hour),
+            [None, None, None, None, Some(700.0)]
+        );
+    }
+
+    #[test]
+    fn latest_buckets_kept() {
+        let mut history = History::new();
+        for minute in 0_u16..8 {
+            history.record(
+                u64::from(minute) * MINUTE_MS + 1,
+                &readings(400.0 + f32::from(minute)),
+            );
+        }
+        defmt::assert_eq!(
+            history.window(Metric::Co2, Span::FiveMinutes),
+            [
+                Some(403.0),
+                Some(404.0),
+                Some(405.0),
+                Some(406.0),
+                Some(407.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        defmt::assert_eq!(Metric::Humidity.next(), Metric::Co2);
+        defmt::assert_eq!(Span::Day.next(), Span::FiveMinutes);
+        defmt::assert_eq!(Span::Hour.bucket_ms(), 12 * MINUTE_MS);
+    }
+}
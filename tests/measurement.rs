@@ -7,29 +7,48 @@ use microbit_bsp as _;
 #[cfg(test)]
 #[embedded_test::tests]
 mod tests {
-    use rustymicrobit_moxi::measurement::{Co2Measurement, PressureMeasurement, fahrenheit};
+    use rustymicrobit_moxi::measurement::{Co2Measurement, PmMeasurement, PressureMeasurement};
+    use rustymicrobit_moxi::units::{RangeError, RelativeHumidity};
 
     #[test]
     #[expect(clippy::float_cmp, reason = "values are exact and representable")]
     fn co2_measurement_is_full_precision() {
-        let m = Co2Measurement::new(842, 41.7, 22.5);
-        defmt::assert_eq!(m.co2, 842.0);
-        defmt::assert_eq!(m.humidity, 41.7);
-        defmt::assert_eq!(m.temp_c, 22.5);
+        let m = defmt::unwrap!(Co2Measurement::new(842, 41.7, 22.5));
+        defmt::assert_eq!(m.co2.into_inner(), 842.0);
+        defmt::assert_eq!(m.humidity.into_inner(), 41.7);
+        defmt::assert_eq!(m.temp_c.into_inner(), 22.5);
+    }
+
+    #[test]
+    fn co2_measurement_out_of_range() {
+        defmt::assert_eq!(
+            Co2Measurement::new(842, 100.5, 22.5).map(|m| m.co2),
+            Err(RangeError::RelativeHumidity)
+        );
+        defmt::assert_eq!(
+            Co2Measurement::new(842, 41.7, f32::NAN).map(|m| m.co2),
+            Err(RangeError::Celsius)
+        );
     }
 
     #[test]
     #[expect(clippy::float_cmp, reason = "values are exact and representable")]
     fn pressure_pa_to_hpa() {
-        let m = PressureMeasurement::new(101_325.0, 19.0);
-        defmt::assert_eq!(m.hpa, 1013.25);
-        defmt::assert_eq!(m.temp_c, 19.0);
+        let m = defmt::unwrap!(PressureMeasurement::new(101_325.0, 19.0));
+        defmt::assert_eq!(m.hpa.into_inner(), 1013.25);
+        defmt::assert_eq!(m.temp_c.into_inner(), 19.0);
+        defmt::assert_eq!(
+            PressureMeasurement::new(29_000.0, 19.0).map(|m| m.hpa),
+            Err(RangeError::Hectopascal)
+        );
     }
 
     #[test]
-    #[expect(clippy::float_cmp, reason = "values are exact and representable")]
-    fn fahrenheit_conversion() {
-        defmt::assert_eq!(fahrenheit(0.0), 32.0);
-        defmt::assert_eq!(fahrenheit(100.0), 212.0);
+    fn pm_gated_by_humidity() {
+        let m = PmMeasurement::new(3.0, 5.0, 8.0);
+        defmt::assert!(!m.unreliable);
+        defmt::assert!(!m.gated(None).unreliable);
+        defmt::assert!(!m.gated(RelativeHumidity::try_new(90.0).ok()).unreliable);
+        defmt::assert!(m.gated(RelativeHumidity::try_new(92.5).ok()).unreliable);
     }
 }
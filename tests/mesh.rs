@@ -0,0 +1,111 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::mesh::{DecodeError, PACKET_LEN, PeerReading, PeerTable};
+    use rustymicrobit_moxi::modbus::crc16;
+
+    const READING: PeerReading = PeerReading {
+        serial: 0xdead_beef,
+        co2: 842,
+        humidity: 41.5,
+        temp_c: -3.25,
+        hpa: Some(1013.2),
+    };
+
+    const fn reading(serial: u32, co2: u16) -> PeerReading {
+        PeerReading {
+            serial,
+            co2,
+            ..READING
+        }
+    }
+
+    #[test]
+    fn packet_layout() {
+        #[rustfmt::skip]
+        let expected = [
+            0x4d, 0x58, 0x02, 0x01, 0x07,
+            0xef, 0xbe, 0xad, 0xde,
+            0x4a, 0x03,
+            0xbb, 0xfe,
+            0x36, 0x10,
+            0x94, 0x27,
+            0x70, 0x10,
+        ];
+        defmt::assert_eq!(READING.encode(7), expected);
+    }
+
+    #[test]
+    fn packet_round_trip() {
+        let (seq, decoded) = PeerReading::decode(&READING.encode(7)).unwrap();
+        defmt::assert_eq!(seq, 7);
+        defmt::assert_eq!(decoded, READING);
+
+        let no_pressure = PeerReading {
+            hpa: None,
+            ..READING
+        };
+        let (_, decoded) = PeerReading::decode(&no_pressure.encode(8)).unwrap();
+        defmt::assert_eq!(decoded.hpa, None);
+    }
+
+    #[test]
+    fn packet_rejects_foreign() {
+        let mut packet = READING.encode(0);
+        defmt::assert_eq!(
+            PeerReading::decode(&packet[..PACKET_LEN - 1]),
+            Err(DecodeError::Length)
+        );
+        packet[2] = 3;
+        let crc = crc16(&packet[..PACKET_LEN - 2]).to_le_bytes();
+        packet[PACKET_LEN - 2..].copy_from_slice(&crc);
+        defmt::assert_eq!(PeerReading::decode(&packet), Err(DecodeError::Version));
+        // Foreign traffic that happens to start with the magic
+        packet[8] ^= 0x01;
+        defmt::assert_eq!(PeerReading::decode(&packet), Err(DecodeError::Crc));
+        packet[1] = 0;
+        defmt::assert_eq!(PeerReading::decode(&packet), Err(DecodeError::Magic));
+        defmt::assert_eq!(PeerReading::decode(&[]), Err(DecodeError::Magic));
+    }
+
+    #[test]
+    fn table_dedups_and_updates() {
+        let mut table = PeerTable::<4>::new(60_000);
+        defmt::assert!(table.update(1, reading(1, 600), 0));
+        defmt::assert!(!table.update(1, reading(1, 900), 10));
+        defmt::assert!(table.update(2, reading(1, 900), 20));
+        defmt::assert_eq!(table.worst(None).map(|r| r.co2), Some(900));
+    }
+
+    #[test]
+    fn table_evicts_stalest_when_full() {
+        let mut table = PeerTable::<2>::new(60_000);
+        table.update(0, reading(1, 1500), 0);
+        table.update(0, reading(2, 600), 10);
+        table.update(0, reading(3, 700), 20);
+        defmt::assert_eq!(table.worst(None).map(|r| r.serial), Some(3));
+    }
+
+    #[test]
+    fn table_expires_and_includes_local() {
+        let mut table = PeerTable::<4>::new(60_000);
+        table.update(0, reading(1, 1500), 0);
+        table.update(0, reading(2, 600), 50_000);
+
+        table.expire(70_000);
+        defmt::assert_eq!(table.worst(None).map(|r| r.serial), Some(2));
+        defmt::assert_eq!(
+            table.worst(Some(reading(9, 800))).map(|r| r.serial),
+            Some(9)
+        );
+
+        table.expire(200_000);
+        defmt::assert_eq!(table.worst(None), None);
+    }
+}
@@ -0,0 +1,148 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::measurement::Co2Measurement;
+    use rustymicrobit_moxi::modbus::{
+        Exception, INPUT_COUNT, STATUS_CO2, STATUS_CO2_ALARM, crc16, input_registers, respond,
+        write_holding,
+    };
+    use rustymicrobit_moxi::power::PowerMode;
+    use rustymicrobit_moxi::settings::Settings;
+
+    fn inputs() -> [u16; INPUT_COUNT] {
+        let m_co2 = defmt::unwrap!(Co2Measurement::new(842, 41.7, 22.5));
+        input_registers(Some(&m_co2), None, &Settings::new(PowerMode::High))
+    }
+
+    #[test]
+    fn crc_reference_frames() {
+        defmt::assert_eq!(crc16(&[0x01, 0x04, 0x00, 0x00, 0x00, 0x02]), 0xcb71);
+        defmt::assert_eq!(crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x01]), 0x0a84);
+    }
+
+    #[test]
+    fn input_register_map() {
+        defmt::assert_eq!(inputs(), [842, 2250, 4170, 0, STATUS_CO2]);
+    }
+
+    #[test]
+    fn co2_alarm_status() {
+        let m_co2 = defmt::unwrap!(Co2Measurement::new(1200, 41.7, 22.5));
+        let mut settings = Settings::new(PowerMode::High);
+        let [.., status] = input_registers(Some(&m_co2), None, &settings);
+        defmt::assert_eq!(status, STATUS_CO2 | STATUS_CO2_ALARM);
+
+        settings.co2_alarm_ppm = 1201;
+        let [.., status] = input_registers(Some(&m_co2), None, &settings);
+        defmt::assert_eq!(status, STATUS_CO2);
+        // No alarm without a reading
+        settings.co2_alarm_ppm = 400;
+        let [.., status] = input_registers(None, None, &settings);
+        defmt::assert_eq!(status, 0);
+    }
+
+    #[test]
+    fn read_input_registers() {
+        let mut settings = Settings::new(PowerMode::High);
+        let request = [0x01, 0x04, 0x00, 0x00, 0x00, 0x02, 0x71, 0xcb];
+        let expected = [0x01, 0x04, 0x04, 0x03, 0x4a, 0x08, 0xca, 0x5d, 0x81];
+        let response = respond(&request, &inputs(), &mut settings).unwrap();
+        defmt::assert_eq!(response.as_slice(), &expected);
+    }
+
+    #[test]
+    fn read_holding_registers() {
+        let mut settings = Settings::new(PowerMode::High);
+        let request = [0x01, 0x03, 0x00, 0x00, 0x00, 0x04, 0x44, 0x09];
+        #[rustfmt::skip]
+        let expected = [
+            0x01, 0x03, 0x08,
+            0x03, 0xe8, 0x00, 0x00, 0x01, 0x27, 0x00, 0x01,
+            0xcd, 0xfb,
+        ];
+        let response = respond(&request, &inputs(), &mut settings).unwrap();
+        defmt::assert_eq!(response.as_slice(), &expected);
+    }
+
+    #[test]
+    fn write_single_register_echoes() {
+        let mut settings = Settings::new(PowerMode::High);
+        let request = [0x01, 0x06, 0x00, 0x01, 0x00, 0x01, 0x19, 0xca];
+        let response = respond(&request, &inputs(), &mut settings).unwrap();
+        defmt::assert_eq!(response.as_slice(), &request);
+        defmt::assert_eq!(settings.power_mode, PowerMode::Low);
+    }
+
+    #[test]
+    fn write_multiple_registers() {
+        let mut settings = Settings::new(PowerMode::High);
+        let request = [
+            0x01, 0x10, 0x00, 0x00, 0x00, 0x02, 0x04, 0x04, 0xb0, 0x00, 0x01, 0x32, 0xb8,
+        ];
+        let expected = [0x01, 0x10, 0x00, 0x00, 0x00, 0x02, 0x41, 0xc8];
+        let response = respond(&request, &inputs(), &mut settings).unwrap();
+        defmt::assert_eq!(response.as_slice(), &expected);
+        defmt::assert_eq!(settings.co2_alarm_ppm, 1200);
+        defmt::assert_eq!(settings.power_mode, PowerMode::Low);
+    }
+
+    #[test]
+    fn exceptions() {
+        let mut settings = Settings::new(PowerMode::High);
+
+        // Past the end of the input registers
+        let request = [0x01, 0x04, 0x00, 0x04, 0x00, 0x02, 0x30, 0x0a];
+        let response = respond(&request, &inputs(), &mut settings).unwrap();
+        defmt::assert_eq!(response.as_slice(), &[0x01, 0x84, 0x02, 0xc2, 0xc1]);
+
+        // CO2 alarm below 400 ppm
+        let request = [0x01, 0x06, 0x00, 0x00, 0x00, 0x01, 0x48, 0x0a];
+        let response = respond(&request, &inputs(), &mut settings).unwrap();
+        defmt::assert_eq!(response.as_slice(), &[0x01, 0x86, 0x03, 0x02, 0x61]);
+        defmt::assert_eq!(settings, Settings::new(PowerMode::High));
+
+        // Unsupported function
+        let request = [0x01, 0x2b, 0x00, 0x00, 0x71, 0xd0];
+        let response = respond(&request, &inputs(), &mut settings).unwrap();
+        defmt::assert_eq!(response.as_slice(), &[0x01, 0xab, 0x01, 0x9e, 0xf0]);
+    }
+
+    #[test]
+    fn silent_frames() {
+        let mut settings = Settings::new(PowerMode::High);
+
+        // Bad CRC
+        let request = [0x01, 0x04, 0x00, 0x00, 0x00, 0x02, 0x71, 0xcc];
+        defmt::assert!(respond(&request, &inputs(), &mut settings).is_none());
+
+        // Another unit
+        let request = [0x02, 0x04, 0x00, 0x00, 0x00, 0x02, 0x71, 0xf8];
+        defmt::assert!(respond(&request, &inputs(), &mut settings).is_none());
+
+        // Broadcast writes apply without a reply
+        let request = [0x00, 0x06, 0x00, 0x01, 0x00, 0x01, 0x18, 0x1b];
+        defmt::assert!(respond(&request, &inputs(), &mut settings).is_none());
+        defmt::assert_eq!(settings.power_mode, PowerMode::Low);
+    }
+
+    #[test]
+    fn holding_ranges() {
+        let mut settings = Settings::new(PowerMode::High);
+        defmt::assert_eq!(write_holding(&mut settings, 3, 247), Ok(()));
+        defmt::assert_eq!(settings.unit_id, 247);
+        defmt::assert_eq!(
+            write_holding(&mut settings, 3, 248),
+            Err(Exception::IllegalDataValue)
+        );
+        defmt::assert_eq!(
+            write_holding(&mut settings, 4, 0),
+            Err(Exception::IllegalDataAddress)
+        );
+    }
+}
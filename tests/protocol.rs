@@ -0,0 +1,42 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::measurement::{Co2Measurement, PressureMeasurement};
+    use rustymicrobit_moxi::power::PowerMode;
+    use rustymicrobit_moxi::protocol::{self, Config, NackReason};
+    use rustymicrobit_moxi::settings::Settings;
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = Settings::new(PowerMode::Low);
+        let config = Config::from(&settings);
+        defmt::assert_eq!(config.power_mode, protocol::PowerMode::Low);
+        defmt::assert_eq!(Settings::try_from(config), Ok(settings));
+    }
+
+    #[test]
+    fn invalid_config_is_refused() {
+        let config = Config {
+            unit_id: 0,
+            ..Config::from(&Settings::new(PowerMode::High))
+        };
+        defmt::assert_eq!(Settings::try_from(config), Err(NackReason::Invalid));
+    }
+
+    #[test]
+    #[expect(clippy::float_cmp, reason = "values are exact and representable")]
+    fn measurement_from_readings() {
+        let m_co2 = defmt::unwrap!(Co2Measurement::new(842, 41.7, 22.5));
+        let m_pa = defmt::unwrap!(PressureMeasurement::new(101_325.0, 19.0));
+        let m = protocol::measurement(&m_co2, Some(&m_pa));
+        defmt::assert_eq!(m.co2_ppm, 842.0);
+        defmt::assert_eq!(m.hpa, Some(1013.25));
+        defmt::assert_eq!(m.timestamp.uptime_ms, m_co2.timestamp.uptime_ms);
+    }
+}
@@ -0,0 +1,262 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use embassy_futures::block_on;
+    use heapless::Vec;
+    use rustymicrobit_moxi::measurement::{Co2Measurement, PressureMeasurement};
+    use rustymicrobit_moxi::power::PowerMode;
+    use rustymicrobit_moxi::sensor::{CO2Sensor, Co2Control, PressureControl, PressureSensor};
+    use rustymicrobit_moxi::settings::Settings;
+
+    /// Sensor command, as seen by a mock.
+    #[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+    enum Call {
+        Stop,
+        Start(PowerMode),
+        Offset(f32),
+        Altitude(u16),
+        Pressure(u16),
+        Calibrate(u16),
+        Read,
+    }
+
+    /// Mock CO2 sensor, recording commands.
+    #[derive(Default)]
+    struct MockCo2 {
+        calls: Vec<Call, 16>,
+        /// Init failures before succeeding.
+        init_failures: u8,
+        ready: bool,
+        /// Fail to start low power measurement.
+        fail_low_power: bool,
+    }
+
+    impl MockCo2 {
+        fn record(&mut self, call: Call) {
+            self.calls.push(call).unwrap();
+        }
+    }
+
+    #[expect(clippy::unused_async_trait_impl, reason = "mocks answer immediately")]
+    impl CO2Sensor for MockCo2 {
+        type Error = ();
+
+        async fn init(&mut self) -> Result<(), ()> {
+            if self.init_failures > 0 {
+                self.init_failures -= 1;
+                return Err(());
+            }
+            self.stop().await
+        }
+
+        async fn start(&mut self, power_mode: PowerMode) -> Result<(), ()> {
+            self.record(Call::Start(power_mode));
+            if self.fail_low_power && power_mode == PowerMode::Low {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+
+        async fn stop(&mut self) -> Result<(), ()> {
+            self.record(Call::Stop);
+            Ok(())
+        }
+
+        async fn data_ready(&mut self) -> Result<bool, ()> {
+            Ok(self.ready)
+        }
+
+        async fn read(&mut self) -> Result<Co2Measurement, ()> {
+            self.record(Call::Read);
+            Co2Measurement::new(612, 41.5, 21.25).ok().ok_or(())
+        }
+
+        async fn temperature_offset(&mut self) -> Result<f32, ()> {
+            Ok(4.0)
+        }
+
+        async fn set_temperature_offset(&mut self, offset_c: f32) -> Result<(), ()> {
+            self.record(Call::Offset(offset_c));
+            Ok(())
+        }
+
+        async fn set_altitude(&mut self, altitude_m: u16) -> Result<(), ()> {
+            self.record(Call::Altitude(altitude_m));
+            Ok(())
+        }
+
+        async fn set_ambient_pressure(&mut self, hpa: u16) -> Result<(), ()> {
+            self.record(Call::Pressure(hpa));
+            Ok(())
+        }
+
+        async fn calibrate(&mut self, ppm: u16) -> Result<Option<i16>, ()> {
+            self.record(Call::Calibrate(ppm));
+            Ok(Some(-12))
+        }
+    }
+
+    /// Mock pressure sensor that fails every other read.
+    #[derive(Default)]
+    struct MockPressure {
+        reads: u8,
+    }
+
+    #[expect(clippy::unused_async_trait_impl, reason = "mocks answer immediately")]
+    impl PressureSensor for MockPressure {
+        type Error = ();
+
+        async fn init(&mut self) -> Result<(), ()> {
+            Ok(())
+        }
+
+        async fn read(&mut self) -> Result<PressureMeasurement, ()> {
+            self.reads += 1;
+            if self.reads.is_multiple_of(2) {
+                Err(())
+            } else {
+                PressureMeasurement::new(101_320.0, 21.0).ok().ok_or(())
+            }
+        }
+    }
+
+    const SETTINGS: Settings = Settings::new(PowerMode::High);
+
+    fn started(sensor: MockCo2) -> Co2Control<MockCo2> {
+        block_on(Co2Control::start(sensor, SETTINGS)).unwrap()
+    }
+
+    #[test]
+    fn start_retries_init() {
+        let control = started(MockCo2 {
+            init_failures: 2,
+            ..MockCo2::default()
+        });
+        defmt::assert_eq!(
+            control.sensor().calls.as_slice(),
+            [
+                Call::Stop,
+                Call::Offset(SETTINGS.temp_offset_c),
+                Call::Start(PowerMode::High),
+            ]
+        );
+    }
+
+    #[test]
+    fn start_gives_up() {
+        let sensor = MockCo2 {
+            init_failures: 3,
+            ..MockCo2::default()
+        };
+        defmt::assert!(block_on(Co2Control::start(sensor, SETTINGS)).is_err());
+    }
+
+    #[test]
+    fn start_at_altitude() {
+        let control = block_on(Co2Control::start_at_altitude(
+            MockCo2::default(),
+            SETTINGS,
+            350,
+        ))
+        .unwrap();
+        defmt::assert_eq!(
+            control.sensor().calls.as_slice(),
+            [
+                Call::Stop,
+                Call::Altitude(350),
+                Call::Offset(SETTINGS.temp_offset_c),
+                Call::Start(PowerMode::High),
+            ]
+        );
+    }
+
+    #[test]
+    fn poll_compensates_pressure() {
+        let mut control = started(MockCo2 {
+            ready: true,
+            ..MockCo2::default()
+        });
+        let m_pa = PressureMeasurement::new(101_320.0, 21.0).unwrap();
+        let m_co2 = block_on(control.poll(Some(&m_pa), None, SETTINGS)).unwrap();
+
+        defmt::assert_eq!(m_co2.map(|m| m.co2.into_inner()), Some(612.0));
+        defmt::assert_eq!(
+            control.sensor().calls[3..],
+            [Call::Read, Call::Pressure(1013)]
+        );
+    }
+
+    #[test]
+    fn poll_without_data() {
+        let mut control = started(MockCo2::default());
+        defmt::assert!(
+            block_on(control.poll(None, None, SETTINGS))
+                .unwrap()
+                .is_none()
+        );
+        defmt::assert_eq!(control.sensor().calls.len(), 3);
+    }
+
+    #[test]
+    fn poll_calibrates_and_restarts() {
+        let mut control = started(MockCo2::default());
+        block_on(control.poll(None, Some(420), SETTINGS)).unwrap();
+        defmt::assert_eq!(
+            control.sensor().calls[3..],
+            [
+                Call::Stop,
+                Call::Calibrate(420),
+                Call::Start(PowerMode::High)
+            ]
+        );
+    }
+
+    #[test]
+    fn poll_applies_changed_settings() {
+        let mut control = started(MockCo2::default());
+        let low = Settings {
+            co2_alarm_ppm: 1500,
+            ..Settings::new(PowerMode::Low)
+        };
+        block_on(control.poll(None, None, low)).unwrap();
+        defmt::assert_eq!(
+            control.sensor().calls[3..],
+            [
+                Call::Stop,
+                Call::Offset(low.temp_offset_c),
+                Call::Start(PowerMode::Low),
+            ]
+        );
+        defmt::assert_eq!(control.interval(), PowerMode::Low.interval());
+
+        // Only polling and offset changes restart measurement
+        block_on(control.poll(None, None, Settings::new(PowerMode::Low))).unwrap();
+        defmt::assert_eq!(control.sensor().calls.len(), 6);
+    }
+
+    #[test]
+    fn poll_reports_failed_restart() {
+        let mut control = started(MockCo2 {
+            fail_low_power: true,
+            ..MockCo2::default()
+        });
+        let low = Settings::new(PowerMode::Low);
+        block_on(control.poll(None, None, low)).unwrap_err();
+    }
+
+    #[test]
+    #[expect(clippy::float_cmp, reason = "values are exact and representable")]
+    fn pressure_failures_skipped() {
+        let mut control = block_on(PressureControl::start(MockPressure::default())).unwrap();
+        let m_pa = block_on(control.poll()).unwrap();
+        defmt::assert_eq!(m_pa.hpa.into_inner(), 1013.2);
+        defmt::assert!(block_on(control.poll()).is_none());
+    }
+}
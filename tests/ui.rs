@@ -0,0 +1,548 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use embassy_time::Duration;
+    use rustymicrobit_moxi::air_quality::AirQuality;
+    use rustymicrobit_moxi::dashboard::{
+        ANIMATION_FRAMES, construct_air_quality_rows, construct_dashboard_rows, construct_gas_rows,
+        construct_history_frame, construct_pm_rows, still,
+    };
+    use rustymicrobit_moxi::detect::{Found, Inventory, Part};
+    use rustymicrobit_moxi::measurement::{PmMeasurement, PressureMeasurement};
+    use rustymicrobit_moxi::mesh::PeerReading;
+    use rustymicrobit_moxi::page::{Page, Pages};
+    use rustymicrobit_moxi::readout;
+    use rustymicrobit_moxi::ui::{
+        ButtonState, GREETING, Gases, Particulates, Pollutants, Readings, Screen, Ui,
+    };
+    use rustymicrobit_moxi::units::{Fahrenheit, Ppm, RangeError, RelativeHumidity};
+
+    fn local() -> Readings {
+        defmt::unwrap!(Readings::new(612.0, 41.5, 21.25))
+    }
+
+    const NONE: Pollutants = Pollutants {
+        particulates: None,
+        gases: None,
+    };
+
+    fn scroll(text: &str, duration_ms: Option<u64>) -> Screen {
+        Screen::Scroll {
+            text: text.try_into().unwrap(),
+            duration: duration_ms.map(Duration::from_millis),
+        }
+    }
+
+    #[test]
+    fn readouts() {
+        let mut ui = Ui::new();
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &NONE),
+            scroll(" 70.3 F", Some(2750))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &NONE),
+            scroll(" 612 ppm", Some(4500))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::C, &local(), &NONE),
+            scroll(" 41 %", Some(2750))
+        );
+        defmt::assert_eq!(ui.page(), Page::Dashboard);
+    }
+
+    #[test]
+    fn readout_cancelled() {
+        let mut ui = Ui::new();
+        let temp = scroll(" 70.3 F", Some(2750));
+        defmt::assert_eq!(ui.press(ButtonState::A, &local(), &NONE), temp);
+        // Same button again, mid-scroll
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &NONE),
+            ui.dashboard(&local(), &NONE)
+        );
+        defmt::assert_eq!(ui.press(ButtonState::A, &local(), &NONE), temp);
+
+        // Another button interrupts
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &NONE),
+            scroll(" 612 ppm", Some(4500))
+        );
+        defmt::assert_eq!(ui.press(ButtonState::A, &local(), &NONE), temp);
+
+        // Finished scrolls are shown again
+        ui.scrolled();
+        defmt::assert_eq!(ui.press(ButtonState::A, &local(), &NONE), temp);
+        defmt::assert_eq!(ui.page(), Page::Dashboard);
+    }
+
+    #[test]
+    fn page_cycling_not_cancelled() {
+        let mut ui = Ui::new();
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("Worst", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("Sensor B", None)
+        );
+    }
+
+    #[test]
+    fn sub_zero_readout() {
+        let freezer = defmt::unwrap!(Readings::new(450.0, 60.0, -20.0));
+        defmt::assert_eq!(
+            Ui::new().press(ButtonState::A, &freezer, &NONE),
+            scroll(" -4.0 F", Some(2750))
+        );
+    }
+
+    #[test]
+    fn pages_cycle() {
+        let mut ui = Ui::new();
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("Worst", None)
+        );
+        defmt::assert_eq!(ui.page(), Page::Worst);
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("Sensor B", None)
+        );
+        defmt::assert_eq!(ui.page(), Page::Second);
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("PM", None)
+        );
+        defmt::assert_eq!(ui.page(), Page::Particulates);
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("VOC", None)
+        );
+        defmt::assert_eq!(ui.page(), Page::Gases);
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("Air", None)
+        );
+        defmt::assert_eq!(ui.page(), Page::AirQuality);
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("History", None)
+        );
+        defmt::assert_eq!(ui.page(), Page::History);
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("Home", None)
+        );
+        defmt::assert_eq!(ui.page(), Page::Dashboard);
+    }
+
+    #[test]
+    fn pages_without_source_skipped() {
+        let mut ui = Ui::with_pages(Pages {
+            local: true,
+            co2: true,
+            mesh: false,
+            second: false,
+            particulates: false,
+            gases: true,
+        });
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("VOC", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("Air", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("History", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::AB, &local(), &NONE),
+            scroll("Home", None)
+        );
+    }
+
+    #[test]
+    fn pages_found_at_boot() {
+        let found = |part| Found {
+            part,
+            address: part.addresses()[0],
+            channel: None,
+        };
+        let mut inventory = Inventory::default();
+        defmt::assert_eq!(
+            Pages::found(&inventory, false),
+            Pages {
+                local: false,
+                co2: false,
+                mesh: false,
+                second: false,
+                particulates: false,
+                gases: false,
+            }
+        );
+
+        inventory.parts.push(found(Part::Scd4x)).unwrap();
+        inventory.parts.push(found(Part::Sps30)).unwrap();
+        let pages = Pages::found(&inventory, true);
+        defmt::assert!(pages.contains(Page::History));
+        defmt::assert!(pages.contains(Page::Worst));
+        defmt::assert!(!pages.contains(Page::Second));
+        defmt::assert!(pages.contains(Page::Particulates));
+        defmt::assert!(!pages.contains(Page::Gases));
+
+        inventory.parts.push(found(Part::Scd4x)).unwrap();
+        defmt::assert!(Pages::found(&inventory, false).contains(Page::Second));
+    }
+
+    #[test]
+    fn no_co2_sensor() {
+        let mut inventory = Inventory::default();
+        for part in [Part::Bmp581, Part::Sps30] {
+            let found = Found {
+                part,
+                address: part.addresses()[0],
+                channel: None,
+            };
+            inventory.parts.push(found).unwrap();
+        }
+        let mut ui = Ui::with_pages(Pages::found(&inventory, false));
+
+        // Temperature from the pressure sensor alone
+        let m_pa = defmt::unwrap!(PressureMeasurement::new(101_325.0, 22.0));
+        let local = defmt::unwrap!(Readings::local(None, Some(&m_pa)));
+        defmt::assert_eq!((local.co2, local.humidity), (None, None));
+        defmt::assert_eq!(
+            ui.dashboard(&local, &NONE),
+            Screen::Dashboard(construct_dashboard_rows(None, None, local.temp_f))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local, &NONE),
+            scroll(" 71.6 F", Some(2750))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local, &NONE),
+            scroll(" -- ppm", Some(4500))
+        );
+        ui.scrolled();
+
+        for title in ["PM", "Air", "History", "Home"] {
+            defmt::assert_eq!(
+                ui.press(ButtonState::AB, &local, &NONE),
+                scroll(title, None)
+            );
+        }
+    }
+
+    #[test]
+    fn worst_page_readings() {
+        let peer = PeerReading {
+            serial: 1,
+            co2: 1500,
+            humidity: 60.0,
+            temp_c: 25.0,
+            hpa: None,
+        };
+        let worst = defmt::unwrap!(Readings::try_from(&peer));
+        let mut ui = Ui::new();
+        defmt::assert_eq!(ui.readings(local(), Some(worst), None), local());
+
+        ui.press(ButtonState::AB, &local(), &NONE);
+        defmt::assert_eq!(ui.readings(local(), None, None), local());
+        let readings = ui.readings(local(), Some(worst), None);
+        defmt::assert_eq!(
+            (
+                readings.co2.map(Ppm::whole),
+                readings.humidity.map(RelativeHumidity::whole),
+                readings.temp_f.map(Fahrenheit::whole)
+            ),
+            (Some(1500), Some(60), Some(77))
+        );
+
+        // Peers' readings are checked as received
+        let humid = PeerReading {
+            humidity: 120.0,
+            ..peer
+        };
+        defmt::assert_eq!(
+            Readings::try_from(&humid),
+            Err(RangeError::RelativeHumidity)
+        );
+    }
+
+    #[test]
+    fn second_page_readings() {
+        let second = defmt::unwrap!(Readings::new(1040.0, 48.0, 23.0));
+        let mut ui = Ui::new();
+        defmt::assert_eq!(ui.readings(local(), None, Some(second)), local());
+
+        ui.press(ButtonState::AB, &local(), &NONE);
+        ui.press(ButtonState::AB, &local(), &NONE);
+        defmt::assert_eq!(ui.readings(local(), None, None), local());
+        let readings = ui.readings(local(), None, Some(second));
+        defmt::assert_eq!(readings, second);
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &readings, &NONE),
+            scroll(" B 1040 ppm", Some(4500))
+        );
+    }
+
+    #[test]
+    fn particulates_page() {
+        let pm = Particulates {
+            pm1: 4.0,
+            pm2_5: 38.0,
+            pm10: 60.0,
+            unreliable: false,
+        };
+        let pollutants = Pollutants {
+            particulates: Some(pm),
+            gases: None,
+        };
+        let mut ui = Ui::new();
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &pollutants),
+            scroll(" 612 ppm", Some(4500))
+        );
+        for _ in 0..3 {
+            ui.press(ButtonState::AB, &local(), &NONE);
+        }
+        defmt::assert_eq!(ui.page(), Page::Particulates);
+        defmt::assert_eq!(
+            ui.dashboard(&local(), &pollutants),
+            Screen::Dashboard(still(construct_pm_rows(4.0, 38.0, 60.0, false)))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &pollutants),
+            scroll(" PM2.5 38 ug/m3", Some(4500))
+        );
+
+        // Humid readings are approximate; without any, CO2 readouts remain
+        let humid = Pollutants {
+            particulates: Some(Particulates {
+                unreliable: true,
+                ..pm
+            }),
+            gases: None,
+        };
+        defmt::assert_eq!(
+            ui.press(ButtonState::C, &local(), &humid),
+            scroll(" PM10 ~60 ug/m3", Some(4500))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &NONE),
+            scroll(" 612 ppm", Some(4500))
+        );
+    }
+
+    #[test]
+    fn gases_page() {
+        let sgp41 = Pollutants {
+            particulates: None,
+            gases: Some(Gases {
+                voc_index: 180,
+                nox_index: Some(12),
+            }),
+        };
+        let mut ui = Ui::new();
+        for _ in 0..4 {
+            ui.press(ButtonState::AB, &local(), &NONE);
+        }
+        defmt::assert_eq!(ui.page(), Page::Gases);
+        defmt::assert_eq!(
+            ui.dashboard(&local(), &sgp41),
+            Screen::Dashboard(still(construct_gas_rows(180, Some(12))))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &sgp41),
+            scroll(" VOC 180", Some(2750))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &sgp41),
+            scroll(" NOx 12", Some(2750))
+        );
+        ui.scrolled();
+
+        // Without NOx, B reads VOC too
+        let sgp40 = Pollutants {
+            particulates: None,
+            gases: Some(Gases {
+                voc_index: 95,
+                nox_index: None,
+            }),
+        };
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &sgp40),
+            scroll(" VOC 95", Some(2750))
+        );
+        ui.scrolled();
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &NONE),
+            scroll(" 612 ppm", Some(4500))
+        );
+    }
+
+    #[test]
+    fn aqi_keeps_pm2_5_tenths() {
+        // Past the Good breakpoint (9.0) only by its tenth
+        let pm = Particulates::from(&PmMeasurement::new(0.0, 9.1, 0.0));
+        defmt::assert_eq!(pm.aqi(), 51);
+    }
+
+    #[test]
+    fn air_quality_page() {
+        let pm = Particulates {
+            pm1: 20.0,
+            pm2_5: 40.0,
+            pm10: 60.0,
+            unreliable: false,
+        };
+        let smoky = Pollutants {
+            particulates: Some(pm),
+            gases: None,
+        };
+        let mut ui = Ui::new();
+        for _ in 0..5 {
+            ui.press(ButtonState::AB, &local(), &NONE);
+        }
+        defmt::assert_eq!(ui.page(), Page::AirQuality);
+        defmt::assert_eq!(
+            ui.dashboard(&local(), &NONE),
+            Screen::Dashboard(still(construct_air_quality_rows(AirQuality::Good)))
+        );
+        defmt::assert_eq!(
+            ui.dashboard(&local(), &smoky),
+            Screen::Dashboard(still(construct_air_quality_rows(AirQuality::Poor)))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &smoky),
+            scroll(" AQI 112", Some(2750))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &smoky),
+            scroll(" CO2 Good", Some(2750))
+        );
+
+        // Humid readings are approximate, and left out of the glyph
+        let humid = Pollutants {
+            particulates: Some(Particulates {
+                unreliable: true,
+                ..pm
+            }),
+            gases: None,
+        };
+        defmt::assert_eq!(
+            ui.dashboard(&local(), &humid),
+            Screen::Dashboard(still(construct_air_quality_rows(AirQuality::Good)))
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &humid),
+            scroll(" AQI ~112", Some(2750))
+        );
+        ui.scrolled();
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &NONE),
+            scroll(" 70.3 F", Some(2750))
+        );
+    }
+    #[test]
+    fn history_page() {
+        let mut ui = Ui::new();
+        ui.record(1000, &local());
+        ui.record(61_000, &defmt::unwrap!(Readings::new(812.0, 45.0, 22.0)));
+        for _ in 0..6 {
+            ui.press(ButtonState::AB, &local(), &NONE);
+        }
+        defmt::assert_eq!(ui.page(), Page::History);
+        defmt::assert_eq!(
+            ui.dashboard(&local(), &NONE),
+            Screen::Dashboard(
+                [construct_history_frame([None, None, None, Some(612.0), Some(812.0)]);
+                    ANIMATION_FRAMES]
+            )
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::C, &local(), &NONE),
+            scroll(" CO2 812 ppm", Some(4500))
+        );
+
+        // Cycling isn't cancelled by pressing again
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &NONE),
+            scroll("Temp", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &NONE),
+            scroll("RH", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &NONE),
+            scroll("CO2", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::A, &local(), &NONE),
+            scroll("Temp", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::B, &local(), &NONE),
+            scroll("1 h", None)
+        );
+        defmt::assert_eq!(
+            ui.press(ButtonState::C, &local(), &NONE),
+            scroll(" Temp 70.9 F", Some(4500))
+        );
+    }
+
+    #[test]
+    fn readouts_are_ascii() {
+        // The matrix font has no glyphs beyond printable ASCII
+        let cold = defmt::unwrap!(Readings::new(612.0, 41.5, -20.0));
+        let pollutants = Pollutants {
+            particulates: Some(Particulates {
+                pm1: 4.0,
+                pm2_5: 38.0,
+                pm10: 60.0,
+                unreliable: true,
+            }),
+            gases: Some(Gases {
+                voc_index: 180,
+                nox_index: Some(12),
+            }),
+        };
+        let mut ui = Ui::new();
+        ui.record(1000, &cold);
+        for readings in [cold, Readings::default()] {
+            for pollutants in [pollutants, NONE] {
+                // Every page, and its title, readouts and history cycles
+                for _ in 0..8 {
+                    for button in [
+                        ButtonState::AB,
+                        ButtonState::A,
+                        ButtonState::B,
+                        ButtonState::C,
+                        ButtonState::C,
+                    ] {
+                        if let Screen::Scroll { text, .. } =
+                            ui.press(button, &readings, &pollutants)
+                        {
+                            defmt::assert!(text.is_ascii());
+                        }
+                        ui.scrolled();
+                    }
+                }
+            }
+        }
+        defmt::assert!(readout::DEGREES_F.is_ascii() && readout::UNKNOWN.is_ascii());
+        defmt::assert!(GREETING.is_ascii());
+    }
+}
@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+use defmt_rtt as _;
+use microbit_bsp as _;
+
+#[cfg(test)]
+#[embedded_test::tests]
+mod tests {
+    use rustymicrobit_moxi::units::{
+        Celsius, Fahrenheit, Hectopascal, Ppm, RangeError, RelativeHumidity,
+    };
+
+    #[test]
+    fn ranges_checked() {
+        let ppm = |ppm| Ppm::try_new(ppm).map_err(RangeError::from);
+        defmt::assert_eq!(ppm(0.0).map(Ppm::whole), Ok(0));
+        defmt::assert_eq!(ppm(-1.0), Err(RangeError::Ppm));
+        defmt::assert_eq!(ppm(f32::INFINITY), Err(RangeError::Ppm));
+
+        let humidity = |rh| RelativeHumidity::try_new(rh).map_err(RangeError::from);
+        defmt::assert_eq!(humidity(100.0).map(RelativeHumidity::whole), Ok(100));
+        defmt::assert_eq!(humidity(100.1), Err(RangeError::RelativeHumidity));
+
+        let celsius = |temp_c| Celsius::try_new(temp_c).map_err(RangeError::from);
+        defmt::assert_eq!(celsius(-273.15).map(Celsius::into_inner), Ok(-273.15));
+        defmt::assert_eq!(celsius(-274.0), Err(RangeError::Celsius));
+        defmt::assert_eq!(
+            Fahrenheit::try_new(f32::NAN).map_err(RangeError::from),
+            Err(RangeError::Fahrenheit)
+        );
+
+        let hpa = |hpa| Hectopascal::try_new(hpa).map_err(RangeError::from);
+        defmt::assert_eq!(hpa(300.0).map(Hectopascal::whole), Ok(300));
+        defmt::assert_eq!(hpa(1250.1), Err(RangeError::Hectopascal));
+    }
+
+    #[test]
+    fn fahrenheit_conversion() {
+        let temp_f = |temp_c| {
+            Celsius::try_new(temp_c)
+                .map_err(RangeError::from)
+                .and_then(Celsius::to_fahrenheit)
+                .map(Fahrenheit::into_inner)
+        };
+        defmt::assert_eq!(temp_f(0.0), Ok(32.0));
+        defmt::assert_eq!(temp_f(100.0), Ok(212.0));
+        defmt::assert_eq!(temp_f(f32::MAX), Err(RangeError::Fahrenheit));
+    }
+
+    #[test]
+    fn hectopascal_from_pa() {
+        defmt::assert_eq!(
+            Hectopascal::from_pa(101_325.0).map(Hectopascal::into_inner),
+            Ok(1013.25)
+        );
+        defmt::assert_eq!(
+            Hectopascal::from_pa(125_100.0),
+            Err(RangeError::Hectopascal)
+        );
+    }
+
+    #[test]
+    fn whole_units_truncate() {
+        let whole = |ppm, rh, temp_f, hpa| {
+            Some((
+                Ppm::try_new(ppm).ok()?.whole(),
+                RelativeHumidity::try_new(rh).ok()?.whole(),
+                Fahrenheit::try_new(temp_f).ok()?.whole(),
+                Hectopascal::try_new(hpa).ok()?.whole(),
+            ))
+        };
+        defmt::assert_eq!(whole(612.9, 41.5, -3.7, 1013.25), Some((612, 41, -3, 1013)));
+        // Saturating past the display range
+        defmt::assert_eq!(
+            whole(65535.0, 100.0, 1.0e6, 1250.0),
+            Some((65535, 100, i16::MAX, 1250))
+        );
+    }
+}